@@ -1,33 +1,106 @@
 use std::{
-    collections::BTreeMap,
-    f64::consts::PI,
-    path::PathBuf,
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
     sync::mpsc::{channel, Receiver, Sender},
 };
 
-use crate::{color_from_tag, link_text, readable_text, Deadline, Note, StorageMode};
+use crate::{
+    add_tag_where_tagged, agenda_for, apply_format, backlinks, blockers, color_from_tag,
+    complete_line, continue_list, current_line, decrypt_notes, dependencies_of,
+    dependency_neighborhood, expand_placeholders, find_by_title, find_links, format_date,
+    format_duration, format_effort, fuzzy_match, gamma_mult, heading_marker, high_contrast,
+    is_blocked, local_time, logbook_to_markdown, migrate, new_id, next_midnight, next_monday,
+    note_link, notes_tagged, parse_checklist_line, parse_effort, parse_note_link, readable_text,
+    recent_log, remove_tag_from_all, set_high_contrast, set_linked_note, set_tag_palette,
+    set_time_zone, split_by_day, startup_note_link, strip_links, suggest_tags, tag_palette, today,
+    valid_date_format, weekly_summary, wikilinks, would_create_cycle, Attachments, BoardGrouping,
+    BundleImport, Command, Credentials, Deadline, DeadlineBucket, Density, DependencyGraph,
+    FileLink, Format, FuzzyMatch, JournalOrder, JournalRange, JsonBinStorage, LegacySettings, Link,
+    Loaded, LocalStorage, Lock, LogbookIndex, Note, NoteBundle, NoteChange, NoteGraph, NoteStats,
+    NoteTemplate, Settings, Status, StorageMode, TagPalette, UndoStack, ViewMode, CARD_WIDTH_RANGE,
+    DEFAULT_DATE_FORMAT, FORMAT_VERSION, MAX_ATTACHMENT_BYTES, NOTE_TEXT_SIZE_RANGE,
+    SETTINGS_VERSION, UI_SCALE_RANGE,
+};
+#[cfg(feature = "spellcheck")]
+use crate::{dictionary_dirs, load_dictionary, Dictionary, SpellChecker};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{encrypt_userdata, FileWatcher, GlobalHotkey};
 use egui::{
-    epaint::{ahash::HashSet, RectShape, Shadow, TextShape},
-    global_dark_light_mode_buttons, popup_below_widget, vec2, Color32, FontData, FontFamily,
-    FontId, Id, Layout, Pos2, Rect, Response, RichText, Rounding, SelectableLabel, Sense, Shape,
-    Stroke, Ui, Vec2,
+    epaint::{
+        ahash::{HashMap, HashSet},
+        RectShape, Shadow,
+    },
+    global_theme_preference_buttons,
+    text::{LayoutJob, TextFormat},
+    vec2, Color32, FontData, FontFamily, FontId, Id, Layout, Pos2, Rect, Response, RichText,
+    Rounding, SelectableLabel, Sense, Shape, Stroke, Ui, Vec2,
 };
 use egui_dnd::dnd;
-use egui_graphs::{Graph, GraphView};
+use egui_graphs::{
+    DefaultEdgeShape, DefaultNodeShape, GraphView, SettingsInteraction, SettingsStyle,
+};
 use egui_notify::Toasts;
-use log::{error, info};
-use petgraph::{stable_graph::StableGraph, visit::NodeIndexable, Directed};
+use log::{error, info, warn};
+use zeroize::Zeroizing;
 
 // use egui_commonmark::*;
 
-#[derive(serde::Deserialize, serde::Serialize, Default, Debug, PartialEq, Eq)]
-pub enum ViewMode {
+const UNDO_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+const REDO_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::Z,
+);
+const PALETTE_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::P);
+const GO_TO_NOTE_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::K);
+/// Formatting applied with the keyboard while editing the text of a note. The link takes the
+/// shortcut of going to a note there.
+const FORMAT_SHORTCUTS: [(egui::KeyboardShortcut, Format); 3] = [
+    (
+        egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::B),
+        Format::Bold,
+    ),
+    (
+        egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::I),
+        Format::Italic,
+    ),
+    (GO_TO_NOTE_SHORTCUT, Format::Link),
+];
+
+/// Most entries listed in the command palette at once
+const MAX_PALETTE_ENTRIES: usize = 50;
+
+/// The sections of the settings panel
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum SettingsTab {
     #[default]
-    Board,
-    List,
-    Graph,
+    Appearance,
+    Behavior,
+    Sync,
+    Shortcuts,
 }
-#[derive(serde::Deserialize, serde::Serialize, Default)]
+
+impl SettingsTab {
+    const ALL: [SettingsTab; 4] = [
+        SettingsTab::Appearance,
+        SettingsTab::Behavior,
+        SettingsTab::Sync,
+        SettingsTab::Shortcuts,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            SettingsTab::Appearance => "Appearance",
+            SettingsTab::Behavior => "Behavior",
+            SettingsTab::Sync => "Sync",
+            SettingsTab::Shortcuts => "Shortcuts",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct UiState {
     #[serde(skip)]
@@ -35,34 +108,800 @@ pub struct UiState {
     scratchpad_enabled: bool,
     logbook_enabled: bool,
     tags_enabled: bool,
+    /// Show the recent log messages
+    diagnostics_enabled: bool,
+    /// Show note counts and the storage state along the bottom
+    status_bar_enabled: bool,
+    /// The tab shown in the settings panel
+    settings_tab: SettingsTab,
+    /// List tags no note uses in the tags panel, to apply or delete them
+    show_unused_tags: bool,
+    /// The zoom factor last applied to the context
+    #[serde(skip)]
+    applied_ui_scale: f32,
+    /// Only show logbook days containing this text
+    #[serde(skip)]
+    logbook_search: String,
+    /// The day last picked to jump to in the logbook
+    #[serde(skip)]
+    logbook_date: Option<chrono::NaiveDate>,
+    /// Show the last 7 days of the logbook as one summary instead of editing it day by day
+    logbook_week: bool,
+    journal_order: JournalOrder,
+    journal_range: JournalRange,
+    /// Where the logbook is exported to as Markdown
+    journal_path: PathBuf,
+    /// The first day of the week shown in the weekly summary window, if open
+    #[serde(skip)]
+    summary_week: Option<chrono::NaiveDate>,
+    /// Where the note editor was last placed and how large it was
+    editor_rect: Option<Rect>,
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Default)]
+impl Default for UiState {
+    fn default() -> Self {
+        Self {
+            settings_enabled: false,
+            scratchpad_enabled: false,
+            logbook_enabled: false,
+            tags_enabled: false,
+            diagnostics_enabled: false,
+            status_bar_enabled: true,
+            settings_tab: SettingsTab::Appearance,
+            show_unused_tags: false,
+            applied_ui_scale: 1.0,
+            logbook_search: String::new(),
+            logbook_date: None,
+            logbook_week: false,
+            journal_order: Default::default(),
+            journal_range: Default::default(),
+            journal_path: PathBuf::from("logbook.md"),
+            summary_week: None,
+            editor_rect: None,
+        }
+    }
+}
+
+/// Number of link chips shown on a board card, the rest is summarized
+const MAX_CARD_LINKS: usize = 4;
+
+/// Height of the row of link chips on a board card
+const LINK_FOOTER_HEIGHT: f32 = 22.;
+
+/// Maximum size of an image preview in the editor
+const THUMBNAIL_SIZE: f32 = 160.;
+
+/// Size of the note editor until it is resized
+const EDITOR_SIZE: Vec2 = vec2(960., 720.);
+
+/// Width from which the editor shows the text and the details of a note side by side
+const EDITOR_COLUMNS_WIDTH: f32 = 760.;
+
+/// Height of the image preview on a board card
+const CARD_THUMBNAIL_HEIGHT: f32 = 80.;
+
+/// Space below a card with collapsed dependencies, hinting at the cards underneath
+const COLLAPSED_STACK_HEIGHT: f32 = 6.;
+
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone, PartialEq)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct ScratchPad {
-    sections: Vec<String>,
+    items: Vec<ScratchSection>,
+    /// Older versions stored sections as plain strings. Migrated into `items` on load.
+    #[serde(rename = "sections", skip_serializing)]
+    legacy_sections: Vec<String>,
+}
+
+impl ScratchPad {
+    /// Append a section with the first line of `text` as title
+    pub fn add_section(&mut self, text: &str) {
+        self.items.push(ScratchSection::from_text(text));
+    }
+
+    /// Turn sections saved by older versions into items, using the first line as title.
+    pub(crate) fn migrate(&mut self) {
+        for section in std::mem::take(&mut self.legacy_sections) {
+            self.items.push(ScratchSection {
+                collapsed: true,
+                ..ScratchSection::from_text(&section)
+            });
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone, Hash, PartialEq)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct ScratchSection {
+    title: String,
+    text: String,
+    collapsed: bool,
+}
+
+impl ScratchSection {
+    /// A section with the first line of `text` as title and the rest as text.
+    fn from_text(text: &str) -> Self {
+        let (title, text) = text.split_once('\n').unwrap_or((text, ""));
+        Self {
+            title: title.to_string(),
+            text: text.to_string(),
+            collapsed: false,
+        }
+    }
+}
+
+/// A saved storage target together with the settings that belong to it.
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct Profile {
+    pub storage_mode: StorageMode,
+    pub username: String,
+    /// Older versions could store username and secret here. Only the username is migrated.
+    #[serde(rename = "credentials", skip_serializing)]
+    legacy_credentials: (String, String),
+    pub viewmode: ViewMode,
+    pub active_tags: HashSet<String>,
+    /// Number of notes at the last save or load
+    pub note_count: usize,
+    /// Time of the last save or load
+    pub last_sync: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Profile {
+    /// A short description of the last sync for the profile list
+    fn sync_summary(&self, date_format: &str) -> String {
+        match self.last_sync {
+            Some(time) => {
+                let time = local_time(time);
+                format!(
+                    "{} notes, synced {} {}",
+                    self.note_count,
+                    format_date(time.date(), date_format),
+                    time.format("%H:%M")
+                )
+            }
+            None => "never synced".to_string(),
+        }
+    }
+}
+
+/// An action on the user data that can be undone for a short while.
+/// Destructive actions must be confirmed before they are applied.
+#[derive(Clone, PartialEq, Debug)]
+pub enum PendingAction {
+    DeleteNote(u128),
+    /// Delete a tag from the tag list and all notes
+    DeleteTag(String),
+    /// Select the notes with a tag
+    SelectTagged(String),
+    /// Tag all notes that have `tag` with `added` as well
+    AddTagWhereTagged {
+        tag: String,
+        added: String,
+    },
+    /// Remove a tag from all notes, keeping it in the tag list
+    RemoveTagFromAll(String),
+    /// Save the current data, then start over with an empty default profile
+    NewProfile,
+    /// Replace a note by a scratchpad section with its text
+    MoveToScratchpad(u128),
+    /// Turn the logbook item of a day at an index into a note
+    PromoteLogItem(chrono::NaiveDate, usize),
+    /// Load the notes again after another program changed them, discarding unsaved edits
+    #[cfg(not(target_arch = "wasm32"))]
+    ReloadNotes,
+    /// Load the notes from the storage again, discarding unsaved edits
+    RestoreNotes,
+    /// Replace all data by `UserData::sample` with a seed
+    #[cfg(any(debug_assertions, feature = "demo"))]
+    LoadSampleData(u64),
+    /// Write the notes in the app state to the storage, even if it could not be loaded
+    ExportToStorage,
+    /// Load the encrypted notes of the backup, as the saved ones could not be read
+    LoadBackup(String),
+}
+
+impl PendingAction {
+    fn needs_confirmation(&self) -> bool {
+        !matches!(
+            self,
+            PendingAction::MoveToScratchpad(_) | PendingAction::PromoteLogItem(..)
+        )
+    }
+}
+
+/// The state of the open command palette
+#[derive(Default)]
+struct CommandPalette {
+    query: String,
+    /// Only offer to jump to notes, as a quick switcher
+    notes_only: bool,
+    /// Index of the highlighted entry
+    selected: usize,
+    focus_requested: bool,
+}
+
+/// A command matching the query of the command palette
+struct PaletteEntry {
+    label: String,
+    /// The matched chars of `label`
+    matched: FuzzyMatch,
+    command: Command,
+}
+
+impl PaletteEntry {
+    /// The label with the matched chars emphasized
+    fn layout(&self, ui: &Ui) -> LayoutJob {
+        let mut job = LayoutJob::default();
+        let plain = TextFormat {
+            font_id: egui::TextStyle::Button.resolve(ui.style()),
+            color: ui.visuals().text_color(),
+            ..Default::default()
+        };
+        let emphasized = TextFormat {
+            color: ui.visuals().strong_text_color(),
+            underline: Stroke::new(1.0_f32, ui.visuals().strong_text_color()),
+            ..plain.clone()
+        };
+        if let Command::JumpTo(_) = self.command {
+            job.append(egui_phosphor::regular::NOTE, 0., plain.clone());
+            job.append(" ", 0., plain.clone());
+        }
+        for (i, c) in self.label.chars().enumerate() {
+            let format = if self.matched.positions.contains(&i) {
+                emphasized.clone()
+            } else {
+                plain.clone()
+            };
+            job.append(c.encode_utf8(&mut [0; 4]), 0., format);
+        }
+        job
+    }
+}
+
+/// How many notes use each tag. Kept up to date as notes change instead of counting every frame.
+#[derive(Default)]
+struct TagUsage {
+    counts: BTreeMap<String, usize>,
+    /// The tags each note was counted with
+    counted: HashMap<u128, BTreeSet<String>>,
+    /// Tags that came into use since the last call to `take_new`
+    new: BTreeSet<String>,
+}
+
+impl TagUsage {
+    /// Count the tags of all notes from scratch, after the notes were replaced
+    fn rebuild(&mut self, notes: &Notes) {
+        *self = Self::default();
+        for (id, note) in notes {
+            self.update(*id, Some(&note.tags));
+        }
+    }
+
+    /// Count note `id` with `tags`, or no longer count it if the note is gone
+    fn update(&mut self, id: u128, tags: Option<&BTreeSet<String>>) {
+        if tags.is_some() && self.counted.get(&id) == tags {
+            return;
+        }
+        for tag in self.counted.remove(&id).unwrap_or_default() {
+            if let Some(count) = self.counts.get_mut(&tag) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(&tag);
+                }
+            }
+        }
+        let Some(tags) = tags else {
+            return;
+        };
+        for tag in tags {
+            let count = self.counts.entry(tag.clone()).or_default();
+            *count += 1;
+            if *count == 1 {
+                self.new.insert(tag.clone());
+            }
+        }
+        self.counted.insert(id, tags.clone());
+    }
+
+    /// The number of notes with `tag`
+    fn count(&self, tag: &str) -> usize {
+        self.counts.get(tag).copied().unwrap_or_default()
+    }
+
+    fn take_new(&mut self) -> BTreeSet<String> {
+        std::mem::take(&mut self.new)
+    }
+}
+
+/// Drag-and-drop payload of a board card
+struct DraggedNote(u128);
+
+/// A note edited in a window of its own
+#[cfg(not(target_arch = "wasm32"))]
+struct NoteWindow {
+    id: u128,
+    /// The note when the window was opened. Its edits are committed to the undo history on close.
+    before: Note,
+}
+
+/// A note being written in the quick capture window
+#[derive(Default)]
+struct QuickCapture {
+    text: String,
+    /// Tags the note gets, initially the active tags
+    tags: HashSet<String>,
+    /// The app was brought up by the global hotkey and is minimized again afterwards
+    from_hotkey: bool,
+    focus_requested: bool,
+}
+
+/// Quick capture as a window inside the app
+fn capture_window(
+    ctx: &egui::Context,
+    capture: &mut QuickCapture,
+    active_tags: &HashSet<String>,
+) -> Option<bool> {
+    egui::Window::new("Quick capture")
+        .collapsible(false)
+        .resizable(false)
+        .order(egui::Order::Foreground)
+        .anchor(egui::Align2::CENTER_TOP, vec2(0., 60.))
+        .show(ctx, |ui| capture_form(ui, capture, active_tags))
+        .and_then(|r| r.inner)
+        .flatten()
+}
+
+/// The text and tags of a quick capture note. Returns whether it was submitted (true) or
+/// cancelled (false), if either.
+fn capture_form(
+    ui: &mut Ui,
+    capture: &mut QuickCapture,
+    active_tags: &HashSet<String>,
+) -> Option<bool> {
+    let mut submit = false;
+    let mut cancel = false;
+    let text_id = Id::new("quick_capture_text");
+    if ui.memory(|m| m.has_focus(text_id)) {
+        // before the text field would turn it into a line break
+        submit = ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Enter));
+    }
+    let resp = ui.add(
+        egui::TextEdit::multiline(&mut capture.text)
+            .id(text_id)
+            .hint_text("Enter to add, Shift+Enter for a new line")
+            .desired_rows(4)
+            .desired_width(400.),
+    );
+    if !capture.focus_requested {
+        resp.request_focus();
+        capture.focus_requested = true;
+    }
+    if !active_tags.is_empty() {
+        ui.horizontal_wrapped(|ui| {
+            for tag in active_tags {
+                let selected = capture.tags.contains(tag);
+                ui.style_mut().visuals.selection.bg_fill =
+                    color_from_tag(tag).gamma_multiply(gamma_mult());
+                if ui.selectable_label(selected, tag).clicked() {
+                    if selected {
+                        capture.tags.remove(tag);
+                    } else {
+                        capture.tags.insert(tag.clone());
+                    }
+                }
+            }
+        });
+    }
+    ui.horizontal(|ui| {
+        submit |= ui.button("Add").clicked();
+        cancel = ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape));
+    });
+
+    if submit {
+        Some(true)
+    } else {
+        cancel.then_some(false)
+    }
+}
+
+/// A note title being edited in place on a board card or list row
+struct InlineEdit {
+    id: u128,
+    /// Edit buffer for the first line of the note
+    title: String,
+    focus_requested: bool,
+}
+
+/// A one-shot copy of the user data taken before a destructive action, so it can be undone.
+pub struct UndoSnapshot {
+    description: String,
+    userdata: UserData,
+    /// The tag filter, which the action may have changed as well
+    active_tags: HashSet<String>,
+    /// Time (as in `InputState::time`) at which the undo offer disappears
+    expires: f64,
 }
 
+/// Number of search filters to remember
+const MAX_RECENT_FILTERS: usize = 10;
+
+/// How long the undo offer stays visible, in seconds
+const UNDO_TIMEOUT: f64 = 8.0;
+
+/// How long notes that woke up from a snooze are highlighted, in seconds
+const WAKE_HIGHLIGHT: f64 = 4.0;
+
 pub const GAMMA_MULT: f32 = 0.8;
 
 pub type Notes = BTreeMap<u128, Note>;
 
-#[derive(serde::Deserialize, serde::Serialize, Default)]
+/// Which notes are shown, before search filter and tags are applied
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Visibility {
+    /// All notes, including finished ones
+    All,
+    /// Notes that are not finished
+    Open,
+    /// Notes that are not finished and not waiting for a dependency
+    Unblocked,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct UserData {
+    /// `FORMAT_VERSION` at the time the data was stored, missing before it was recorded
+    #[serde(default)]
+    pub format_version: u32,
     /// All notes
     pub notes: Notes,
     pub tags: Vec<String>,
     pub scratchpad: ScratchPad,
     pub logbook: BTreeMap<chrono::NaiveDate, Vec<Note>>,
+    pub templates: Vec<NoteTemplate>,
+    /// Text of the first logbook entry of each day, may contain a `{{date}}` placeholder
+    pub logbook_template: String,
+    /// Custom note colors picked recently, most recent first
+    pub recent_colors: Vec<[u8; 3]>,
+    /// The contents of the attachments of the notes
+    pub attachments: Attachments,
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Default)]
-#[serde(default)] // if we add new fields, give them default values when deserializing old state
-pub struct LogItem {
-    /// All notes
-    pub text: String,
-    pub tags: Vec<String>,
+impl Default for UserData {
+    fn default() -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            notes: Default::default(),
+            tags: Default::default(),
+            scratchpad: Default::default(),
+            logbook: Default::default(),
+            templates: Default::default(),
+            logbook_template: Default::default(),
+            recent_colors: Default::default(),
+            attachments: Default::default(),
+        }
+    }
+}
+
+impl UserData {
+    /// Ids of the notes matching `filter` and any of `active_tags`, in display order.
+    /// An empty filter or tag set matches all notes. Snoozed notes are left out.
+    pub fn visible_notes<S: std::hash::BuildHasher>(
+        &self,
+        filter: &str,
+        active_tags: &std::collections::HashSet<String, S>,
+        visibility: Visibility,
+        ascending: bool,
+    ) -> Vec<u128> {
+        let mut v: Vec<(u128, &Note)> = self
+            .notes
+            .iter()
+            .filter(|(_, note)| match visibility {
+                Visibility::All => true,
+                Visibility::Open => !note.is_done(),
+                Visibility::Unblocked => !note.is_done() && !is_blocked(note, &self.notes),
+            })
+            .filter(|(_, note)| !note.is_snoozed(today()))
+            .filter(|(_, note)| note.has_any_tag(active_tags) && note.matches(filter))
+            .map(|(id, note)| (*id, note))
+            .collect();
+        sort_notes(&mut v, ascending);
+        v.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Ids of the finished notes matching `filter` and any of `active_tags`, the most recently
+    /// completed first. Notes without a completion date come last.
+    pub fn completed_notes<S: std::hash::BuildHasher>(
+        &self,
+        filter: &str,
+        active_tags: &std::collections::HashSet<String, S>,
+    ) -> Vec<u128> {
+        let mut v: Vec<&Note> = self
+            .notes
+            .values()
+            .filter(|note| note.is_done())
+            .filter(|note| note.has_any_tag(active_tags) && note.matches(filter))
+            .collect();
+        v.sort_by(|a, b| b.completed_on.cmp(&a.completed_on).then(a.id.cmp(&b.id)));
+        v.into_iter().map(|note| note.id).collect()
+    }
+
+    /// Ids of the notes hidden until a later day than `today`, the first to wake up first
+    pub fn snoozed_notes(&self, today: chrono::NaiveDate) -> Vec<u128> {
+        let mut v: Vec<&Note> = self
+            .notes
+            .values()
+            .filter(|note| note.is_snoozed(today))
+            .collect();
+        v.sort_by(|a, b| a.snoozed_until.cmp(&b.snoozed_until).then(a.id.cmp(&b.id)));
+        v.into_iter().map(|note| note.id).collect()
+    }
+
+    /// End the snooze of the notes whose day has come by `today`. Returns the ids of the notes
+    /// that woke up.
+    pub fn wake_snoozed(&mut self, today: chrono::NaiveDate) -> Vec<u128> {
+        let mut woken = vec![];
+        for note in self.notes.values_mut() {
+            if note.snoozed_until.is_some() && !note.is_snoozed(today) {
+                note.snoozed_until = None;
+                woken.push(note.id);
+            }
+        }
+        woken
+    }
+
+    /// The remaining effort in hours of the open notes that are overdue or due within a week
+    pub fn effort_due_this_week(&self, today: chrono::NaiveDate) -> f32 {
+        self.notes
+            .values()
+            .filter(|n| !n.is_done())
+            .filter(|n| {
+                matches!(
+                    DeadlineBucket::of(n, today),
+                    DeadlineBucket::Overdue | DeadlineBucket::Today | DeadlineBucket::ThisWeek
+                )
+            })
+            .map(|n| n.remaining_effort())
+            .sum()
+    }
+
+    /// The remaining effort in hours of the notes `ids`, in total and by tag.
+    /// A note counts towards each of its tags.
+    pub fn workload(&self, ids: &[u128]) -> (f32, BTreeMap<String, f32>) {
+        let mut total = 0.;
+        let mut by_tag: BTreeMap<String, f32> = BTreeMap::new();
+        for note in ids.iter().filter_map(|id| self.notes.get(id)) {
+            let effort = note.remaining_effort();
+            if effort <= 0. {
+                continue;
+            }
+            total += effort;
+            for tag in &note.tags {
+                *by_tag.entry(tag.clone()).or_default() += effort;
+            }
+        }
+        (total, by_tag)
+    }
+
+    /// Add `tag` to a note and to the tag list. Returns false if there is no such note.
+    pub fn tag_note(&mut self, id: u128, tag: &str) -> bool {
+        let Some(note) = self.notes.get_mut(&id) else {
+            return false;
+        };
+        note.tags.insert(tag.to_string());
+        if !self.tags.iter().any(|t| t == tag) {
+            self.tags.push(tag.to_string());
+        }
+        true
+    }
+
+    /// Add an entry to the logbook of `date` and return it. The first entry of a day is filled
+    /// in from `logbook_template`, later ones start out empty.
+    pub fn add_log_entry(&mut self, date: chrono::NaiveDate) -> &mut Note {
+        let items = self.logbook.entry(date).or_default();
+        let mut item = Note::new();
+        if items.is_empty() {
+            item.text = expand_placeholders(&self.logbook_template, date).0;
+        }
+        items.push(item);
+        items.last_mut().unwrap()
+    }
+
+    /// Record a finished session of work on note `id` and write the time spent on each day to
+    /// the logbook. A session over midnight counts for both days. Returns false if there is no
+    /// such note.
+    pub fn log_work(
+        &mut self,
+        id: u128,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+    ) -> bool {
+        let Some(note) = self.notes.get_mut(&id) else {
+            return false;
+        };
+        if end <= start {
+            return true;
+        }
+        note.work_log.push((start, end));
+        let title = note.get_title().to_string();
+        for (date, spent) in split_by_day(start, end) {
+            // a few seconds aren't worth a line
+            if spent.num_minutes() == 0 {
+                continue;
+            }
+            let line = format!("Worked {} on {title}", format_duration(spent));
+            let item = self.add_log_entry(date);
+            if item.text.is_empty() {
+                item.text = line;
+            } else {
+                item.text = format!("{}\n{line}", item.text.trim_end());
+            }
+        }
+        true
+    }
+
+    /// Remove `tag` from a note. Returns false if the note didn't have it.
+    pub fn untag_note(&mut self, id: u128, tag: &str) -> bool {
+        self.notes
+            .get_mut(&id)
+            .is_some_and(|note| note.tags.remove(tag))
+    }
+
+    /// Make note `id` depend on `dependency`, unless either is missing or this would create a cycle.
+    pub fn add_dependency(&mut self, id: u128, dependency: u128) -> anyhow::Result<()> {
+        let Some(title) = self.notes.get(&dependency).map(|n| n.get_title()) else {
+            anyhow::bail!("There is no note to depend on");
+        };
+        if id == dependency {
+            anyhow::bail!("A note can't depend on itself");
+        }
+        if would_create_cycle(&self.notes, id, dependency) {
+            anyhow::bail!("'{title}' already depends on this note, so it can't be a dependency.");
+        }
+        let Some(note) = self.notes.get_mut(&id) else {
+            anyhow::bail!("There is no such note");
+        };
+        note.depends.insert(dependency);
+        Ok(())
+    }
+
+    /// Make note `id` depend on the notes it references as `[[Title]]`. Links to unknown titles
+    /// and links that would create a cycle are left out. Existing dependencies are kept.
+    pub fn link_wikilinks(&mut self, id: u128) {
+        let Some(note) = self.notes.get(&id) else {
+            return;
+        };
+        let targets: Vec<u128> = wikilinks(&note.text)
+            .into_iter()
+            .filter_map(|title| find_by_title(&self.notes, title))
+            .filter(|target| !note.depends.contains(target))
+            .collect();
+        for target in targets {
+            _ = self.add_dependency(id, target);
+        }
+    }
+
+    /// Remove a dependency of note `id`. Returns false if it didn't exist.
+    pub fn remove_dependency(&mut self, id: u128, dependency: u128) -> bool {
+        self.notes
+            .get_mut(&id)
+            .is_some_and(|note| note.depends.remove(&dependency))
+    }
+
+    /// Insert a note, giving it a fresh id if its id is already taken. Returns the id used.
+    pub fn add_note(&mut self, mut note: Note) -> u128 {
+        while self.notes.contains_key(&note.id) {
+            note.id = new_id();
+        }
+        let id = note.id;
+        self.notes.insert(id, note);
+        id
+    }
+
+    /// Add the notes of a shared bundle. They all get fresh ids, so they never replace a note,
+    /// even one that was shared before, and their dependencies are pointed at the new ids.
+    pub fn import_bundle(&mut self, bundle: &NoteBundle) -> BundleImport {
+        let mut ids = BTreeMap::new();
+        for note in &bundle.notes {
+            let mut id = new_id();
+            while self.notes.contains_key(&id) || ids.values().any(|i| *i == id) {
+                id = new_id();
+            }
+            ids.insert(note.id, id);
+        }
+        let mut added = vec![];
+        let mut dependencies = 0;
+        for note in &bundle.notes {
+            let mut note = note.clone();
+            note.id = ids[&note.id];
+            note.depends = bundle
+                .dependencies
+                .iter()
+                .filter(|(n, _)| ids.get(n) == Some(&note.id))
+                .filter_map(|(_, d)| ids.get(d).copied())
+                .collect();
+            dependencies += note.depends.len();
+            added.push(note.id);
+            self.notes.insert(note.id, note);
+        }
+        // attachment ids are random, so they don't clash with the ones already here
+        self.attachments
+            .0
+            .extend(bundle.attachments.0.iter().map(|(id, d)| (*id, d.clone())));
+        BundleImport {
+            root: ids.get(&bundle.root).copied(),
+            notes: added,
+            dependencies,
+        }
+    }
+
+    /// Remove a note and all references to it from other notes' dependencies.
+    pub fn delete_note(&mut self, id: u128) -> Option<Note> {
+        let note = self.notes.remove(&id)?;
+        for other in self.notes.values_mut() {
+            other.depends.remove(&id);
+        }
+        Some(note)
+    }
+
+    /// Repair inconsistent data: dependencies on missing notes, duplicate, empty and unknown tags.
+    /// Returns a description of each kind of fix applied.
+    pub fn sanitize(&mut self) -> Vec<String> {
+        let mut fixes = vec![];
+
+        let ids: HashSet<u128> = self.notes.keys().copied().collect();
+        let mut dangling = 0;
+        for note in self.notes.values_mut() {
+            let before = note.depends.len();
+            note.depends.retain(|d| ids.contains(d));
+            dangling += before - note.depends.len();
+        }
+        if dangling > 0 {
+            fixes.push(format!("removed {dangling} dependencies on missing notes"));
+        }
+
+        let mut empty = 0;
+        for note in self.notes.values_mut() {
+            if note.tags.remove("") {
+                empty += 1;
+            }
+        }
+        let before = self.tags.len();
+        self.tags.retain(|t| !t.is_empty());
+        empty += before - self.tags.len();
+        if empty > 0 {
+            fixes.push(format!("removed {empty} empty tags"));
+        }
+
+        let before = self.logbook.len();
+        self.logbook
+            .retain(|_, items| items.iter().any(|item| !item.text.trim().is_empty()));
+        let empty_days = before - self.logbook.len();
+        if empty_days > 0 {
+            fixes.push(format!("removed {empty_days} empty logbook days"));
+        }
+
+        let mut seen = HashSet::default();
+        let before = self.tags.len();
+        self.tags.retain(|t| seen.insert(t.clone()));
+        let duplicates = before - self.tags.len();
+        if duplicates > 0 {
+            fixes.push(format!("removed {duplicates} duplicate tags"));
+        }
+
+        let unknown: BTreeSet<String> = self
+            .notes
+            .values()
+            .flat_map(|n| &n.tags)
+            .filter(|t| !seen.contains(*t))
+            .cloned()
+            .collect();
+        if !unknown.is_empty() {
+            fixes.push(format!("added {} unknown tags", unknown.len()));
+            self.tags.extend(unknown);
+        }
+
+        fixes
+    }
 }
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
@@ -72,38 +911,240 @@ pub struct MeteoraApp {
     userdata: UserData,
     active_tags: HashSet<String>,
     active_note: Option<u128>,
-    /// Authentication/encryption
-    credentials: (String, String),
+    /// The note time is tracked for and when the session started. Only one runs at a time.
+    running_timer: Option<(u128, chrono::DateTime<chrono::Utc>)>,
+    /// Authentication/encryption. Only the username is persisted.
+    #[serde(rename = "login")]
+    credentials: Credentials,
+    /// Older versions persisted username and secret as a pair. Only the username is migrated.
+    #[serde(rename = "credentials", skip_serializing)]
+    legacy_credentials: (String, String),
+    /// Storage is not written to until the secret has been entered on startup or after switching
+    /// profiles
+    #[serde(skip)]
+    lock: Lock,
+    /// Secrets of profiles unlocked in this session, by profile name. Never persisted.
+    #[serde(skip)]
+    profile_secrets: BTreeMap<String, Zeroizing<String>>,
     /// The search filter
     filter: String,
-    /// How notes are displayed
-    viewmode: ViewMode,
-    always_on_top: bool,
-    /// How data is stored
-    storage_mode: StorageMode,
-
+    /// Recently used search filters, most recent first
+    recent_filters: Vec<String>,
+    /// Missing in state stored by older versions, which is migrated on start
+    #[serde(default = "Settings::unmigrated")]
+    settings: Settings,
+
+    /// Saved profiles by name
+    profiles: BTreeMap<String, Profile>,
+    /// The profile currently in use
+    active_profile: Option<String>,
+    /// Older versions only stored the storage mode per profile. Migrated into `profiles` on start.
+    #[serde(skip_serializing)]
     saved_profiles: BTreeMap<String, StorageMode>,
+    /// Edit buffer for renaming the active profile
+    #[serde(skip)]
+    profile_name: String,
+    #[serde(skip)]
+    pending_action: Option<PendingAction>,
+    /// A new profile starts once the storage confirmed saving the notes of the current one
+    #[serde(skip)]
+    new_profile_requested: bool,
+    /// The note shown on its own in focus mode. Everything else is hidden but left as it was.
+    #[serde(skip)]
+    focused_note: Option<u128>,
+    #[serde(skip)]
+    capture: Option<QuickCapture>,
+    /// The command palette, if open
+    #[serde(skip)]
+    palette: Option<CommandPalette>,
+    /// A shared bundle pasted in the settings, to import
+    #[serde(skip)]
+    bundle_text: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    capture_hotkey: Option<GlobalHotkey>,
+    /// The local notes file being watched for changes by other programs
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    watched_path: Option<PathBuf>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    file_watcher: Option<FileWatcher>,
+    /// Notes edited in windows of their own
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    note_windows: Vec<NoteWindow>,
+    /// Hash of the encrypted user data as last saved or loaded, to tell if there are unsaved edits
+    #[serde(skip)]
+    synced_hash: u64,
+    /// Whether the notes made it into the configured storage, by saving to or loading from it.
+    /// Until then they only live in the app state. Reset when another storage is chosen.
+    last_save_ok: bool,
+    /// The notes in the storage could not be loaded, so the next save must not make a backup
+    /// of them over the previous one
+    #[serde(skip)]
+    load_failed: bool,
+    /// When the notes were last written to the storage in this session
+    #[serde(skip)]
+    last_saved: Option<chrono::DateTime<chrono::Utc>>,
+    /// Notes that woke up from a snooze and the time they did, to highlight them for a moment
+    #[serde(skip)]
+    woken: HashMap<u128, f64>,
+    /// Notes picked to act on together, by clicking them with the command key held
+    #[serde(skip)]
+    selected_notes: BTreeSet<u128>,
+    /// The system asks for reduced motion, read at startup
+    #[serde(skip)]
+    system_reduced_motion: bool,
+    /// Lines written in the logbook before, to complete entries with. Built when the logbook is
+    /// shown and dropped when an entry is done being edited.
+    #[serde(skip)]
+    logbook_index: Option<LogbookIndex>,
+    /// Checks the spelling of the note being edited
+    #[cfg(feature = "spellcheck")]
+    #[serde(skip)]
+    spell_checker: Option<SpellChecker>,
+    /// The language a dictionary was last loaded for, or tried to
+    #[cfg(feature = "spellcheck")]
+    #[serde(skip)]
+    spell_language: Option<String>,
+    /// The dictionary for `spell_language` while it is loaded
+    #[cfg(feature = "spellcheck")]
+    #[serde(skip)]
+    dictionary_loading: Option<Receiver<anyhow::Result<Dictionary>>>,
+    /// The offer to set up the storage was put off for this session
+    #[serde(skip)]
+    storage_banner_dismissed: bool,
+    /// A note to open once the notes are loaded, from a deep link
+    #[serde(skip)]
+    requested_note: Option<u128>,
+    /// The note last shown in the URL
+    #[serde(skip)]
+    linked_note: Option<u128>,
+    /// The note open in the editor when focus mode was entered, reopened on exit
+    #[serde(skip)]
+    focus_return_note: Option<u128>,
+    /// Title edited in place after a double click
+    #[serde(skip)]
+    inline_edit: Option<InlineEdit>,
+    /// A clicked note and the click time. It is opened once the click can't become a double click.
+    #[serde(skip)]
+    pending_open: Option<(u128, f64)>,
+    /// The note expanded for editing in the list view
+    #[serde(skip)]
+    list_expanded: Option<u128>,
+    /// Heights of the rows of the list view as last drawn, to skip the rows out of view
+    #[serde(skip)]
+    list_row_heights: HashMap<Id, f32>,
+    #[serde(skip)]
+    undo: Option<UndoSnapshot>,
+    /// Undo/redo history of note changes
+    #[serde(skip)]
+    undo_stack: UndoStack,
+    /// The note edited in the main window in the last frame
+    #[serde(skip)]
+    edited_note: Option<u128>,
+    #[serde(skip)]
+    tag_usage: TagUsage,
     #[serde(skip)]
     toasts: Toasts,
     #[serde(skip)]
     channels: Channels,
+    /// All notes and their dependencies
+    #[serde(skip)]
+    dependency_graph: DependencyGraph,
+    /// The part of the graph around `graph_focus`, built again when the focus or the notes change
     #[serde(skip)]
-    graph: Option<Graph<String, (), Directed>>,
+    focus_graph: Option<NoteGraph>,
+    /// Graph node positions by note id, kept across rebuilds
+    graph_positions: BTreeMap<u128, Pos2>,
+    /// Only show this note and its dependencies in the graph
+    #[serde(skip)]
+    graph_focus: Option<u128>,
+    /// How many dependency steps from the focused note are shown in the graph
+    graph_depth: usize,
+    #[serde(skip)]
+    image_previews: ImagePreviews,
     ui_state: UiState,
 }
 
+/// Textures of the images linked in notes, by URL
+#[derive(Default)]
+struct ImagePreviews {
+    /// The loaded textures, or why loading failed. Failures are kept so they are not retried.
+    textures: BTreeMap<String, Result<egui::load::SizedTexture, String>>,
+    /// Mirrors the "Load remote images" setting
+    remote_enabled: bool,
+    /// Notes whose images were loaded for this session, although remote images are off
+    allowed_notes: HashSet<u128>,
+}
+
+impl ImagePreviews {
+    /// Whether images linked in `note` may be fetched
+    fn allowed(&self, note: &Note) -> bool {
+        self.remote_enabled || self.allowed_notes.contains(&note.id)
+    }
+
+    /// The texture of the image at `url`, starting to load it if needed. `None` while loading.
+    fn get(
+        &mut self,
+        ctx: &egui::Context,
+        url: &str,
+    ) -> Option<Result<egui::load::SizedTexture, String>> {
+        if let Some(texture) = self.textures.get(url) {
+            return Some(texture.clone());
+        }
+        let result = match ctx.try_load_texture(
+            url,
+            egui::TextureOptions::default(),
+            egui::SizeHint::default(),
+        ) {
+            Ok(egui::load::TexturePoll::Pending { .. }) => return None,
+            Ok(egui::load::TexturePoll::Ready { texture }) => Ok(texture),
+            Err(e) => Err(e.to_string()),
+        };
+        self.textures.insert(url.to_string(), result.clone());
+        Some(result)
+    }
+
+    /// Show a thumbnail of the image at `url` no larger than `max_size`, with a placeholder while
+    /// it loads or if it can't be loaded.
+    fn thumbnail(&mut self, ui: &mut Ui, url: &str, max_size: Vec2) -> Response {
+        match self.get(ui.ctx(), url) {
+            Some(Ok(texture)) => ui
+                .add(
+                    egui::Image::from_texture(texture)
+                        .max_size(max_size)
+                        .sense(Sense::click()),
+                )
+                .on_hover_text(url),
+            Some(Err(e)) => ui
+                .add(
+                    egui::Label::new(RichText::new(egui_phosphor::regular::IMAGE_BROKEN).size(32.))
+                        .sense(Sense::click()),
+                )
+                .on_hover_text(format!("{url}\nCould not load the image: {e}")),
+            None => ui.spinner().on_hover_text(url),
+        }
+    }
+}
+
 pub struct Channels {
-    pub userdata_channel: (Sender<UserData>, Receiver<UserData>),
+    /// The encrypted notes loaded from the storage, decrypted once they arrive
+    pub loaded_channel: (Sender<Loaded>, Receiver<Loaded>),
     pub id_channel: (Sender<String>, Receiver<String>),
     pub msg_channel: (Sender<Message>, Receiver<Message>),
+    /// Signals that the notes were written to the storage
+    pub saved_channel: (Sender<()>, Receiver<()>),
 }
 
 impl Default for Channels {
     fn default() -> Self {
         Self {
-            userdata_channel: channel(),
+            loaded_channel: channel(),
             id_channel: channel(),
             msg_channel: channel(),
+            saved_channel: channel(),
         }
     }
 }
@@ -126,25 +1167,66 @@ impl Message {
     }
 }
 
-impl MeteoraApp {
-    /// Called once before the first frame.
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let mut fonts = egui::FontDefinitions::default();
-        fonts.font_data.insert(
-            "inter".to_owned(),
-            FontData::from_static(include_bytes!("fonts/Inter-Regular.ttf")),
-        );
-        egui_extras::install_image_loaders(&cc.egui_ctx);
+/// Install the bundled fonts, preferring `custom_font` for proportional text if given.
+/// If the custom font can't be loaded, the bundled fonts are installed and the error is returned.
+fn install_fonts(ctx: &egui::Context, custom_font: Option<&Path>) -> anyhow::Result<()> {
+    let mut fonts = egui::FontDefinitions::default();
+    fonts.font_data.insert(
+        "inter".to_owned(),
+        FontData::from_static(include_bytes!("fonts/Inter-Regular.ttf")),
+    );
 
-        egui_phosphor::add_to_fonts(&mut fonts, egui_phosphor::Variant::Regular);
+    egui_phosphor::add_to_fonts(&mut fonts, egui_phosphor::Variant::Regular);
+
+    fonts
+        .families
+        .get_mut(&FontFamily::Proportional)
+        .unwrap()
+        .insert(0, "inter".to_owned());
+
+    let result = custom_font.map(load_font).transpose().map(|custom| {
+        if let Some(font) = custom {
+            fonts.font_data.insert("custom".to_owned(), font);
+            fonts
+                .families
+                .get_mut(&FontFamily::Proportional)
+                .unwrap()
+                .insert(0, "custom".to_owned());
+        }
+    });
 
-        fonts
-            .families
-            .get_mut(&FontFamily::Proportional)
-            .unwrap()
-            .insert(0, "inter".to_owned());
+    ctx.set_fonts(fonts);
+    result
+}
+
+/// Read and validate a font file.
+fn load_font(path: &Path) -> anyhow::Result<FontData> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use anyhow::Context;
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Could not read font {}", path.display()))?;
+        // egui panics on fonts it can't parse, so check first
+        ab_glyph::FontRef::try_from_slice(&bytes)
+            .with_context(|| format!("{} is not a valid font", path.display()))?;
+        Ok(FontData::from_owned(bytes))
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        anyhow::bail!("Can't load {} in the browser", path.display())
+    }
+}
+
+impl MeteoraApp {
+    /// Called once before the first frame.
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        egui_extras::install_image_loaders(&cc.egui_ctx);
+        _ = install_fonts(&cc.egui_ctx, None);
+        #[cfg(target_arch = "wasm32")]
+        if let Err(e) = listen_for_pasted_images(&cc.egui_ctx) {
+            warn!("Pasting images is not available: {e}");
+        }
 
-        cc.egui_ctx.set_fonts(fonts);
         let mut style = (*cc.egui_ctx.style()).clone();
         style.text_styles = [
             (
@@ -173,705 +1255,5368 @@ impl MeteoraApp {
         cc.egui_ctx.set_style(style);
 
         if let Some(storage) = cc.storage {
-            let s: Self = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
-            _ = s.storage_mode.load_userdata(&s.credentials, &s.channels);
+            let mut s: Self = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            if s.settings.version < SETTINGS_VERSION {
+                // older versions kept the settings in the app state, read them from there
+                s.settings = eframe::get_value::<LegacySettings>(storage, eframe::APP_KEY)
+                    .unwrap_or_default()
+                    .into();
+            }
+            s.migrate_profiles();
+            if let Err(e) = migrate(&mut s.userdata) {
+                // the notes are loaded from storage once unlocked, which refuses them as well
+                s.toasts.error(format!("{e}"));
+                s.userdata = Default::default();
+            }
+            s.notes_replaced();
+            s.lock = Lock::Startup;
+            if let Some(path) = &s.settings.appearance.custom_font {
+                if let Err(e) = install_fonts(&cc.egui_ctx, Some(path)) {
+                    s.toasts
+                        .warning(format!("{e:#}. Falling back to the default font."));
+                }
+            }
+            s.settings.appearance.ui_scale = s
+                .settings
+                .appearance
+                .ui_scale
+                .clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end());
+            cc.egui_ctx.set_zoom_factor(s.settings.appearance.ui_scale);
+            s.profile_name = s.active_profile.clone().unwrap_or_default();
+            s.requested_note = startup_note_link();
+            s.system_reduced_motion = prefers_reduced_motion();
+            s.start_native_integration(&cc.egui_ctx);
             return s;
         }
 
-        Default::default()
+        let mut s = Self {
+            lock: Lock::Startup,
+            requested_note: startup_note_link(),
+            system_reduced_motion: prefers_reduced_motion(),
+            ..Default::default()
+        };
+        s.start_native_integration(&cc.egui_ctx);
+        s
     }
-}
 
-impl eframe::App for MeteoraApp {
-    fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
-        {
-            if let Err(e) = self.storage_mode.save_userdata(
-                &self.userdata,
-                &self.credentials,
-                &self.channels,
-                false,
-            ) {
-                error!("{e}")
-            }
-        }
+    /// Whether motion is reduced, by the setting or by the system
+    fn reduce_motion(&self) -> bool {
+        self.settings.behavior.reduce_motion || self.system_reduced_motion
     }
 
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        use egui_phosphor::regular::*;
+    /// Whether the window is in the background and power is saved by only repainting on input
+    fn in_background(&self, ctx: &egui::Context) -> bool {
+        self.settings.behavior.save_power && !ctx.input(|i| i.focused)
+    }
 
-        #[cfg(feature = "demo")]
-        ctx.request_repaint();
+    /// Turn widget animations off while motion is reduced or the window is in the background,
+    /// as each animation keeps repainting until it is done.
+    fn apply_motion(&self, ctx: &egui::Context) {
+        let animation_time = if self.reduce_motion() || self.in_background(ctx) {
+            0.
+        } else {
+            egui::Style::default().animation_time
+        };
+        if ctx.style().animation_time != animation_time {
+            ctx.style_mut(|s| s.animation_time = animation_time);
+        }
+    }
 
-        if let Ok(id) = self.channels.id_channel.1.try_recv() {
-            self.credentials.0 = id.clone();
-            match &mut self.storage_mode {
-                StorageMode::Local { .. } => {}
-                StorageMode::JsonBin { bin_id, .. } => {
-                    *bin_id = Some(id);
-                    self.toasts.info("Registered JsonBin.".to_string());
+    /// Start loading the dictionary when the language changed, then check the spelling of note
+    /// `id` once its text stays the same for a moment.
+    #[cfg(feature = "spellcheck")]
+    fn check_spelling(&mut self, ctx: &egui::Context, id: u128) {
+        let behavior = &self.settings.behavior;
+        if !behavior.spellcheck {
+            return;
+        }
+        if self.spell_language.as_ref() != Some(&behavior.spell_language) {
+            self.spell_language = Some(behavior.spell_language.clone());
+            let dirs = dictionary_dirs(behavior.dictionary_dir.as_deref());
+            self.spell_checker = None;
+            self.dictionary_loading =
+                Some(load_dictionary(ctx, behavior.spell_language.clone(), dirs));
+        }
+        if let Some(loading) = &self.dictionary_loading {
+            match loading.try_recv() {
+                Ok(Ok(dictionary)) => self.spell_checker = Some(SpellChecker::new(dictionary)),
+                Ok(Err(e)) => {
+                    self.toasts.warning(format!("Spelling is not checked: {e}"));
                 }
+                Err(std::sync::mpsc::TryRecvError::Empty) => return,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {}
             }
+            self.dictionary_loading = None;
         }
-
-        if let Ok(userdata) = self.channels.userdata_channel.1.try_recv() {
-            self.userdata = userdata;
-        }
-        if let Ok(msg) = self.channels.msg_channel.1.try_recv() {
-            match msg {
-                Message::Info(t) => self.toasts.info(t),
-                Message::Warn(t) => self.toasts.warning(t),
-                Message::Err(t) => self.toasts.error(t),
-            };
+        let (Some(checker), Some(note)) = (&mut self.spell_checker, self.userdata.notes.get(&id))
+        else {
+            return;
+        };
+        let now = ctx.input(|i| i.time);
+        if let Some(wait) = checker.update(&note.text, &self.settings.behavior.spell_words, now) {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(wait));
         }
+    }
 
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            //    ui.allocate_exact_size(vec2(ui.available_width(), 30.), Sense::drag());
-            let padding = 1.;
-            ui.add_space(padding + 2.);
-
-            ui.horizontal(|ui| {
-                let not_settings = !self.ui_state.settings_enabled;
+    /// Apply the window level and register the quick capture hotkey.
+    fn start_native_integration(&mut self, ctx: &egui::Context) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if self.settings.behavior.always_on_top {
+                ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(
+                    egui::WindowLevel::AlwaysOnTop,
+                ));
+            }
+            self.register_capture_hotkey(ctx);
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = ctx;
+    }
 
-                ui.selectable_value(
-                    &mut self.ui_state.settings_enabled,
-                    not_settings,
-                    egui::RichText::new(if not_settings { LIST } else { X }).size(32.0),
-                );
-                ui.add(
-                    egui::TextEdit::singleline(&mut self.filter)
-                        .frame(false)
-                        .hint_text("🔍 Search notes..."),
-                );
-                if !self.filter.is_empty() {
-                    if bare_button(X, ui).clicked() {
-                        self.filter.clear();
-                    }
-                }
-            });
+    /// (Re-)register the configured quick capture hotkey
+    #[cfg(not(target_arch = "wasm32"))]
+    fn register_capture_hotkey(&mut self, ctx: &egui::Context) {
+        // the old registration has to go first, in case the shortcut is unchanged
+        self.capture_hotkey = None;
+        match GlobalHotkey::register(&self.settings.shortcuts.capture_hotkey, ctx) {
+            Ok(hotkey) => self.capture_hotkey = Some(hotkey),
+            Err(e) => {
+                self.toasts
+                    .warning(format!("Quick capture hotkey not available: {e:#}"));
+            }
+        }
+    }
 
-            ui.add_space(padding);
+    /// Edit the note `id` in a window of its own, instead of the editor in the main window
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_note_window(&mut self, id: u128) {
+        if self.note_windows.iter().any(|w| w.id == id) {
+            self.toasts.warning("This note is already open in a window");
+            return;
+        }
+        let Some(note) = self.userdata.notes.get(&id) else {
+            return;
+        };
+        self.note_windows.push(NoteWindow {
+            id,
+            before: note.clone(),
         });
+        if self.active_note == Some(id) {
+            self.active_note = None;
+        }
+    }
 
-        if self.ui_state.settings_enabled {
-            egui::SidePanel::left("side_panel_settings").show(ctx, |ui| {
-                let mut r = ui.scope(|ui| {
-                    ui.horizontal(|ui| {
-                        ui.heading(SLIDERS);
-                        ui.label("SETTINGS")
-                    });
-                    ui.separator();
-
-                    if ui.button("Save profile").clicked() {
-                        let key = match &self.storage_mode {
-                            StorageMode::Local { path } => path.to_string_lossy().to_string(),
-                            StorageMode::JsonBin { bin_id, .. } => {
-                                bin_id.clone().unwrap_or_default()
-                            }
-                        };
+    /// Show the notes opened in windows of their own
+    #[cfg(not(target_arch = "wasm32"))]
+    fn note_windows_ui(&mut self, ctx: &egui::Context) {
+        let viewport_id = |id: u128| egui::ViewportId::from_hash_of(("note_window", id));
+        // a note already open in a window is not edited in the main window as well
+        if let Some(id) = self
+            .active_note
+            .filter(|id| self.note_windows.iter().any(|w| w.id == *id))
+        {
+            self.active_note = None;
+            ctx.send_viewport_cmd_to(viewport_id(id), egui::ViewportCommand::Focus);
+        }
 
-                        self.saved_profiles.insert(key, self.storage_mode.clone());
+        let mut closed = vec![];
+        let ids = self.note_windows.iter().map(|w| w.id).collect::<Vec<_>>();
+        for id in ids {
+            let Some(title) = self
+                .userdata
+                .notes
+                .get(&id)
+                .map(|n| n.get_title().to_string())
+            else {
+                // deleted
+                closed.push(id);
+                continue;
+            };
+            ctx.show_viewport_immediate(
+                viewport_id(id),
+                egui::ViewportBuilder::default()
+                    .with_title(format!("{title} - Meteora"))
+                    .with_inner_size([500., 700.]),
+                |ctx, class| {
+                    if class == egui::ViewportClass::Embedded {
+                        // the platform has no multiple windows, show a regular window instead
+                        let mut open = true;
+                        egui::Window::new(&title)
+                            .id(Id::new(("note_window", id)))
+                            .open(&mut open)
+                            .show(ctx, |ui| self.note_window_editor(ui, id));
+                        if !open {
+                            closed.push(id);
+                        }
+                    } else {
+                        egui::CentralPanel::default().show(ctx, |ui| {
+                            egui::ScrollArea::vertical().show(ui, |ui| {
+                                self.note_window_editor(ui, id);
+                            });
+                        });
+                        if ctx.input(|i| i.viewport().close_requested()) {
+                            closed.push(id);
+                        }
                     }
+                },
+            );
+        }
 
-                    if ui.button("New profile").clicked() {
-                        _ = self.storage_mode.save_userdata(
-                            &self.userdata,
-                            &self.credentials,
-                            &self.channels,
-                            false,
-                        );
-                        self.storage_mode = Default::default();
-                        self.userdata = Default::default();
-                    }
-
-                    egui::ComboBox::from_label("Storage")
-                        .selected_text(format!("{:?}", self.storage_mode))
-                        .show_ui(ui, |ui| {
-                            ui.selectable_value(
-                                &mut self.storage_mode,
-                                StorageMode::Local {
-                                    path: PathBuf::from("backup.json"),
-                                },
-                                "Local",
-                            );
-                            // let k = env!("KEY", "");
-                            ui.selectable_value(
-                                &mut self.storage_mode,
-                                StorageMode::JsonBin {
-                                    masterkey: include_str!("../key.jsonbin.master").into(),
-                                    bin_id: None,
-                                },
-                                "JsonBin",
-                            );
-                        });
+        for id in closed {
+            let Some(i) = self.note_windows.iter().position(|w| w.id == id) else {
+                continue;
+            };
+            let window = self.note_windows.remove(i);
+            if let Some(note) = self.userdata.notes.get_mut(&id) {
+                record_text_revision(ctx, note);
+            }
+            if let Some(after) = self.userdata.notes.get(&id) {
+                self.undo_stack.push(NoteChange {
+                    id,
+                    before: Some(window.before),
+                    after: Some(after.clone()),
+                    ..Default::default()
+                });
+            }
+        }
+    }
 
-                    if !self.saved_profiles.is_empty() {
-                        egui::ComboBox::from_label("Saved profiles")
-                            .selected_text("Select saved profile")
-                            .show_ui(ui, |ui| {
-                                for (n, p) in &self.saved_profiles {
-                                    if ui.button(format!("{n}")).clicked() {
-                                        _ = self.storage_mode.save_userdata(
-                                            &self.userdata,
-                                            &self.credentials,
-                                            &self.channels,
-                                            false,
-                                        );
-                                        self.storage_mode = p.clone();
-
-                                        _ = self
-                                            .storage_mode
-                                            .load_userdata(&self.credentials, &self.channels);
-                                    }
-                                }
-                            });
-                    }
+    #[cfg(not(target_arch = "wasm32"))]
+    fn note_window_editor(&mut self, ui: &mut Ui, id: u128) {
+        let timer = self.timer_of(id);
+        let request = edit_note(
+            ui,
+            &id,
+            &mut self.userdata,
+            &mut self.pending_action,
+            &mut self.toasts,
+            &mut self.image_previews,
+            EditorOptions {
+                date_format: &self.settings.appearance.date_format,
+                highlight: &self.filter,
+                detachable: false,
+                timer,
+                #[cfg(feature = "spellcheck")]
+                spelling: None,
+            },
+        );
+        // edits in windows only reach the history once the window is closed
+        self.note_changed(id);
+        match request {
+            Some(EditorRequest::Focus) => {
+                self.focused_note = Some(id);
+                ui.ctx()
+                    .send_viewport_cmd_to(egui::ViewportId::ROOT, egui::ViewportCommand::Focus);
+            }
+            Some(EditorRequest::Open(other)) => self.open_note_window(other),
+            Some(EditorRequest::ToggleTimer) => self.toggle_timer(id),
+            #[cfg(feature = "spellcheck")]
+            Some(EditorRequest::AddToDictionary(word)) => {
+                self.settings.behavior.spell_words.insert(word);
+            }
+            Some(EditorRequest::Detach) | None => {}
+        }
+    }
 
-                    ui.horizontal(|ui| {
-                        if ui.button("SAVE").clicked() {
-                            _ = self.storage_mode.save_userdata(
-                                &self.userdata,
-                                &self.credentials,
-                                &self.channels,
-                                true,
-                            );
+    /// Watch the notes file while storing locally and load the notes again when another program
+    /// changed it. Unsaved edits are only discarded after confirmation.
+    fn poll_file_watcher(&mut self, ctx: &egui::Context) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = match &self.settings.sync.storage_mode {
+                StorageMode::Local(LocalStorage { path }) if !self.lock.is_locked() => {
+                    Some(path.clone())
+                }
+                _ => None,
+            };
+            if path != self.watched_path {
+                self.file_watcher = None;
+                if let Some(path) = &path {
+                    match FileWatcher::watch(path, ctx) {
+                        Ok(watcher) => self.file_watcher = Some(watcher),
+                        Err(e) => {
+                            self.toasts.warning(format!(
+                                "Changes to the notes file won't be noticed: {e:#}"
+                            ));
                         }
+                    }
+                }
+                self.watched_path = path;
+            }
+            if self.file_watcher.as_mut().is_some_and(|w| w.changed()) {
+                // encrypting the same notes gives the same blob
+                let unchanged = encrypt_userdata(&self.userdata, &self.credentials)
+                    .is_ok_and(|blob| blob_hash(&blob) == self.synced_hash);
+                if unchanged {
+                    self.reload_userdata();
+                } else {
+                    self.pending_action = Some(PendingAction::ReloadNotes);
+                }
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = ctx;
+    }
 
-                        if ui.button("RESTORE").clicked() {
-                            _ = self
-                                .storage_mode
-                                .load_userdata(&self.credentials, &self.channels);
-                        }
-                    });
+    /// Decrypt the notes loaded from the storage and replace the current ones with them
+    fn notes_loaded(&mut self, blob: &str) {
+        let mut userdata = match decrypt_notes(blob, &self.credentials) {
+            Ok(userdata) => userdata,
+            Err(e) => {
+                self.toasts.error(format!("Could not load notes: {e}"));
+                self.load_failed = true;
+                self.offer_backup();
+                return;
+            }
+        };
+        _ = self.channels.msg_channel.0.send(Message::Info(format!(
+            "Loaded {} notes",
+            userdata.notes.len()
+        )));
+        let fixes = userdata.sanitize();
+        if !fixes.is_empty() {
+            _ = self.channels.msg_channel.0.send(Message::warn(&format!(
+                "Repaired notes: {}",
+                fixes.join(", ")
+            )));
+        }
+        self.userdata = userdata;
+        self.notes_replaced();
+        self.lock = Lock::Unlocked;
+        self.last_save_ok = true;
+        self.load_failed = false;
+        // repairs and migrations only count as changes once saved
+        self.record_sync(blob);
+        self.record_profile_sync();
+        // history refers to the replaced notes
+        self.undo_stack = Default::default();
+    }
 
-                    ui.separator();
+    /// Offer to load the backup of the storage instead of notes that could not be read, if it
+    /// can be decrypted. With a wrong secret neither can, so nothing is offered.
+    fn offer_backup(&mut self) {
+        match self.settings.sync.storage_mode.backend().read_backup() {
+            Ok(Some(blob)) if decrypt_notes(&blob, &self.credentials).is_ok() => {
+                self.pending_action = Some(PendingAction::LoadBackup(blob));
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Could not read the backup: {e:#}"),
+        }
+    }
 
-                    ui.horizontal(|ui| {
-                        ui.add(
-                            egui::TextEdit::singleline(&mut self.credentials.0)
-                                .hint_text("Username")
-                                .desired_width(150.),
-                        );
-                        ui.label("USER");
-                    });
-                    ui.horizontal(|ui| {
-                        ui.add(
-                            egui::TextEdit::singleline(&mut self.credentials.1)
-                                .hint_text("Encryption Key")
-                                .desired_width(150.)
-                                .password(true),
-                        );
-                        ui.label("SECRET");
-                    });
+    /// Keep the current notes when the storage has none yet, as on the first start. They are
+    /// saved there with the next save.
+    fn nothing_loaded(&mut self) {
+        _ = self.channels.msg_channel.0.send(Message::Info(format!(
+            "No notes saved in {:?} yet",
+            self.settings.sync.storage_mode
+        )));
+        self.lock = Lock::Unlocked;
+        self.last_save_ok = false;
+        self.load_failed = false;
+    }
 
-                    egui::ComboBox::from_label("View")
-                        .selected_text(format!("{:?}", self.viewmode))
-                        .show_ui(ui, |ui| {
-                            ui.selectable_value(&mut self.viewmode, ViewMode::Board, "Board");
-                            ui.selectable_value(&mut self.viewmode, ViewMode::List, "List");
-                            ui.selectable_value(&mut self.viewmode, ViewMode::Graph, "Graph");
-                        });
+    fn reload_userdata(&mut self) {
+        if let Err(e) = self
+            .settings
+            .sync
+            .storage_mode
+            .load_userdata(&self.channels)
+        {
+            self.toasts.error(format!("Could not load notes: {e}"));
+            self.load_failed = true;
+        }
+    }
 
-                    match &mut self.storage_mode {
-                        StorageMode::Local { path } => {
-                            let mut s = path.to_string_lossy().to_string();
-                            if ui.text_edit_singleline(&mut s).changed() {
-                                *path = PathBuf::from(s);
-                            }
-                        }
-                        StorageMode::JsonBin {
-                            masterkey: _,
-                            bin_id,
-                        } => {
-                            if bin_id.is_none() {
-                                ui.label("Your data has never been published.");
-
-                                if ui.button("Restore from username").clicked() {
-                                    *bin_id = Some(self.credentials.0.clone());
-
-                                    _ = self
-                                        .storage_mode
-                                        .load_userdata(&self.credentials, &self.channels);
-                                }
+    /// Open the quick capture window when the global hotkey is pressed,
+    /// bringing the app to the front.
+    fn poll_capture_hotkey(&mut self, ctx: &egui::Context) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.capture_hotkey.as_ref().is_some_and(|h| h.pressed()) {
+            // the separate capture window shows up on its own, as long as the app is not
+            // minimized, which stops it from updating
+            let minimized = ctx.input(|i| i.viewport().minimized.unwrap_or(false));
+            let raise = !self.settings.behavior.capture_on_top || minimized;
+            if raise {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            }
+            if self.capture.is_none() {
+                self.capture = Some(QuickCapture {
+                    tags: self.active_tags.clone(),
+                    from_hotkey: raise,
+                    ..Default::default()
+                });
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = ctx;
+    }
 
-                                if ui.button("Publish as new").clicked() {
-                                    if let Err(e) = self.storage_mode.save_userdata(
-                                        &self.userdata,
-                                        &self.credentials,
-                                        &self.channels,
-                                        true,
-                                    ) {
-                                        self.toasts.error(format!("Error publishing notes! {e}"));
-                                    }
-                                }
-                            } else {
-                                ui.label(format!("Bin ID: {}", bin_id.clone().unwrap_or_default()));
-                                if ui.button("Copy to clipboard").clicked() {
-                                    ui.output_mut(|o| {
-                                        o.copied_text = bin_id.clone().unwrap_or_default()
-                                    });
-                                }
-                            }
-                        }
+    /// A small window to write a note. Enter adds it, Shift+Enter starts a new line, Esc cancels.
+    fn capture_ui(&mut self, ctx: &egui::Context) {
+        let Some(capture) = &mut self.capture else {
+            return;
+        };
+        // a window of its own, which can stay above other apps without pinning the whole app
+        let separate = cfg!(not(target_arch = "wasm32")) && self.settings.behavior.capture_on_top;
+        let outcome = if separate {
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of("quick_capture"),
+                egui::ViewportBuilder::default()
+                    .with_title("Quick capture")
+                    .with_inner_size([440., 200.])
+                    .with_always_on_top(),
+                |ctx, class| {
+                    if class == egui::ViewportClass::Embedded {
+                        return capture_window(ctx, capture, &self.active_tags);
+                    }
+                    let outcome = egui::CentralPanel::default()
+                        .show(ctx, |ui| capture_form(ui, capture, &self.active_tags))
+                        .inner;
+                    if ctx.input(|i| i.viewport().close_requested()) {
+                        Some(false)
+                    } else {
+                        outcome
                     }
+                },
+            )
+        } else {
+            capture_window(ctx, capture, &self.active_tags)
+        };
+
+        let Some(submit) = outcome else {
+            return;
+        };
+        let Some(capture) = self.capture.take() else {
+            return;
+        };
+        if submit && !capture.text.trim().is_empty() {
+            let note = Note {
+                text: capture.text.trim().to_string(),
+                ..Note::new()
+            };
+            let id = self.userdata.add_note(note);
+            for tag in &capture.tags {
+                self.userdata.tag_note(id, tag);
+            }
+            self.undo_stack.push(NoteChange {
+                id,
+                before: None,
+                after: self.userdata.notes.get(&id).cloned(),
+                ..Default::default()
+            });
+            self.toasts.info("Note added");
+        }
+        // hand the focus back to the app that was in use
+        if capture.from_hotkey {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+        }
+    }
 
-                    global_dark_light_mode_buttons(ui);
+    /// Move profiles and credentials saved by older versions into their current place.
+    /// Secrets stored by older versions are dropped.
+    fn migrate_profiles(&mut self) {
+        for (name, storage_mode) in std::mem::take(&mut self.saved_profiles) {
+            self.profiles.entry(name).or_insert(Profile {
+                storage_mode,
+                ..Default::default()
+            });
+        }
+        for profile in self.profiles.values_mut() {
+            let (username, _) = std::mem::take(&mut profile.legacy_credentials);
+            if profile.username.is_empty() {
+                profile.username = username;
+            }
+        }
+        let (username, _) = std::mem::take(&mut self.legacy_credentials);
+        if self.credentials.username.is_empty() {
+            self.credentials.username = username;
+        }
+    }
 
-                    ui.checkbox(&mut self.ui_state.scratchpad_enabled, "Scratchpad");
-                    ui.checkbox(&mut self.ui_state.tags_enabled, "Tags");
+    /// Capture the current settings as a profile.
+    fn current_profile(&self) -> Profile {
+        Profile {
+            storage_mode: self.settings.sync.storage_mode.clone(),
+            username: self.credentials.username.clone(),
+            viewmode: self.settings.appearance.viewmode.clone(),
+            active_tags: self.active_tags.clone(),
+            ..Default::default()
+        }
+    }
 
-                    #[cfg(not(target_arch = "wasm32"))]
-                    ui.checkbox(&mut self.always_on_top, "Always on top");
+    /// Write the current settings back into the active profile, if there is one.
+    fn update_active_profile(&mut self) {
+        let profile = self.current_profile();
+        if let Some(existing) = self
+            .active_profile
+            .as_ref()
+            .and_then(|name| self.profiles.get_mut(name))
+        {
+            *existing = Profile {
+                note_count: existing.note_count,
+                last_sync: existing.last_sync,
+                ..profile
+            };
+        }
+    }
 
-                    #[cfg(debug_assertions)]
-                    {
-                        if ui.button("restore from debug save").clicked() {
-                            let userdata: UserData =
-                                serde_json::from_reader(std::fs::File::open("debug.json").unwrap())
-                                    .unwrap();
-                            self.userdata = userdata;
-                        }
-                        if ui.button("save to debug file").clicked() {
-                            let _ = serde_json::to_writer_pretty(
-                                std::fs::File::create("debug.json").unwrap(),
-                                &self.userdata,
-                            )
-                            .unwrap();
-                        }
-                    }
+    /// Note that the data of the active profile was just saved or loaded as `blob`.
+    fn record_sync(&mut self, blob: &str) {
+        self.synced_hash = blob_hash(blob);
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(watcher) = &mut self.file_watcher {
+            watcher.mark_known();
+        }
+    }
 
-                    ui.add_space(ui.available_height());
-                });
-                r.response.sense = Sense::click_and_drag();
+    /// Note the time the data of the active profile was loaded, or confirmed as saved.
+    fn record_profile_sync(&mut self) {
+        if let Some(profile) = self
+            .active_profile
+            .as_ref()
+            .and_then(|name| self.profiles.get_mut(name))
+        {
+            profile.note_count = self.userdata.notes.len();
+            profile.last_sync = Some(chrono::Utc::now());
+        }
+    }
 
-                if r.response.clicked_elsewhere() {
-                    let pos = ui.ctx().input(|r| r.pointer.hover_pos());
-                    if let Some(pos) = pos {
-                        if pos.x > 50. || pos.y > 50. {
-                            self.ui_state.settings_enabled = false;
+    /// Switch the storage to a JsonBin that still needs to be configured in the settings.
+    fn set_up_jsonbin(&mut self) {
+        self.settings.sync.storage_mode = StorageMode::JsonBin(JsonBinStorage {
+            masterkey: String::new(),
+            bin_id: None,
+            backup_path: None,
+        });
+        self.last_save_ok = false;
+        self.toasts
+            .error("No JsonBin master key configured. Enter it below.");
+    }
+
+    /// Offer to set up the storage while the notes only live in the app state, which is the case
+    /// for new users until the first save.
+    fn storage_banner_ui(&mut self, ctx: &egui::Context) {
+        if self.lock.is_locked()
+            || self.last_save_ok
+            || self.storage_banner_dismissed
+            || self.userdata.notes.is_empty()
+        {
+            return;
+        }
+        egui::TopBottomPanel::top("storage_banner").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(format!(
+                    "{} Your notes are only kept by the app so far, not in {:?}.",
+                    egui_phosphor::regular::WARNING,
+                    self.settings.sync.storage_mode
+                ));
+                if ui.button("Save now").clicked() {
+                    if let Err(e) = self.save_userdata(true) {
+                        self.toasts.error(format!("Could not save notes: {e}"));
+                    }
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button("Save to file…").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .set_file_name("meteora.json")
+                        .save_file()
+                    {
+                        self.settings.sync.storage_mode = StorageMode::Local(LocalStorage { path });
+                        if let Err(e) = self.save_userdata(true) {
+                            self.toasts.error(format!("Could not save notes: {e}"));
                         }
                     }
                 }
+                if ui.button("Set up JsonBin").clicked() {
+                    self.set_up_jsonbin();
+                    self.ui_state.settings_enabled = true;
+                    self.ui_state.settings_tab = SettingsTab::Sync;
+                }
+                if ui.button("Later").clicked() {
+                    self.storage_banner_dismissed = true;
+                }
             });
-        }
+        });
+    }
 
-        egui::SidePanel::left("scratchpad")
-            .width_range(0.0..=1000.)
+    /// Save the user data, unless the secret has not been entered yet.
+    fn save_userdata(&mut self, manual_save: bool) -> anyhow::Result<()> {
+        self.lock.ensure_unlocked()?;
+        // keep the contents of attachments that are in use or may come back through undo
+        let userdata = &mut self.userdata;
+        userdata.attachments.retain_used(
+            userdata
+                .notes
+                .values()
+                .chain(userdata.logbook.values().flatten())
+                .chain(self.undo_stack.notes())
+                .chain(self.undo.iter().flat_map(|u| {
+                    u.userdata
+                        .notes
+                        .values()
+                        .chain(u.userdata.logbook.values().flatten())
+                })),
+        );
+        let storage = &mut self.settings.sync.storage_mode;
+        let blob = if self.load_failed {
+            storage.replace_userdata(
+                &self.userdata,
+                &self.credentials,
+                &self.channels,
+                manual_save,
+            )
+        } else {
+            storage.save_userdata(
+                &self.userdata,
+                &self.credentials,
+                &self.channels,
+                manual_save,
+            )
+        }?;
+        self.load_failed = false;
+        self.record_sync(&blob);
+        Ok(())
+    }
+
+    /// Ask for the secret while storage is locked and load the notes with it.
+    fn unlock_ui(&mut self, ctx: &egui::Context) {
+        if !self.lock.is_locked() {
+            return;
+        }
+        let mut unlock = false;
+        let mut skip = false;
+        egui::Window::new("Unlock")
+            .collapsible(false)
+            .resizable(false)
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
             .show(ctx, |ui| {
+                ui.label(format!(
+                    "Enter the secret for {:?} to load your notes.",
+                    self.settings.sync.storage_mode
+                ));
+                let r = ui.add(
+                    egui::TextEdit::singleline(&mut *self.credentials.secret)
+                        .hint_text("Encryption Key")
+                        .password(true),
+                );
+                if !r.has_focus() && ctx.memory(|m| m.focused().is_none()) {
+                    r.request_focus();
+                }
+                unlock = r.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                // the master key is not stored, it is needed again after a restart
+                if let StorageMode::JsonBin(JsonBinStorage { masterkey, .. }) =
+                    &mut self.settings.sync.storage_mode
+                {
+                    let r = ui.add(
+                        egui::TextEdit::singleline(masterkey)
+                            .hint_text("JsonBin master key")
+                            .password(true),
+                    );
+                    unlock |= r.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                }
                 ui.horizontal(|ui| {
-                    if bare_button_sized(NOTEPAD, 20., ui).clicked() {
-                        self.ui_state.scratchpad_enabled = !self.ui_state.scratchpad_enabled;
-                    }
-
-                    if !self.ui_state.scratchpad_enabled {
-                        return;
+                    unlock |= ui.button("Unlock").clicked();
+                    // after switching profiles the notes shown are not the ones of this profile
+                    if self.lock.may_keep_notes() {
+                        skip = ui
+                            .button("Keep current notes")
+                            .on_hover_text("Don't load, overwrite storage with the notes shown")
+                            .clicked();
                     }
-                    ui.label("SCRATCH");
                 });
-                if !self.ui_state.scratchpad_enabled {
-                    return;
-                }
-                ui.separator();
-                if bare_button(FILE_PLUS, ui).clicked() {
-                    self.userdata.scratchpad.sections.push("".into());
-                }
+            });
+        if unlock {
+            // the lock is lifted once the notes have been decrypted
+            if let Err(e) = self
+                .settings
+                .sync
+                .storage_mode
+                .load_userdata(&self.channels)
+            {
+                self.toasts.error(format!("Could not load notes: {e}"));
+            }
+        }
+        if skip {
+            if let Err(e) = self.lock.keep_notes() {
+                self.toasts.error(format!("{e}"));
+            }
+        }
+    }
 
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    let mut remove: Option<usize> = None;
-                    let mut i = 0;
-                    dnd(ui, "dnd_example").show_vec(
-                        &mut self.userdata.scratchpad.sections,
-                        |ui, item, handle, state| {
-                            ui.horizontal(|ui| {
-                                egui::CollapsingHeader::new(
-                                    item.lines().next().unwrap_or("New scratch".into()),
-                                )
-                                .id_source(i)
-                                .show_unindented(ui, |ui| {
-                                    ui.indent(i, |ui| {
-                                        ui.style_mut().visuals.selection.stroke = Stroke::NONE;
-                                        egui::TextEdit::multiline(item)
-                                            .desired_width(f32::INFINITY)
-                                            .hint_text("Enter some quick thoughts here!")
-                                            .show(ui);
-                                        ui.horizontal(|ui| {
-                                            if bare_button(NOTE, ui)
-                                                .on_hover_text("Turn into note")
-                                                .clicked()
-                                            {
-                                                let mut n = Note::new();
-                                                n.text = item.clone();
-                                                self.userdata.notes.insert(n.id, n);
-                                                remove = Some(i);
-                                            }
+    /// Save the current profile and its data, then load the profile `name` and apply its settings.
+    /// Nothing changes if the data could not be saved.
+    fn switch_profile(&mut self, name: &str) {
+        let Some(target) = self.profiles.get(name).cloned() else {
+            return;
+        };
+        self.update_active_profile();
+        // a locked profile has no notes loaded that could be lost
+        if !self.lock.is_locked() {
+            if let Err(e) = self.save_userdata(false) {
+                self.toasts.error(format!(
+                    "Could not save notes, staying on this profile: {e}"
+                ));
+                return;
+            }
+        }
+        if let Some(current) = &self.active_profile {
+            if !self.lock.is_locked() {
+                self.profile_secrets
+                    .insert(current.clone(), self.credentials.secret.clone());
+            }
+        }
 
-                                            if bare_button(TRASH, ui)
-                                                .on_hover_text("Delete")
-                                                .clicked()
-                                            {
-                                                remove = Some(i);
-                                            }
-                                        });
-                                    });
-                                });
-                                ui.add_space(ui.available_width() - 30.);
-                                handle.ui(ui, |ui| {
-                                    ui.label(DOTS_SIX_VERTICAL);
-                                });
-                            });
-                            i += 1;
-                        },
-                    );
+        self.settings.sync.storage_mode = target.storage_mode;
+        // set again once the notes of the profile are loaded
+        self.last_save_ok = false;
+        self.credentials = Credentials {
+            username: target.username,
+            secret: self.profile_secrets.get(name).cloned().unwrap_or_default(),
+        };
+        self.settings.appearance.viewmode = target.viewmode;
+        self.active_tags = target.active_tags;
+        self.active_profile = Some(name.to_string());
+        self.profile_name = name.to_string();
+        // stays locked until the notes of the new profile have been decrypted, without showing
+        // the notes of the previous one meanwhile
+        self.lock = Lock::ProfileSwitch;
+        self.userdata = Default::default();
+        self.notes_replaced();
+        self.undo_stack = Default::default();
+        self.undo = None;
+
+        if !self.credentials.secret.is_empty() {
+            if let Err(e) = self
+                .settings
+                .sync
+                .storage_mode
+                .load_userdata(&self.channels)
+            {
+                self.toasts
+                    .error(format!("Could not load notes of '{name}': {e}"));
+            }
+        }
+    }
 
-                    if let Some(remove) = remove {
-                        self.userdata.scratchpad.sections.remove(remove);
-                    }
-                });
-            });
+    /// Replace the current profile by an empty default one, once its notes are saved.
+    fn start_new_profile(&mut self) {
+        self.new_profile_requested = false;
+        self.settings.sync.storage_mode = Default::default();
+        self.last_save_ok = false;
+        self.userdata = Default::default();
+        self.notes_replaced();
+        self.active_profile = None;
+        self.profile_name.clear();
+        self.undo = None;
+    }
 
-        egui::SidePanel::left("logbook")
-            .width_range(0.0..=1000.)
-            .show(ctx, |ui| {
-                ui.horizontal(|ui| {
-                    if bare_button_sized(NOTEBOOK, 20., ui).clicked() {
-                        self.ui_state.logbook_enabled = !self.ui_state.logbook_enabled;
-                    }
+    /// Remove `tag` from all notes, keeping the tag usage up to date. Returns the number of notes
+    /// that had it.
+    fn remove_tag_from_all(&mut self, tag: &str) -> usize {
+        let changed = remove_tag_from_all(&mut self.userdata.notes, tag);
+        for id in &changed {
+            self.note_changed(*id);
+        }
+        changed.len()
+    }
 
-                    if !self.ui_state.logbook_enabled {
-                        return;
-                    }
-                    ui.label("LOGBOOK");
-                });
-                if !self.ui_state.logbook_enabled {
-                    return;
+    /// Ask for confirmation of `pending_action` and apply it if confirmed.
+    fn confirm_pending_action(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.pending_action.clone() else {
+            return;
+        };
+        if !action.needs_confirmation() {
+            self.pending_action = None;
+            self.apply_action(action, ctx.input(|i| i.time));
+            return;
+        }
+        let description = match &action {
+            PendingAction::DeleteNote(id) => format!(
+                "Delete note '{}'?",
+                self.userdata
+                    .notes
+                    .get(id)
+                    .map(|n| n.get_title())
+                    .unwrap_or_default()
+            ),
+            PendingAction::DeleteTag(tag) => {
+                match self.tag_usage.count(tag) {
+                    0 => format!("Delete tag '{tag}'? No notes use it."),
+                    1 => format!("Remove tag '{tag}' from 1 note and delete it?"),
+                    n => format!("Remove tag '{tag}' from {n} notes and delete it?"),
                 }
-                ui.separator();
-                let current_date = chrono::Utc::now().date_naive();
-
-                if !self.userdata.logbook.contains_key(&current_date) {
-                    self.userdata
-                        .logbook
-                        .insert(current_date, vec![Note::new()]);
+            }
+            PendingAction::SelectTagged(tag) => {
+                match notes_tagged(&self.userdata.notes, tag).len() {
+                    0 => format!("No notes are tagged '{tag}'. Clear the selection?"),
+                    1 => format!("Select the note tagged '{tag}'?"),
+                    n => format!("Select the {n} notes tagged '{tag}'?"),
                 }
+            }
+            PendingAction::AddTagWhereTagged { tag, added } => {
+                let count = notes_tagged(&self.userdata.notes, tag)
+                    .iter()
+                    .filter(|id| !self.userdata.notes[id].tags.contains(added))
+                    .count();
+                match count {
+                    0 => format!("All notes tagged '{tag}' are tagged '{added}' already."),
+                    1 => format!("Add tag '{added}' to 1 note tagged '{tag}'?"),
+                    n => format!("Add tag '{added}' to {n} notes tagged '{tag}'?"),
+                }
+            }
+            PendingAction::RemoveTagFromAll(tag) => match self.tag_usage.count(tag) {
+                0 => format!("No notes are tagged '{tag}'."),
+                1 => format!("Remove tag '{tag}' from 1 note? The tag is kept."),
+                n => format!("Remove tag '{tag}' from {n} notes? The tag is kept."),
+            },
+            PendingAction::NewProfile => {
+                "Start a new profile? Your notes are saved first, then replaced by an empty profile."
+                    .to_string()
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            PendingAction::ReloadNotes => {
+                "The notes file was changed by another program. Load it and discard your unsaved edits?"
+                    .to_string()
+            }
+            PendingAction::RestoreNotes => format!(
+                "Load the notes from {:?} again and discard your unsaved edits?",
+                self.settings.sync.storage_mode
+            ),
+            #[cfg(any(debug_assertions, feature = "demo"))]
+            PendingAction::LoadSampleData(_) => {
+                "Replace all notes, the logbook and the scratchpad by sample data?".to_string()
+            }
+            PendingAction::ExportToStorage => format!(
+                "Write the {} notes shown here to {:?}, replacing what is stored there?",
+                self.userdata.notes.len(),
+                self.settings.sync.storage_mode
+            ),
+            PendingAction::LoadBackup(_) => format!(
+                "The notes in {:?} could not be read, they may be damaged. Load the backup of \
+                 the save before instead?",
+                self.settings.sync.storage_mode
+            ),
+            PendingAction::MoveToScratchpad(_) | PendingAction::PromoteLogItem(..) => {
+                return;
+            }
+        };
 
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    for (d, items) in self.userdata.logbook.iter_mut() {
-                        egui::CollapsingHeader::new(format!("{}", d))
-                            .default_open(d == &current_date)
-                            .show_unindented(ui, |ui| {
-                                if bare_button(FILE_PLUS, ui).clicked() {
-                                    items.push(Note::new());
-                                }
-                                let mut remove: Option<usize> = None;
-
-                                for (i, item) in items.iter_mut().enumerate() {
-                                    ui.horizontal(|ui| {
-                                    egui::TextEdit::multiline(&mut item.text).show(ui);
-                                        tag_ui(ui, item, &mut self.userdata.tags);
-                                        if ui.button("del").clicked() {
-                                            remove = Some(i);
-                                        }
-                                    });
-                                }
-                                if let Some(r) = remove {
-                                    items.remove(r);
-                                }
-
-                                ui.collapsing("Summary", |ui| {
-                                    let mut text = String::default();
-
-                                    for n in items.iter() {
-                                        text.push_str(&format!("\n- {}", n.text));
-                                    }
+        match confirm_dialog(ctx, &description) {
+            Some(true) => {
+                self.pending_action = None;
+                self.apply_action(action, ctx.input(|i| i.time));
+            }
+            Some(false) => self.pending_action = None,
+            None => {}
+        }
+    }
 
-                                    ui.label(text);
-                                });
-                            });
+    /// Perform a (confirmed) action, keeping a snapshot to undo it where possible.
+    fn apply_action(&mut self, action: PendingAction, now: f64) {
+        let snapshot = self.userdata.clone();
+        let active_tags = self.active_tags.clone();
+        let description = match action {
+            PendingAction::DeleteNote(id) => {
+                // commit pending edits first, so they are undone after the deletion
+                self.undo_stack.flush();
+                let dependents = backlinks(id, &self.userdata.notes);
+                let Some(note) = self.userdata.delete_note(id) else {
+                    return;
+                };
+                let description = format!("Deleted '{}'", note.get_title());
+                self.undo_stack.push(NoteChange {
+                    id,
+                    before: Some(note),
+                    after: None,
+                    dependents,
+                });
+                description
+            }
+            PendingAction::DeleteTag(tag) => {
+                self.remove_tag_from_all(&tag);
+                self.userdata.tags.retain(|t| t != &tag);
+                self.active_tags.remove(&tag);
+                format!("Deleted tag '{tag}'")
+            }
+            PendingAction::SelectTagged(tag) => {
+                self.selected_notes = notes_tagged(&self.userdata.notes, &tag)
+                    .into_iter()
+                    .collect();
+                self.toasts.info(match self.selected_notes.len() {
+                    1 => "Selected 1 note".to_string(),
+                    n => format!("Selected {n} notes"),
+                });
+                return;
+            }
+            PendingAction::AddTagWhereTagged { tag, added } => {
+                let changed = add_tag_where_tagged(&mut self.userdata.notes, &tag, &added);
+                for id in &changed {
+                    self.note_changed(*id);
+                }
+                if !self.userdata.tags.contains(&added) {
+                    self.userdata.tags.push(added.clone());
+                }
+                let description = match changed.len() {
+                    1 => format!("Added tag '{added}' to 1 note"),
+                    n => format!("Added tag '{added}' to {n} notes"),
+                };
+                self.toasts.info(description.clone());
+                description
+            }
+            PendingAction::RemoveTagFromAll(tag) => {
+                let changed = self.remove_tag_from_all(&tag);
+                let description = match changed {
+                    1 => format!("Removed tag '{tag}' from 1 note"),
+                    n => format!("Removed tag '{tag}' from {n} notes"),
+                };
+                self.toasts.info(description.clone());
+                description
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            PendingAction::ReloadNotes => {
+                self.reload_userdata();
+                return;
+            }
+            PendingAction::RestoreNotes => {
+                self.reload_userdata();
+                return;
+            }
+            PendingAction::ExportToStorage => {
+                if self.credentials.secret.is_empty() {
+                    self.toasts
+                        .error("Enter the secret to encrypt the notes with first.");
+                    return;
+                }
+                // the notes shown replace whatever could not be loaded
+                if let Err(e) = self.lock.keep_notes() {
+                    self.toasts.error(format!("{e}"));
+                    return;
+                }
+                if let Err(e) = self.save_userdata(true) {
+                    self.toasts.error(format!("Could not save notes: {e}"));
+                }
+                return;
+            }
+            PendingAction::LoadBackup(blob) => {
+                // the next save replaces the damaged notes
+                self.notes_loaded(&blob);
+                return;
+            }
+            #[cfg(any(debug_assertions, feature = "demo"))]
+            PendingAction::LoadSampleData(seed) => {
+                self.undo_stack.flush();
+                self.userdata = UserData::sample(seed);
+                self.notes_replaced();
+                self.undo_stack = Default::default();
+                self.active_note = None;
+                self.active_tags.clear();
+                "Loaded sample data".to_string()
+            }
+            PendingAction::NewProfile => {
+                self.update_active_profile();
+                if let Err(e) = self.save_userdata(false) {
+                    self.toasts
+                        .error(format!("Could not save notes, keeping them: {e}"));
+                    return;
+                }
+                // JsonBin saves in the background, it may still fail
+                self.new_profile_requested = true;
+                return;
+            }
+            PendingAction::MoveToScratchpad(id) => {
+                self.undo_stack.flush();
+                let dependents = backlinks(id, &self.userdata.notes);
+                let Some(note) = self.userdata.delete_note(id) else {
+                    return;
+                };
+                let description = format!("Moved '{}' to the scratchpad", note.get_title());
+                self.userdata
+                    .scratchpad
+                    .items
+                    .push(ScratchSection::from_text(&note.text));
+                if self.active_note == Some(id) {
+                    self.active_note = None;
+                }
+                self.undo_stack.push(NoteChange {
+                    id,
+                    before: Some(note),
+                    after: None,
+                    dependents,
+                });
+                description
+            }
+            PendingAction::PromoteLogItem(date, index) => {
+                let Some(items) = self.userdata.logbook.get_mut(&date) else {
+                    return;
+                };
+                if index >= items.len() {
+                    return;
+                }
+                let item = items.remove(index);
+                if items.is_empty() {
+                    self.userdata.logbook.remove(&date);
+                }
+                self.logbook_index = None;
+                for tag in &item.tags {
+                    if !self.userdata.tags.contains(tag) {
+                        self.userdata.tags.push(tag.clone());
                     }
+                }
+                let note = Note {
+                    text: item.text,
+                    tags: item.tags,
+                    ..Note::new()
+                };
+                let description = format!("Promoted '{}' to a note", note.get_title());
+                let id = self.userdata.add_note(note);
+                self.undo_stack.push(NoteChange {
+                    id,
+                    before: None,
+                    after: self.userdata.notes.get(&id).cloned(),
+                    ..Default::default()
+                });
+                description
+            }
+        };
+        self.undo = Some(UndoSnapshot {
+            description,
+            userdata: snapshot,
+            active_tags,
+            expires: now + UNDO_TIMEOUT,
+        });
+    }
+
+    /// Show the undo offer for the last destructive action, if any.
+    fn undo_ui(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        let Some(undo) = &self.undo else {
+            return;
+        };
+        if now > undo.expires {
+            self.undo = None;
+            return;
+        }
+        let mut restore = false;
+        egui::Area::new(Id::new("undo_toast"))
+            .anchor(egui::Align2::CENTER_BOTTOM, vec2(0., -20.))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(&undo.description);
+                        restore = ui.button("Undo").clicked();
+                    });
                 });
             });
+        if restore {
+            if let Some(undo) = self.undo.take() {
+                self.userdata = undo.userdata;
+                self.active_tags = undo.active_tags;
+                self.notes_replaced();
+                self.toasts.info("Restored.");
+            }
+        } else {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(undo.expires - now));
+        }
+    }
 
-        egui::SidePanel::left("side_panel")
-            .width_range(0.0..=1000.)
+    /// Show the weekly summary window, if open.
+    fn weekly_summary_ui(&mut self, ctx: &egui::Context) {
+        let Some(start) = self.ui_state.summary_week else {
+            return;
+        };
+        let end = start + chrono::Days::new(6);
+        let summary = weekly_summary(&self.userdata, start, end);
+        let mut open = true;
+        egui::Window::new("Weekly summary")
+            .open(&mut open)
             .show(ctx, |ui| {
                 ui.horizontal(|ui| {
-                    if bare_button_sized(TAG, 20., ui).clicked() {
-                        self.ui_state.tags_enabled = !self.ui_state.tags_enabled;
+                    if ui.button(egui_phosphor::regular::CARET_LEFT).clicked() {
+                        self.ui_state.summary_week = Some(start - chrono::Days::new(7));
                     }
-                    if !self.ui_state.tags_enabled {
-                        return;
+                    let fmt = &self.settings.appearance.date_format;
+                    ui.label(format!(
+                        "{} to {}",
+                        format_date(start, fmt),
+                        format_date(end, fmt)
+                    ));
+                    if ui.button(egui_phosphor::regular::CARET_RIGHT).clicked() {
+                        self.ui_state.summary_week = Some(start + chrono::Days::new(7));
+                    }
+                    if ui.button("Copy").clicked() {
+                        ui.output_mut(|o| o.copied_text = summary.clone());
+                        self.toasts.info("Copied summary to clipboard.");
                     }
-                    ui.label("TAGS");
                 });
-                if !self.ui_state.tags_enabled {
-                    return;
-                }
                 ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.label(&summary);
+                });
+            });
+        if !open {
+            self.ui_state.summary_week = None;
+        }
+    }
 
-                ui.horizontal_wrapped(|ui| {
-                    let all_used_tags = self
-                        .userdata
-                        .notes
-                        .iter()
-                        .flat_map(|(_, n)| &n.tags)
-                        .collect::<Vec<_>>();
-                    for tag in &self.userdata.tags {
-                        // Hide tags that are unused.
-                        if !all_used_tags.contains(&tag) {
-                            continue;
-                        }
-                        let contained = self.active_tags.contains(tag);
-
-                        let tag_color = color_from_tag(tag);
+    /// Hide note `id` until `until`, or show it again if `None`.
+    fn snooze(&mut self, id: u128, until: Option<chrono::NaiveDate>) {
+        let Some(note) = self.userdata.notes.get_mut(&id) else {
+            return;
+        };
+        let before = note.clone();
+        note.snoozed_until = until;
+        self.undo_stack.flush();
+        self.undo_stack.push(NoteChange {
+            id,
+            before: Some(before),
+            after: Some(note.clone()),
+            ..Default::default()
+        });
+        if let Some(until) = until {
+            self.toasts.info(format!(
+                "Snoozed until {}",
+                format_date(until, &self.settings.appearance.date_format)
+            ));
+        }
+    }
 
-                        if contained {
-                            ui.style_mut().visuals.selection.bg_fill =
-                                tag_color.gamma_multiply(GAMMA_MULT);
-                        }
+    /// Bring back the snoozed notes whose day has come and highlight them for a moment.
+    fn wake_snoozed(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        self.woken.retain(|_, woke| now - *woke < WAKE_HIGHLIGHT);
+        let woken = self.userdata.wake_snoozed(today());
+        if woken.is_empty() {
+            return;
+        }
+        self.toasts.info(match woken.len() {
+            1 => "1 note woke up".to_string(),
+            n => format!("{n} notes woke up"),
+        });
+        self.woken.extend(woken.into_iter().map(|id| (id, now)));
+    }
 
-                        if ui
-                            .add(SelectableLabel::new(
-                                contained,
-                                if contained {
-                                    RichText::new(tag).color(readable_text(&tag_color))
-                                } else {
-                                    RichText::new(tag)
-                                },
-                            ))
-                            .clicked()
-                        {
-                            // if ui.selectable_label(contained, tag).clicked() {
-                            if contained {
-                                self.active_tags.remove(tag);
-                            } else {
-                                self.active_tags.insert(tag.clone());
-                            }
-                        }
-                    }
-                });
+    /// Outline `rect` if note `id` is selected
+    fn paint_selected(&self, ui: &Ui, id: u128, rect: Rect) {
+        if self.selected_notes.contains(&id) {
+            ui.painter().rect_stroke(
+                rect.expand(2.),
+                6.,
+                Stroke::new(2.0_f32, ui.visuals().selection.stroke.color),
+            );
+        }
+    }
 
-                ui.separator();
+    /// Outline `rect` if note `id` just woke up from a snooze, fading out over `WAKE_HIGHLIGHT`.
+    fn paint_woken(&self, ui: &Ui, id: u128, rect: Rect) {
+        let Some(woke) = self.woken.get(&id) else {
+            return;
+        };
+        let age = ui.input(|i| i.time) - woke;
+        // without motion the highlight stays as it is until it disappears at once
+        let strength = if self.reduce_motion() {
+            1.
+        } else {
+            (1. - age / WAKE_HIGHLIGHT).clamp(0., 1.) as f32
+        };
+        ui.painter().rect_stroke(
+            rect.expand(3.),
+            6.,
+            Stroke::new(
+                3.0_f32,
+                ui.visuals().selection.stroke.color.gamma_multiply(strength),
+            ),
+        );
+        if self.reduce_motion() || self.in_background(ui.ctx()) {
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs_f64(
+                    (WAKE_HIGHLIGHT - age).max(0.),
+                ));
+        } else {
+            ui.ctx().request_repaint();
+        }
+    }
 
-                if !self.active_tags.is_empty() {
-                    if ui.button("Show all").clicked() {
-                        self.active_tags.clear();
-                    }
+    /// The snoozed notes with a button each to show them again right away
+    fn snoozed_ui(&mut self, ui: &mut Ui) {
+        let snoozed = self.userdata.snoozed_notes(today());
+        if snoozed.is_empty() {
+            ui.label("No snoozed notes.");
+            return;
+        }
+        let mut wake = None;
+        egui::Grid::new("snoozed").show(ui, |ui| {
+            for id in snoozed {
+                let note = &self.userdata.notes[&id];
+                if ui
+                    .link(note.get_title())
+                    .on_hover_text("Open in the editor")
+                    .clicked()
+                {
+                    self.active_note = Some(id);
+                }
+                if let Some(until) = note.snoozed_until {
+                    ui.label(format_date(until, &self.settings.appearance.date_format));
                 }
+                if ui
+                    .small_button("Unsnooze")
+                    .on_hover_text("Show the note again now")
+                    .clicked()
+                {
+                    wake = Some(id);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(id) = wake {
+            self.snooze(id, None);
+        }
+    }
 
-                ui.collapsing("Edit", |ui| {
-                    if ui.button("Add tag").clicked() {
-                        self.userdata.tags.push("New Tag".into());
-                    }
+    /// When time tracking for note `id` started, if it is running
+    fn timer_of(&self, id: u128) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.running_timer
+            .filter(|(running, _)| *running == id)
+            .map(|(_, start)| start)
+    }
 
-                    egui::ScrollArea::horizontal().show(ui, |ui| {
-                        let mut tag_index_to_delete: Option<usize> = None;
+    /// Stop tracking time for note `id` if it runs, or start it. A timer running for another
+    /// note is stopped first.
+    fn toggle_timer(&mut self, id: u128) {
+        if self.stop_timer() != Some(id) {
+            self.running_timer = Some((id, chrono::Utc::now()));
+        }
+    }
 
-                        for (i, tag) in &mut self.userdata.tags.iter_mut().enumerate() {
-                            ui.horizontal(|ui| {
-                                if ui
-                                    .button("🗑")
-                                    .on_hover_text("Delete this tag from list and all notes.")
-                                    .clicked()
-                                {
-                                    for note in self.userdata.notes.values_mut() {
-                                        note.tags.remove(tag);
-                                    }
-                                    tag_index_to_delete = Some(i);
-                                }
-                                let old_tag = tag.clone();
-                                if ui.text_edit_singleline(tag).changed() {
-                                    // If a tag is renamed, we need to rename it in all notes.
-                                    for note in self.userdata.notes.values_mut() {
-                                        if note.tags.contains(&old_tag) {
-                                            note.tags.remove(&old_tag);
-                                            note.tags.insert(tag.clone());
-                                        }
-                                    }
-                                }
-                            });
-                        }
+    /// Stop the running timer and log the session. Returns the note it ran for.
+    fn stop_timer(&mut self) -> Option<u128> {
+        let (id, start) = self.running_timer.take()?;
+        let now = chrono::Utc::now();
+        if self.userdata.log_work(id, start, now) {
+            self.logbook_index = None;
+            self.toasts.info(format!(
+                "Tracked {} on {}",
+                format_duration(now - start),
+                self.userdata.notes[&id].get_title()
+            ));
+        }
+        Some(id)
+    }
 
-                        if let Some(i) = tag_index_to_delete {
-                            self.userdata.tags.remove(i);
-                        }
-                    });
-                });
-            });
+    /// Add a new note with the active tags and open it in the editor. Returns its id.
+    fn create_note(&mut self, mut note: Note) -> u128 {
+        note.tags.extend(self.active_tags.iter().cloned());
+        let id = self.userdata.add_note(note);
+        self.active_note = Some(id);
+        self.undo_stack.push(NoteChange {
+            id,
+            before: None,
+            after: self.userdata.notes.get(&id).cloned(),
+            ..Default::default()
+        });
+        id
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // Offer restore fuctionality if local
+    /// Add the notes of an exported bundle and open the exported note.
+    fn import_bundle(&mut self, json: &str) -> anyhow::Result<()> {
+        let bundle = NoteBundle::from_json(json)?;
+        let import = self.userdata.import_bundle(&bundle);
+        for id in &import.notes {
+            self.undo_stack.push(NoteChange {
+                id: *id,
+                before: None,
+                after: self.userdata.notes.get(id).cloned(),
+                ..Default::default()
+            });
+        }
+        self.toasts.info(format!(
+            "Imported {} note{} and {} dependenc{}",
+            import.notes.len(),
+            if import.notes.len() == 1 { "" } else { "s" },
+            import.dependencies,
+            if import.dependencies == 1 { "y" } else { "ies" }
+        ));
+        if import.root.is_some() {
+            self.active_note = import.root;
+        }
+        Ok(())
+    }
 
-            match self.viewmode {
-                ViewMode::Board => {
-                    boardview(ui, self);
+    /// Show the command palette, if open, and run the command picked in it.
+    fn command_palette_ui(&mut self, ctx: &egui::Context) {
+        let Some(palette) = &mut self.palette else {
+            return;
+        };
+        let commands = match palette.notes_only {
+            true => vec![],
+            false => Command::ALL
+                .into_iter()
+                .filter(|c| !c.needs_selection() || !self.selected_notes.is_empty())
+                .collect(),
+        };
+        let notes = self
+            .userdata
+            .notes
+            .iter()
+            .map(|(id, note)| (note.get_title(), Command::JumpTo(*id)));
+        let mut entries: Vec<PaletteEntry> = commands
+            .into_iter()
+            .map(|command| (command.name(), command))
+            .chain(notes)
+            .filter_map(|(label, command)| {
+                let matched = fuzzy_match(&palette.query, label)?;
+                Some(PaletteEntry {
+                    label: label.to_string(),
+                    matched,
+                    command,
+                })
+            })
+            .collect();
+        // best matches first, commands before notes when they match equally well
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.matched.score));
+        entries.truncate(MAX_PALETTE_ENTRIES);
+
+        let mut run = None;
+        let mut close = false;
+        let window = egui::Window::new("Command palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_TOP, vec2(0., 80.))
+            .show(ctx, |ui| {
+                ui.set_width(400.);
+                let query = ui.add(
+                    egui::TextEdit::singleline(&mut palette.query)
+                        .hint_text(match palette.notes_only {
+                            true => "Go to note",
+                            false => "Type a command or a note title",
+                        })
+                        .desired_width(f32::INFINITY),
+                );
+                if !palette.focus_requested {
+                    query.request_focus();
+                    palette.focus_requested = true;
                 }
-                ViewMode::List => {
-                    listview(ui, self);
+                if query.changed() {
+                    palette.selected = 0;
                 }
-                ViewMode::Graph => {
-                    ui.label("Work in progress!");
-
-                    // add graph if not present
-                    if self.graph.is_none() {
-                        let mut g: StableGraph<String, ()> = StableGraph::new();
-                        let mut added = vec![];
-                        for note in self.userdata.notes.values() {
-                            if !added.contains(&note.id) {
-                                let a = g.add_node(note.get_title().into());
-                                added.push(note.id);
-                                for c in &note.depends {
-                                    if let Some(depend) = self.userdata.notes.get(c) {
-                                        let b = g.add_node(depend.get_title().into());
-                                        g.add_edge(a, b, ());
-                                        added.push(*c);
-                                    }
-                                }
+                let (up, down, enter, escape) = ui.input_mut(|i| {
+                    (
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                        i.key_pressed(egui::Key::Enter),
+                        i.key_pressed(egui::Key::Escape),
+                    )
+                });
+                if down {
+                    palette.selected = (palette.selected + 1).min(entries.len().saturating_sub(1));
+                }
+                if up {
+                    palette.selected = palette.selected.saturating_sub(1);
+                }
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(300.)
+                    .show(ui, |ui| {
+                        if entries.is_empty() {
+                            ui.label("Nothing matches.");
+                        }
+                        for (i, entry) in entries.iter().enumerate() {
+                            let r = ui.selectable_label(i == palette.selected, entry.layout(ui));
+                            if i == palette.selected && (up || down) {
+                                r.scroll_to_me(None);
+                            }
+                            if r.clicked() {
+                                run = Some(entry.command.clone());
                             }
                         }
-                        self.graph = Some(Graph::from(&g));
-                    }
-
-                    if let Some(g) = self.graph.as_mut() {
-                        // ui.add(&mut GraphView::new(g).with_custom_node_draw(
-                        //     |ctx, n, meta, _style, l| {
-                        //         // lets draw a rect with label in the center for every node
-
-                        //         // find node center location on the screen coordinates
-                        //         let node_center_loc = n.screen_location(meta).to_pos2();
-
-                        //         // find node radius accounting for current zoom level; we will use it as a reference for the rect and label sizes
-                        //         let rad = n.screen_radius(meta);
-
-                        //         // first create rect shape
-                        //         let size = Vec2::new(rad * 1.5, rad * 1.5);
-                        //         let rect = Rect::from_center_size(node_center_loc, size);
-                        //         let shape_rect = Shape::rect_stroke(
-                        //             rect,
-                        //             Rounding::default(),
-                        //             Stroke::new(1., n.color(ctx)),
-                        //         );
-
-                        //         // then create shape for the label placing it in the center of the rect
-                        //         let color = ctx.style().visuals.text_color();
-                        //         let galley = ctx.fonts(|f| {
-                        //             f.layout_no_wrap(
-                        //                 n.data().unwrap().clone(),
-                        //                 FontId::new(rad, FontFamily::Monospace),
-                        //                 color,
-                        //             )
-                        //         });
-                        //         // we need to offset a bit to place the label in the center of the rect
-                        //         let label_loc = Pos2::new(
-                        //             node_center_loc.x - rad / 2.,
-                        //             node_center_loc.y - rad / 2.,
-                        //         );
-                        //         let shape_label = TextShape::new(label_loc, galley, Color32::BLACK);
-
-                        //         // add shapes to the drawing layers; the drawing process is happening in the widget lifecycle.
-                        //         l.add(shape_rect);
-                        //         l.add(shape_label);
-                        //     },
-                        // ));
-                    }
+                    });
+                if enter {
+                    run = entries.get(palette.selected).map(|e| e.command.clone());
                 }
-            }
-
-            //create a round button at an absolute position
+                close = escape;
+            });
+        if close || run.is_some() || window.is_some_and(|w| w.response.clicked_elsewhere()) {
+            self.palette = None;
+        }
+        if let Some(command) = run {
+            self.run_command(command);
+        }
+    }
 
-            if draw_note_add_button(ui).clicked() {
-                let mut n = Note::new();
-                n.tags = self.active_tags.iter().cloned().collect();
-                self.active_note = Some(n.id);
-                self.userdata.notes.insert(n.id, n);
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::NewNote => {
+                self.create_note(Note::new());
             }
+            Command::View(viewmode) => self.settings.appearance.viewmode = viewmode,
+            Command::ToggleSettings => {
+                self.ui_state.settings_enabled = !self.ui_state.settings_enabled;
+            }
+            Command::ToggleScratchpad => {
+                self.ui_state.scratchpad_enabled = !self.ui_state.scratchpad_enabled;
+            }
+            Command::ToggleLogbook => {
+                self.ui_state.logbook_enabled = !self.ui_state.logbook_enabled;
+            }
+            Command::ToggleTags => self.ui_state.tags_enabled = !self.ui_state.tags_enabled,
+            Command::Save => {
+                if let Err(e) = self.save_userdata(true) {
+                    self.toasts.error(format!("Could not save notes: {e}"));
+                }
+            }
+            Command::Restore => self.pending_action = Some(PendingAction::RestoreNotes),
+            Command::JumpTo(id) => self.active_note = Some(id),
+            Command::CompleteSelected => self.complete_selected(),
+            Command::ClearSelection => self.selected_notes.clear(),
+        }
+    }
 
-            // Draw black background if editing note
-            if self.active_note.is_some() {
-                let rect_all = ui.allocate_rect(Rect::EVERYTHING, Sense::click());
-                ui.painter_at(Rect::EVERYTHING).rect_filled(
-                    Rect::EVERYTHING,
-                    Rounding::ZERO,
-                    Color32::from_rgba_premultiplied(0, 0, 0, 150),
-                );
+    /// Mark the selected notes done and clear the selection. Each note can be undone on its own.
+    fn complete_selected(&mut self) {
+        self.undo_stack.flush();
+        let mut completed = 0;
+        for id in std::mem::take(&mut self.selected_notes) {
+            let Some(note) = self.userdata.notes.get_mut(&id) else {
+                continue;
+            };
+            if note.status == Status::Done {
+                continue;
+            }
+            let before = note.clone();
+            note.set_status(Status::Done, today());
+            self.undo_stack.push(NoteChange {
+                id,
+                before: Some(before),
+                after: Some(note.clone()),
+                ..Default::default()
+            });
+            completed += 1;
+        }
+        self.toasts.info(match completed {
+            1 => "Marked 1 note done".to_string(),
+            n => format!("Marked {n} notes done"),
+        });
+    }
 
-                // if rect is clicked, close edit mode
-                if rect_all.clicked() {
-                    self.active_note = None;
+    /// A line along the bottom with how many notes there are and are shown, the tag filter and
+    /// where the notes are saved.
+    fn status_bar_ui(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.status_bar_enabled {
+            return;
+        }
+        let shown = self.shown_notes().len();
+        let total = self.userdata.notes.len();
+        let mut stop_timer = false;
+        let mut clear_selection = false;
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.spacing_mut().item_spacing.x = 12.;
+                if shown == total {
+                    ui.small(format!("{total} notes"));
+                } else {
+                    ui.small(format!("{shown} of {total} notes shown"));
+                }
+                if !self.active_tags.is_empty() {
+                    let tags = self.active_tags.len();
+                    ui.small(format!(
+                        "{} {tags} tag{}",
+                        egui_phosphor::regular::TAG,
+                        if tags == 1 { "" } else { "s" }
+                    ))
+                    .on_hover_text(
+                        self.active_tags
+                            .iter()
+                            .cloned()
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    );
+                }
+                if !self.selected_notes.is_empty() {
+                    ui.small(format!("{} selected", self.selected_notes.len()));
+                    clear_selection = ui
+                        .small_button("Clear")
+                        .on_hover_text(
+                            "Command-click notes to select, act on them in the command palette",
+                        )
+                        .clicked();
+                }
+                if let Some((id, start)) = self.running_timer {
+                    let title = self
+                        .userdata
+                        .notes
+                        .get(&id)
+                        .map(|n| n.get_title())
+                        .unwrap_or_default();
+                    let elapsed = format_duration(chrono::Utc::now() - start);
+                    if ui
+                        .small_button(format!(
+                            "{} {elapsed} {title}",
+                            egui_phosphor::regular::PAUSE
+                        ))
+                        .on_hover_text("Stop the timer")
+                        .clicked()
+                    {
+                        stop_timer = true;
+                    }
+                    // keep the elapsed time current, it catches up once the window is back
+                    if !self.in_background(ui.ctx()) {
+                        ui.ctx()
+                            .request_repaint_after(std::time::Duration::from_secs(10));
+                    }
+                }
+                ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+                    match self.last_saved {
+                        Some(time) => {
+                            ui.small(format!("Saved {}", local_time(time).format("%H:%M")))
+                        }
+                        None => ui.small("Not saved this session"),
+                    };
+                    ui.small(format!("{:?}", self.settings.sync.storage_mode));
+                });
+            });
+        });
+        if stop_timer {
+            self.stop_timer();
+        }
+        if clear_selection {
+            self.selected_notes.clear();
+        }
+    }
+
+    /// The recent log messages in a panel along the bottom, if enabled
+    fn diagnostics_ui(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.diagnostics_enabled {
+            return;
+        }
+        let lines = recent_log();
+        egui::TopBottomPanel::bottom("diagnostics")
+            .resizable(true)
+            .default_height(120.)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("DIAGNOSTICS");
+                    if ui.button("Copy").clicked() {
+                        let text = lines
+                            .iter()
+                            .map(|line| line.to_string())
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        ui.output_mut(|o| o.copied_text = text);
+                        self.toasts.info("Copied log to clipboard.");
+                    }
+                    if bare_button(egui_phosphor::regular::X, ui).clicked() {
+                        self.ui_state.diagnostics_enabled = false;
+                    }
+                });
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        if lines.is_empty() {
+                            ui.weak("Nothing logged yet.");
+                        }
+                        for line in &lines {
+                            let color = match line.level {
+                                log::Level::Error => ui.visuals().error_fg_color,
+                                log::Level::Warn => ui.visuals().warn_fg_color,
+                                _ => ui.visuals().text_color(),
+                            };
+                            ui.label(
+                                RichText::new(line.to_string())
+                                    .small()
+                                    .monospace()
+                                    .color(color),
+                            );
+                        }
+                    });
+            });
+    }
+
+    /// The logbook of the 7 days up to `today` in one read-only summary, with the number of
+    /// entries per tag.
+    fn logbook_week_ui(&self, ui: &mut Ui, today: chrono::NaiveDate) {
+        let days = self
+            .userdata
+            .logbook
+            .range(today - chrono::Days::new(6)..=today);
+        let mut tag_counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for item in days.clone().flat_map(|(_, items)| items) {
+            for tag in &item.tags {
+                *tag_counts.entry(tag).or_default() += 1;
+            }
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            if days.clone().next().is_none() {
+                ui.label("Nothing logged in the last 7 days.");
+                return;
+            }
+            if !tag_counts.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    for (tag, count) in &tag_counts {
+                        ui.label(
+                            RichText::new(format!("{tag} {count}")).color(color_from_tag(tag)),
+                        );
+                    }
+                });
+                ui.separator();
+            }
+            for (date, items) in days.rev() {
+                ui.strong(format_date(*date, &self.settings.appearance.date_format));
+                for item in items {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(format!("- {}", item.text.trim()));
+                        for tag in &item.tags {
+                            ui.label(RichText::new(tag).small().color(color_from_tag(tag)));
+                        }
+                    });
                 }
+                ui.add_space(6.);
             }
         });
+    }
 
-        if let Some(id) = self.active_note {
-            // clean invalid id (because of deletion)
-            if !self.userdata.notes.contains_key(&id) {
-                self.active_note = None;
+    /// Bring the tag counts and the dependency graph up to date with a changed note.
+    fn note_changed(&mut self, id: u128) {
+        let notes = &self.userdata.notes;
+        if !notes.contains_key(&id) {
+            self.graph_positions.remove(&id);
+            self.selected_notes.remove(&id);
+            if self.timer_of(id).is_some() {
+                self.running_timer = None;
             }
+        }
+        self.tag_usage.update(id, notes.get(&id).map(|n| &n.tags));
+        self.dependency_graph
+            .update(id, notes, &self.graph_positions);
+        self.focus_graph = None;
+    }
 
-            egui::Window::new("xxxxx")
-                .collapsible(false)
-                .title_bar(false)
-                .fixed_rect(
-                    ctx.screen_rect().shrink(20.), // .translate(vec2(-10.0, 00.))
-                )
-                .show(ctx, |ui| {
-                    ui.vertical_centered_justified(|ui| {
-                        edit_note(ui, &id, &mut self.userdata.tags, &mut self.userdata.notes);
+    /// Count the tags and build the dependency graph again after all notes were replaced.
+    fn notes_replaced(&mut self) {
+        let notes = &self.userdata.notes;
+        self.tag_usage.rebuild(notes);
+        self.dependency_graph.rebuild(notes, &self.graph_positions);
+        self.focus_graph = None;
+        self.logbook_index = None;
+    }
+
+    /// Catch up with the notes changed through the history. Tags that came into use and are
+    /// missing from the tag list are added to it.
+    fn update_changed_notes(&mut self, ctx: &egui::Context) {
+        let touched = self.undo_stack.take_touched();
+        for id in &touched {
+            self.note_changed(*id);
+        }
+        let new = self.tag_usage.take_new();
+        for tag in &new {
+            if !self.userdata.tags.contains(tag) {
+                self.userdata.tags.push(tag.clone());
+            }
+        }
+        if !touched.is_empty() || !new.is_empty() {
+            // this frame was drawn with the tags as they were before
+            ctx.request_repaint();
+        }
+    }
+
+    /// Add or remove a tag of a note that was dropped on it.
+    fn tag_dropped_note(&mut self, id: u128, tag: &str, remove: bool) {
+        let Some(before) = self.userdata.notes.get(&id).cloned() else {
+            return;
+        };
+        let changed = if remove {
+            self.userdata.untag_note(id, tag)
+        } else {
+            !before.tags.contains(tag) && self.userdata.tag_note(id, tag)
+        };
+        if !changed {
+            return;
+        }
+        self.undo_stack.flush();
+        self.undo_stack.push(NoteChange {
+            id,
+            before: Some(before.clone()),
+            after: self.userdata.notes.get(&id).cloned(),
+            ..Default::default()
+        });
+        if remove {
+            self.toasts
+                .info(format!("Removed tag '{tag}' from '{}'", before.get_title()));
+        } else {
+            self.toasts
+                .info(format!("Added tag '{tag}' to '{}'", before.get_title()));
+        }
+    }
+
+    /// Open a clicked note in the editor, or edit its title in place on a double click. Clicking
+    /// with the command key held adds the note to the selection or removes it.
+    fn note_clicked(&mut self, resp: &Response, id: u128) {
+        if resp.double_clicked() {
+            self.pending_open = None;
+            let title = self
+                .userdata
+                .notes
+                .get(&id)
+                .and_then(|n| n.text.lines().next())
+                .unwrap_or_default()
+                .to_string();
+            self.inline_edit = Some(InlineEdit {
+                id,
+                title,
+                focus_requested: false,
+            });
+        } else if resp.clicked() && resp.ctx.input(|i| i.modifiers.command) {
+            if !self.selected_notes.remove(&id) {
+                self.selected_notes.insert(id);
+            }
+        } else if resp.clicked() {
+            self.pending_open = Some((id, resp.ctx.input(|i| i.time)));
+        }
+    }
+
+    /// Open the clicked note once the double click delay has passed.
+    fn open_pending_note(&mut self, ctx: &egui::Context) {
+        let Some((id, clicked_at)) = self.pending_open else {
+            return;
+        };
+        let delay = ctx.options(|o| o.input_options.max_double_click_delay);
+        let elapsed = ctx.input(|i| i.time) - clicked_at;
+        if elapsed > delay {
+            self.pending_open = None;
+            self.active_note = Some(id);
+        } else {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(delay - elapsed));
+        }
+    }
+
+    /// Apply or discard the inline title edit.
+    fn finish_inline_edit(&mut self, commit: bool) {
+        let Some(edit) = self.inline_edit.take() else {
+            return;
+        };
+        if !commit {
+            return;
+        }
+        let Some(note) = self.userdata.notes.get_mut(&edit.id) else {
+            return;
+        };
+        let before = note.clone();
+        note.set_title(&edit.title);
+        self.undo_stack.flush();
+        self.undo_stack.push(NoteChange {
+            id: edit.id,
+            before: Some(before),
+            after: Some(note.clone()),
+            ..Default::default()
+        });
+    }
+
+    /// The remaining effort of the open notes currently shown, in total and by tag
+    fn workload_ui(&self, ui: &mut Ui) {
+        let due = self.userdata.effort_due_this_week(today());
+        let capacity = self.settings.behavior.weekly_capacity;
+        ui.label("Due within a week");
+        let over = due > capacity;
+        ui.add(
+            egui::ProgressBar::new(if capacity > 0. { due / capacity } else { 1. })
+                .text(format!("{due:.1} of {capacity:.0} h"))
+                .fill(if over {
+                    ui.visuals().error_fg_color
+                } else {
+                    ui.visuals().selection.bg_fill
+                }),
+        )
+        .on_hover_text(
+            "The effort left on open notes that are overdue or due in the next seven days, \
+             compared to the weekly capacity set in the settings",
+        );
+        ui.separator();
+
+        let ids =
+            self.userdata
+                .visible_notes(&self.filter, &self.active_tags, Visibility::Open, false);
+        let (total, by_tag) = self.userdata.workload(&ids);
+        if total <= 0. {
+            ui.label("No effort estimated for the shown notes.");
+            return;
+        }
+        ui.label(format!("{total:.1} h outstanding"));
+        egui::Grid::new("workload").show(ui, |ui| {
+            for (tag, hours) in by_tag {
+                ui.label(RichText::new(&tag).color(color_from_tag(&tag)));
+                ui.label(format!("{hours:.1} h"));
+                ui.end_row();
+            }
+        });
+    }
+
+    /// The notes the current view shows, in its order. Focus mode steps through them.
+    fn shown_notes(&self) -> Vec<u128> {
+        match self.settings.appearance.viewmode {
+            ViewMode::Agenda => self.agenda_notes(),
+            ViewMode::List => self.userdata.visible_notes(
+                &self.filter,
+                &self.active_tags,
+                Visibility::All,
+                self.settings.behavior.sort_ascending,
+            ),
+            _ => self.board_notes(),
+        }
+    }
+
+    /// The unfinished notes on the board, of the statuses it is set to show
+    fn board_notes(&self) -> Vec<u128> {
+        let visibility = if self.settings.behavior.hide_blocked {
+            Visibility::Unblocked
+        } else {
+            Visibility::Open
+        };
+        let statuses = &self.settings.behavior.board_statuses;
+        self.userdata
+            .visible_notes(
+                &self.filter,
+                &self.active_tags,
+                visibility,
+                self.settings.behavior.sort_ascending,
+            )
+            .into_iter()
+            .filter(|id| statuses.contains(&self.userdata.notes[id].status))
+            .collect()
+    }
+
+    /// The notes due today or overdue that match the search filter and tags, the most urgent
+    /// first
+    fn agenda_notes(&self) -> Vec<u128> {
+        agenda_for(today(), &self.userdata.notes)
+            .into_iter()
+            .filter(|id| {
+                let note = &self.userdata.notes[id];
+                note.has_any_tag(&self.active_tags) && note.matches(&self.filter)
+            })
+            .collect()
+    }
+
+    /// Show the focused note full screen in large text. Esc leaves focus mode,
+    /// the arrow keys step to the previous or next note.
+    fn focus_ui(&mut self, ctx: &egui::Context) {
+        let Some(id) = self.focused_note else {
+            return;
+        };
+        let Some(note) = self.userdata.notes.get(&id).cloned() else {
+            self.focused_note = None;
+            return;
+        };
+
+        let order = self.shown_notes();
+        let position = order.iter().position(|n| *n == id);
+        let mut step: Option<isize> = None;
+        let mut exit = false;
+        let mut toggle = None;
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                exit = bare_button(egui_phosphor::regular::X, ui)
+                    .on_hover_text("Leave focus mode (Esc)")
+                    .clicked();
+                if bare_button(egui_phosphor::regular::CARET_LEFT, ui)
+                    .on_hover_text("Previous note")
+                    .clicked()
+                {
+                    step = Some(-1);
+                }
+                if bare_button(egui_phosphor::regular::CARET_RIGHT, ui)
+                    .on_hover_text("Next note")
+                    .clicked()
+                {
+                    step = Some(1);
+                }
+                if let Some(position) = position {
+                    ui.label(format!("{} / {}", position + 1, order.len()));
+                }
+            });
+            ui.separator();
 
-                        if ui.button("Close").clicked() {
-                            self.active_note = None;
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                let size = 2. * self.settings.appearance.note_text_size;
+                for (line, l) in note.text.lines().enumerate() {
+                    if let Some((mut checked, text)) = parse_checklist_line(l) {
+                        if ui
+                            .checkbox(&mut checked, RichText::new(text).size(size))
+                            .changed()
+                        {
+                            toggle = Some(line);
                         }
+                    } else if let Some(marker) = heading_marker(l) {
+                        let level = marker.len() - 1;
+                        let heading = l[marker.len()..].trim();
+                        ui.label(
+                            RichText::new(heading)
+                                .size(size * (1. + 0.5 / level as f32))
+                                .strong(),
+                        );
+                    } else {
+                        ui.horizontal_wrapped(|ui| {
+                            let mut last = 0;
+                            for link in find_links(l) {
+                                for word in l[last..link.span.start].split_whitespace() {
+                                    ui.label(RichText::new(word).size(size));
+                                }
+                                ui.hyperlink_to(RichText::new(link.text()).size(size), link.url)
+                                    .on_hover_text(link.url);
+                                last = link.span.end;
+                            }
+                            for word in l[last..].split_whitespace() {
+                                ui.label(RichText::new(word).size(size));
+                            }
+                        });
+                    }
+                }
+            });
+        });
+
+        if !ctx.wants_keyboard_input() {
+            ctx.input(|i| {
+                exit |= i.key_pressed(egui::Key::Escape);
+                if i.key_pressed(egui::Key::ArrowLeft) || i.key_pressed(egui::Key::ArrowUp) {
+                    step = Some(-1);
+                }
+                if i.key_pressed(egui::Key::ArrowRight) || i.key_pressed(egui::Key::ArrowDown) {
+                    step = Some(1);
+                }
+            });
+        }
+
+        if let Some(line) = toggle {
+            if let Some(changed) = self.userdata.notes.get_mut(&id) {
+                changed.toggle_checklist_item(line);
+                self.undo_stack.push(NoteChange {
+                    id,
+                    before: Some(note),
+                    after: Some(changed.clone()),
+                    ..Default::default()
+                });
+            }
+        }
+        if exit {
+            self.focused_note = None;
+            self.active_note = self.focus_return_note.take();
+        } else if let Some(step) = step.filter(|_| !order.is_empty()) {
+            let next = match position {
+                Some(p) => (p as isize + step).rem_euclid(order.len() as isize) as usize,
+                None => 0,
+            };
+            self.focused_note = Some(order[next]);
+        }
+    }
+
+    /// Pick a note to center the graph on, by title or by id / link
+    fn graph_focus_ui(&mut self, ui: &mut Ui) {
+        let focus_before = (self.graph_focus, self.graph_depth);
+        ui.horizontal(|ui| {
+            let selected = self
+                .graph_focus
+                .and_then(|id| self.userdata.notes.get(&id))
+                .map(|n| n.get_title().to_string())
+                .unwrap_or_else(|| "All notes".to_string());
+            egui::ComboBox::from_label("Focus")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for id in
+                        self.userdata
+                            .visible_notes("", &HashSet::default(), Visibility::All, false)
+                    {
+                        let title = self.userdata.notes[&id].get_title();
+                        ui.selectable_value(&mut self.graph_focus, Some(id), title);
+                    }
+                });
+
+            let id_field = Id::new("graph_focus_id");
+            let mut link = ui.data_mut(|d| d.get_temp::<String>(id_field).unwrap_or_default());
+            let resp = ui.add(
+                egui::TextEdit::singleline(&mut link)
+                    .hint_text("Note id or link")
+                    .desired_width(150.),
+            );
+            if resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                match parse_note_link(&link).filter(|id| self.userdata.notes.contains_key(id)) {
+                    Some(id) => {
+                        self.graph_focus = Some(id);
+                        link.clear();
+                    }
+                    None => {
+                        self.toasts.warning(format!("No note '{link}'"));
+                    }
+                }
+            }
+            ui.data_mut(|d| d.insert_temp(id_field, link));
+
+            if self.graph_focus.is_some() {
+                ui.add(egui::Slider::new(&mut self.graph_depth, 0..=10).text("steps"));
+                if ui.button("Clear focus").clicked() {
+                    self.graph_focus = None;
+                }
+            }
+        });
+        if (self.graph_focus, self.graph_depth) != focus_before {
+            self.focus_graph = None;
+        }
+    }
+
+    /// Show the dragged card under the pointer.
+    fn dragged_note_ui(&self, ctx: &egui::Context) {
+        let Some(dragged) = egui::DragAndDrop::payload::<DraggedNote>(ctx) else {
+            return;
+        };
+        let (Some(note), Some(pos)) = (
+            self.userdata.notes.get(&dragged.0),
+            ctx.pointer_interact_pos(),
+        ) else {
+            return;
+        };
+        ctx.set_cursor_icon(egui::CursorIcon::Grabbing);
+        egui::Area::new(Id::new("dragged_note"))
+            .order(egui::Order::Tooltip)
+            .fixed_pos(pos + vec2(8., 8.))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style())
+                    .fill(note.get_color().gamma_multiply(0.8))
+                    .show(ui, |ui| {
+                        ui.label(
+                            RichText::new(note.get_title()).color(readable_text(&note.get_color())),
+                        );
                     });
+            });
+    }
+
+    /// Add the current filter to the front of the recent filters.
+    fn remember_filter(&mut self) {
+        let filter = self.filter.trim().to_string();
+        if filter.is_empty() {
+            return;
+        }
+        self.recent_filters.retain(|f| f != &filter);
+        self.recent_filters.insert(0, filter);
+        self.recent_filters.truncate(MAX_RECENT_FILTERS);
+    }
+
+    /// Rename the active profile to `new_name`, unless that name is taken.
+    fn rename_active_profile(&mut self, new_name: &str) {
+        let Some(old_name) = self.active_profile.clone() else {
+            return;
+        };
+        if new_name.is_empty() || new_name == old_name {
+            return;
+        }
+        if self.profiles.contains_key(new_name) {
+            self.toasts
+                .error(format!("A profile named '{new_name}' already exists."));
+            return;
+        }
+        if let Some(profile) = self.profiles.remove(&old_name) {
+            self.profiles.insert(new_name.to_string(), profile);
+            self.active_profile = Some(new_name.to_string());
+        }
+        if let Some(secret) = self.profile_secrets.remove(&old_name) {
+            self.profile_secrets.insert(new_name.to_string(), secret);
+        }
+    }
+
+    fn appearance_settings_ui(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        egui::ComboBox::from_label("View")
+            .selected_text(format!("{:?}", self.settings.appearance.viewmode))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(
+                    &mut self.settings.appearance.viewmode,
+                    ViewMode::Board,
+                    "Board",
+                );
+                ui.selectable_value(
+                    &mut self.settings.appearance.viewmode,
+                    ViewMode::List,
+                    "List",
+                );
+                ui.selectable_value(
+                    &mut self.settings.appearance.viewmode,
+                    ViewMode::Graph,
+                    "Graph",
+                );
+                ui.selectable_value(
+                    &mut self.settings.appearance.viewmode,
+                    ViewMode::Agenda,
+                    "Agenda",
+                );
+            });
+
+        global_theme_preference_buttons(ui);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.horizontal(|ui| {
+            let path_id = Id::new("custom_font_path");
+            let mut path = ui.data_mut(|d| {
+                d.get_temp_mut_or_insert_with(path_id, || {
+                    self.settings
+                        .appearance
+                        .custom_font
+                        .as_ref()
+                        .map(|p| p.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                })
+                .clone()
+            });
+            if ui
+                .add(
+                    egui::TextEdit::singleline(&mut path)
+                        .hint_text("Path to .ttf/.otf")
+                        .desired_width(150.),
+                )
+                .changed()
+            {
+                ui.data_mut(|d| d.insert_temp(path_id, path.clone()));
+            }
+            if ui.button("Load font").clicked() {
+                let font = PathBuf::from(path.trim());
+                match install_fonts(ctx, Some(&font)) {
+                    Ok(_) => self.settings.appearance.custom_font = Some(font),
+                    Err(e) => {
+                        self.toasts
+                            .warning(format!("{e:#}. Falling back to the default font."));
+                        self.settings.appearance.custom_font = None;
+                    }
+                }
+            }
+            if self.settings.appearance.custom_font.is_some() && ui.button("Reset").clicked() {
+                _ = install_fonts(ctx, None);
+                self.settings.appearance.custom_font = None;
+                ui.data_mut(|d| d.remove::<String>(path_id));
+            }
+        });
+
+        let scale = ui.add(
+            egui::Slider::new(&mut self.settings.appearance.ui_scale, UI_SCALE_RANGE)
+                .text("UI scale"),
+        );
+        // rescaling while dragging would move the slider away from the pointer
+        if scale.drag_stopped() || (scale.changed() && !scale.dragged()) {
+            ctx.set_zoom_factor(self.settings.appearance.ui_scale);
+            self.ui_state.applied_ui_scale = self.settings.appearance.ui_scale;
+        }
+        ui.checkbox(&mut self.settings.appearance.high_contrast, "High contrast")
+            .on_hover_text("Outline cards and show their colors at full strength");
+        ui.checkbox(
+            &mut self.settings.appearance.list_word_count,
+            "Word count in the list",
+        );
+        ui.checkbox(
+            &mut self.settings.appearance.list_by_status,
+            "Group the list by status",
+        );
+        ui.horizontal(|ui| {
+            ui.label("Tag colors");
+            egui::ComboBox::from_id_salt("tag_palette")
+                .selected_text(self.settings.appearance.tag_palette.name())
+                .show_ui(ui, |ui| {
+                    for palette in TagPalette::ALL {
+                        ui.selectable_value(
+                            &mut self.settings.appearance.tag_palette,
+                            palette,
+                            palette.name(),
+                        );
+                    }
                 });
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.settings.appearance.date_format)
+                    .hint_text(DEFAULT_DATE_FORMAT)
+                    .desired_width(100.),
+            );
+            ui.label(format!(
+                "Date format: {}",
+                format_date(today(), &self.settings.appearance.date_format)
+            ))
+            .on_hover_text("A strftime pattern, e.g. %m/%d/%Y or %d.%m.%Y");
+        });
+
+        ui.horizontal(|ui| {
+            let mut fixed = self.settings.appearance.utc_offset.is_some();
+            if ui
+                .checkbox(&mut fixed, "Fixed time zone")
+                .on_hover_text("Use a UTC offset instead of the system time zone")
+                .changed()
+            {
+                self.settings.appearance.utc_offset = fixed.then_some(0);
+            }
+            if let Some(offset) = &mut self.settings.appearance.utc_offset {
+                ui.add(egui::DragValue::new(offset).range(-12..=14).prefix("UTC "));
+            }
+        });
+
+        ui.add(
+            egui::Slider::new(
+                &mut self.settings.appearance.note_text_size,
+                NOTE_TEXT_SIZE_RANGE,
+            )
+            .text("Note text size"),
+        );
+        ui.add(
+            egui::Slider::new(&mut self.settings.appearance.card_width, CARD_WIDTH_RANGE)
+                .text("Card width"),
+        );
+        egui::ComboBox::from_label("Density")
+            .selected_text(format!("{:?}", self.settings.appearance.density))
+            .show_ui(ui, |ui| {
+                for density in [Density::Compact, Density::Comfortable] {
+                    ui.selectable_value(
+                        &mut self.settings.appearance.density,
+                        density,
+                        format!("{density:?}"),
+                    );
+                }
+            });
+        ui.horizontal(|ui| {
+            let mut columns = self.settings.appearance.board_columns.is_some();
+            if ui.checkbox(&mut columns, "Board columns").changed() {
+                self.settings.appearance.board_columns = columns.then_some(3);
+            }
+            if let Some(n) = &mut self.settings.appearance.board_columns {
+                ui.add(egui::DragValue::new(n).range(1..=10));
+            }
+        });
+        egui::ComboBox::from_label("Group board by")
+            .selected_text(self.settings.appearance.board_grouping.name())
+            .show_ui(ui, |ui| {
+                for grouping in BoardGrouping::ALL {
+                    ui.selectable_value(
+                        &mut self.settings.appearance.board_grouping,
+                        grouping,
+                        grouping.name(),
+                    );
+                }
+            });
+    }
+
+    fn behavior_settings_ui(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        ui.checkbox(&mut self.ui_state.scratchpad_enabled, "Scratchpad");
+        ui.checkbox(&mut self.ui_state.tags_enabled, "Tags");
+        ui.checkbox(&mut self.ui_state.status_bar_enabled, "Status bar")
+            .on_hover_text("Note counts and the storage state along the bottom");
+        ui.checkbox(&mut self.ui_state.diagnostics_enabled, "Diagnostics")
+            .on_hover_text("Show recent log messages, to attach to bug reports");
+        ui.checkbox(
+            &mut self.settings.behavior.stack_dependencies,
+            "Stack dependencies on board",
+        );
+        ui.checkbox(
+            &mut self.settings.behavior.hide_blocked,
+            "Hide blocked notes",
+        );
+        ui.checkbox(&mut self.settings.behavior.show_done, "Show finished notes")
+            .on_hover_text("In a \"Done\" column on the board and a group in the list");
+        ui.horizontal(|ui| {
+            ui.label("Board shows");
+            for status in [Status::Active, Status::Waiting, Status::Someday] {
+                let statuses = &mut self.settings.behavior.board_statuses;
+                let mut shown = statuses.contains(&status);
+                if ui
+                    .toggle_value(&mut shown, format!("{} {}", status.icon(), status.name()))
+                    .changed()
+                {
+                    if shown {
+                        statuses.insert(status);
+                    } else {
+                        statuses.remove(&status);
+                    }
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Sort by priority");
+            ui.selectable_value(
+                &mut self.settings.behavior.sort_ascending,
+                false,
+                "Highest first",
+            );
+            ui.selectable_value(
+                &mut self.settings.behavior.sort_ascending,
+                true,
+                "Lowest first",
+            );
+        });
+        ui.checkbox(&mut self.settings.behavior.reduce_motion, "Reduce motion")
+            .on_hover_text(if self.system_reduced_motion {
+                "The system asks for reduced motion, so it is reduced anyway"
+            } else {
+                "Turn off animations and highlights that fade out"
+            });
+        ui.checkbox(
+            &mut self.settings.behavior.save_power,
+            "Save power in the background",
+        )
+        .on_hover_text("Only repaint on input while the window is not focused");
+        ui.checkbox(
+            &mut self.settings.behavior.continuous_repaint,
+            "Repaint continuously",
+        )
+        .on_hover_text("Repaint every frame even if nothing happens, for demos. Uses more power.");
+        ui.checkbox(
+            &mut self.settings.behavior.load_remote_images,
+            "Load remote images",
+        )
+        .on_hover_text(
+            "Show previews of images linked in notes. This contacts the sites hosting them.",
+        );
+        ui.horizontal(|ui| {
+            ui.label("Weekly capacity");
+            ui.add(
+                egui::DragValue::new(&mut self.settings.behavior.weekly_capacity)
+                    .range(0.0..=168.0)
+                    .suffix(" h"),
+            )
+            .on_hover_text("Hours of work per week, compared to the effort of notes due soon");
+        });
+
+        #[cfg(feature = "spellcheck")]
+        ui.collapsing("Spelling", |ui| {
+            ui.checkbox(
+                &mut self.settings.behavior.spellcheck,
+                "Underline misspelled words",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Language");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.settings.behavior.spell_language)
+                        .hint_text("en_US")
+                        .desired_width(80.),
+                )
+                .on_hover_text("The name of the Hunspell dictionary, without .dic or .aff");
+            });
+            ui.horizontal(|ui| {
+                let dir = &mut self.settings.behavior.dictionary_dir;
+                match dir {
+                    Some(path) => ui.label(path.display().to_string()),
+                    None => ui.weak("Dictionaries of the system"),
+                };
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button("Choose folder…").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                        *dir = Some(path);
+                        self.spell_language = None;
+                    }
+                }
+                if dir.is_some() && ui.button("Reset").clicked() {
+                    *dir = None;
+                    self.spell_language = None;
+                }
+            });
+            if self.dictionary_loading.is_some() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.weak("Loading the dictionary…");
+                });
+            } else if self.spell_checker.is_none()
+                && self.spell_language.is_some()
+                && ui.button("Load dictionary again").clicked()
+            {
+                self.spell_language = None;
+            }
+            let words = &mut self.settings.behavior.spell_words;
+            if !words.is_empty() {
+                ui.label("Added words");
+            }
+            let mut remove = None;
+            for word in words.iter() {
+                ui.horizontal(|ui| {
+                    ui.label(word);
+                    if bare_button_sized(egui_phosphor::regular::TRASH, 20., ui).clicked() {
+                        remove = Some(word.clone());
+                    }
+                });
+            }
+            if let Some(word) = remove {
+                words.remove(&word);
+            }
+        });
+
+        ui.collapsing("Templates", |ui| {
+            ui.label("Placeholders: {{date}}, {{cursor}}");
+            let mut remove: Option<usize> = None;
+            for (i, template) in self.userdata.templates.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut template.name);
+                    if bare_button_sized(egui_phosphor::regular::TRASH, 20., ui).clicked() {
+                        remove = Some(i);
+                    }
+                });
+                ui.add(
+                    egui::TextEdit::multiline(&mut template.text)
+                        .desired_rows(3)
+                        .desired_width(200.),
+                );
+                let mut due = template.deadline_days.is_some();
+                ui.horizontal(|ui| {
+                    if ui.checkbox(&mut due, "Due in").changed() {
+                        template.deadline_days = due.then_some(7);
+                    }
+                    if let Some(days) = &mut template.deadline_days {
+                        ui.add(egui::DragValue::new(days).suffix(" days"));
+                    }
+                });
+                ui.separator();
+            }
+            if let Some(i) = remove {
+                self.userdata.templates.remove(i);
+            }
+        });
+
+        ui.collapsing("Logbook template", |ui| {
+            ui.label("Fills in the first logbook entry of a day. Placeholders: {{date}}");
+            ui.add(
+                egui::TextEdit::multiline(&mut self.userdata.logbook_template)
+                    .hint_text("- [ ] What did I do?\n- [ ] Blockers?\n- [ ] Tomorrow?")
+                    .desired_rows(3)
+                    .desired_width(200.),
+            );
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui
+            .checkbox(&mut self.settings.behavior.always_on_top, "Always on top")
+            .changed()
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(
+                if self.settings.behavior.always_on_top {
+                    egui::WindowLevel::AlwaysOnTop
+                } else {
+                    egui::WindowLevel::Normal
+                },
+            ));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.checkbox(
+            &mut self.settings.behavior.capture_on_top,
+            "Quick capture always on top",
+        )
+        .on_hover_text("Show quick capture in a window of its own, above other windows");
+        #[cfg(target_arch = "wasm32")]
+        let _ = ctx;
+    }
+
+    fn sync_settings_ui(&mut self, ui: &mut Ui) {
+        if ui.button("Save profile").clicked() {
+            let key = match &self.settings.sync.storage_mode {
+                StorageMode::Local(LocalStorage { path }) => path.to_string_lossy().to_string(),
+                StorageMode::JsonBin(JsonBinStorage { bin_id, .. }) => {
+                    bin_id.clone().unwrap_or_default()
+                }
+            };
+            self.profiles.insert(key.clone(), self.current_profile());
+            self.active_profile = Some(key.clone());
+            self.profile_name = key;
+        }
+
+        if ui.button("New profile").clicked() {
+            self.pending_action = Some(PendingAction::NewProfile);
+        }
+
+        egui::ComboBox::from_label("Storage")
+            .selected_text(format!("{:?}", self.settings.sync.storage_mode))
+            .show_ui(ui, |ui| {
+                if ui
+                    .selectable_value(
+                        &mut self.settings.sync.storage_mode,
+                        StorageMode::Local(LocalStorage {
+                            path: PathBuf::from("backup.json"),
+                        }),
+                        "Local",
+                    )
+                    .changed()
+                {
+                    self.last_save_ok = false;
+                }
+                let is_jsonbin = matches!(self.settings.sync.storage_mode, StorageMode::JsonBin(_));
+                if ui.selectable_label(is_jsonbin, "JsonBin").clicked() && !is_jsonbin {
+                    self.set_up_jsonbin();
+                }
+            });
+
+        if !self.profiles.is_empty() {
+            let mut switch_to: Option<String> = None;
+            egui::ComboBox::from_label("Saved profiles")
+                .selected_text(
+                    self.active_profile
+                        .as_deref()
+                        .unwrap_or("Select saved profile"),
+                )
+                .show_ui(ui, |ui| {
+                    for (n, profile) in &self.profiles {
+                        let active = self.active_profile.as_ref() == Some(n);
+                        let label = format!(
+                            "{n} ({})",
+                            profile.sync_summary(&self.settings.appearance.date_format)
+                        );
+                        if ui.selectable_label(active, label).clicked() && !active {
+                            switch_to = Some(n.clone());
+                        }
+                    }
+                });
+            if let Some(name) = switch_to {
+                self.switch_profile(&name);
+            }
+        }
+
+        if let Some(name) = self.active_profile.clone() {
+            ui.horizontal(|ui| {
+                let r = ui.add(
+                    egui::TextEdit::singleline(&mut self.profile_name)
+                        .hint_text("Profile name")
+                        .desired_width(150.),
+                );
+                if r.lost_focus() {
+                    let new_name = self.profile_name.trim().to_string();
+                    self.rename_active_profile(&new_name);
+                    self.profile_name = self.active_profile.clone().unwrap_or_default();
+                }
+                if bare_button_sized(egui_phosphor::regular::TRASH, 20., ui)
+                    .on_hover_text("Delete this profile")
+                    .clicked()
+                {
+                    self.profiles.remove(&name);
+                    self.profile_secrets.remove(&name);
+                    self.active_profile = None;
+                    self.profile_name.clear();
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("SAVE").clicked() {
+                if let Err(e) = self.save_userdata(true) {
+                    self.toasts.error(format!("Could not save notes: {e}"));
+                }
+            }
+
+            if ui.button("RESTORE").clicked() {
+                self.pending_action = Some(PendingAction::RestoreNotes);
+            }
+        });
+
+        if ui
+            .button("Export app-state to storage backend")
+            .on_hover_text(
+                "Write the notes kept by the app to the storage, even if it could not be loaded. \
+                For recovering a broken storage.",
+            )
+            .clicked()
+        {
+            self.pending_action = Some(PendingAction::ExportToStorage);
+        }
+
+        ui.collapsing("Import shared notes", |ui| {
+            ui.label("Notes exported with \"Export subtree\" are added as new notes.");
+            ui.add(
+                egui::TextEdit::multiline(&mut self.bundle_text)
+                    .hint_text("Paste an exported JSON bundle")
+                    .desired_rows(3),
+            );
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        !self.bundle_text.trim().is_empty(),
+                        egui::Button::new("Import"),
+                    )
+                    .clicked()
+                {
+                    let text = std::mem::take(&mut self.bundle_text);
+                    if let Err(e) = self.import_bundle(&text) {
+                        self.toasts.error(format!("Could not import notes: {e}"));
+                        self.bundle_text = text;
+                    }
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button("From file…").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Bundle", &["json"])
+                        .pick_file()
+                    {
+                        let imported = std::fs::read_to_string(&path)
+                            .map_err(anyhow::Error::from)
+                            .and_then(|json| self.import_bundle(&json));
+                        if let Err(e) = imported {
+                            self.toasts
+                                .error(format!("Could not import {}: {e}", path.display()));
+                        }
+                    }
+                }
+            });
+        });
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.credentials.username)
+                    .hint_text("Username")
+                    .desired_width(150.),
+            );
+            ui.label("USER");
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut *self.credentials.secret)
+                    .hint_text("Encryption Key")
+                    .desired_width(150.)
+                    .password(true),
+            );
+            ui.label("SECRET");
+        });
+
+        let mut test_connection = false;
+        match &mut self.settings.sync.storage_mode {
+            StorageMode::Local(LocalStorage { path }) => {
+                let mut s = path.to_string_lossy().to_string();
+                if ui.text_edit_singleline(&mut s).changed() {
+                    *path = PathBuf::from(s);
+                    self.last_save_ok = false;
+                }
+                #[cfg(target_arch = "wasm32")]
+                ui.label(
+                    "The notes are kept in this browser under this name. Clearing the data of \
+                     the site deletes them, use JsonBin to keep them safe and synced.",
+                );
+            }
+            StorageMode::JsonBin(JsonBinStorage {
+                masterkey,
+                bin_id,
+                backup_path,
+            }) => {
+                test_connection = ui
+                    .horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(masterkey)
+                                .hint_text("JsonBin master key")
+                                .desired_width(150.)
+                                .password(true),
+                        );
+                        ui.label("MASTER KEY");
+                        ui.button("Test connection")
+                            .on_hover_text("Check the master key with JsonBin, without saving")
+                            .clicked()
+                    })
+                    .inner;
+                #[cfg(not(target_arch = "wasm32"))]
+                ui.horizontal(|ui| {
+                    let mut backup = backup_path.is_some();
+                    if ui
+                        .checkbox(&mut backup, "Local backup")
+                        .on_hover_text("Also write the encrypted notes to this file on every save")
+                        .changed()
+                    {
+                        *backup_path = backup.then(|| PathBuf::from("backup.json"));
+                    }
+                    if let Some(path) = backup_path {
+                        let mut s = path.to_string_lossy().to_string();
+                        if ui.text_edit_singleline(&mut s).changed() {
+                            *path = PathBuf::from(s);
+                        }
+                    }
+                });
+                #[cfg(target_arch = "wasm32")]
+                let _ = backup_path;
+                ui.label(format!(
+                    "Payload size: {}",
+                    format_size(payload_size(ui.ctx(), &mut self.userdata))
+                ))
+                .on_hover_text("The size of the notes before encryption");
+                if bin_id.is_none() {
+                    ui.label("Your data has never been published.");
+
+                    if ui.button("Restore from username").clicked() {
+                        *bin_id = Some(self.credentials.username.clone());
+
+                        if let Err(e) = self
+                            .settings
+                            .sync
+                            .storage_mode
+                            .load_userdata(&self.channels)
+                        {
+                            self.toasts.error(format!("Could not load notes: {e}"));
+                        }
+                    }
+
+                    if ui.button("Publish as new").clicked() {
+                        if let Err(e) = self.settings.sync.storage_mode.save_userdata(
+                            &self.userdata,
+                            &self.credentials,
+                            &self.channels,
+                            true,
+                        ) {
+                            self.toasts.error(format!("Error publishing notes! {e}"));
+                        }
+                    }
+                } else {
+                    ui.label(format!("Bin ID: {}", bin_id.clone().unwrap_or_default()));
+                    if ui.button("Copy to clipboard").clicked() {
+                        ui.output_mut(|o| o.copied_text = bin_id.clone().unwrap_or_default());
+                    }
+                }
+            }
+        }
+        if test_connection {
+            if let StorageMode::JsonBin(storage) = &self.settings.sync.storage_mode {
+                if let Err(e) = storage.test_connection(&self.channels) {
+                    self.toasts
+                        .error(format!("Could not connect to JsonBin: {e}"));
+                }
+            }
+        }
+
+        #[cfg(debug_assertions)]
+        {
+            if ui.button("restore from debug save").clicked() {
+                self.userdata =
+                    crate::userdata_from_json(&std::fs::read_to_string("debug.json").unwrap())
+                        .unwrap();
+                self.notes_replaced();
+            }
+            if ui.button("save to debug file").clicked() {
+                serde_json::to_writer_pretty(
+                    std::fs::File::create("debug.json").unwrap(),
+                    &self.userdata,
+                )
+                .unwrap();
+            }
+        }
+
+        #[cfg(any(debug_assertions, feature = "demo"))]
+        ui.horizontal(|ui| {
+            let seed_id = Id::new("sample_data_seed");
+            let mut seed = ui.data_mut(|d| *d.get_temp_mut_or_default::<u64>(seed_id));
+            if ui
+                .button("Load sample data")
+                .on_hover_text("Replace the data by generated notes, for trying out views")
+                .clicked()
+            {
+                self.pending_action = Some(PendingAction::LoadSampleData(seed));
+            }
+            if ui
+                .add(egui::DragValue::new(&mut seed).prefix("seed "))
+                .changed()
+            {
+                ui.data_mut(|d| d.insert_temp(seed_id, seed));
+            }
+        });
+    }
+
+    /// The configurable system wide hotkey and a reference of the keyboard shortcuts in the app
+    fn shortcut_settings_ui(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.horizontal(|ui| {
+                ui.label("Quick capture hotkey");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.settings.shortcuts.capture_hotkey)
+                        .desired_width(120.),
+                );
+                if ui.button("Apply").clicked() {
+                    self.register_capture_hotkey(ctx);
+                }
+            });
+            ui.separator();
+        }
+
+        egui::Grid::new("shortcut_reference").show(ui, |ui| {
+            for (action, keys) in [
+                ("Undo", ctx.format_shortcut(&UNDO_SHORTCUT)),
+                ("Redo", ctx.format_shortcut(&REDO_SHORTCUT)),
+                ("Command palette", ctx.format_shortcut(&PALETTE_SHORTCUT)),
+                ("Go to note", ctx.format_shortcut(&GO_TO_NOTE_SHORTCUT)),
+                ("Bold, italic, link in the editor", {
+                    let keys = FORMAT_SHORTCUTS.map(|(shortcut, _)| ctx.format_shortcut(&shortcut));
+                    keys.join(", ")
+                }),
+                ("Close editor or focus mode", "Esc".to_string()),
+                ("Previous/next note in focus mode", "Arrow keys".to_string()),
+            ] {
+                ui.label(action);
+                ui.label(RichText::new(keys).monospace());
+                ui.end_row();
+            }
+        });
+    }
+}
+
+impl eframe::App for MeteoraApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.update_active_profile();
+        eframe::set_value(storage, eframe::APP_KEY, self);
+        if let Err(e) = self.save_userdata(false) {
+            error!("{e}")
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        use egui_phosphor::regular::*;
+
+        if self.settings.behavior.continuous_repaint && !self.in_background(ctx) {
+            ctx.request_repaint();
+        }
+        self.apply_motion(ctx);
+
+        set_time_zone(self.settings.appearance.utc_offset);
+        set_high_contrast(self.settings.appearance.high_contrast);
+        set_tag_palette(self.settings.appearance.tag_palette);
+        self.image_previews.remote_enabled = self.settings.behavior.load_remote_images;
+        self.poll_capture_hotkey(ctx);
+        self.poll_file_watcher(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.note_windows_ui(ctx);
+
+        if self.channels.saved_channel.1.try_iter().count() > 0 {
+            self.last_save_ok = true;
+            self.last_saved = Some(chrono::Utc::now());
+            self.record_profile_sync();
+            if self.new_profile_requested {
+                self.start_new_profile();
+            }
+        }
+
+        if let Ok(id) = self.channels.id_channel.1.try_recv() {
+            self.credentials.username = id.clone();
+            match &mut self.settings.sync.storage_mode {
+                StorageMode::Local(_) => {}
+                StorageMode::JsonBin(JsonBinStorage { bin_id, .. }) => {
+                    *bin_id = Some(id);
+                    self.toasts.info("Registered JsonBin.".to_string());
+                }
+            }
+        }
+
+        // keep the setting in sync with zooming via keyboard shortcuts, within the same range
+        if ctx.zoom_factor() != self.ui_state.applied_ui_scale {
+            let zoom = ctx
+                .zoom_factor()
+                .clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end());
+            if zoom != ctx.zoom_factor() {
+                ctx.set_zoom_factor(zoom);
+            }
+            self.settings.appearance.ui_scale = zoom;
+            self.ui_state.applied_ui_scale = zoom;
+        }
+
+        if !ctx.wants_keyboard_input() {
+            let redo = ctx.input_mut(|i| i.consume_shortcut(&REDO_SHORTCUT));
+            let undo = ctx.input_mut(|i| i.consume_shortcut(&UNDO_SHORTCUT));
+            if redo {
+                if self.undo_stack.redo(&mut self.userdata.notes).is_none() {
+                    self.toasts.info("Nothing to redo");
+                }
+            } else if undo && self.undo_stack.undo(&mut self.userdata.notes).is_none() {
+                self.toasts.info("Nothing to undo");
+            }
+        }
+
+        match self.channels.loaded_channel.1.try_recv() {
+            Ok(Loaded::Notes(blob)) => self.notes_loaded(&blob),
+            Ok(Loaded::Nothing) => self.nothing_loaded(),
+            Err(_) => {}
+        }
+        if !self.lock.is_locked() {
+            if let Some(id) = self.requested_note.take() {
+                if self.userdata.notes.contains_key(&id) {
+                    self.active_note = Some(id);
+                } else {
+                    self.toasts
+                        .warning(format!("The linked note {id} does not exist."));
+                }
+            }
+            self.wake_snoozed(ctx);
+        }
+
+        if let Ok(msg) = self.channels.msg_channel.1.try_recv() {
+            match msg {
+                Message::Info(t) => {
+                    info!("{t}");
+                    self.toasts.info(t)
+                }
+                Message::Warn(t) => {
+                    warn!("{t}");
+                    self.toasts.warning(t)
+                }
+                Message::Err(t) => {
+                    error!("{t}");
+                    if self.new_profile_requested {
+                        // the notes may not be saved, keep them
+                        self.new_profile_requested = false;
+                        self.toasts.warning("Not starting a new profile.");
+                    }
+                    self.toasts.error(t)
+                }
+            };
+        }
+
+        if self.focused_note.is_some() {
+            self.focus_ui(ctx);
+            self.capture_ui(ctx);
+            self.toasts.show(ctx);
+            return;
+        }
+
+        if !self.lock.is_locked() {
+            // the editor makes a link with the same shortcut
+            let editing_text = self
+                .active_note
+                .is_some_and(|id| ctx.memory(|m| m.has_focus(note_text_id(id))));
+            let (commands, go_to_note) = ctx.input_mut(|i| {
+                (
+                    i.consume_shortcut(&PALETTE_SHORTCUT),
+                    !editing_text && i.consume_shortcut(&GO_TO_NOTE_SHORTCUT),
+                )
+            });
+            if commands || go_to_note {
+                // the same shortcut again closes it
+                self.palette = match &self.palette {
+                    Some(open) if open.notes_only == go_to_note => None,
+                    _ => Some(CommandPalette {
+                        notes_only: go_to_note,
+                        ..Default::default()
+                    }),
+                };
+            }
+        }
+        self.command_palette_ui(ctx);
+
+        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            //    ui.allocate_exact_size(vec2(ui.available_width(), 30.), Sense::drag());
+            let padding = 1.;
+            ui.add_space(padding + 2.);
+
+            ui.horizontal(|ui| {
+                let not_settings = !self.ui_state.settings_enabled;
+
+                ui.selectable_value(
+                    &mut self.ui_state.settings_enabled,
+                    not_settings,
+                    egui::RichText::new(if not_settings { LIST } else { X }).size(32.0),
+                );
+                let search = ui.add(
+                    egui::TextEdit::singleline(&mut self.filter)
+                        .frame(false)
+                        .hint_text("🔍 Search notes..."),
+                );
+                if search.lost_focus() {
+                    self.remember_filter();
+                }
+
+                // offer recent searches while the search box is empty
+                let popup_id = ui.make_persistent_id("recent_filters");
+                if search.has_focus() && self.filter.is_empty() && !self.recent_filters.is_empty() {
+                    ui.memory_mut(|m| m.open_popup(popup_id));
+                } else if !self.filter.is_empty() {
+                    ui.memory_mut(|m| {
+                        if m.is_popup_open(popup_id) {
+                            m.close_popup()
+                        }
+                    });
+                }
+                egui::popup_below_widget(
+                    ui,
+                    popup_id,
+                    &search,
+                    egui::PopupCloseBehavior::CloseOnClickOutside,
+                    |ui| {
+                        ui.set_min_width(200.);
+                        for recent in &self.recent_filters {
+                            if ui.selectable_label(false, recent).clicked() {
+                                self.filter = recent.clone();
+                                ui.memory_mut(|m| m.close_popup());
+                            }
+                        }
+                    },
+                );
+
+                if !self.filter.is_empty() && bare_button(X, ui).clicked() {
+                    self.remember_filter();
+                    self.filter.clear();
+                }
+            });
+
+            ui.add_space(padding);
+        });
+        self.storage_banner_ui(ctx);
+
+        let pressed_outside_id = Id::new("settings_pressed_outside");
+        if !self.ui_state.settings_enabled {
+            ctx.data_mut(|d| d.remove::<bool>(pressed_outside_id));
+        } else {
+            // a popup of the settings may reach out of the panel
+            let popup_open = ctx.memory(|m| m.any_popup_open());
+            let panel = egui::SidePanel::left("side_panel_settings").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(SLIDERS);
+                    ui.label("SETTINGS");
+                    ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+                        if bare_button(X, ui).on_hover_text("Close (Esc)").clicked() {
+                            self.ui_state.settings_enabled = false;
+                        }
+                    });
+                });
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    for tab in SettingsTab::ALL {
+                        ui.selectable_value(&mut self.ui_state.settings_tab, tab, tab.name());
+                    }
+                });
+                ui.separator();
+
+                match self.ui_state.settings_tab {
+                    SettingsTab::Appearance => self.appearance_settings_ui(ui, ctx),
+                    SettingsTab::Behavior => self.behavior_settings_ui(ui, ctx),
+                    SettingsTab::Sync => self.sync_settings_ui(ui),
+                    SettingsTab::Shortcuts => self.shortcut_settings_ui(ui, ctx),
+                }
+            });
+
+            // a click closes the panel only if it was pressed outside while the panel was
+            // open, so dragging a slider out of it or the click opening it don't count
+            let (pressed, clicked, released, origin) = ctx.input(|i| {
+                (
+                    i.pointer.any_pressed(),
+                    i.pointer.any_click(),
+                    i.pointer.any_released(),
+                    i.pointer.press_origin(),
+                )
+            });
+            if pressed && !popup_open {
+                let outside = origin.is_some_and(|pos| !panel.response.rect.contains(pos));
+                ctx.data_mut(|d| d.insert_temp(pressed_outside_id, outside));
+            }
+            if released {
+                let outside = ctx.data_mut(|d| d.remove_temp::<bool>(pressed_outside_id));
+                if clicked && outside == Some(true) {
+                    self.ui_state.settings_enabled = false;
+                }
+            }
+
+            // Esc leaves a text field first, like in the editor
+            let close_requested = ctx.memory(|m| m.focused().is_none() && !m.any_popup_open())
+                && self.pending_action.is_none()
+                && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape));
+            if close_requested {
+                self.ui_state.settings_enabled = false;
+            }
+        }
+
+        egui::SidePanel::left("scratchpad")
+            .width_range(0.0..=1000.)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if bare_button_sized(NOTEPAD, 20., ui).clicked() {
+                        self.ui_state.scratchpad_enabled = !self.ui_state.scratchpad_enabled;
+                    }
+
+                    if !self.ui_state.scratchpad_enabled {
+                        return;
+                    }
+                    ui.label("SCRATCH");
+                });
+                if !self.ui_state.scratchpad_enabled {
+                    return;
+                }
+                ui.separator();
+                if bare_button(FILE_PLUS, ui).clicked() {
+                    self.userdata.scratchpad.items.push(Default::default());
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let mut remove: Option<usize> = None;
+                    let mut new_note: Option<Note> = None;
+                    let mut i = 0;
+                    dnd(ui, "dnd_example").show_vec(
+                        &mut self.userdata.scratchpad.items,
+                        |ui, item, handle, _state| {
+                            let mut state =
+                                egui::collapsing_header::CollapsingState::load_with_default_open(
+                                    ui.ctx(),
+                                    ui.make_persistent_id(("scratch", i)),
+                                    !item.collapsed,
+                                );
+                            state.set_open(!item.collapsed);
+                            let (toggle, _, _) = state
+                                .show_header(ui, |ui| {
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut item.title)
+                                            .hint_text("New scratch")
+                                            .frame(false)
+                                            .desired_width(ui.available_width() - 30.),
+                                    );
+                                    handle.ui(ui, |ui| {
+                                        ui.label(DOTS_SIX_VERTICAL);
+                                    });
+                                })
+                                .body_unindented(|ui| {
+                                    ui.indent(i, |ui| {
+                                        ui.style_mut().visuals.selection.stroke = Stroke::NONE;
+                                        egui::TextEdit::multiline(&mut item.text)
+                                            .desired_width(f32::INFINITY)
+                                            .hint_text("Enter some quick thoughts here!")
+                                            .show(ui);
+                                        ui.horizontal(|ui| {
+                                            if bare_button(NOTE, ui)
+                                                .on_hover_text("Turn into note")
+                                                .clicked()
+                                            {
+                                                let mut n = Note::new();
+                                                n.text = if item.title.is_empty() {
+                                                    item.text.clone()
+                                                } else {
+                                                    format!("{}\n{}", item.title, item.text)
+                                                };
+                                                new_note = Some(n);
+                                                remove = Some(i);
+                                            }
+
+                                            if bare_button(TRASH, ui)
+                                                .on_hover_text("Delete")
+                                                .clicked()
+                                            {
+                                                remove = Some(i);
+                                            }
+                                        });
+                                    });
+                                });
+                            if toggle.clicked() {
+                                item.collapsed = !item.collapsed;
+                            }
+                            i += 1;
+                        },
+                    );
+
+                    if let Some(remove) = remove {
+                        self.userdata.scratchpad.items.remove(remove);
+                    }
+                    if let Some(n) = new_note {
+                        self.userdata.add_note(n);
+                    }
+                });
+            });
+
+        egui::SidePanel::left("logbook")
+            .width_range(0.0..=1000.)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if bare_button_sized(NOTEBOOK, 20., ui).clicked() {
+                        self.ui_state.logbook_enabled = !self.ui_state.logbook_enabled;
+                    }
+
+                    if !self.ui_state.logbook_enabled {
+                        return;
+                    }
+                    ui.label("LOGBOOK");
+                    ui.toggle_value(&mut self.ui_state.logbook_week, "Week")
+                        .on_hover_text("Review the last 7 days");
+                });
+                if !self.ui_state.logbook_enabled {
+                    return;
+                }
+                ui.separator();
+                let current_date = today();
+                if self.ui_state.logbook_week {
+                    self.logbook_week_ui(ui, current_date);
+                    return;
+                }
+
+                let mut jump_to = None;
+                ui.horizontal(|ui| {
+                    if bare_button(FILE_PLUS, ui)
+                        .on_hover_text("Add an entry for today")
+                        .clicked()
+                    {
+                        self.userdata.add_log_entry(current_date);
+                        jump_to = Some(current_date);
+                    }
+                    let mut date = self.ui_state.logbook_date.unwrap_or(current_date);
+                    if ui
+                        .add(
+                            egui_extras::DatePickerButton::new(&mut date)
+                                .id_salt("logbook_date")
+                                .format(valid_date_format(&self.settings.appearance.date_format)),
+                        )
+                        .changed()
+                    {
+                        self.ui_state.logbook_date = Some(date);
+                        jump_to = Some(date);
+                    }
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.ui_state.logbook_search)
+                            .hint_text(format!("{MAGNIFYING_GLASS} Search"))
+                            .desired_width(120.),
+                    );
+                    if bare_button(CALENDAR_CHECK, ui)
+                        .on_hover_text("Weekly summary")
+                        .clicked()
+                    {
+                        let monday = current_date
+                            - chrono::Days::new(
+                                chrono::Datelike::weekday(&current_date)
+                                    .num_days_from_monday()
+                                    .into(),
+                            );
+                        self.ui_state.summary_week = Some(monday);
+                    }
+                    ui.menu_button(EXPORT, |ui| {
+                        egui::ComboBox::from_label("Order")
+                            .selected_text(format!("{:?}", self.ui_state.journal_order))
+                            .show_ui(ui, |ui| {
+                                for order in [JournalOrder::NewestFirst, JournalOrder::OldestFirst]
+                                {
+                                    ui.selectable_value(
+                                        &mut self.ui_state.journal_order,
+                                        order,
+                                        format!("{order:?}"),
+                                    );
+                                }
+                            });
+                        egui::ComboBox::from_label("Range")
+                            .selected_text(format!("{:?}", self.ui_state.journal_range))
+                            .show_ui(ui, |ui| {
+                                for range in [
+                                    JournalRange::LastWeek,
+                                    JournalRange::LastMonth,
+                                    JournalRange::All,
+                                ] {
+                                    ui.selectable_value(
+                                        &mut self.ui_state.journal_range,
+                                        range,
+                                        format!("{range:?}"),
+                                    );
+                                }
+                            });
+                        let journal = || {
+                            logbook_to_markdown(
+                                &self
+                                    .ui_state
+                                    .journal_range
+                                    .filter(&self.userdata.logbook, current_date),
+                                self.ui_state.journal_order,
+                            )
+                        };
+                        if ui.button("Copy as Markdown").clicked() {
+                            let text = journal();
+                            ui.output_mut(|o| o.copied_text = text);
+                            self.toasts.info("Copied logbook to clipboard.");
+                            ui.close_menu();
+                        }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        ui.horizontal(|ui| {
+                            let mut path = self.ui_state.journal_path.to_string_lossy().to_string();
+                            if ui.text_edit_singleline(&mut path).changed() {
+                                self.ui_state.journal_path = PathBuf::from(path);
+                            }
+                            if ui.button("Save to file…").clicked() {
+                                match std::fs::write(&self.ui_state.journal_path, journal()) {
+                                    Ok(_) => self.toasts.info(format!(
+                                        "Saved logbook to {}",
+                                        self.ui_state.journal_path.display()
+                                    )),
+                                    Err(e) => {
+                                        self.toasts.error(format!("Could not save logbook: {e}"))
+                                    }
+                                };
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                });
+                // the newest day on or before the picked one
+                let jump_to = jump_to.and_then(|date| {
+                    self.userdata
+                        .logbook
+                        .range(..=date)
+                        .next_back()
+                        .map(|(d, _)| *d)
+                });
+                let search = self.ui_state.logbook_search.to_lowercase();
+
+                let mut promote = None;
+                let mut remove_day = None;
+                // built from the logbook as it was before the entry being edited
+                let index = self
+                    .logbook_index
+                    .get_or_insert_with(|| LogbookIndex::new(&self.userdata.logbook));
+                let mut index_stale = false;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (d, items) in self.userdata.logbook.iter_mut().rev() {
+                        if !search.is_empty()
+                            && !items.iter().any(|item| {
+                                item.text.to_lowercase().contains(&search)
+                                    || item.tags.iter().any(|t| t.to_lowercase().contains(&search))
+                            })
+                        {
+                            continue;
+                        }
+                        let jump = jump_to == Some(*d);
+                        let r = egui::CollapsingHeader::new(format_date(
+                            *d,
+                            &self.settings.appearance.date_format,
+                        ))
+                        .default_open(d == &current_date)
+                        .open(jump.then_some(true))
+                        .show_unindented(ui, |ui| {
+                            if bare_button(FILE_PLUS, ui).clicked() {
+                                items.push(Note::new());
+                            }
+                            let mut remove: Option<usize> = None;
+
+                            for (i, item) in items.iter_mut().enumerate() {
+                                ui.horizontal(|ui| {
+                                    index_stale |= logbook_item_ui(ui, item, index);
+                                    tag_ui(ui, item, &mut self.userdata.tags);
+                                    if bare_button(NOTE, ui)
+                                        .on_hover_text("Promote to note")
+                                        .clicked()
+                                    {
+                                        promote = Some(PendingAction::PromoteLogItem(*d, i));
+                                    }
+                                    if ui.button("del").clicked() {
+                                        remove = Some(i);
+                                    }
+                                });
+                            }
+                            if let Some(r) = remove {
+                                index_stale = true;
+                                items.remove(r);
+                                if items.is_empty() {
+                                    remove_day = Some(*d);
+                                }
+                            }
+
+                            ui.collapsing("Summary", |ui| {
+                                let mut text = String::default();
+
+                                for n in items.iter() {
+                                    text.push_str(&format!("\n- {}", n.text));
+                                }
+
+                                ui.label(text);
+                            });
+                        });
+                        if jump {
+                            r.header_response.scroll_to_me(Some(egui::Align::TOP));
+                        }
+                    }
+                });
+                if let Some(day) = remove_day {
+                    self.userdata.logbook.remove(&day);
+                }
+                if index_stale {
+                    self.logbook_index = None;
+                }
+                if promote.is_some() {
+                    self.pending_action = promote;
+                }
+            });
+
+        egui::SidePanel::left("side_panel")
+            .width_range(0.0..=1000.)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if bare_button_sized(TAG, 20., ui).clicked() {
+                        self.ui_state.tags_enabled = !self.ui_state.tags_enabled;
+                    }
+                    if !self.ui_state.tags_enabled {
+                        return;
+                    }
+                    ui.label("TAGS");
+                });
+                if !self.ui_state.tags_enabled {
+                    return;
+                }
+                ui.separator();
+
+                let open: Vec<u128> = self
+                    .userdata
+                    .notes
+                    .values()
+                    .filter(|n| !n.is_done())
+                    .map(|n| n.id)
+                    .collect();
+                let (_, effort_by_tag) = self.userdata.workload(&open);
+                let mut dropped = None;
+                ui.horizontal_wrapped(|ui| {
+                    for tag in &self.userdata.tags {
+                        let count = self.tag_usage.count(tag);
+                        let effort = effort_by_tag.get(tag).copied().unwrap_or_default();
+                        let used = count > 0;
+                        // Hide tags that are unused.
+                        if !used && !self.ui_state.show_unused_tags {
+                            continue;
+                        }
+                        let contained = self.active_tags.contains(tag);
+
+                        let tag_color = color_from_tag(tag);
+
+                        if contained {
+                            ui.style_mut().visuals.selection.bg_fill =
+                                tag_color.gamma_multiply(gamma_mult());
+                        }
+
+                        let r = ui.add(SelectableLabel::new(
+                            contained,
+                            if contained {
+                                RichText::new(tag).color(readable_text(&tag_color))
+                            } else if !used {
+                                RichText::new(tag).weak().italics()
+                            } else {
+                                RichText::new(tag)
+                            },
+                        ));
+                        if effort > 0. {
+                            ui.label(RichText::new(format_effort(effort)).small().weak());
+                        }
+                        let r = if used && effort > 0. {
+                            r.on_hover_text(format!(
+                                "Used by {count} note{}, with {} of work left",
+                                if count == 1 { "" } else { "s" },
+                                format_effort(effort)
+                            ))
+                        } else if used {
+                            r.on_hover_text(format!(
+                                "Used by {count} note{}",
+                                if count == 1 { "" } else { "s" }
+                            ))
+                        } else {
+                            if bare_button_sized(TRASH, 14., ui)
+                                .on_hover_text("Delete this tag")
+                                .clicked()
+                            {
+                                self.pending_action = Some(PendingAction::DeleteTag(tag.clone()));
+                            }
+                            r.on_hover_text("Not used by any note. Drop a note here to apply it.")
+                        };
+                        if r.dnd_hover_payload::<DraggedNote>().is_some() {
+                            ui.painter().rect_stroke(
+                                r.rect.expand(2.),
+                                4.,
+                                Stroke::new(2.0_f32, tag_color),
+                            );
+                        }
+                        if let Some(note) = r.dnd_release_payload::<DraggedNote>() {
+                            dropped = Some((note.0, tag.clone()));
+                        }
+                        if r.clicked() {
+                            // if ui.selectable_label(contained, tag).clicked() {
+                            if contained {
+                                self.active_tags.remove(tag);
+                            } else {
+                                self.active_tags.insert(tag.clone());
+                            }
+                        }
+                    }
+                });
+
+                if let Some((id, tag)) = dropped {
+                    // shift-drop removes the tag instead
+                    let remove = ctx.input(|i| i.modifiers.shift);
+                    self.tag_dropped_note(id, &tag, remove);
+                }
+
+                ui.separator();
+
+                if !self.active_tags.is_empty() && ui.button("Show all").clicked() {
+                    self.active_tags.clear();
+                }
+                ui.checkbox(&mut self.ui_state.show_unused_tags, "Show unused tags");
+
+                ui.collapsing("Workload", |ui| self.workload_ui(ui));
+
+                let snoozed = self.userdata.snoozed_notes(today()).len();
+                ui.collapsing(format!("Snoozed ({snoozed})"), |ui| self.snoozed_ui(ui))
+                    .header_response
+                    .on_hover_text("Notes hidden until a later day");
+
+                ui.collapsing("Edit", |ui| {
+                    if ui.button("Add tag").clicked() {
+                        self.userdata.tags.push("New Tag".into());
+                    }
+
+                    let all_tags = self.userdata.tags.clone();
+                    egui::ScrollArea::horizontal().show(ui, |ui| {
+                        for tag in self.userdata.tags.iter_mut() {
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .button("🗑")
+                                    .on_hover_text("Delete this tag from list and all notes.")
+                                    .clicked()
+                                {
+                                    self.pending_action =
+                                        Some(PendingAction::DeleteTag(tag.clone()));
+                                }
+                                if let Some(action) = tag_actions_menu(ui, tag, &all_tags) {
+                                    self.pending_action = Some(action);
+                                }
+                                let old_tag = tag.clone();
+                                if ui.text_edit_singleline(tag).changed() {
+                                    // If a tag is renamed, we need to rename it in all notes.
+                                    for (id, note) in self.userdata.notes.iter_mut() {
+                                        if note.tags.contains(&old_tag) {
+                                            note.tags.remove(&old_tag);
+                                            note.tags.insert(tag.clone());
+                                            self.tag_usage.update(*id, Some(&note.tags));
+                                        }
+                                        if note.primary_tag.as_ref() == Some(&old_tag) {
+                                            note.primary_tag = Some(tag.clone());
+                                        }
+                                    }
+                                }
+                            });
+                        }
+                    });
+                });
+
+                if egui::DragAndDrop::has_payload_of_type::<DraggedNote>(ctx) {
+                    ui.separator();
+                    let (_, payload) = ui.dnd_drop_zone::<DraggedNote, _>(
+                        egui::Frame::default().inner_margin(8.),
+                        |ui| {
+                            ui.label(format!("{TRASH} Drop here to delete"));
+                        },
+                    );
+                    if let Some(note) = payload {
+                        self.pending_action = Some(PendingAction::DeleteNote(note.0));
+                    }
+                }
+            });
+
+        self.status_bar_ui(ctx);
+        self.diagnostics_ui(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // Offer restore fuctionality if local
+
+            match self.settings.appearance.viewmode {
+                ViewMode::Board => {
+                    boardview(ui, self);
+                }
+                ViewMode::List => {
+                    listview(ui, self);
+                }
+                ViewMode::Agenda => {
+                    agendaview(ui, self);
+                }
+                ViewMode::Graph => {
+                    ui.horizontal(|ui| {
+                        ui.label("Work in progress!");
+                        if bare_button(egui_phosphor::regular::ARROWS_CLOCKWISE, ui)
+                            .on_hover_text("Rebuild the graph")
+                            .clicked()
+                        {
+                            self.dependency_graph
+                                .rebuild(&self.userdata.notes, &self.graph_positions);
+                            self.focus_graph = None;
+                        }
+                    });
+                    self.graph_focus_ui(ui);
+
+                    let g = match self.graph_focus {
+                        Some(focus) => self.focus_graph.get_or_insert_with(|| {
+                            let ids = dependency_neighborhood(
+                                focus,
+                                self.graph_depth,
+                                &self.userdata.notes,
+                            );
+                            self.dependency_graph.subgraph(&ids)
+                        }),
+                        None => self.dependency_graph.graph_mut(),
+                    };
+                    ui.add(
+                        &mut GraphView::<_, _, _, _, DefaultNodeShape, DefaultEdgeShape>::new(g)
+                            .with_interactions(
+                                &SettingsInteraction::new().with_dragging_enabled(true),
+                            )
+                            .with_styles(&SettingsStyle::new().with_labels_always(true)),
+                    );
+                    for (_, node) in g.nodes_iter() {
+                        self.graph_positions
+                            .insert(*node.payload(), node.location());
+                    }
+                    // ui.add(&mut GraphView::new(g).with_custom_node_draw(
+                    //     |ctx, n, meta, _style, l| {
+                    //         // lets draw a rect with label in the center for every node
+
+                    //         // find node center location on the screen coordinates
+                    //         let node_center_loc = n.screen_location(meta).to_pos2();
+
+                    //         // find node radius accounting for current zoom level; we will use it as a reference for the rect and label sizes
+                    //         let rad = n.screen_radius(meta);
+
+                    //         // first create rect shape
+                    //         let size = Vec2::new(rad * 1.5, rad * 1.5);
+                    //         let rect = Rect::from_center_size(node_center_loc, size);
+                    //         let shape_rect = Shape::rect_stroke(
+                    //             rect,
+                    //             Rounding::default(),
+                    //             Stroke::new(1., n.color(ctx)),
+                    //         );
+
+                    //         // then create shape for the label placing it in the center of the rect
+                    //         let color = ctx.style().visuals.text_color();
+                    //         let galley = ctx.fonts(|f| {
+                    //             f.layout_no_wrap(
+                    //                 n.data().unwrap().clone(),
+                    //                 FontId::new(rad, FontFamily::Monospace),
+                    //                 color,
+                    //             )
+                    //         });
+                    //         // we need to offset a bit to place the label in the center of the rect
+                    //         let label_loc = Pos2::new(
+                    //             node_center_loc.x - rad / 2.,
+                    //             node_center_loc.y - rad / 2.,
+                    //         );
+                    //         let shape_label = TextShape::new(label_loc, galley, Color32::BLACK);
+
+                    //         // add shapes to the drawing layers; the drawing process is happening in the widget lifecycle.
+                    //         l.add(shape_rect);
+                    //         l.add(shape_label);
+                    //     },
+                    // ));
+                }
+            }
+
+            //create a round button at an absolute position
+
+            let add_button = draw_note_add_button(ui);
+            let mut new_note: Option<(Note, Option<usize>)> = None;
+            if add_button.clicked() {
+                new_note = Some((Note::new(), None));
+            }
+            add_button.context_menu(|ui| {
+                if self.userdata.templates.is_empty() {
+                    ui.label("Save a note as template to see it here.");
+                }
+                for template in &self.userdata.templates {
+                    if ui.button(&template.name).clicked() {
+                        new_note = Some(template.instantiate(today()));
+                        ui.close_menu();
+                    }
+                }
+            });
+
+            if let Some((n, cursor)) = new_note {
+                let id = self.create_note(n);
+                if let Some(cursor) = cursor {
+                    ui.data_mut(|d| d.insert_temp(Id::new(("note_cursor", id)), cursor));
+                }
+            }
+
+            // Draw black background if editing note
+            if self.active_note.is_some() {
+                let rect_all = ui.allocate_rect(Rect::EVERYTHING, Sense::click());
+                ui.painter_at(Rect::EVERYTHING).rect_filled(
+                    Rect::EVERYTHING,
+                    Rounding::ZERO,
+                    Color32::from_rgba_premultiplied(0, 0, 0, 150),
+                );
+
+                // if rect is clicked, close edit mode
+                if rect_all.clicked() {
+                    self.active_note = None;
+                }
+            }
+        });
+
+        if let Some(id) = self.active_note {
+            // clean invalid id (because of deletion)
+            if !self.userdata.notes.contains_key(&id) {
+                self.active_note = None;
+            }
+
+            // Esc leaves the text first, then closes the editor
+            let close_requested = ctx.input(|i| i.key_pressed(egui::Key::Escape))
+                && ctx.memory(|m| m.focused().is_none() && !m.any_popup_open())
+                && self.pending_action.is_none();
+
+            let screen = ctx.screen_rect().shrink(20.);
+            let rect = self.ui_state.editor_rect.unwrap_or_else(|| {
+                Rect::from_center_size(screen.center(), EDITOR_SIZE.min(screen.size()))
+            });
+            let window = egui::Window::new("Edit note")
+                .id(Id::new("note_editor"))
+                .collapsible(false)
+                .title_bar(false)
+                .resizable(true)
+                .constrain(true)
+                .default_pos(rect.min)
+                .default_size(rect.size())
+                .max_size(screen.size())
+                .show(ctx, |ui| {
+                    egui::TopBottomPanel::bottom("note_editor_buttons")
+                        .show_separator_line(false)
+                        .show_inside(ui, |ui| {
+                            ui.vertical_centered_justified(|ui| {
+                                if ui.button("Close").clicked() {
+                                    self.active_note = None;
+                                }
+                            });
+                        });
+                    ui.vertical_centered_justified(|ui| {
+                        let timer = self.timer_of(id);
+                        #[cfg(feature = "spellcheck")]
+                        self.check_spelling(ui.ctx(), id);
+                        match edit_note(
+                            ui,
+                            &id,
+                            &mut self.userdata,
+                            &mut self.pending_action,
+                            &mut self.toasts,
+                            &mut self.image_previews,
+                            EditorOptions {
+                                date_format: &self.settings.appearance.date_format,
+                                highlight: &self.filter,
+                                #[cfg(not(target_arch = "wasm32"))]
+                                detachable: true,
+                                timer,
+                                #[cfg(feature = "spellcheck")]
+                                spelling: self
+                                    .spell_checker
+                                    .as_ref()
+                                    .filter(|_| self.settings.behavior.spellcheck),
+                            },
+                        ) {
+                            Some(EditorRequest::Focus) => self.focused_note = Some(id),
+                            Some(EditorRequest::Open(other)) => self.active_note = Some(other),
+                            #[cfg(not(target_arch = "wasm32"))]
+                            Some(EditorRequest::Detach) => self.open_note_window(id),
+                            Some(EditorRequest::ToggleTimer) => self.toggle_timer(id),
+                            #[cfg(feature = "spellcheck")]
+                            Some(EditorRequest::AddToDictionary(word)) => {
+                                self.settings.behavior.spell_words.insert(word);
+                            }
+                            None => {}
+                        }
+                    });
+                });
+            if let Some(window) = window {
+                self.ui_state.editor_rect = Some(window.response.rect);
+            }
+            if close_requested {
+                self.active_note = None;
+            }
+        }
+
+        // focus mode was entered from the editor
+        if self.focused_note.is_some() {
+            self.focus_return_note = self.active_note.take();
+        }
+
+        if self.linked_note != self.active_note {
+            set_linked_note(self.active_note);
+            self.linked_note = self.active_note;
+        }
+
+        // the editor of a note that was closed in any way can't tell it lost focus
+        let edited = self.active_note.or(self.list_expanded);
+        if edited != self.edited_note {
+            if let Some(note) = self
+                .edited_note
+                .and_then(|id| self.userdata.notes.get_mut(&id))
+            {
+                record_text_revision(ctx, note);
+            }
+            self.edited_note = edited;
+        }
+        let edited_note = edited.and_then(|id| self.userdata.notes.get(&id));
+        if self.undo_stack.watch(edited_note, ctx.input(|i| i.time)) {
+            ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        }
+
+        self.open_pending_note(ctx);
+        self.capture_ui(ctx);
+        self.dragged_note_ui(ctx);
+        self.unlock_ui(ctx);
+        self.weekly_summary_ui(ctx);
+        self.confirm_pending_action(ctx);
+        self.undo_ui(ctx);
+        self.update_changed_notes(ctx);
+        // images pasted while no note was being edited are not attached later on
+        #[cfg(target_arch = "wasm32")]
+        if let Ok(mut pasted) = PASTED_IMAGES.lock() {
+            pasted.clear();
+        }
+
+        self.toasts.show(ctx);
+
+        // });
+    }
+}
+
+/// Something the editor asks the app to do
+enum EditorRequest {
+    /// Show the edited note in focus mode
+    Focus,
+    /// Edit another note
+    Open(u128),
+    /// Edit the note in a window of its own
+    #[cfg(not(target_arch = "wasm32"))]
+    Detach,
+    /// Start or stop tracking time for the note
+    ToggleTimer,
+    /// Take a word as spelled right from now on
+    #[cfg(feature = "spellcheck")]
+    AddToDictionary(String),
+}
+
+/// Settings the editor is shown with
+struct EditorOptions<'a> {
+    date_format: &'a str,
+    /// Text to highlight, the search filter
+    highlight: &'a str,
+    /// Offer to open the note in a window of its own
+    #[cfg(not(target_arch = "wasm32"))]
+    detachable: bool,
+    /// When time tracking for the note started, if it is running
+    timer: Option<chrono::DateTime<chrono::Utc>>,
+    /// Underline misspelled words with what this found
+    #[cfg(feature = "spellcheck")]
+    spelling: Option<&'a SpellChecker>,
+}
+
+fn edit_note(
+    ui: &mut Ui,
+    note_id: &u128,
+    userdata: &mut UserData,
+    pending_action: &mut Option<PendingAction>,
+    toasts: &mut Toasts,
+    previews: &mut ImagePreviews,
+    options: EditorOptions<'_>,
+) -> Option<EditorRequest> {
+    // make sure id is valid
+    if !userdata.notes.contains_key(note_id) {
+        ui.label("No such ID");
+        return None;
+    }
+
+    // narrow editors show the details below the text
+    if ui.available_width() < EDITOR_COLUMNS_WIDTH {
+        return egui::ScrollArea::vertical()
+            .show(ui, |ui| {
+                let note = userdata.notes.get_mut(note_id).unwrap();
+                let (text_changed, text_request) = note_text_ui(ui, note, &options, 10.);
+                let (request, restored) = note_details_ui(
+                    ui,
+                    note_id,
+                    userdata,
+                    pending_action,
+                    toasts,
+                    previews,
+                    options,
+                );
+                if text_changed || restored {
+                    userdata.link_wikilinks(*note_id);
+                }
+                request.or(text_request)
+            })
+            .inner;
+    }
+
+    let size = ui.available_size();
+    ui.horizontal_top(|ui| {
+        let (text_changed, text_request) = ui
+            .allocate_ui_with_layout(
+                vec2(size.x * 0.6, size.y),
+                Layout::top_down_justified(egui::Align::Min),
+                |ui| {
+                    // leave room for the statistics below the text
+                    let height = size.y - 2. * ui.spacing().interact_size.y;
+                    let note = userdata.notes.get_mut(note_id).unwrap();
+                    note_text_ui(ui, note, &options, height)
+                },
+            )
+            .inner;
+        ui.separator();
+        let (request, restored) = ui
+            .vertical(|ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("note_details")
+                    .show(ui, |ui| {
+                        note_details_ui(
+                            ui,
+                            note_id,
+                            userdata,
+                            pending_action,
+                            toasts,
+                            previews,
+                            options,
+                        )
+                    })
+                    .inner
+            })
+            .inner;
+        if text_changed || restored {
+            userdata.link_wikilinks(*note_id);
+        }
+        request.or(text_request)
+    })
+    .inner
+}
+
+/// The text of a note with its statistics below. Returns whether the text changed and what the
+/// editor asks of the app.
+fn note_text_ui(
+    ui: &mut Ui,
+    note: &mut Note,
+    options: &EditorOptions<'_>,
+    min_height: f32,
+) -> (bool, Option<EditorRequest>) {
+    let mut text_changed = false;
+
+    // ui.text_edit_multiline(&mut note.text);
+    let text_id = note_text_id(note.id);
+    let has_focus = ui.memory(|m| m.has_focus(text_id));
+    let mut format = formatting_toolbar(ui);
+    if has_focus {
+        format = format.or_else(|| {
+            ui.input_mut(|i| {
+                FORMAT_SHORTCUTS
+                    .iter()
+                    .find(|(shortcut, _)| i.consume_shortcut(shortcut))
+                    .map(|(_, format)| *format)
+            })
+        });
+    }
+    if let Some(format) = format {
+        let mut state = egui::TextEdit::load_state(ui.ctx(), text_id).unwrap_or_default();
+        // without a cursor yet, formatting goes at the end
+        let end = egui::text::CCursor::new(note.text.chars().count());
+        let range = state
+            .cursor
+            .char_range()
+            .unwrap_or(egui::text::CCursorRange::one(end));
+        let [start, end] = range.sorted();
+        let selection = apply_format(&mut note.text, start.index..end.index, format);
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::two(
+                egui::text::CCursor::new(selection.start),
+                egui::text::CCursor::new(selection.end),
+            )));
+        state.store(ui.ctx(), text_id);
+        ui.memory_mut(|m| m.request_focus(text_id));
+        note.sync_checklist_progress();
+        text_changed = true;
+    }
+    let entered =
+        has_focus && ui.input(|i| i.key_pressed(egui::Key::Enter) && i.modifiers.is_none());
+
+    let output = ui
+        .allocate_ui_with_layout(
+            vec2(ui.available_width(), min_height),
+            Layout::centered_and_justified(ui.layout().main_dir()),
+            |ui| {
+                egui::TextEdit::multiline(&mut note.text)
+                    .id(text_id)
+                    // .frame(false)
+                    .desired_width(f32::INFINITY)
+                    .margin(vec2(20., 20.))
+                    .desired_rows(15)
+                    .layouter(&mut |ui, text, wrap_width| {
+                        let mut job = highlighted(
+                            ui.ctx(),
+                            text,
+                            options.highlight,
+                            ui.visuals().text_color(),
+                        );
+                        job.wrap.max_width = wrap_width;
+                        ui.fonts(|f| f.layout_job(job))
+                    })
+                    .show(ui)
+            },
+        )
+        .inner;
+    let text_response = output.response.clone();
+    #[cfg(feature = "spellcheck")]
+    let request = options
+        .spelling
+        .and_then(|spelling| spelling_ui(ui, note, spelling, &output))
+        .and_then(|(range, replacement)| match replacement {
+            Some(word) => {
+                note.text.replace_range(range, &word);
+                note.sync_checklist_progress();
+                text_changed = true;
+                None
+            }
+            None => Some(EditorRequest::AddToDictionary(note.text[range].to_string())),
+        });
+    #[cfg(not(feature = "spellcheck"))]
+    let request = None;
+    if entered && text_response.changed() {
+        let cursor = output
+            .state
+            .cursor
+            .char_range()
+            .map(|range| range.primary.index);
+        if let Some(cursor) = cursor.and_then(|cursor| continue_list(&mut note.text, cursor)) {
+            let mut state = output.state.clone();
+            state
+                .cursor
+                .set_char_range(Some(egui::text::CCursorRange::one(
+                    egui::text::CCursor::new(cursor),
+                )));
+            state.store(ui.ctx(), text_id);
+            ui.ctx().request_repaint();
+        }
+    }
+    if text_response.changed() {
+        note.sync_checklist_progress();
+        text_changed = true;
+    }
+    // the text from before editing goes to the history once the editor is left
+    if text_response.gained_focus() {
+        ui.data_mut(|d| d.insert_temp(text_baseline_id(note.id), note.text.clone()));
+    }
+    if text_response.lost_focus() {
+        record_text_revision(ui.ctx(), note);
+    }
+    let stats = note_stats(ui.ctx(), note);
+    ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+        ui.weak(format!(
+            "{} · {} min read",
+            word_count(&stats),
+            stats.reading_minutes()
+        ))
+        .on_hover_text(format!(
+            "{} characters, {} lines, {} links, {} of {} checklist items done",
+            stats.characters, stats.lines, stats.links, stats.checked, stats.checklist
+        ));
+    });
+
+    // place the cursor where a template asked for it
+    let cursor_id = Id::new(("note_cursor", note.id));
+    if let Some(cursor) = ui.data_mut(|d| d.remove_temp::<usize>(cursor_id)) {
+        if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), text_id) {
+            let ccursor = egui::text::CCursor::new(cursor);
+            state
+                .cursor
+                .set_char_range(Some(egui::text::CCursorRange::one(ccursor)));
+            state.store(ui.ctx(), text_id);
+        }
+        ui.memory_mut(|m| m.request_focus(text_id));
+    }
+
+    (text_changed, request)
+}
+
+/// The id of the text editor of note `id`
+fn note_text_id(id: u128) -> Id {
+    Id::new(("note_text", id))
+}
+
+/// The id of the text of note `id` from before its editor got focus
+fn text_baseline_id(id: u128) -> Id {
+    Id::new(("note_text_baseline", id))
+}
+
+/// Keep the text of `note` from before it was edited as a revision, once its editor is left
+fn record_text_revision(ctx: &egui::Context, note: &mut Note) {
+    if let Some(baseline) = ctx.data_mut(|d| d.remove_temp::<String>(text_baseline_id(note.id))) {
+        note.record_revision(baseline, today());
+    }
+}
+
+/// Buttons putting markdown around the selected text. Returns the one clicked.
+fn formatting_toolbar(ui: &mut Ui) -> Option<Format> {
+    let mut clicked = None;
+    ui.horizontal(|ui| {
+        for format in Format::ALL {
+            let icon = match format {
+                Format::Bold => egui_phosphor::regular::TEXT_B,
+                Format::Italic => egui_phosphor::regular::TEXT_ITALIC,
+                Format::Heading => egui_phosphor::regular::TEXT_H,
+                Format::BulletList => egui_phosphor::regular::LIST_BULLETS,
+                Format::Checkbox => egui_phosphor::regular::CHECK_SQUARE,
+                Format::Code => egui_phosphor::regular::CODE,
+                Format::Link => egui_phosphor::regular::LINK,
+            };
+            let shortcut = FORMAT_SHORTCUTS
+                .iter()
+                .find(|(_, f)| *f == format)
+                .map(|(shortcut, _)| ui.ctx().format_shortcut(shortcut));
+            let hover = match shortcut {
+                Some(keys) => format!("{} ({keys})", format.name()),
+                None => format.name().to_string(),
+            };
+            if bare_button_sized(icon, 18., ui)
+                .on_hover_text(hover)
+                .clicked()
+            {
+                clicked = Some(format);
+            }
+        }
+    });
+    clicked
+}
+
+/// Underline the misspelled words of the note, and offer corrections for the one right-clicked.
+/// Returns the place of a word to replace by the picked correction, or to take as spelled right
+/// if there is none.
+#[cfg(feature = "spellcheck")]
+fn spelling_ui(
+    ui: &Ui,
+    note: &Note,
+    spelling: &SpellChecker,
+    output: &egui::text_edit::TextEditOutput,
+) -> Option<(std::ops::Range<usize>, Option<String>)> {
+    let char_index = |byte: usize| note.text[..byte].chars().count();
+    let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+    let stroke = Stroke::new(1.0_f32, ui.visuals().error_fg_color);
+    let misspelled: Vec<_> = spelling.misspelled_in(&note.text).collect();
+    for range in &misspelled {
+        let start = output
+            .galley
+            .pos_from_ccursor(egui::text::CCursor::new(char_index(range.start)));
+        let end = output
+            .galley
+            .pos_from_ccursor(egui::text::CCursor::new(char_index(range.end)));
+        // words broken across lines are underlined on the first one
+        let right = if end.min.y == start.min.y {
+            end.min.x
+        } else {
+            output.galley.rect.right()
+        };
+        let y = output.galley_pos.y + start.max.y;
+        let mut x = output.galley_pos.x + start.min.x;
+        let right = output.galley_pos.x + right;
+        let mut points = vec![];
+        let mut up = false;
+        while x < right {
+            points.push(egui::pos2(x, if up { y - 1.5 } else { y }));
+            x += 2.;
+            up = !up;
+        }
+        painter.add(Shape::line(points, stroke));
+    }
+
+    let menu_id = Id::new(("spelling_menu", note.id));
+    if output.response.secondary_clicked() {
+        let clicked = ui.input(|i| i.pointer.interact_pos()).map(|pos| {
+            let cursor = output.galley.cursor_from_pos(pos - output.galley_pos);
+            cursor.ccursor.index
+        });
+        let word = clicked.and_then(|index| {
+            misspelled
+                .iter()
+                .find(|range| (char_index(range.start)..=char_index(range.end)).contains(&index))
+        });
+        let menu = word.map(|range| {
+            let suggestions = spelling.dictionary.suggest(&note.text[range.clone()]);
+            (
+                range.clone(),
+                note.text[range.clone()].to_string(),
+                suggestions,
+            )
+        });
+        ui.data_mut(|d| d.insert_temp(menu_id, menu));
+    }
+
+    let mut picked = None;
+    output.response.context_menu(|ui| {
+        type Menu = Option<(std::ops::Range<usize>, String, Vec<String>)>;
+        let Some((range, word, suggestions)) = ui.data(|d| d.get_temp::<Menu>(menu_id)).flatten()
+        else {
+            ui.close_menu();
+            return;
+        };
+        // the text changed since the menu opened
+        if note.text.get(range.clone()) != Some(word.as_str()) {
+            ui.close_menu();
+            return;
+        }
+        if suggestions.is_empty() {
+            ui.weak("No suggestions");
+        }
+        for suggestion in suggestions {
+            if ui.button(&suggestion).clicked() {
+                picked = Some((range.clone(), Some(suggestion)));
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        if ui.button(format!("Add \"{word}\" to dictionary")).clicked() {
+            picked = Some((range, None));
+            ui.close_menu();
+        }
+    });
+    picked
+}
+
+/// Everything about a note besides its text. Returns what the editor asks of the app and
+/// whether an older version of the text was restored.
+fn note_details_ui(
+    ui: &mut Ui,
+    note_id: &u128,
+    userdata: &mut UserData,
+    pending_action: &mut Option<PendingAction>,
+    toasts: &mut Toasts,
+    previews: &mut ImagePreviews,
+    options: EditorOptions<'_>,
+) -> (Option<EditorRequest>, bool) {
+    let EditorOptions {
+        date_format,
+        highlight: _,
+        #[cfg(not(target_arch = "wasm32"))]
+        detachable,
+        timer,
+        #[cfg(feature = "spellcheck")]
+            spelling: _,
+    } = options;
+    let notes = &mut userdata.notes;
+    let immutable_notes = notes.clone();
+    let note = notes.get_mut(note_id).unwrap();
+    let mut restored = false;
+    let mut request = None;
+
+    ui.horizontal(|ui| {
+        ui.label("Base Priority");
+        ui.add(egui::Slider::new(&mut note.priority, 0.0..=1.0));
+    });
+
+    effort_ui(ui, note);
+
+    ui.horizontal(|ui| {
+        let (icon, label) = match timer {
+            Some(start) => (
+                egui_phosphor::regular::PAUSE,
+                format!(
+                    "Stop timer ({})",
+                    format_duration(chrono::Utc::now() - start)
+                ),
+            ),
+            None => (egui_phosphor::regular::PLAY, "Start timer".to_string()),
+        };
+        if ui.button(format!("{icon} {label}")).clicked() {
+            request = Some(EditorRequest::ToggleTimer);
+        }
+        let tracked = note.tracked_time();
+        if tracked > chrono::TimeDelta::zero() {
+            let by_day = note
+                .tracked_by_day()
+                .iter()
+                .rev()
+                .take(7)
+                .map(|(date, spent)| {
+                    format!(
+                        "{}: {}",
+                        format_date(*date, date_format),
+                        format_duration(*spent)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            ui.label(format!("Tracked {}", format_duration(tracked)))
+                .on_hover_text(by_day);
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Progress");
+        let (done, total) = note.checklist_progress();
+        if total == 0 {
+            ui.add(egui::Slider::new(&mut note.progress, 0.0..=1.0));
+        } else {
+            // derived from the checklist
+            ui.add(
+                egui::ProgressBar::new(note.progress)
+                    .text(format!("{done}/{total}"))
+                    .desired_width(200.),
+            );
+        }
+    });
+
+    status_ui(ui, note);
+    deadline_ui(ui, note, date_format);
+    snooze_ui(ui, note, date_format);
+
+    // Color comes from tags, so only show selector if there are no tags.
+    if note.tags.is_empty() {
+        note_color_ui(ui, &mut note.color, &mut userdata.recent_colors);
+    } else if note.tags.len() > 1 {
+        ui.horizontal(|ui| {
+            ui.label("Color from");
+            let primary = note.primary_tag().unwrap_or_default().to_string();
+            egui::ComboBox::from_id_salt(("primary_tag", note.id))
+                .selected_text(&primary)
+                .show_ui(ui, |ui| {
+                    for tag in note.tags.clone() {
+                        let selected = tag == primary;
+                        if ui
+                            .selectable_label(
+                                selected,
+                                RichText::new(&tag).color(color_from_tag(&tag)),
+                            )
+                            .clicked()
+                        {
+                            note.primary_tag = Some(tag);
+                        }
+                    }
+                });
+        });
+    }
+
+    tag_ui(ui, note, &mut userdata.tags);
+    attachments_ui(ui, note, &mut userdata.attachments, toasts);
+    file_links_ui(ui, note, toasts);
+    image_previews_ui(ui, note, previews);
+
+    let mut toggle_dependency = None;
+    ui.horizontal(|ui| {
+        let note = notes.get_mut(note_id).unwrap();
+
+        ui.toggle_value(
+            &mut note.pinned,
+            format!("{} Pin", egui_phosphor::regular::PUSH_PIN),
+        );
+
+        egui::ComboBox::from_id_salt(format!("{}x", note.id))
+            .selected_text("☞ depends on...".to_string())
+            .show_ui(ui, |ui| {
+                for (i, n) in immutable_notes.iter() {
+                    let contains = note.depends.contains(i);
+                    if ui.selectable_label(contains, n.get_title()).clicked() {
+                        toggle_dependency = Some((*i, contains));
+                    }
+                }
+            });
+
+        if !note.history.is_empty() {
+            ui.menu_button(
+                format!(
+                    "{} History",
+                    egui_phosphor::regular::CLOCK_COUNTER_CLOCKWISE
+                ),
+                |ui| {
+                    let mut restore = None;
+                    for (i, (date, text)) in note.history.iter().enumerate().rev() {
+                        let title = text.lines().next().unwrap_or_default();
+                        let preview: String = text.chars().take(500).collect();
+                        if ui
+                            .button(format!("{} · {title}", format_date(*date, date_format)))
+                            .on_hover_text(format!("Restore this version:\n\n{preview}"))
+                            .clicked()
+                        {
+                            restore = Some(i);
+                        }
+                    }
+                    if let Some(i) = restore {
+                        note.restore_revision(i, today());
+                        restored = true;
+                        ui.close_menu();
+                    }
+                },
+            );
+        }
+
+        if ui.button("Save as template").clicked() {
+            userdata.templates.push(NoteTemplate::from_note(note));
+        }
+
+        if ui
+            .button(format!("{} Focus", egui_phosphor::regular::PRESENTATION))
+            .on_hover_text("Show only this note, in large text")
+            .clicked()
+        {
+            request = Some(EditorRequest::Focus);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if detachable
+            && ui
+                .button(format!(
+                    "{} Open in new window",
+                    egui_phosphor::regular::APP_WINDOW
+                ))
+                .clicked()
+        {
+            request = Some(EditorRequest::Detach);
+        }
+
+        if ui
+            .button(format!("{} Copy link", egui_phosphor::regular::LINK))
+            .clicked()
+        {
+            ui.ctx().copy_text(note_link(*note_id));
+            toasts.info("Copied link to note");
+        }
+
+        ui.menu_button(
+            format!("{} Export subtree", egui_phosphor::regular::EXPORT),
+            |ui| {
+                ui.label("This note and all it depends on");
+                let export = if ui.button("As JSON").clicked() {
+                    NoteBundle::new(*note_id, &immutable_notes).map(|bundle| {
+                        let bundle = bundle.with_attachments(&userdata.attachments);
+                        let json = bundle.to_json().map_err(anyhow::Error::from);
+                        (bundle.file_name("json"), json, bundle.notes.len())
+                    })
+                } else if ui.button("As Markdown").clicked() {
+                    NoteBundle::new(*note_id, &immutable_notes).map(|bundle| {
+                        let markdown = Ok(bundle.to_markdown());
+                        (bundle.file_name("md"), markdown, bundle.notes.len())
+                    })
+                } else {
+                    None
+                };
+                if let Some((file_name, contents, count)) = export {
+                    ui.close_menu();
+                    match contents.and_then(|c| save_export(&file_name, &c)) {
+                        Ok(true) => {
+                            toasts.info(format!(
+                                "Exported {count} note{}",
+                                if count == 1 { "" } else { "s" }
+                            ));
+                        }
+                        // cancelled
+                        Ok(false) => {}
+                        Err(e) => {
+                            toasts.error(format!("Could not export notes: {e}"));
+                        }
+                    }
+                }
+            },
+        );
+
+        if ui.button("Move to scratchpad").clicked() {
+            *pending_action = Some(PendingAction::MoveToScratchpad(*note_id));
+        }
+
+        if ui.button("🗑 delete").clicked() {
+            *pending_action = Some(PendingAction::DeleteNote(*note_id));
+        }
+    });
+
+    match toggle_dependency {
+        Some((dependency, true)) => {
+            userdata.remove_dependency(*note_id, dependency);
+        }
+        Some((dependency, false)) => {
+            if let Err(e) = userdata.add_dependency(*note_id, dependency) {
+                toasts.warning(e.to_string());
+            }
+        }
+        None => {}
+    }
+
+    let referenced_by = backlinks(*note_id, &userdata.notes);
+    if !referenced_by.is_empty() {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Referenced by");
+            for id in referenced_by {
+                let title = userdata.notes[&id].get_title();
+                if ui.link(title).on_hover_text("Open note").clicked() {
+                    request = Some(EditorRequest::Open(id));
+                }
+            }
+        });
+    }
+
+    // offer to create the notes that wikilinks refer to but don't exist yet
+    let unmatched: Vec<String> = userdata
+        .notes
+        .get(note_id)
+        .map(|n| wikilinks(&n.text))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|title| find_by_title(&userdata.notes, title).is_none())
+        .map(|title| title.to_string())
+        .collect();
+    let mut create = None;
+    for title in unmatched {
+        ui.horizontal(|ui| {
+            ui.label(format!("No note '{title}'"));
+            if ui.button("Create").clicked() {
+                create = Some(title.clone());
+            }
+        });
+    }
+    if let Some(title) = create {
+        let id = userdata.add_note(Note {
+            text: title,
+            ..Note::new()
+        });
+        if let Err(e) = userdata.add_dependency(*note_id, id) {
+            toasts.warning(e.to_string());
+        }
+    }
+
+    // ui.collapsing("RND", |ui| {
+    // let mut cache = CommonMarkCache::default();
+    //     CommonMarkViewer::new("viewer").show(ui, &mut cache, &note.text);
+
+    // });
+    (request, restored)
+}
+
+/// The marker of a tag on a board card. With the monochrome palette, tags also differ in shape:
+/// a circle, a square or a triangle.
+fn tag_marker(tag: &str, rect: Rect) -> Shape {
+    let color = color_from_tag(tag).gamma_multiply(gamma_mult());
+    if tag_palette() != TagPalette::Monochrome {
+        return Shape::Rect(RectShape::new(rect, 10.0, color, Stroke::NONE));
+    }
+    let stroke = Stroke::new(1.0_f32, readable_text(&color));
+    let shape: u32 = tag.bytes().map(u32::from).sum::<u32>() % 3;
+    match shape {
+        0 => Shape::circle_filled(rect.center(), rect.width() / 2. - 1., color),
+        1 => Shape::Rect(RectShape::new(rect.shrink(2.), 1.0, color, stroke)),
+        _ => Shape::convex_polygon(
+            vec![
+                rect.center_top(),
+                rect.right_bottom() - vec2(1., 1.),
+                rect.left_bottom() + vec2(1., -1.),
+            ],
+            color,
+            stroke,
+        ),
+    }
+}
+
+/// A link shown as a chip in the footer of a board card
+#[derive(PartialEq)]
+enum CardLink<'a> {
+    Url(Link<'a>),
+    /// A note referenced by a `[[Title]]` wikilink
+    Note(u128, &'a str),
+    File(&'a FileLink),
+}
+
+/// How a board card is laid out
+#[derive(Clone, Copy)]
+struct CardStyle {
+    width: f32,
+    density: Density,
+    text_size: f32,
+}
+
+/// What a board card shows below its text
+struct CardParts<'a> {
+    links: Vec<CardLink<'a>>,
+    /// Height of the rows of link chips, if any
+    footer_height: f32,
+    /// The first linked image, previewed between the text and the links
+    thumbnail: Option<String>,
+}
+
+fn card_parts<'a>(note: &'a Note, notes: &Notes, previews: &ImagePreviews) -> CardParts<'a> {
+    let mut links: Vec<CardLink<'_>> = vec![];
+    for title in wikilinks(&note.text) {
+        if let Some(id) = find_by_title(notes, title) {
+            if !links.contains(&CardLink::Note(id, title)) {
+                links.push(CardLink::Note(id, title));
+            }
+        }
+    }
+    links.extend(note.get_links().into_iter().map(CardLink::Url));
+    links.extend(note.file_links.iter().map(CardLink::File));
+    // keep the links clear of the tag dots, blockers and checklist progress along the bottom edge
+    let has_bottom_row = note.tags.len() > 1
+        || note.checklist_progress().1 > 0
+        || blockers(note, notes) > 0
+        || !note.attachments.is_empty()
+        || note.effort_hours > 0.
+        || note.status != Status::Active;
+    let footer_height = match (links.is_empty(), has_bottom_row) {
+        (true, _) => 0.,
+        (false, false) => LINK_FOOTER_HEIGHT,
+        (false, true) => 2. * LINK_FOOTER_HEIGHT,
+    };
+    let thumbnail = note
+        .get_image_links()
+        .first()
+        .filter(|_| previews.allowed(note))
+        .map(|url| url.to_string());
+    CardParts {
+        links,
+        footer_height,
+        thumbnail,
+    }
+}
+
+/// The size of a board card, estimated from its text. `extra_height` is taken by the parts below
+/// the text.
+fn card_size(ui: &Ui, note: &Note, extra_height: f32, style: &CardStyle) -> Vec2 {
+    estimated_card_size(note, extra_height, style, card_font_metrics(ui, style))
+}
+
+/// The width of a char and the height of a row of the card text
+fn card_font_metrics(ui: &Ui, style: &CardStyle) -> (f32, f32) {
+    let font = FontId::proportional(style.text_size);
+    ui.fonts(|f| (f.glyph_width(&font, 'n'), f.row_height(&font)))
+}
+
+/// `card_size` with the font metrics measured beforehand
+fn estimated_card_size(
+    note: &Note,
+    extra_height: f32,
+    style: &CardStyle,
+    (char_width, row_height): (f32, f32),
+) -> Vec2 {
+    let chars_per_row = ((style.width - 2. * style.density.padding()) / char_width) as usize;
+    let estimated_size =
+        note.get_approx_height(row_height + 2., chars_per_row, style.density.text_limit());
+    Vec2::new(
+        style.width,
+        estimated_size.max(style.density.min_card_height()) + extra_height,
+    )
+}
+
+/// The size of the board card of `note`, without drawing it. Cached until the card changes.
+fn measure_card(
+    ui: &Ui,
+    note: &Note,
+    notes: &Notes,
+    previews: &ImagePreviews,
+    style: &CardStyle,
+) -> Vec2 {
+    let card = CardInputs {
+        note,
+        notes,
+        previews,
+        style: *style,
+        font_metrics: card_font_metrics(ui, style),
+    };
+    ui.ctx()
+        .memory_mut(|m| m.caches.cache::<CardSizeCache>().get(card))
+}
+
+/// Everything the size of a board card depends on
+#[derive(Clone, Copy)]
+struct CardInputs<'a> {
+    note: &'a Note,
+    notes: &'a Notes,
+    previews: &'a ImagePreviews,
+    style: CardStyle,
+    font_metrics: (f32, f32),
+}
+
+impl std::hash::Hash for CardInputs<'_> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let CardInputs {
+            note,
+            notes,
+            previews,
+            style,
+            font_metrics,
+        } = self;
+        note.id.hash(state);
+        note.text.hash(state);
+        // what decides if there is a row of badges below the links
+        note.tags.len().hash(state);
+        blockers(note, notes).hash(state);
+        note.attachments.is_empty().hash(state);
+        note.effort_hours.to_bits().hash(state);
+        note.status.hash(state);
+        note.file_links.len().hash(state);
+        previews.allowed(note).hash(state);
+        // links to other notes come and go with the titles of those
+        for title in wikilinks(&note.text) {
+            find_by_title(notes, title).hash(state);
+        }
+        style.width.to_bits().hash(state);
+        (style.density as u8).hash(state);
+        style.text_size.to_bits().hash(state);
+        font_metrics.0.to_bits().hash(state);
+        font_metrics.1.to_bits().hash(state);
+    }
+}
+
+/// Measures board cards, so they are only measured again once they changed
+#[derive(Default)]
+struct CardSizeComputer;
+
+impl egui::util::cache::ComputerMut<CardInputs<'_>, Vec2> for CardSizeComputer {
+    fn compute(&mut self, card: CardInputs<'_>) -> Vec2 {
+        let parts = card_parts(card.note, card.notes, card.previews);
+        let thumbnail_height = if parts.thumbnail.is_some() {
+            CARD_THUMBNAIL_HEIGHT
+        } else {
+            0.
+        };
+        estimated_card_size(
+            card.note,
+            parts.footer_height + thumbnail_height,
+            &card.style,
+            card.font_metrics,
+        )
+    }
+}
+
+type CardSizeCache = egui::util::cache::FrameCache<Vec2, CardSizeComputer>;
+
+fn draw_note(
+    ui: &mut Ui,
+    note_id: &u128,
+    notes: &mut Notes,
+    open_note: &mut Option<u128>,
+    previews: &mut ImagePreviews,
+    style: CardStyle,
+) -> Response {
+    let CardStyle {
+        density, text_size, ..
+    } = style;
+    // make sure id is valid
+    if notes.get(note_id).is_none() {
+        return ui.label("No such ID");
+    }
+
+    let note = notes.get(note_id).unwrap();
+
+    let CardParts {
+        links,
+        footer_height,
+        thumbnail,
+    } = card_parts(note, notes, previews);
+    let thumbnail_height = if thumbnail.is_some() {
+        CARD_THUMBNAIL_HEIGHT
+    } else {
+        0.
+    };
+    let note_size = card_size(ui, note, footer_height + thumbnail_height, &style);
+
+    let (rect, resp) = ui.allocate_exact_size(note_size, Sense::click_and_drag());
+    resp.dnd_set_drag_payload(DraggedNote(*note_id));
+
+    let stroke = if resp.hovered() {
+        Stroke::new(3.0_f32, Color32::GRAY)
+    } else if high_contrast() {
+        Stroke::new(2.0_f32, readable_text(&note.get_color()))
+    } else {
+        Stroke::NONE
+    };
+
+    let frame_shape = Shape::Rect(RectShape::new(rect, 5.0, note.get_color(), stroke));
+
+    let mut shapes_to_draw = vec![frame_shape];
+
+    // the primary tag colors the card, the others are shown as squares along the bottom
+    let primary = note.primary_tag();
+    let secondary_tags = note.tags.iter().filter(|tag| Some(tag.as_str()) != primary);
+    for (i, tag) in (1..).zip(secondary_tags) {
+        let offset = 20.;
+
+        let r = Rect::from_min_max(
+            rect.left_top(),
+            Pos2::new(rect.left_top().x + offset, rect.left_top().y + offset),
+        )
+        .translate(vec2(offset * i as f32, 0.0))
+        .translate(vec2(-offset + 2., note_size.y - offset - 2.));
+
+        shapes_to_draw.push(tag_marker(tag, r));
+    }
+
+    let s = Shadow {
+        offset: Default::default(),
+        blur: 30.0,
+        spread: 5.0,
+        color: Color32::from_black_alpha(70),
+    }
+    .as_shape(rect, 4.0);
+    ui.painter().add(s);
+
+    ui.painter().add(shapes_to_draw);
+
+    // pin toggle, always visible on pinned notes and on hover otherwise
+    let mut toggle_pin = false;
+    if note.pinned || ui.rect_contains_pointer(rect) {
+        let pin_rect = Rect::from_min_size(rect.right_top() + vec2(-22., 2.), vec2(20., 20.));
+        let pin_color = if note.pinned {
+            readable_text(&note.get_color())
+        } else {
+            readable_text(&note.get_color()).gamma_multiply(0.4)
+        };
+        toggle_pin = ui
+            .put(
+                pin_rect,
+                egui::Button::new(
+                    RichText::new(egui_phosphor::regular::PUSH_PIN)
+                        .size(16.)
+                        .color(pin_color),
+                )
+                .frame(false),
+            )
+            .on_hover_text(if note.pinned { "Unpin" } else { "Pin to top" })
+            .clicked();
+    }
+
+    let mut text_rect = rect.shrink(density.padding());
+    text_rect.max.y -= footer_height + thumbnail_height;
+    let mut sub_ui = ui.new_child(
+        egui::UiBuilder::new()
+            .max_rect(text_rect)
+            .layout(Layout::left_to_right(egui::Align::TOP).with_main_wrap(true)),
+    );
+
+    // if note.contains_markdown() {
+    //     let mut fcache = CommonMarkCache::default();
+    //     CommonMarkViewer::new("viewer").show(&mut sub_ui, &mut cache, &note.get_clean_text());
+    // } else {
+    //     sub_ui.label(note.get_clean_text());
+    // }
+
+    let text_color = readable_text(&note.get_color());
+    let (done, total) = note.checklist_progress();
+
+    let blocker_count = blockers(note, notes);
+    let mut badges = vec![];
+    if note.status != Status::Active {
+        badges.push(status_chip(note));
+    }
+    if blocker_count > 0 {
+        badges.push(format!(
+            "{} {blocker_count} blocker{}",
+            egui_phosphor::regular::LOCK,
+            if blocker_count == 1 { "" } else { "s" }
+        ));
+    }
+    if !note.attachments.is_empty() {
+        badges.push(format!(
+            "{} {}",
+            egui_phosphor::regular::PAPERCLIP,
+            note.attachments.len()
+        ));
+    }
+    if note.effort_hours > 0. {
+        badges.push(format!(
+            "{} {}",
+            egui_phosphor::regular::TIMER,
+            format_effort(note.effort_hours)
+        ));
+    }
+    if !badges.is_empty() {
+        let offset = if total > 0 { 22. } else { 6. };
+        ui.painter().text(
+            rect.right_bottom() - vec2(8., offset),
+            egui::Align2::RIGHT_BOTTOM,
+            badges.join("  "),
+            FontId::proportional(12.),
+            text_color,
+        );
+    }
+    // checklist line to toggle, if a checkbox was clicked
+    let mut toggle: Option<usize> = None;
+
+    if total == 0 {
+        sub_ui.add(
+            egui::Label::new(
+                RichText::new(note.get_clean_text_truncated(density.text_limit()))
+                    .size(text_size)
+                    .color(text_color),
+            )
+            .truncate()
+            .wrap(),
+        );
+    } else {
+        // Render checklist lines as checkboxes, so we need to walk the original lines
+        sub_ui.vertical(|ui| {
+            let mut budget: usize = density.text_limit();
+            for (line, l) in note.text.lines().enumerate() {
+                if budget == 0 {
+                    break;
+                }
+                budget = budget.saturating_sub(l.chars().count());
+                if let Some((mut checked, text)) = parse_checklist_line(l) {
+                    if ui
+                        .checkbox(
+                            &mut checked,
+                            RichText::new(text).size(text_size).color(text_color),
+                        )
+                        .changed()
+                    {
+                        toggle = Some(line);
+                    }
+                } else {
+                    let l = strip_links(l);
+                    ui.add(
+                        egui::Label::new(RichText::new(l).size(text_size).color(text_color)).wrap(),
+                    );
+                }
+            }
+        });
+        ui.painter().text(
+            rect.right_bottom() - vec2(8., 6.),
+            egui::Align2::RIGHT_BOTTOM,
+            format!("{done}/{total}"),
+            FontId::proportional(12.),
+            text_color,
+        );
+    }
+
+    // sub_ui.label(
+    //     RichText::new(&note.get_clean_text())
+    //     .color(readable_text(&Color32::from_rgb(
+    //         note.color[0],
+    //         note.color[1],
+    //         note.color[2],
+    //     ))
+    // ), // .size(12.)
+    // );
+
+    // sub_ui.label(&note.text);
+    // sub_ui.add_space(20.);
+
+    if let Some(url) = &thumbnail {
+        let thumbnail_rect = Rect::from_min_size(
+            text_rect.left_bottom(),
+            vec2(text_rect.width(), CARD_THUMBNAIL_HEIGHT),
+        );
+        let mut thumbnail_ui = ui.new_child(
+            egui::UiBuilder::new()
+                .max_rect(thumbnail_rect)
+                .layout(Layout::left_to_right(egui::Align::Center)),
+        );
+        if previews
+            .thumbnail(&mut thumbnail_ui, url, thumbnail_rect.size())
+            .clicked()
+        {
+            ui.ctx().open_url(egui::OpenUrl::new_tab(url));
+        }
+    }
+
+    if !links.is_empty() {
+        let footer_rect = Rect::from_min_size(
+            Pos2::new(text_rect.left(), text_rect.bottom() + thumbnail_height),
+            vec2(text_rect.width(), LINK_FOOTER_HEIGHT),
+        );
+        let mut footer = ui.new_child(
+            egui::UiBuilder::new()
+                .max_rect(footer_rect)
+                .layout(Layout::left_to_right(egui::Align::Center)),
+        );
+        footer.spacing_mut().item_spacing.x = 3.;
+        for link in links.iter().take(MAX_CARD_LINKS) {
+            let (label, hover) = match link {
+                CardLink::Url(link) => (
+                    RichText::new(format!("{} {}", egui_phosphor::regular::GLOBE, link.text())),
+                    link.url.to_string(),
+                ),
+                CardLink::Note(_, title) => (
+                    RichText::new(format!("{} {title}", egui_phosphor::regular::NOTE)),
+                    "Open note".to_string(),
+                ),
+                CardLink::File(file) => (file_link_label(file), file_link_hover(file)),
+            };
+            let chip = egui::Button::new(label.small())
+                .rounding(8.)
+                .fill(Color32::from_black_alpha(40));
+            if footer.add(chip).on_hover_text(hover).clicked() {
+                match link {
+                    CardLink::Url(link) => footer.ctx().open_url(egui::OpenUrl::new_tab(link.url)),
+                    CardLink::Note(id, _) => *open_note = Some(*id),
+                    CardLink::File(file) => {
+                        if let Err(e) = open_file_link(file) {
+                            error!("{e}");
+                        }
+                    }
+                }
+            }
+        }
+        if links.len() > MAX_CARD_LINKS {
+            footer.label(
+                RichText::new(format!("+{} more", links.len() - MAX_CARD_LINKS))
+                    .small()
+                    .color(text_color),
+            );
+        }
+    }
+
+    // ui.put(rect, egui::Label::new(note.get_title()));
+
+    // });
+    // let resp = r.response.interact(egui::Sense::click());
+
+    if let Some(line) = toggle {
+        if let Some(note) = notes.get_mut(note_id) {
+            note.toggle_checklist_item(line);
+        }
+    }
+    if toggle_pin {
+        if let Some(note) = notes.get_mut(note_id) {
+            note.pinned = !note.pinned;
+        }
+    }
+    resp
+}
+
+/// Settings list rows are drawn with
+#[derive(Clone, Copy)]
+struct ListRowOptions<'a> {
+    /// Text to highlight, the search filter
+    filter: &'a str,
+    /// Show the number of words
+    word_count: bool,
+}
+
+/// Computes `Note::stats` for a note text, so it only runs again once the text changed
+#[derive(Default)]
+struct StatsComputer;
+
+impl egui::util::cache::ComputerMut<&str, NoteStats> for StatsComputer {
+    fn compute(&mut self, text: &str) -> NoteStats {
+        Note {
+            text: text.to_string(),
+            ..Default::default()
+        }
+        .stats()
+    }
+}
+
+type StatsCache = egui::util::cache::FrameCache<NoteStats, StatsComputer>;
+
+/// The stats of `note`, cached by its text
+fn note_stats(ctx: &egui::Context, note: &Note) -> NoteStats {
+    ctx.memory_mut(|m| m.caches.cache::<StatsCache>().get(note.text.as_str()))
+}
+
+/// "1 word", "412 words"
+fn word_count(stats: &NoteStats) -> String {
+    format!(
+        "{} word{}",
+        stats.words,
+        if stats.words == 1 { "" } else { "s" }
+    )
+}
+
+/// Draw a list row and its dependencies. The clicked row is written to `clicked`,
+/// the outcome of an inline title edit to `inline_commit`.
+fn draw_list_note(
+    ui: &mut Ui,
+    note_id: &u128,
+    notes: &Notes,
+    options: ListRowOptions<'_>,
+    inline_edit: &mut Option<InlineEdit>,
+    events: &mut ListRowEvents,
+    expanded: Option<&mut Option<u128>>,
+) {
+    // make sure id is valid
+    if notes.get(note_id).is_none() {
+        ui.label("No such ID");
+        return;
+    }
+
+    let note = notes.get(note_id).unwrap();
+
+    let frame = egui::Frame {
+        fill: note.get_color(),
+        inner_margin: 5.0.into(),
+        ..Default::default()
+    };
+    let inner = frame.show(ui, |ui| {
+        ui.allocate_exact_size(vec2(ui.available_width(), 0.), Sense::click());
+        ui.horizontal(|ui| {
+            if let Some(expanded) = expanded {
+                let is_expanded = *expanded == Some(*note_id);
+                let icon = if is_expanded {
+                    egui_phosphor::regular::CARET_UP
+                } else {
+                    egui_phosphor::regular::PENCIL_SIMPLE
+                };
+                if ui
+                    .small_button(icon)
+                    .on_hover_text(if is_expanded {
+                        "Stop editing"
+                    } else {
+                        "Edit inline"
+                    })
+                    .clicked()
+                {
+                    *expanded = if is_expanded { None } else { Some(*note_id) };
+                }
+            }
+            if note.pinned {
+                ui.label(egui_phosphor::regular::PUSH_PIN);
+            }
+            if note.status != Status::Active {
+                ui.label(RichText::new(status_chip(note)).small())
+                    .on_hover_text(note.status.name());
+            }
+            match inline_edit.as_mut().filter(|e| e.id == *note_id) {
+                Some(edit) => {
+                    let rect = Rect::from_min_size(
+                        ui.cursor().min,
+                        vec2(ui.available_width(), ui.spacing().interact_size.y),
+                    );
+                    events.inline_commit = inline_title_ui(ui, edit, rect);
+                }
+                None => {
+                    let text_color = ui.visuals().text_color();
+                    ui.label(highlighted(
+                        ui.ctx(),
+                        note.get_title(),
+                        options.filter,
+                        text_color,
+                    ));
+                    if options.word_count {
+                        let stats = note_stats(ui.ctx(), note);
+                        ui.label(RichText::new(word_count(&stats)).small().weak());
+                    }
+                    let mut excerpt =
+                        highlighted(ui.ctx(), &note.get_excerpt(), options.filter, text_color);
+                    for section in &mut excerpt.sections {
+                        section.format.font_id = FontId::proportional(10.);
+                    }
+                    ui.add(egui::Label::new(excerpt).truncate());
+                }
+            }
+        });
+        for d in &note.depends {
+            if let Some(dependent) = notes.get(d) {
+                ui.collapsing(dependent.get_title(), |ui| {
+                    draw_list_note(ui, d, notes, options, inline_edit, events, None);
+                });
+            }
+        }
+        if ui.ui_contains_pointer() {
+            ui.painter().rect_filled(
+                ui.min_rect().expand(5.),
+                0.0,
+                Color32::from_rgb_additive(11, 11, 11),
+            );
+        }
+    });
+
+    let resp = inner.response.interact(Sense::click());
+
+    if resp.clicked() || resp.double_clicked() {
+        events.clicked = Some((*note_id, resp));
+    }
+}
+
+/// What happened on a row of the list view, including the rows of its dependencies
+#[derive(Default)]
+struct ListRowEvents {
+    clicked: Option<(u128, Response)>,
+    /// The inline title edit was committed (true) or cancelled (false)
+    inline_commit: Option<bool>,
+}
+
+/// Computes `highlight_job`, so the text is only searched again once it or the needle changed
+#[derive(Default)]
+struct HighlightComputer;
+
+impl egui::util::cache::ComputerMut<(&str, &str, Color32), LayoutJob> for HighlightComputer {
+    fn compute(&mut self, (text, needle, base): (&str, &str, Color32)) -> LayoutJob {
+        highlight_job(text, needle, base)
+    }
+}
+
+type HighlightCache = egui::util::cache::FrameCache<LayoutJob, HighlightComputer>;
+
+/// `highlight_job`, cached by its arguments
+fn highlighted(ctx: &egui::Context, text: &str, needle: &str, base: Color32) -> LayoutJob {
+    ctx.memory_mut(|m| m.caches.cache::<HighlightCache>().get((text, needle, base)))
+}
+
+/// `text` with the occurrences of `needle` highlighted, ignoring case like the search filter
+fn highlight_job(text: &str, needle: &str, base: Color32) -> LayoutJob {
+    let format = TextFormat {
+        color: base,
+        ..Default::default()
+    };
+    let highlighted = TextFormat {
+        color: Color32::BLACK,
+        background: Color32::from_rgb(255, 220, 80),
+        ..Default::default()
+    };
+    let mut job = LayoutJob::default();
+    let needle = needle.to_lowercase();
+    if needle.is_empty() {
+        job.append(text, 0., format);
+        return job;
+    }
+
+    // lowercasing can change the length of a char, so map positions back to the original text
+    let mut lower = String::new();
+    let mut origin = vec![];
+    for (i, c) in text.char_indices() {
+        for l in c.to_lowercase() {
+            origin.extend(std::iter::repeat_n(i, l.len_utf8()));
+            lower.push(l);
+        }
+    }
+    origin.push(text.len());
+
+    let mut last = 0;
+    for (start, m) in lower.match_indices(&needle) {
+        let (start, end) = (origin[start], origin[start + m.len()]);
+        if start < last || start == end {
+            continue;
         }
+        job.append(&text[last..start], 0., format.clone());
+        job.append(&text[start..end], 0., highlighted.clone());
+        last = end;
+    }
+    job.append(&text[last..], 0., format);
+    job
+}
 
-        self.toasts.show(ctx);
+/// Colors offered for notes in one click
+const NOTE_COLOR_PRESETS: [[u8; 3]; 8] = [
+    [200, 200, 200],
+    [255, 215, 0],
+    [255, 160, 90],
+    [240, 110, 110],
+    [230, 130, 200],
+    [150, 140, 240],
+    [90, 190, 230],
+    [110, 200, 130],
+];
+
+/// Number of custom colors kept in `UserData::recent_colors`
+const MAX_RECENT_COLORS: usize = 8;
+
+/// Preset and recently used color swatches above a picker for custom colors
+fn note_color_ui(ui: &mut Ui, color: &mut [u8; 3], recent_colors: &mut Vec<[u8; 3]>) {
+    for swatches in [&NOTE_COLOR_PRESETS[..], &recent_colors[..]] {
+        if swatches.is_empty() {
+            continue;
+        }
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 4.;
+            for swatch in swatches {
+                if color_swatch(ui, *swatch, *swatch == *color).clicked() {
+                    *color = *swatch;
+                }
+            }
+        });
+    }
 
-        // });
+    let picker = ui.color_edit_button_srgb(color);
+    // remember the color once the picker is closed, not every color passed while dragging
+    let picked_id = picker.id.with("picked");
+    if picker.changed() {
+        ui.data_mut(|d| d.insert_temp(picked_id, *color));
+    } else if !ui.memory(|m| m.any_popup_open()) {
+        if let Some(picked) = ui.data_mut(|d| d.remove_temp::<[u8; 3]>(picked_id)) {
+            if !NOTE_COLOR_PRESETS.contains(&picked) {
+                recent_colors.retain(|c| *c != picked);
+                recent_colors.insert(0, picked);
+                recent_colors.truncate(MAX_RECENT_COLORS);
+            }
+        }
     }
 }
 
-fn edit_note(ui: &mut Ui, note_id: &u128, tags: &mut Vec<String>, notes: &mut Notes) {
-    // make sure id is valid
-    if notes.get(note_id).is_none() {
-        ui.label("No such ID");
-        return;
-    }
-    let immutable_notes = notes.clone();
+fn color_swatch(ui: &mut Ui, color: [u8; 3], selected: bool) -> Response {
+    let (rect, response) = ui.allocate_exact_size(vec2(18., 18.), Sense::click());
+    let [r, g, b] = color;
+    let fill = Color32::from_rgb(r, g, b);
+    let stroke = if selected || response.hovered() {
+        Stroke::new(2.0_f32, ui.visuals().strong_text_color())
+    } else {
+        Stroke::new(1.0_f32, ui.visuals().weak_text_color())
+    };
+    ui.painter().rect(rect, 3., fill, stroke);
+    response.on_hover_text(format!("#{r:02x}{g:02x}{b:02x}"))
+}
 
-    let note = notes.get_mut(note_id).unwrap();
+/// Enter the estimated effort of a note as text like "2h". While the text can't be read, the
+/// effort stays as it was and the problem is shown next to it.
+fn effort_ui(ui: &mut Ui, note: &mut Note) {
+    ui.horizontal(|ui| {
+        ui.label("Effort");
+        let text_id = Id::new(("effort_text", note.id));
+        let mut text = ui
+            .data(|d| d.get_temp::<String>(text_id))
+            .unwrap_or_else(|| format_effort(note.effort_hours));
+        let r = ui
+            .add(
+                egui::TextEdit::singleline(&mut text)
+                    .hint_text("90m, 2h, 1.5d")
+                    .desired_width(80.),
+            )
+            .on_hover_text("Estimated work in minutes, hours or days of 8 hours");
+        let parsed = parse_effort(&text);
+        if r.changed() {
+            if let Ok(hours) = parsed {
+                note.effort_hours = hours;
+            }
+        }
+        // keep the text as typed while editing, show the effort as it is otherwise
+        if r.has_focus() {
+            ui.data_mut(|d| d.insert_temp(text_id, text));
+        } else {
+            ui.data_mut(|d| d.remove::<String>(text_id));
+        }
+        if let (Err(e), true) = (parsed, r.has_focus()) {
+            ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+        }
+    });
+}
 
-    // ui.text_edit_multiline(&mut note.text);
-    ui.add_sized(
-        [ui.available_width(), 10.],
-        egui::TextEdit::multiline(&mut note.text)
-            // .frame(false)
-            .desired_width(f32::INFINITY)
-            .margin(vec2(20., 20.))
-            .desired_rows(15),
-    );
+/// The status of a note in a few words, with whom it waits for
+fn status_chip(note: &Note) -> String {
+    let waiting_on = note.waiting_on.trim();
+    if note.status == Status::Waiting && !waiting_on.is_empty() {
+        format!("{} Waiting on {waiting_on}", note.status.icon())
+    } else {
+        format!("{} {}", note.status.icon(), note.status.name())
+    }
+}
 
+/// Pick the status of a note, and whom it waits for if it is waiting
+fn status_ui(ui: &mut Ui, note: &mut Note) {
     ui.horizontal(|ui| {
-        ui.label("Base Priority");
-        ui.add(egui::Slider::new(&mut note.priority, 0.0..=1.0));
+        ui.label("Status");
+        let mut status = note.status;
+        egui::ComboBox::from_id_salt(("status", note.id))
+            .selected_text(format!("{} {}", status.icon(), status.name()))
+            .show_ui(ui, |ui| {
+                for option in Status::ALL {
+                    ui.selectable_value(
+                        &mut status,
+                        option,
+                        format!("{} {}", option.icon(), option.name()),
+                    );
+                }
+            });
+        if status != note.status {
+            note.set_status(status, today());
+        }
+        if note.status == Status::Waiting {
+            ui.add(
+                egui::TextEdit::singleline(&mut note.waiting_on)
+                    .hint_text("Waiting on whom or what?")
+                    .desired_width(200.),
+            );
+        }
     });
+}
 
+/// Pick the kind of deadline of a note and its date
+fn deadline_ui(ui: &mut Ui, note: &mut Note, date_format: &str) {
     ui.horizontal(|ui| {
         ui.label("Deadline");
 
-        egui::ComboBox::from_id_source(format!("{}xx", note.id))
-            .selected_text(format!("{:?}", note.deadline))
+        egui::ComboBox::from_id_salt(format!("{}xx", note.id))
+            .selected_text(match &note.deadline {
+                Deadline::Eternal => "Eternal".to_string(),
+                Deadline::Fixed(date) => format_date(*date, date_format),
+                Deadline::Periodic { days, .. } => format!("Every {days} days"),
+            })
             .show_ui(ui, |ui| {
                 ui.selectable_value(&mut note.deadline, Deadline::Eternal, "Eternal");
 
-                ui.selectable_value(
-                    &mut note.deadline,
-                    Deadline::Fixed(chrono::Utc::now().date_naive()),
-                    "Date",
-                );
+                ui.selectable_value(&mut note.deadline, Deadline::Fixed(today()), "Date");
                 ui.selectable_value(
                     &mut note.deadline,
                     Deadline::Periodic {
-                        start: chrono::Utc::now().date_naive(),
+                        start: today(),
                         days: 0,
                     },
                     "Repeating",
@@ -882,316 +6627,1482 @@ fn edit_note(ui: &mut Ui, note_id: &u128, tags: &mut Vec<String>, notes: &mut No
     match &mut note.deadline {
         crate::Deadline::Eternal => {}
         crate::Deadline::Periodic { start, days } => {
-            ui.add(egui_extras::DatePickerButton::new(start));
+            ui.add(
+                egui_extras::DatePickerButton::new(start).format(valid_date_format(date_format)),
+            );
             ui.add(egui::Slider::new(days, 1..=100).text("days offset"));
         }
         crate::Deadline::Fixed(date) => {
-            ui.add(egui_extras::DatePickerButton::new(date));
-            // egui_extras::DatePickerButton::new(date);
+            ui.add(egui_extras::DatePickerButton::new(date).format(valid_date_format(date_format)));
         }
     }
+}
 
-    // Color comes from tags, so only show selector if there are no tags.
-    if note.tags.is_empty() {
-        ui.color_edit_button_srgb(&mut note.color);
+/// The quick choices to snooze a note for. Returns the day picked.
+fn snooze_choices(ui: &mut Ui) -> Option<chrono::NaiveDate> {
+    let today = today();
+    if ui.button("Tomorrow").clicked() {
+        return today.succ_opt();
     }
+    let next_week = next_monday(today);
+    if ui
+        .button("Next week")
+        .on_hover_text(next_week.format("%A, %Y-%m-%d").to_string())
+        .clicked()
+    {
+        return Some(next_week);
+    }
+    None
+}
 
-    tag_ui(ui, note, tags);
+/// Hide a note until a later day, or change the day it comes back
+fn snooze_ui(ui: &mut Ui, note: &mut Note, date_format: &str) {
+    ui.horizontal(|ui| match &mut note.snoozed_until {
+        Some(until) => {
+            ui.label(format!("{} Snoozed until", egui_phosphor::regular::BELL_Z));
+            ui.add(
+                egui_extras::DatePickerButton::new(until)
+                    .id_salt(&format!("snooze{}", note.id))
+                    .format(valid_date_format(date_format)),
+            );
+            if ui
+                .button("Unsnooze")
+                .on_hover_text("Show the note again now")
+                .clicked()
+            {
+                note.snoozed_until = None;
+            }
+        }
+        None => {
+            ui.menu_button(
+                format!("{} Snooze…", egui_phosphor::regular::BELL_Z),
+                |ui| {
+                    if let Some(until) = snooze_choices(ui) {
+                        note.snoozed_until = Some(until);
+                        ui.close_menu();
+                    }
+                    if ui.button("Pick a date…").clicked() {
+                        note.snoozed_until = today().succ_opt();
+                        ui.close_menu();
+                    }
+                },
+            )
+            .response
+            .on_hover_text("Hide the note until a later day");
+        }
+    });
+}
 
+/// Quick editing of a note right in the list: its text, tags and deadline
+fn list_inline_editor(ui: &mut Ui, state: &mut MeteoraApp, id: u128) {
+    let Some(note) = state.userdata.notes.get_mut(&id) else {
+        state.list_expanded = None;
+        return;
+    };
+    let resp = ui.add(
+        egui::TextEdit::multiline(&mut note.text)
+            .id(Id::new(("list_note_text", id)))
+            .desired_rows(4)
+            .desired_width(f32::INFINITY),
+    );
+    deadline_ui(ui, note, &state.settings.appearance.date_format);
     ui.horizontal(|ui| {
-        let note = notes.get_mut(note_id).unwrap();
+        tag_ui(ui, note, &mut state.userdata.tags);
+        if ui.button("Open editor").clicked() {
+            state.active_note = Some(id);
+            state.list_expanded = None;
+        }
+        if ui.button("Done").clicked() {
+            state.list_expanded = None;
+        }
+    });
+    if resp.changed() {
+        state.userdata.link_wikilinks(id);
+    }
+}
 
-        ui.checkbox(&mut note.complete, "Finished");
+/// A single line editor for the title of a note, placed at `rect`.
+/// Returns `Some(true)` to commit on Enter or focus loss and `Some(false)` to cancel on Esc.
+fn inline_title_ui(ui: &mut Ui, edit: &mut InlineEdit, rect: Rect) -> Option<bool> {
+    let resp = ui.put(rect, egui::TextEdit::singleline(&mut edit.title));
+    if !edit.focus_requested {
+        resp.request_focus();
+        edit.focus_requested = true;
+    }
+    if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+        Some(false)
+    } else if resp.lost_focus() {
+        Some(true)
+    } else {
+        None
+    }
+}
 
-        egui::ComboBox::from_id_source(format!("{}x", note.id))
-            .selected_text("☞ depends on...".to_string())
-            .show_ui(ui, |ui| {
-                for (i, n) in immutable_notes.iter() {
-                    let contains = note.depends.contains(i);
-                    if ui.selectable_label(contains, n.get_title()).clicked() {
-                        if contains {
-                            note.depends.remove(i);
-                        } else {
-                            note.depends.insert(*i);
-                        }
+/// Actions on all notes with `tag`, offering the other tags of `all_tags` to add
+fn tag_actions_menu(ui: &mut Ui, tag: &str, all_tags: &[String]) -> Option<PendingAction> {
+    let mut action = None;
+    ui.menu_button(egui_phosphor::regular::DOTS_THREE, |ui| {
+        if ui.button("Select notes").clicked() {
+            action = Some(PendingAction::SelectTagged(tag.to_string()));
+            ui.close_menu();
+        }
+        ui.menu_button("Add tag to all…", |ui| {
+            for other in all_tags.iter().filter(|t| *t != tag) {
+                if ui
+                    .button(RichText::new(other).color(color_from_tag(other)))
+                    .clicked()
+                {
+                    action = Some(PendingAction::AddTagWhereTagged {
+                        tag: tag.to_string(),
+                        added: other.clone(),
+                    });
+                    ui.close_menu();
+                }
+            }
+            ui.separator();
+            let new_id = Id::new(("add_tag_to_all", tag));
+            let mut new_tag = ui
+                .data(|d| d.get_temp::<String>(new_id))
+                .unwrap_or_default();
+            let r = ui.add(
+                egui::TextEdit::singleline(&mut new_tag)
+                    .hint_text("New tag")
+                    .desired_width(120.),
+            );
+            let added = new_tag.trim();
+            if r.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) && !added.is_empty()
+            {
+                action = Some(PendingAction::AddTagWhereTagged {
+                    tag: tag.to_string(),
+                    added: added.to_string(),
+                });
+                new_tag.clear();
+                ui.close_menu();
+            }
+            ui.data_mut(|d| d.insert_temp(new_id, new_tag));
+        });
+        if ui
+            .button("Remove from all")
+            .on_hover_text("Remove the tag from all notes, but keep it in the list")
+            .clicked()
+        {
+            action = Some(PendingAction::RemoveTagFromAll(tag.to_string()));
+            ui.close_menu();
+        }
+    })
+    .response
+    .on_hover_text("Act on all notes with this tag");
+    action
+}
+
+/// Whether the browser asks for reduced motion. Native platforms don't tell.
+#[cfg(target_arch = "wasm32")]
+fn prefers_reduced_motion() -> bool {
+    web_sys::window()
+        .and_then(|w| w.match_media("(prefers-reduced-motion: reduce)").ok())
+        .flatten()
+        .is_some_and(|query| query.matches())
+}
+
+/// Whether the browser asks for reduced motion. Native platforms don't tell.
+#[cfg(not(target_arch = "wasm32"))]
+fn prefers_reduced_motion() -> bool {
+    false
+}
+
+/// A modal asking to confirm `description`. Returns `Some(true)` on confirm, `Some(false)` on cancel.
+fn confirm_dialog(ctx: &egui::Context, description: &str) -> Option<bool> {
+    let mut result = None;
+    egui::Window::new("Confirm")
+        .collapsible(false)
+        .resizable(false)
+        .order(egui::Order::Foreground)
+        .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label(description);
+            ui.horizontal(|ui| {
+                if ui.button("Confirm").clicked() {
+                    result = Some(true);
+                }
+                if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    result = Some(false);
+                }
+            });
+        });
+    result
+}
+
+/// The lower-right plus-button
+fn draw_note_add_button(ui: &mut Ui) -> Response {
+    let button_size = Vec2::splat(60.);
+    let margin = 10.;
+    let pos = Pos2::new(
+        ui.ctx().screen_rect().right() - button_size.x / 2. - margin,
+        ui.ctx().screen_rect().bottom() - button_size.y / 2. - margin,
+    );
+    let rect = Rect::from_center_size(pos, button_size);
+    ui.put(
+        rect,
+        egui::widgets::Button::new(RichText::new("✚").heading())
+            .rounding(100.)
+            .fill(Color32::from_rgba_premultiplied(50, 50, 50, 100)),
+    )
+}
+
+/// Sort notes for display: pinned notes first, then by descending (or `ascending`) priority.
+/// Ties are broken by creation date, oldest first (newest first if `ascending`), and id,
+/// so the order doesn't change between frames.
+fn sort_notes(notes: &mut [(u128, &Note)], ascending: bool) {
+    notes.sort_by(|(a_id, a), (b_id, b)| {
+        let by_prio = b
+            .get_final_prio()
+            .total_cmp(&a.get_final_prio())
+            .then(a.created.cmp(&b.created));
+        b.pinned
+            .cmp(&a.pinned)
+            .then(a.status.rank().cmp(&b.status.rank()))
+            .then(if ascending {
+                by_prio.reverse()
+            } else {
+                by_prio
+            })
+            .then(a_id.cmp(b_id))
+    });
+}
+
+fn boardview(ui: &mut Ui, state: &mut MeteoraApp) {
+    let notes = &state.userdata.notes;
+    let order: Vec<u128> = state
+        .board_notes()
+        .into_iter()
+        .filter(|id| notes.contains_key(id))
+        .collect();
+    let visible: HashSet<u128> = order.iter().copied().collect();
+
+    // With stacking, notes only appear on their own if nothing visible depends on them.
+    let roots: Vec<u128> = if state.settings.behavior.stack_dependencies {
+        let stacked: HashSet<u128> = order
+            .iter()
+            .flat_map(|id| visible_dependencies(*id, notes, &visible))
+            .collect();
+        let mut roots: Vec<u128> = order
+            .iter()
+            .copied()
+            .filter(|id| !stacked.contains(id))
+            .collect();
+        // Notes in a dependency cycle are not reachable from any root, so show them anyway.
+        let mut reachable = HashSet::default();
+        let mut stack = roots.clone();
+        for id in &order {
+            while let Some(id) = stack.pop() {
+                if reachable.insert(id) {
+                    stack.extend(visible_dependencies(id, notes, &visible));
+                }
+            }
+            if !reachable.contains(id) {
+                roots.push(*id);
+                stack.push(*id);
+            }
+        }
+        roots
+    } else {
+        order
+    };
+
+    if state.settings.behavior.show_done {
+        egui::SidePanel::right("done_column")
+            .resizable(false)
+            .show_inside(ui, |ui| done_column(ui, state));
+    }
+
+    // measuring and drawing go through the stacks in the same order, each keeping track of the
+    // cards already placed
+    let mut measured = HashSet::default();
+    let mut drawn = HashSet::default();
+    let mut rendered = RenderedCards::default();
+    let notes = &state.userdata.notes;
+    let groups: Vec<(String, Vec<u128>)> = match state.settings.appearance.board_grouping {
+        BoardGrouping::None => vec![],
+        BoardGrouping::Deadline => DeadlineBucket::ALL
+            .iter()
+            .map(|bucket| {
+                let ids = roots
+                    .iter()
+                    .filter(|id| DeadlineBucket::of(&notes[*id], today()) == *bucket)
+                    .copied()
+                    .collect();
+                (bucket.name().to_string(), ids)
+            })
+            .collect(),
+        BoardGrouping::Tag => {
+            let mut by_tag: BTreeMap<Option<&str>, Vec<u128>> = BTreeMap::new();
+            for id in &roots {
+                by_tag.entry(notes[id].primary_tag()).or_default().push(*id);
+            }
+            // tags in their usual order, untagged notes last
+            let tags = state.userdata.tags.iter().map(|t| Some(t.as_str()));
+            tags.chain([None])
+                .filter_map(|tag| {
+                    let ids = by_tag.remove(&tag)?;
+                    Some((tag.unwrap_or("Untagged").to_string(), ids))
+                })
+                .collect()
+        }
+    };
+    let board_rect = ui.max_rect();
+    if !groups.is_empty() {
+        let width = state.settings.appearance.card_width;
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            ui.horizontal_top(|ui| {
+                for (name, ids) in groups {
+                    ui.vertical(|ui| {
+                        ui.set_width(width);
+                        ui.label(RichText::new(format!("{name} ({})", ids.len())).strong());
+                        ui.separator();
+                        let stacks =
+                            measure_board_stacks(ui, state, &ids, &visible, width, &mut measured);
+                        egui::ScrollArea::vertical()
+                            .id_salt(("board_group", &name))
+                            .show_viewport(ui, |ui, _| {
+                                let origin = ui.cursor().min;
+                                let mut y = 0.;
+                                let placed: Vec<(Rect, BoardStack)> = stacks
+                                    .into_iter()
+                                    .map(|stack| {
+                                        let rect =
+                                            Rect::from_min_size(origin + vec2(0., y), stack.size);
+                                        y += stack.size.y;
+                                        (rect, stack)
+                                    })
+                                    .collect();
+                                ui.allocate_space(vec2(width, y));
+                                draw_board_stacks(
+                                    ui,
+                                    state,
+                                    placed,
+                                    &visible,
+                                    &mut drawn,
+                                    &mut rendered,
+                                );
+                            });
+                    });
+                }
+            });
+        });
+        rendered.debug_overlay(ui, board_rect);
+        return;
+    }
+
+    if let Some(columns) = state.settings.appearance.board_columns {
+        let columns = columns.max(1);
+        egui::ScrollArea::vertical().show_viewport(ui, |ui, _| {
+            let origin = ui.cursor().min;
+            let spacing = ui.spacing().item_spacing.x;
+            let width = (ui.available_width() - spacing * (columns - 1) as f32) / columns as f32;
+            let stacks = measure_board_stacks(ui, state, &roots, &visible, width, &mut measured);
+            // stacks are dealt to the columns in turn
+            let mut heights = vec![0.; columns];
+            let placed: Vec<(Rect, BoardStack)> = stacks
+                .into_iter()
+                .enumerate()
+                .map(|(i, stack)| {
+                    let column = i % columns;
+                    let offset = vec2(column as f32 * (width + spacing), heights[column]);
+                    heights[column] += stack.size.y;
+                    (Rect::from_min_size(origin + offset, stack.size), stack)
+                })
+                .collect();
+            let height = heights.into_iter().fold(0., f32::max);
+            ui.allocate_space(vec2(ui.available_width(), height));
+            draw_board_stacks(ui, state, placed, &visible, &mut drawn, &mut rendered);
+        });
+        rendered.debug_overlay(ui, board_rect);
+        return;
+    }
+
+    let width = state.settings.appearance.card_width;
+    let stacks = measure_board_stacks(ui, state, &roots, &visible, width, &mut measured);
+    egui::ScrollArea::horizontal()
+        // .auto_shrink([false,false])
+        .hscroll(true)
+        .min_scrolled_width(ui.available_width())
+        .show_viewport(ui, |ui, _| {
+            let origin = ui.cursor().min;
+            let max_height = ui.available_size_before_wrap().y;
+            let spacing = ui.spacing().item_spacing.x;
+            // fill a column top to bottom before starting the next one
+            let mut offset = Vec2::ZERO;
+            let placed: Vec<(Rect, BoardStack)> = stacks
+                .into_iter()
+                .map(|stack| {
+                    if offset.y > 0. && offset.y + stack.size.y > max_height {
+                        offset = vec2(offset.x + width + spacing, 0.);
+                    }
+                    let rect = Rect::from_min_size(origin + offset, stack.size);
+                    offset.y += stack.size.y;
+                    (rect, stack)
+                })
+                .collect();
+            ui.allocate_space(vec2(offset.x + width, max_height));
+            draw_board_stacks(ui, state, placed, &visible, &mut drawn, &mut rendered);
+        });
+    rendered.debug_overlay(ui, board_rect);
+}
+
+/// A card and the cards stacked below it, measured up front so only visible stacks are drawn
+struct BoardStack {
+    /// The cards of the stack in the order they are drawn, empty if all were drawn before
+    ids: Vec<u128>,
+    size: Vec2,
+}
+
+/// How many cards of the board or rows of the list were drawn, of all that are laid out
+#[derive(Default)]
+struct RenderedCards {
+    drawn: usize,
+    total: usize,
+}
+
+impl RenderedCards {
+    /// Show the count in the corner of the view, in debug builds
+    fn debug_overlay(&self, ui: &Ui, view_rect: Rect) {
+        #[cfg(debug_assertions)]
+        ui.ctx().debug_painter().text(
+            view_rect.left_bottom() + vec2(8., -8.),
+            egui::Align2::LEFT_BOTTOM,
+            format!("rendered {} of {} cards", self.drawn, self.total),
+            FontId::monospace(12.),
+            Color32::YELLOW,
+        );
+        #[cfg(not(debug_assertions))]
+        let _ = (ui, view_rect);
+    }
+}
+
+/// Measure the stacks of `roots` the way `draw_board_stack` lays them out
+fn measure_board_stacks(
+    ui: &Ui,
+    state: &MeteoraApp,
+    roots: &[u128],
+    visible: &HashSet<u128>,
+    width: f32,
+    drawn: &mut HashSet<u128>,
+) -> Vec<BoardStack> {
+    roots
+        .iter()
+        .map(|id| {
+            let mut cards = MeasuredCards {
+                drawn: &mut *drawn,
+                stack: vec![],
+            };
+            let height = measure_board_stack(ui, state, *id, visible, width, 0, &mut cards);
+            BoardStack {
+                ids: cards.stack,
+                size: vec2(width, height),
+            }
+        })
+        .collect()
+}
+
+/// The cards measured so far
+struct MeasuredCards<'a> {
+    /// The cards of all stacks
+    drawn: &'a mut HashSet<u128>,
+    /// The cards of the stack being measured
+    stack: Vec<u128>,
+}
+
+/// The height of the stack of `id`, including the space after it
+fn measure_board_stack(
+    ui: &Ui,
+    state: &MeteoraApp,
+    id: u128,
+    visible: &HashSet<u128>,
+    width: f32,
+    depth: usize,
+    cards: &mut MeasuredCards<'_>,
+) -> f32 {
+    if !cards.drawn.insert(id) {
+        return 0.;
+    }
+    let notes = &state.userdata.notes;
+    let Some(note) = notes.get(&id).filter(|_| visible.contains(&id)) else {
+        return 0.;
+    };
+    cards.stack.push(id);
+    let style = CardStyle {
+        width: stacked_card_width(width, depth),
+        density: state.settings.appearance.density,
+        text_size: state.settings.appearance.note_text_size,
+    };
+    let card = measure_card(
+        ui,
+        note,
+        &state.userdata.notes,
+        &state.image_previews,
+        &style,
+    );
+    let mut height = card.y + ui.spacing().item_spacing.y;
+
+    if !state.settings.behavior.stack_dependencies {
+        return height;
+    }
+    let children: Vec<u128> = visible_dependencies(id, notes, visible)
+        .into_iter()
+        .filter(|c| !cards.drawn.contains(c))
+        .collect();
+    if children.is_empty() {
+        return height;
+    }
+    if stack_expanded(ui, id) {
+        for child in children {
+            height += measure_board_stack(ui, state, child, visible, width, depth + 1, cards);
+        }
+    } else {
+        height += COLLAPSED_STACK_HEIGHT;
+    }
+    height
+}
+
+/// Draw the stacks placed at rects that are in view. Cards of the others are only marked as drawn.
+fn draw_board_stacks(
+    ui: &mut Ui,
+    state: &mut MeteoraApp,
+    placed: Vec<(Rect, BoardStack)>,
+    visible: &HashSet<u128>,
+    drawn: &mut HashSet<u128>,
+    rendered: &mut RenderedCards,
+) {
+    for (rect, stack) in placed {
+        let Some(&id) = stack.ids.first() else {
+            continue;
+        };
+        rendered.total += stack.ids.len();
+        if !ui.clip_rect().intersects(rect) {
+            drawn.extend(stack.ids);
+            continue;
+        }
+        rendered.drawn += stack.ids.len();
+        let mut stack_ui = ui.new_child(
+            egui::UiBuilder::new()
+                .id_salt(("board_stack", id))
+                .max_rect(rect)
+                .layout(Layout::top_down(egui::Align::RIGHT)),
+        );
+        draw_board_stack(&mut stack_ui, state, id, visible, rect.width(), 0, drawn);
+    }
+}
+
+/// The finished notes on the board, most recently completed first
+fn done_column(ui: &mut Ui, state: &mut MeteoraApp) {
+    let done = state
+        .userdata
+        .completed_notes(&state.filter, &state.active_tags);
+    let width = state.settings.appearance.card_width;
+    egui::CollapsingHeader::new(format!("Done ({})", done.len()))
+        .id_salt("done_column")
+        .default_open(true)
+        .show(ui, |ui| {
+            ui.set_width(width);
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for id in done {
+                    if let Some(note) = state.userdata.notes.get(&id).cloned() {
+                        draw_board_note(ui, state, &note, width);
                     }
                 }
             });
+        });
+}
 
-        if ui.button("🗑 delete").clicked() {
-            notes.remove(note_id);
+/// Draw a board card and, if stacking is enabled and the stack is expanded, its dependencies below it.
+fn draw_board_stack(
+    ui: &mut Ui,
+    state: &mut MeteoraApp,
+    id: u128,
+    visible: &HashSet<u128>,
+    width: f32,
+    depth: usize,
+    drawn: &mut HashSet<u128>,
+) {
+    // guard against cycles and notes shared by several stacks
+    if !drawn.insert(id) {
+        return;
+    }
+    let notes = &state.userdata.notes;
+    let Some(note) = notes.get(&id).filter(|_| visible.contains(&id)).cloned() else {
+        return;
+    };
+    let resp = draw_board_note(ui, state, &note, stacked_card_width(width, depth));
+
+    if !state.settings.behavior.stack_dependencies {
+        return;
+    }
+    let children: Vec<u128> = visible_dependencies(id, &state.userdata.notes, visible)
+        .into_iter()
+        .filter(|c| !drawn.contains(c))
+        .collect();
+    if children.is_empty() {
+        return;
+    }
+
+    let mut expanded = stack_expanded(ui, id);
+    // left of the pin button
+    let badge_rect = Rect::from_min_size(resp.rect.right_top() + vec2(-66., 4.), vec2(40., 20.));
+    let badge = egui::Button::new(format!(
+        "{} {}",
+        if expanded {
+            egui_phosphor::regular::CARET_DOWN
+        } else {
+            egui_phosphor::regular::STACK
+        },
+        children.len()
+    ))
+    .small();
+    if ui
+        .put(badge_rect, badge)
+        .on_hover_text("Show dependencies")
+        .clicked()
+    {
+        expanded = !expanded;
+        ui.data_mut(|d| d.insert_temp(Id::new(("stack_expanded", id)), expanded));
+    }
+
+    if expanded {
+        for child in children {
+            draw_board_stack(ui, state, child, visible, width, depth + 1, drawn);
+        }
+    } else {
+        // hint at the collapsed cards underneath
+        let color = note.get_color().gamma_multiply(0.6);
+        for i in 1..=2 {
+            let inset = 6. * i as f32;
+            let top = resp.rect.bottom() + 3. * (i - 1) as f32;
+            ui.painter().rect_filled(
+                Rect::from_min_max(
+                    Pos2::new(resp.rect.left() + inset, top),
+                    Pos2::new(resp.rect.right() - inset, top + 3.),
+                ),
+                2.,
+                color,
+            );
+        }
+        ui.add_space(COLLAPSED_STACK_HEIGHT);
+    }
+}
+
+/// The notes `id` depends on that are shown on the board
+fn visible_dependencies(id: u128, notes: &Notes, visible: &HashSet<u128>) -> Vec<u128> {
+    dependencies_of(id, notes)
+        .into_iter()
+        .filter(|d| visible.contains(d))
+        .collect()
+}
+
+/// Cards stacked under another one are narrower, depending on how deep they are
+fn stacked_card_width(width: f32, depth: usize) -> f32 {
+    (width - 15. * depth as f32).max(width * 0.6)
+}
+
+/// Whether the dependencies stacked under the card of `id` are shown
+fn stack_expanded(ui: &Ui, id: u128) -> bool {
+    ui.data(|d| d.get_temp::<bool>(Id::new(("stack_expanded", id))))
+        .unwrap_or_default()
+}
+
+/// Draw a single board card and keep undo history and tags in sync with changes made on it.
+fn draw_board_note(ui: &mut Ui, state: &mut MeteoraApp, note: &Note, width: f32) -> Response {
+    let resp = draw_note(
+        ui,
+        &note.id,
+        &mut state.userdata.notes,
+        &mut state.active_note,
+        &mut state.image_previews,
+        CardStyle {
+            width,
+            density: state.settings.appearance.density,
+            text_size: state.settings.appearance.note_text_size,
+        },
+    );
+    // checklist items can be toggled on the card
+    if let Some(changed) = state.userdata.notes.get(&note.id) {
+        if changed != note {
+            state.undo_stack.push(NoteChange {
+                id: note.id,
+                before: Some(note.clone()),
+                after: Some(changed.clone()),
+                ..Default::default()
+            });
+        }
+    }
+    state.note_clicked(&resp, note.id);
+    state.paint_selected(ui, note.id, resp.rect);
+    state.paint_woken(ui, note.id, resp.rect);
+    resp.context_menu(|ui| {
+        ui.menu_button(format!("{} Snooze", egui_phosphor::regular::BELL_Z), |ui| {
+            if let Some(until) = snooze_choices(ui) {
+                state.snooze(note.id, Some(until));
+                ui.close_menu();
+            }
+            if ui
+                .button("Pick a date…")
+                .on_hover_text("Snooze until tomorrow and change the day in the editor")
+                .clicked()
+            {
+                state.snooze(note.id, today().succ_opt());
+                state.active_note = Some(note.id);
+                ui.close_menu();
+            }
+        });
+        let timer = match state.timer_of(note.id) {
+            Some(_) => format!("{} Stop timer", egui_phosphor::regular::PAUSE),
+            None => format!("{} Start timer", egui_phosphor::regular::PLAY),
+        };
+        if ui.button(timer).clicked() {
+            state.toggle_timer(note.id);
+            ui.close_menu();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui
+            .button(format!(
+                "{} Open in new window",
+                egui_phosphor::regular::APP_WINDOW
+            ))
+            .clicked()
+        {
+            state.open_note_window(note.id);
+            ui.close_menu();
         }
     });
+    if let Some(edit) = state.inline_edit.as_mut().filter(|e| e.id == note.id) {
+        let padding = state.settings.appearance.density.padding();
+        let rect = Rect::from_min_size(
+            resp.rect.left_top() + vec2(padding, padding),
+            vec2(
+                resp.rect.width() - 2. * padding,
+                ui.spacing().interact_size.y,
+            ),
+        );
+        if let Some(commit) = inline_title_ui(ui, edit, rect) {
+            state.finish_inline_edit(commit);
+        }
+    }
+    resp
+}
 
-    // ui.collapsing("RND", |ui| {
-    // let mut cache = CommonMarkCache::default();
-    //     CommonMarkViewer::new("viewer").show(ui, &mut cache, &note.text);
+fn listview(ui: &mut Ui, state: &mut MeteoraApp) {
+    let mut rows: Vec<ListEntry> = vec![];
+    if state.settings.appearance.list_by_status {
+        let v = state.userdata.visible_notes(
+            &state.filter,
+            &state.active_tags,
+            Visibility::All,
+            state.settings.behavior.sort_ascending,
+        );
+        for status in Status::ALL {
+            let ids = if status == Status::Done {
+                state
+                    .userdata
+                    .completed_notes(&state.filter, &state.active_tags)
+            } else {
+                v.iter()
+                    .copied()
+                    .filter(|id| state.userdata.notes[id].status == status)
+                    .collect()
+            };
+            push_status_group(ui.ctx(), &mut rows, status, ids);
+        }
+    } else {
+        // finished notes get their own group if the "Done" section is enabled
+        let visibility = if state.settings.behavior.show_done {
+            Visibility::Open
+        } else {
+            Visibility::All
+        };
+        let v = state.userdata.visible_notes(
+            &state.filter,
+            &state.active_tags,
+            visibility,
+            state.settings.behavior.sort_ascending,
+        );
+        rows.extend(v.into_iter().map(ListEntry::Note));
+        if state.settings.behavior.show_done {
+            let done = state
+                .userdata
+                .completed_notes(&state.filter, &state.active_tags);
+            push_status_group(ui.ctx(), &mut rows, Status::Done, done);
+        }
+    }
 
-    // });
+    let list_rect = ui.max_rect();
+    let mut rendered = RenderedCards::default();
+    // rows not drawn yet are taken to be a single line
+    let estimate = ui.spacing().interact_size.y + 10. + ui.spacing().item_spacing.y;
+    egui::ScrollArea::vertical()
+        // .auto_shrink([false,false])
+        // .min_scrolled_width(ui.available_width())
+        .show_viewport(ui, |ui, _| {
+            let origin = ui.cursor().min;
+            let width = ui.available_width();
+            let mut y = 0.;
+            for row in rows {
+                let key = match row {
+                    ListEntry::Note(id) => Id::new(("list_row", id)),
+                    ListEntry::StatusHeader(status, _) => status_group_id(status),
+                };
+                let height = state
+                    .list_row_heights
+                    .get(&key)
+                    .copied()
+                    .unwrap_or(estimate);
+                let min = origin + vec2(0., y);
+                rendered.total += 1;
+                if !ui
+                    .clip_rect()
+                    .intersects(Rect::from_min_size(min, vec2(width, height)))
+                {
+                    y += height;
+                    continue;
+                }
+                rendered.drawn += 1;
+                let mut row_ui = ui.new_child(
+                    egui::UiBuilder::new()
+                        .id_salt(key)
+                        .max_rect(Rect::from_min_size(min, vec2(width, f32::INFINITY)))
+                        .layout(Layout::top_down(egui::Align::Min)),
+                );
+                match row {
+                    ListEntry::Note(id) => list_row(&mut row_ui, state, &id),
+                    ListEntry::StatusHeader(status, count) => {
+                        status_header(&mut row_ui, status, count)
+                    }
+                }
+                let height = row_ui.min_rect().height() + ui.spacing().item_spacing.y;
+                state.list_row_heights.insert(key, height);
+                y += height;
+            }
+            ui.allocate_space(vec2(width, y));
+        });
+    rendered.debug_overlay(ui, list_rect);
 }
 
-fn draw_note(ui: &mut Ui, note_id: &u128, notes: &Notes, active_note: &mut Option<u128>) {
-    // make sure id is valid
-    if notes.get(note_id).is_none() {
-        ui.label("No such ID");
+/// The notes due today or overdue as list rows, the most urgent first
+fn agendaview(ui: &mut Ui, state: &mut MeteoraApp) {
+    // deadlines roll over at midnight even if nothing else happens
+    let until_midnight = (next_midnight() - chrono::Utc::now())
+        .to_std()
+        .unwrap_or_default();
+    ui.ctx().request_repaint_after(until_midnight);
+
+    let today = today();
+    ui.heading(format!(
+        "Today, {}",
+        format_date(today, &state.settings.appearance.date_format)
+    ));
+    let agenda = state.agenda_notes();
+    if agenda.is_empty() {
+        ui.label("Nothing is due today.");
         return;
     }
+    let (overdue, due_today): (Vec<u128>, Vec<u128>) = agenda.into_iter().partition(|id| {
+        DeadlineBucket::of(&state.userdata.notes[id], today) == DeadlineBucket::Overdue
+    });
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for (name, ids) in [("Overdue", overdue), ("Due today", due_today)] {
+            if ids.is_empty() {
+                continue;
+            }
+            ui.label(RichText::new(format!("{name} ({})", ids.len())).strong());
+            for id in ids {
+                ui.push_id(("agenda_row", id), |ui| list_row(ui, state, &id));
+            }
+            ui.add_space(10.);
+        }
+    });
+}
 
-    let note = notes.get(note_id).unwrap();
-
-    let estimated_size =
-        note.get_approx_height(ui.fonts(|r| r.row_height(&FontId::proportional(15.))) + 2.);
-
-    let note_size = Vec2::new(150., estimated_size.max(150.));
+/// A row of the list view
+#[derive(Clone, Copy)]
+enum ListEntry {
+    Note(u128),
+    /// Folds the notes of a status, of which there are this many
+    StatusHeader(Status, usize),
+}
 
-    let (rect, resp) = ui.allocate_exact_size(note_size, Sense::click());
+/// Where the list view keeps whether the group of `status` is open
+fn status_group_id(status: Status) -> Id {
+    Id::new(("list_group", status))
+}
 
-    let stroke = if resp.hovered() {
-        Stroke::new(3., Color32::GRAY)
-    } else {
-        Stroke::NONE
-    };
+/// Whether the group of `status` is open in the list view. Finished notes are folded at first.
+fn status_group(ctx: &egui::Context, status: Status) -> egui::collapsing_header::CollapsingState {
+    egui::collapsing_header::CollapsingState::load_with_default_open(
+        ctx,
+        status_group_id(status),
+        status != Status::Done,
+    )
+}
 
-    #[cfg(debug_assertions)]
-    if resp.hovered() {
-        info!("note color {:?}", note.color);
+/// Add a header for the notes `ids` of `status` to the list rows, followed by the notes if the
+/// group is open. Nothing is added for no notes.
+fn push_status_group(
+    ctx: &egui::Context,
+    rows: &mut Vec<ListEntry>,
+    status: Status,
+    ids: Vec<u128>,
+) {
+    if ids.is_empty() {
+        return;
+    }
+    rows.push(ListEntry::StatusHeader(status, ids.len()));
+    if status_group(ctx, status).is_open() {
+        rows.extend(ids.into_iter().map(ListEntry::Note));
     }
+}
 
-    let frame_shape = Shape::Rect(RectShape::new(rect, 5.0, note.get_color(), stroke));
+/// The header of a status group in the list view, toggling whether its notes are shown
+fn status_header(ui: &mut Ui, status: Status, count: usize) {
+    let mut state = status_group(ui.ctx(), status);
+    ui.horizontal(|ui| {
+        state.show_toggle_button(ui, egui::collapsing_header::paint_default_icon);
+        let label = format!("{} {} ({count})", status.icon(), status.name());
+        if ui
+            .add(egui::Label::new(label).sense(Sense::click()))
+            .clicked()
+        {
+            state.toggle(ui);
+        }
+    });
+    state.store(ui.ctx());
+}
 
-    let mut shapes_to_draw = vec![frame_shape];
+/// A note in the list view, with clicks and inline title edits applied to the app
+fn list_row(ui: &mut Ui, state: &mut MeteoraApp, id: &u128) {
+    let mut events = ListRowEvents::default();
+    draw_list_note(
+        ui,
+        id,
+        &state.userdata.notes,
+        ListRowOptions {
+            filter: &state.filter,
+            word_count: state.settings.appearance.list_word_count,
+        },
+        &mut state.inline_edit,
+        &mut events,
+        Some(&mut state.list_expanded),
+    );
+    if state.list_expanded == Some(*id) {
+        list_inline_editor(ui, state, *id);
+    }
+    state.paint_selected(ui, *id, ui.min_rect());
+    state.paint_woken(ui, *id, ui.min_rect());
+    if let Some((id, resp)) = events.clicked {
+        state.note_clicked(&resp, id);
+    }
+    if let Some(commit) = events.inline_commit {
+        state.finish_inline_edit(commit);
+    }
+}
 
-    for (i, tag) in note.tags.iter().enumerate().skip(1) {
-        let offset = 20.;
+pub fn bare_button(text: impl Into<String>, ui: &mut Ui) -> Response {
+    ui.add(egui::Button::new(RichText::new(text).size(30.)).frame(false))
+}
 
-        let r = Rect::from_min_max(
-            rect.left_top(),
-            Pos2::new(rect.left_top().x + offset, rect.left_top().y + offset),
-        )
-        .translate(vec2(offset * i as f32, 0.0))
-        .translate(vec2(-offset + 2., note_size.y - offset - 2.));
+pub fn bare_button_sized(text: impl Into<String>, size: f32, ui: &mut Ui) -> Response {
+    ui.add(egui::Button::new(RichText::new(text).size(size)).frame(false))
+}
 
-        let tag_shape = Shape::Rect(RectShape::new(
-            r,
-            10.0,
-            color_from_tag(tag).gamma_multiply(GAMMA_MULT),
-            Stroke::NONE,
-        ));
-        shapes_to_draw.push(tag_shape)
+/// The size of the serialized user data, recomputed at most once a second
+fn payload_size(ctx: &egui::Context, userdata: &mut UserData) -> usize {
+    let id = Id::new("payload_size");
+    let now = ctx.input(|i| i.time);
+    if let Some((measured, size)) = ctx.data(|d| d.get_temp::<(f64, usize)>(id)) {
+        if now - measured < 1.0 {
+            return size;
+        }
     }
+    // the attachment contents are large, but their encoded size is known without serializing
+    let attachments = std::mem::take(&mut userdata.attachments);
+    let size = serde_json::to_vec(userdata)
+        .map(|v| v.len())
+        .unwrap_or_default()
+        + attachments.json_len();
+    userdata.attachments = attachments;
+    ctx.data_mut(|d| d.insert_temp(id, (now, size)));
+    size
+}
 
-    let s = Shadow {
-        offset: Default::default(),
-        blur: 30.0,
-        spread: 5.0,
-        color: Color32::from_black_alpha(70),
+/// A human readable file size
+fn format_size(bytes: usize) -> String {
+    match bytes {
+        0..1024 => format!("{bytes} B"),
+        1024..1_048_576 => format!("{:.1} kB", bytes as f32 / 1024.),
+        _ => format!("{:.1} MB", bytes as f32 / 1_048_576.),
     }
-    .as_shape(rect, 4.0);
-    ui.painter().add(s);
+}
 
-    ui.painter().add(shapes_to_draw);
+/// A hash of the encrypted user data
+fn blob_hash(blob: &str) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    blob.hash(&mut hasher);
+    hasher.finish()
+}
 
-    let mut sub_ui = ui.child_ui(
-        rect.shrink(10.),
-        Layout::left_to_right(egui::Align::TOP).with_main_wrap(true),
-        None,
+/// The image in the clipboard as a PNG, with a name for it
+#[cfg(not(target_arch = "wasm32"))]
+fn clipboard_image() -> anyhow::Result<(String, Vec<u8>)> {
+    let image = arboard::Clipboard::new()?
+        .get_image()
+        .map_err(|e| anyhow::anyhow!("No image in the clipboard: {e}"))?;
+    let rgba = image::RgbaImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.bytes.into_owned(),
+    )
+    .ok_or_else(|| anyhow::anyhow!("The clipboard image has an unexpected size"))?;
+    let mut png = std::io::Cursor::new(vec![]);
+    rgba.write_to(&mut png, image::ImageFormat::Png)?;
+    let name = format!(
+        "pasted-{}.png",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
     );
+    Ok((name, png.into_inner()))
+}
 
-    // if note.contains_markdown() {
-    //     let mut fcache = CommonMarkCache::default();
-    //     CommonMarkViewer::new("viewer").show(&mut sub_ui, &mut cache, &note.get_clean_text());
-    // } else {
-    //     sub_ui.label(note.get_clean_text());
-    // }
-
-    sub_ui.add(
-        egui::Label::new(
-            RichText::new(&note.get_clean_text_truncated()).color(readable_text(&note.get_color())),
-        )
-        .truncate()
-        .wrap(),
-    );
+/// Whether the paste shortcut was used. egui only reports pastes of text, but the release of
+/// the key comes through for any clipboard content.
+#[cfg(not(target_arch = "wasm32"))]
+fn paste_shortcut_released(ctx: &egui::Context) -> bool {
+    ctx.input(|i| {
+        i.events.iter().any(|e| {
+            matches!(
+                e,
+                egui::Event::Key {
+                    key: egui::Key::V,
+                    pressed: false,
+                    modifiers,
+                    ..
+                } if modifiers.command
+            )
+        })
+    })
+}
 
-    // sub_ui.label(
-    //     RichText::new(&note.get_clean_text())
-    //     .color(readable_text(&Color32::from_rgb(
-    //         note.color[0],
-    //         note.color[1],
-    //         note.color[2],
-    //     ))
-    // ), // .size(12.)
-    // );
+/// Images pasted into the page, with their names, until the note editor attaches them
+#[cfg(target_arch = "wasm32")]
+static PASTED_IMAGES: std::sync::Mutex<Vec<(String, Vec<u8>)>> = std::sync::Mutex::new(vec![]);
+
+/// eframe only hands on pasted text, so listen to the paste events of the page for images.
+#[cfg(target_arch = "wasm32")]
+fn listen_for_pasted_images(ctx: &egui::Context) -> anyhow::Result<()> {
+    use wasm_bindgen::{closure::Closure, JsCast};
+    let ctx = ctx.clone();
+    let on_paste = Closure::<dyn FnMut(_)>::new(move |event: web_sys::ClipboardEvent| {
+        let Some(files) = event.clipboard_data().and_then(|data| data.files()) else {
+            return;
+        };
+        for file in (0..files.length()).filter_map(|i| files.get(i)) {
+            if !file.type_().starts_with("image/") {
+                continue;
+            }
+            let ctx = ctx.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let Ok(buffer) = wasm_bindgen_futures::JsFuture::from(file.array_buffer()).await
+                else {
+                    return;
+                };
+                let data = js_sys::Uint8Array::new(&buffer).to_vec();
+                if let Ok(mut pasted) = PASTED_IMAGES.lock() {
+                    pasted.push((file.name(), data));
+                }
+                ctx.request_repaint();
+            });
+        }
+    });
+    web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or(anyhow::anyhow!("No document to paste into"))?
+        .add_event_listener_with_callback("paste", on_paste.as_ref().unchecked_ref())
+        .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+    // the listener stays for the lifetime of the page
+    on_paste.forget();
+    Ok(())
+}
 
-    // sub_ui.label(&note.text);
-    // sub_ui.add_space(20.);
+/// List the attachments of a note with thumbnails for images. New attachments are pasted into
+/// the text of the note, or dropped on the window as files.
+fn attachments_ui(
+    ui: &mut Ui,
+    note: &mut Note,
+    attachments: &mut Attachments,
+    toasts: &mut Toasts,
+) {
+    let mut new_attachments = vec![];
+    for file in ui.ctx().input(|i| i.raw.dropped_files.clone()) {
+        let data = match (&file.bytes, &file.path) {
+            (Some(bytes), _) => Ok(bytes.to_vec()),
+            (None, Some(path)) => std::fs::read(path),
+            (None, None) => continue,
+        };
+        let name = match &file.path {
+            Some(path) => path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            None => file.name.clone(),
+        };
+        match data {
+            Ok(data) => new_attachments.push((name, data)),
+            Err(e) => {
+                toasts.error(format!("Could not read '{name}': {e}"));
+            }
+        }
+    }
+    if ui.memory(|m| m.has_focus(note_text_id(note.id))) {
+        // a paste without an image in the clipboard was a paste of text
+        #[cfg(not(target_arch = "wasm32"))]
+        if paste_shortcut_released(ui.ctx()) {
+            new_attachments.extend(clipboard_image().ok());
+        }
+        #[cfg(target_arch = "wasm32")]
+        if let Ok(mut pasted) = PASTED_IMAGES.lock() {
+            new_attachments.append(&mut pasted);
+        }
+    }
 
-    for link in note.get_links() {
-        // sub_ui.label(format!("l{link}"));
-        sub_ui.hyperlink_to(link_text(link), link);
+    ui.label(format!(
+        "{} {} attachment{} ({} of {})",
+        egui_phosphor::regular::PAPERCLIP,
+        note.attachments.len(),
+        if note.attachments.len() == 1 { "" } else { "s" },
+        format_size(attachments.size_of(note)),
+        format_size(MAX_ATTACHMENT_BYTES)
+    ))
+    .on_hover_text("Paste images into the text or drop files on the window to attach them");
+
+    for (name, data) in new_attachments {
+        if let Err(e) = attachments.attach(note, &name, data) {
+            toasts.error(e.to_string());
+        }
     }
 
-    // ui.put(rect, egui::Label::new(note.get_title()));
+    let mut remove = None;
+    ui.horizontal_wrapped(|ui| {
+        for (i, attachment) in note.attachments.iter().enumerate() {
+            let uri = format!("bytes://attachment/{}/{}", attachment.id, attachment.name);
+            ui.vertical(|ui| {
+                match attachments.get(attachment) {
+                    Some(data) if attachment.is_image() => {
+                        if ui.ctx().try_load_bytes(&uri).is_err() {
+                            ui.ctx().include_bytes(uri.clone(), data.0.clone());
+                        }
+                        ui.add(egui::Image::new(uri.clone()).max_height(100.))
+                            .on_hover_text(&attachment.name);
+                    }
+                    Some(_) => {
+                        ui.label(&attachment.name);
+                    }
+                    None => {
+                        ui.label(RichText::new(&attachment.name).strikethrough())
+                            .on_hover_text("The contents of this attachment are missing");
+                    }
+                }
+                if ui
+                    .small_button(format!("{} Remove", egui_phosphor::regular::TRASH))
+                    .clicked()
+                {
+                    ui.ctx().forget_image(&uri);
+                    remove = Some(i);
+                }
+            });
+        }
+    });
+    if let Some(i) = remove {
+        note.attachments.remove(i);
+    }
+}
 
-    // });
-    // let resp = r.response.interact(egui::Sense::click());
-    if resp.clicked() {
-        *active_note = Some(*note_id);
+/// A file link with its icon, struck through if the file can't be found
+fn file_link_label(link: &FileLink) -> RichText {
+    let label = RichText::new(format!("{} {}", egui_phosphor::regular::FILE, link.name()));
+    if link.exists() {
+        label
+    } else {
+        label.strikethrough()
     }
 }
 
-fn draw_list_note(ui: &mut Ui, note_id: &u128, notes: &Notes, active_note: &mut Option<u128>) {
-    // make sure id is valid
-    if notes.get(note_id).is_none() {
-        ui.label("No such ID");
-        return;
+/// The full path of a file link, and where it was added if that is not this device
+fn file_link_hover(link: &FileLink) -> String {
+    let mut hover = link.path.display().to_string();
+    if !link.exists() {
+        hover.push_str("\nThe file can't be found on this device.");
     }
+    if link.is_foreign() && !link.host.is_empty() {
+        hover.push_str(&format!("\nAdded on {}", link.host));
+    }
+    hover
+}
 
-    let note = notes.get(note_id).unwrap();
+/// Open a linked file with the default application of the system
+fn open_file_link(link: &FileLink) -> anyhow::Result<()> {
+    if !link.exists() {
+        anyhow::bail!("'{}' can't be found on this device.", link.path.display());
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    return open::that_detached(&link.path)
+        .map_err(|e| anyhow::anyhow!("Could not open '{}': {e}", link.path.display()));
+    #[cfg(target_arch = "wasm32")]
+    anyhow::bail!("Files can only be opened in the desktop app.");
+}
 
-    let frame = egui::Frame {
-        fill: note.get_color(),
-        inner_margin: 5.0.into(),
-        ..Default::default()
-    };
-    let inner = frame.show(ui, |ui| {
-        ui.allocate_exact_size(vec2(ui.available_width(), 0.), Sense::click());
+/// Previews of the images linked in a note. Nothing is fetched unless remote images are enabled
+/// or the user asks for the images of this note.
+fn image_previews_ui(ui: &mut Ui, note: &Note, previews: &mut ImagePreviews) {
+    let urls = note.get_image_links();
+    if urls.is_empty() {
+        return;
+    }
+    if !previews.allowed(note) {
         ui.horizontal(|ui| {
-            ui.label(note.get_title());
-            ui.add(egui::Label::new(RichText::new(note.get_excerpt()).size(10.)).truncate());
+            ui.label(format!(
+                "{} {} linked image{} not loaded",
+                egui_phosphor::regular::IMAGE,
+                urls.len(),
+                if urls.len() == 1 { "" } else { "s" }
+            ));
+            if ui
+                .button("Load images")
+                .on_hover_text("Remote images are off in the settings")
+                .clicked()
+            {
+                previews.allowed_notes.insert(note.id);
+            }
         });
-        for d in &note.depends {
-            if let Some(dependent) = notes.get(d) {
-                ui.collapsing(dependent.get_title(), |ui| {
-                    draw_list_note(ui, d, notes, active_note);
-                });
+        return;
+    }
+    ui.horizontal_wrapped(|ui| {
+        for url in urls {
+            if previews
+                .thumbnail(ui, url, Vec2::splat(THUMBNAIL_SIZE))
+                .clicked()
+            {
+                ui.ctx().open_url(egui::OpenUrl::new_tab(url));
             }
         }
-        if ui.ui_contains_pointer() {
-            ui.painter().rect_filled(
-                ui.min_rect().expand(5.),
-                0.0,
-                Color32::from_rgb_additive(11, 11, 11),
-            );
-        }
     });
-
-    let resp = inner.response.interact(Sense::click());
-
-    if resp.clicked() {
-        *active_note = Some(*note_id);
-    }
 }
 
-/// The lower-right plus-button
-fn draw_note_add_button(ui: &mut Ui) -> Response {
-    let button_size = Vec2::splat(60.);
-    let margin = 10.;
-    let pos = Pos2::new(
-        ui.ctx().screen_rect().right() - button_size.x / 2. - margin,
-        ui.ctx().screen_rect().bottom() - button_size.y / 2. - margin,
-    );
-    let rect = Rect::from_center_size(pos, button_size);
-    ui.put(
-        rect,
-        egui::widgets::Button::new(RichText::new("✚").heading())
-            .rounding(100.)
-            .fill(Color32::from_rgba_premultiplied(50, 50, 50, 100)),
-    )
+/// Let the user save an exported file. Returns false if they cancelled.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_export(file_name: &str, contents: &str) -> anyhow::Result<bool> {
+    let Some(path) = rfd::FileDialog::new().set_file_name(file_name).save_file() else {
+        return Ok(false);
+    };
+    std::fs::write(path, contents)?;
+    Ok(true)
 }
 
-fn boardview(ui: &mut Ui, state: &mut MeteoraApp) {
-    let mut v = Vec::from_iter(state.userdata.notes.clone());
-    v.sort_by(|(_, a), (_, b)| b.get_final_prio().total_cmp(&a.get_final_prio()));
+/// Offer an exported file as a download in the browser
+#[cfg(target_arch = "wasm32")]
+fn save_export(file_name: &str, contents: &str) -> anyhow::Result<bool> {
+    use wasm_bindgen::JsCast;
+    let js_error = |e: wasm_bindgen::JsValue| anyhow::anyhow!("{e:?}");
+    let parts = js_sys::Array::of1(&contents.into());
+    let blob = web_sys::Blob::new_with_str_sequence(&parts).map_err(js_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+    let window = web_sys::window().ok_or(anyhow::anyhow!("No window to download from"))?;
+    let link = window
+        .document()
+        .ok_or(anyhow::anyhow!("No document to download from"))?
+        .create_element("a")
+        .map_err(js_error)?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|_| anyhow::anyhow!("Could not create a download link"))?;
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    // the download starts after the click was handled, it fails if the url is gone by then
+    let revoke = wasm_bindgen::closure::Closure::once_into_js(move || {
+        _ = web_sys::Url::revoke_object_url(&url);
+    });
+    window
+        .set_timeout_with_callback_and_timeout_and_arguments_0(revoke.unchecked_ref(), 10_000)
+        .map_err(js_error)?;
+    Ok(true)
+}
 
-    egui::ScrollArea::horizontal()
-        // .auto_shrink([false,false])
-        .hscroll(true)
-        .min_scrolled_width(ui.available_width())
-        .show(ui, |ui| {
-            ui.allocate_ui(Vec2::new(150., ui.available_size_before_wrap().y), |ui| {
-                ui.with_layout(
-                    egui::Layout::top_down(egui::Align::RIGHT).with_main_wrap(true),
-                    |ui| {
-                        // Add a lot of widgets here.
-                        for (id, note) in &v {
-                            if state.active_tags.is_empty()
-                                || note.tags.iter().any(|t| state.active_tags.contains(t))
-                            {
-                                if note.complete {
-                                    continue;
-                                }
+/// List the files linked from a note. New links are picked with a file dialog (native only).
+fn file_links_ui(ui: &mut Ui, note: &mut Note, toasts: &mut Toasts) {
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "{} {} linked file{}",
+            egui_phosphor::regular::FOLDER_OPEN,
+            note.file_links.len(),
+            if note.file_links.len() == 1 { "" } else { "s" }
+        ));
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui.button("Attach file path…").clicked() {
+            for path in rfd::FileDialog::new().pick_files().unwrap_or_default() {
+                if !note.file_links.iter().any(|l| l.path == path) {
+                    note.file_links.push(FileLink::new(path));
+                }
+            }
+        }
+    });
 
-                                if !state.filter.is_empty()
-                                    && !note
-                                        .text
-                                        .to_lowercase()
-                                        .contains(&state.filter.to_lowercase())
-                                {
-                                    continue;
-                                }
-                                draw_note(ui, id, &state.userdata.notes, &mut state.active_note);
-                                // Safety: if note has an unknown tag, add it.
-                                for tag in &note.tags {
-                                    if !state.userdata.tags.contains(tag) {
-                                        state.userdata.tags.push(tag.clone());
-                                    }
-                                }
-                            }
-                        }
-                    },
-                )
-            });
+    let mut remove = None;
+    for (i, link) in note.file_links.iter().enumerate() {
+        ui.horizontal(|ui| {
+            if ui
+                .link(file_link_label(link))
+                .on_hover_text(file_link_hover(link))
+                .clicked()
+            {
+                if let Err(e) = open_file_link(link) {
+                    toasts.warning(format!("{e:#}"));
+                }
+            }
+            if ui
+                .small_button(egui_phosphor::regular::X)
+                .on_hover_text("Remove link")
+                .clicked()
+            {
+                remove = Some(i);
+            }
         });
+    }
+    if let Some(i) = remove {
+        note.file_links.remove(i);
+    }
 }
 
-fn listview(ui: &mut Ui, state: &mut MeteoraApp) {
-    let mut v = Vec::from_iter(state.userdata.notes.clone());
-    v.sort_by(|(_, a), (_, b)| b.get_final_prio().total_cmp(&a.get_final_prio()));
-
-    egui::ScrollArea::vertical()
-        // .auto_shrink([false,false])
-        // .min_scrolled_width(ui.available_width())
-        .show(ui, |ui| {
-            for (id, note) in &v {
-                if state.active_tags.is_empty()
-                    || note.tags.iter().any(|t| state.active_tags.contains(t))
-                {
-                    if !state.filter.is_empty()
-                        && !note
-                            .text
-                            .to_lowercase()
-                            .contains(&state.filter.to_lowercase())
-                    {
-                        continue;
-                    }
+/// The text of a logbook entry, offering lines written before that start like the one being
+/// typed. Accepting one proposes the tags it usually came with. Returns whether the entry is done
+/// being edited.
+fn logbook_item_ui(ui: &mut Ui, item: &mut Note, index: &LogbookIndex) -> bool {
+    let text_id = Id::new(("logbook_item", item.id));
+    let popup_id = text_id.with("completions");
+    let selected_id = text_id.with("selected");
+    let mut open = ui.memory(|m| m.is_popup_open(popup_id));
+    let mut cursor = egui::TextEdit::load_state(ui.ctx(), text_id)
+        .and_then(|state| state.cursor.char_range())
+        .map_or(item.text.chars().count(), |range| range.primary.index);
+    let completions = if open {
+        index.complete(current_line(&item.text, cursor))
+    } else {
+        vec![]
+    };
+    let mut selected = ui
+        .data(|d| d.get_temp::<usize>(selected_id))
+        .unwrap_or_default()
+        .min(completions.len().saturating_sub(1));
+
+    // the keys go to the completions before the text sees them
+    let mut accept = None;
+    if !completions.is_empty() && ui.memory(|m| m.has_focus(text_id)) {
+        ui.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown) {
+                selected = (selected + 1).min(completions.len() - 1);
+            }
+            if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp) {
+                selected = selected.saturating_sub(1);
+            }
+            if i.consume_key(egui::Modifiers::NONE, egui::Key::Tab)
+                || i.consume_key(egui::Modifiers::NONE, egui::Key::Enter)
+            {
+                accept = Some(selected);
+            }
+            if i.consume_key(egui::Modifiers::NONE, egui::Key::Escape) {
+                open = false;
+            }
+        });
+    }
 
-                    draw_list_note(ui, id, &state.userdata.notes, &mut state.active_note);
+    let output = egui::TextEdit::multiline(&mut item.text)
+        .id(text_id)
+        .show(ui);
+    if let Some(range) = output.cursor_range {
+        cursor = range.primary.ccursor.index;
+    }
+    if output.response.changed() {
+        open = !index.complete(current_line(&item.text, cursor)).is_empty();
+        selected = 0;
+    }
 
-                    // Safety: if note has an unknown tag, add it.
-                    for tag in &note.tags {
-                        if !state.userdata.tags.contains(tag) {
-                            state.userdata.tags.push(tag.clone());
-                        }
+    if open && !completions.is_empty() {
+        egui::popup::popup_below_widget(
+            ui,
+            popup_id,
+            &output.response,
+            egui::popup::PopupCloseBehavior::CloseOnClickOutside,
+            |ui| {
+                ui.set_min_width(200.);
+                for (i, line) in completions.iter().enumerate() {
+                    let label = ui
+                        .selectable_label(i == selected, &line.text)
+                        .on_hover_text(format!("Written {} times", line.count));
+                    if label.clicked() {
+                        accept = Some(i);
                     }
                 }
-            }
-        });
+                ui.weak("Tab or Enter to complete");
+            },
+        );
+    }
+
+    if let Some(line) = accept.and_then(|i| completions.get(i)) {
+        let end = complete_line(&mut item.text, cursor, &line.text);
+        let proposed: Vec<(String, bool)> = line
+            .usual_tags()
+            .into_iter()
+            .filter(|tag| !item.tags.contains(*tag))
+            .map(|tag| (tag.to_string(), true))
+            .collect();
+        // continue typing after the completed line
+        let mut state = output.state;
+        let end = egui::text::CCursor::new(end);
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::one(end)));
+        state.store(ui.ctx(), text_id);
+        ui.memory_mut(|m| m.request_focus(text_id));
+        open = false;
+        if !proposed.is_empty() {
+            ui.data_mut(|d| d.insert_temp(proposed_tags_id(item.id), proposed));
+            ui.memory_mut(|m| m.open_popup(tag_popup_id(item.id)));
+        }
+    }
+
+    if open != ui.memory(|m| m.is_popup_open(popup_id)) {
+        ui.memory_mut(|m| m.toggle_popup(popup_id));
+        ui.ctx().request_repaint();
+    }
+    ui.data_mut(|d| d.insert_temp(selected_id, selected));
+    output.response.lost_focus()
 }
 
-pub fn bare_button(text: impl Into<String>, ui: &mut Ui) -> Response {
-    ui.add(egui::Button::new(RichText::new(text).size(30.)).frame(false))
+/// The id of the tag popup of note `id`
+fn tag_popup_id(id: u128) -> Id {
+    Id::new(("tag_popup", id))
 }
 
-pub fn bare_button_sized(text: impl Into<String>, size: f32, ui: &mut Ui) -> Response {
-    ui.add(egui::Button::new(RichText::new(text).size(size)).frame(false))
+/// The id of the tags proposed for note `id`, each picked or not, until they are applied in the
+/// tag popup
+fn proposed_tags_id(id: u128) -> Id {
+    Id::new(("proposed_tags", id))
 }
 
 fn tag_ui(ui: &mut Ui, note: &mut Note, global_tags: &mut Vec<String>) {
     let response = ui.button("Tags");
-    let popup_id = ui.make_persistent_id(note.id);
+    let popup_id = tag_popup_id(note.id);
     if response.clicked() {
         ui.memory_mut(|mem| mem.toggle_popup(popup_id));
     }
+    let proposed_id = proposed_tags_id(note.id);
+    if !ui.memory(|m| m.is_popup_open(popup_id)) {
+        ui.data_mut(|d| d.remove::<Vec<(String, bool)>>(proposed_id));
+    }
     let below = egui::AboveOrBelow::Below;
     let close_on_click_outside = egui::popup::PopupCloseBehavior::CloseOnClickOutside;
     egui::popup::popup_above_or_below_widget(
@@ -1203,13 +8114,55 @@ fn tag_ui(ui: &mut Ui, note: &mut Note, global_tags: &mut Vec<String>) {
         |ui| {
             ui.set_min_width(600.0); // if you want to control the size
 
+            let mut proposed: Vec<(String, bool)> =
+                ui.data(|d| d.get_temp(proposed_id)).unwrap_or_default();
+            if !proposed.is_empty() {
+                let mut apply = false;
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Usually with:");
+                    for (tag, picked) in &mut proposed {
+                        ui.style_mut().visuals.selection.bg_fill =
+                            color_from_tag(tag).gamma_multiply(gamma_mult());
+                        if ui.selectable_label(*picked, tag.as_str()).clicked() {
+                            *picked = !*picked;
+                        }
+                    }
+                    apply = ui.button("Apply").clicked();
+                });
+                if apply {
+                    for (tag, _) in proposed.iter().filter(|(_, picked)| *picked) {
+                        note.tags.insert(tag.clone());
+                        if !global_tags.contains(tag) {
+                            global_tags.push(tag.clone());
+                        }
+                    }
+                    ui.data_mut(|d| d.remove::<Vec<(String, bool)>>(proposed_id));
+                } else {
+                    ui.data_mut(|d| d.insert_temp(proposed_id, proposed));
+                }
+            }
+
+            let suggestions = suggest_tags(note, global_tags);
+            if !suggestions.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Suggested:");
+                    for tag in suggestions {
+                        ui.style_mut().visuals.widgets.inactive.weak_bg_fill =
+                            color_from_tag(&tag).gamma_multiply(gamma_mult());
+                        if ui.small_button(format!("+ {tag}")).clicked() {
+                            note.tags.insert(tag);
+                        }
+                    }
+                });
+            }
+
             ui.group(|ui| {
                 ui.allocate_space(vec2(ui.available_width(), 0.));
                 ui.horizontal_wrapped(|ui| {
                     for tag in global_tags.iter() {
                         let contains = note.tags.contains(tag);
                         ui.style_mut().visuals.selection.bg_fill =
-                            color_from_tag(tag).gamma_multiply(GAMMA_MULT);
+                            color_from_tag(tag).gamma_multiply(gamma_mult());
                         if ui.selectable_label(contains, tag.to_string()).clicked() {
                             if contains {
                                 note.tags.remove(tag);
@@ -0,0 +1,100 @@
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, OnceLock},
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Number of log lines kept for the diagnostics panel
+pub const LOG_CAPACITY: usize = 200;
+
+/// A logged message as shown in the diagnostics panel
+#[derive(Clone)]
+pub struct LogLine {
+    /// Local time of day it was logged at
+    pub time: String,
+    pub level: Level,
+    /// The module it was logged from
+    pub target: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for LogLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:5} {}: {}",
+            self.time, self.level, self.target, self.message
+        )
+    }
+}
+
+static RECENT: Mutex<VecDeque<LogLine>> = Mutex::new(VecDeque::new());
+static FORWARD: OnceLock<Box<dyn Log>> = OnceLock::new();
+static LOGGER: RecentLog = RecentLog;
+
+/// Keeps the recent lines for the diagnostics panel and passes all messages on to the logger of
+/// the platform, which writes them to stderr or the browser console.
+struct RecentLog;
+
+impl RecentLog {
+    /// Whether a message is kept for the diagnostics panel. Trace is left out, as it is used for
+    /// what happens every frame. Dependencies only get their more important messages in.
+    fn keeps(metadata: &Metadata<'_>) -> bool {
+        if metadata.target().starts_with(env!("CARGO_CRATE_NAME")) {
+            metadata.level() <= Level::Debug
+        } else {
+            metadata.level() <= Level::Info
+        }
+    }
+}
+
+impl Log for RecentLog {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        Self::keeps(metadata) || FORWARD.get().is_some_and(|f| f.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if Self::keeps(record.metadata()) {
+            if let Ok(mut recent) = RECENT.lock() {
+                if recent.len() == LOG_CAPACITY {
+                    recent.pop_front();
+                }
+                recent.push_back(LogLine {
+                    time: chrono::Local::now().format("%H:%M:%S").to_string(),
+                    level: record.level(),
+                    target: record.target().to_string(),
+                    message: record.args().to_string(),
+                });
+            }
+        }
+        if let Some(forward) = FORWARD.get() {
+            if forward.enabled(record.metadata()) {
+                forward.log(record);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(forward) = FORWARD.get() {
+            forward.flush();
+        }
+    }
+}
+
+/// Install the logger. Messages are passed on to `forward`, which lets through up to `filter`,
+/// and the recent ones are kept for `recent_log`.
+pub fn init_logging(forward: Box<dyn Log>, filter: LevelFilter) -> Result<(), log::SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+    _ = FORWARD.set(forward);
+    log::set_max_level(filter.max(LevelFilter::Debug));
+    Ok(())
+}
+
+/// The last `LOG_CAPACITY` messages kept for the diagnostics panel, oldest first
+pub fn recent_log() -> Vec<LogLine> {
+    RECENT
+        .lock()
+        .map(|recent| recent.iter().cloned().collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,266 @@
+use chrono::{Days, Months, NaiveDate};
+use std::collections::BTreeMap;
+
+use crate::{dependency_neighborhood, Attachments, Note, Notes, UserData};
+
+pub type Logbook = BTreeMap<NaiveDate, Vec<Note>>;
+
+/// In which order days are written to the journal
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum JournalOrder {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+}
+
+/// How far back the journal reaches
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum JournalRange {
+    LastWeek,
+    LastMonth,
+    #[default]
+    All,
+}
+
+impl JournalRange {
+    /// The days of `logbook` within the range, counting back from `today`
+    pub fn filter(&self, logbook: &Logbook, today: NaiveDate) -> Logbook {
+        let start = match self {
+            JournalRange::LastWeek => today.checked_sub_days(Days::new(7)),
+            JournalRange::LastMonth => today.checked_sub_months(Months::new(1)),
+            JournalRange::All => None,
+        }
+        .unwrap_or(NaiveDate::MIN);
+        logbook
+            .range(start..)
+            .map(|(d, items)| (*d, items.clone()))
+            .collect()
+    }
+}
+
+/// Render the logbook as a Markdown journal: a heading per day and a bullet per item,
+/// with its tags as hashtags. Empty items and days are left out.
+pub fn logbook_to_markdown(logbook: &Logbook, order: JournalOrder) -> String {
+    let days: Box<dyn Iterator<Item = (&NaiveDate, &Vec<Note>)>> = match order {
+        JournalOrder::NewestFirst => Box::new(logbook.iter().rev()),
+        JournalOrder::OldestFirst => Box::new(logbook.iter()),
+    };
+
+    let mut journal = vec![];
+    for (date, items) in days {
+        let bullets = items
+            .iter()
+            .filter(|item| !item.text.trim().is_empty())
+            .map(journal_item)
+            .collect::<Vec<_>>();
+        if bullets.is_empty() {
+            continue;
+        }
+        journal.push(format!("## {date}\n\n{}\n", bullets.join("\n")));
+    }
+    journal.join("\n")
+}
+
+/// A bullet for a logbook item. Continuation lines are indented to stay in the bullet.
+fn journal_item(item: &Note) -> String {
+    let mut bullet = format!("- {}", item.text.trim().replace('\n', "\n  "));
+    for tag in &item.tags {
+        bullet.push_str(&format!(
+            " #{}",
+            tag.split_whitespace().collect::<Vec<_>>().join("_")
+        ));
+    }
+    bullet
+}
+
+/// A Markdown status update for the days from `start` to `end` (inclusive): logbook items grouped
+/// by tag, followed by the notes completed in that time.
+pub fn weekly_summary(userdata: &UserData, start: NaiveDate, end: NaiveDate) -> String {
+    let mut by_tag: BTreeMap<Option<&str>, Vec<String>> = BTreeMap::new();
+    for (date, items) in userdata.logbook.range(start..=end) {
+        for item in items.iter().filter(|item| !item.text.trim().is_empty()) {
+            let bullet = format!("- {} ({date})", item.text.trim().replace('\n', "\n  "));
+            if item.tags.is_empty() {
+                by_tag.entry(None).or_default().push(bullet.clone());
+            }
+            for tag in &item.tags {
+                by_tag.entry(Some(tag)).or_default().push(bullet.clone());
+            }
+        }
+    }
+
+    let completed = userdata
+        .notes
+        .values()
+        .filter(|n| n.completed_on.is_some_and(|d| d >= start && d <= end))
+        .map(|n| format!("- {}", n.get_title()))
+        .collect::<Vec<_>>();
+
+    let mut summary = format!("# Summary {start} to {end}\n");
+    if by_tag.is_empty() && completed.is_empty() {
+        summary.push_str("\nNo activity.\n");
+        return summary;
+    }
+    if !by_tag.is_empty() {
+        summary.push_str("\n## Logbook\n");
+        // tagged groups first, untagged items last
+        let (untagged, tagged): (Vec<_>, Vec<_>) =
+            by_tag.into_iter().partition(|(tag, _)| tag.is_none());
+        for (tag, bullets) in tagged.into_iter().chain(untagged) {
+            summary.push_str(&format!(
+                "\n### {}\n\n{}\n",
+                tag.unwrap_or("Untagged"),
+                bullets.join("\n")
+            ));
+        }
+    }
+    if !completed.is_empty() {
+        summary.push_str(&format!("\n## Completed\n\n{}\n", completed.join("\n")));
+    }
+    summary
+}
+
+/// What importing a `NoteBundle` added
+#[derive(PartialEq, Eq, Debug)]
+pub struct BundleImport {
+    /// The new id of the exported note
+    pub root: Option<u128>,
+    /// The new ids of the added notes
+    pub notes: Vec<u128>,
+    /// The number of dependencies between the added notes
+    pub dependencies: usize,
+}
+
+/// A note and everything it depends on, directly or not, to share a slice of a project
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Clone)]
+pub struct NoteBundle {
+    /// The exported note
+    pub root: u128,
+    /// The exported note first, then its dependencies in breadth first order
+    pub notes: Vec<Note>,
+    /// `(note, dependency)` pairs, so the structure survives an import
+    pub dependencies: Vec<(u128, u128)>,
+    /// The contents of the attachments of the notes
+    #[serde(default)]
+    pub attachments: Attachments,
+}
+
+impl NoteBundle {
+    /// The note `id` with its dependencies, or `None` if there is no such note
+    pub fn new(id: u128, notes: &Notes) -> Option<Self> {
+        let ids = dependency_neighborhood(id, usize::MAX, notes);
+        if ids.is_empty() {
+            return None;
+        }
+        let notes: Vec<Note> = ids
+            .iter()
+            .map(|id| Note {
+                // only the current text is shared
+                history: vec![],
+                ..notes[id].clone()
+            })
+            .collect();
+        let dependencies = notes
+            .iter()
+            .flat_map(|n| {
+                n.depends
+                    .iter()
+                    .filter(|d| ids.contains(d))
+                    .map(|d| (n.id, *d))
+            })
+            .collect();
+        Some(Self {
+            root: id,
+            notes,
+            dependencies,
+            attachments: Default::default(),
+        })
+    }
+
+    /// Include the contents of the attachments of the exported notes.
+    pub fn with_attachments(mut self, attachments: &Attachments) -> Self {
+        for attachment in self.notes.iter().flat_map(|n| &n.attachments) {
+            if let Some(data) = attachments.get(attachment) {
+                self.attachments.0.insert(attachment.id, data.clone());
+            }
+        }
+        self
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let mut bundle: Self = serde_json::from_str(json)
+            .map_err(|e| anyhow::anyhow!("This is not an exported bundle: {e}"))?;
+        // bundles don't carry a format version, but may come from before notes had a status
+        for note in &mut bundle.notes {
+            note.migrate_complete();
+        }
+        // or before attachments were kept apart from the notes
+        if bundle
+            .notes
+            .iter()
+            .flat_map(|n| &n.attachments)
+            .any(|a| a.id == 0)
+        {
+            bundle.attachments.adopt_legacy(&mut bundle.notes);
+        }
+        Ok(bundle)
+    }
+
+    /// A section per note, with the dependencies as `[[Title]]` links
+    pub fn to_markdown(&self) -> String {
+        let title = |id: u128| {
+            self.notes
+                .iter()
+                .find(|n| n.id == id)
+                .map(|n| n.get_title().trim())
+                .unwrap_or_default()
+        };
+        let mut sections = vec![];
+        for note in &self.notes {
+            let mut section = format!("## {}\n", note.get_title().trim());
+            let body = note.text.lines().skip(1).collect::<Vec<_>>().join("\n");
+            if !body.trim().is_empty() {
+                section.push_str(&format!("\n{}\n", body.trim()));
+            }
+            let links = self
+                .dependencies
+                .iter()
+                .filter(|(n, _)| *n == note.id)
+                .map(|(_, d)| format!("[[{}]]", title(*d)))
+                .collect::<Vec<_>>();
+            if !links.is_empty() {
+                section.push_str(&format!("\nDepends on {}\n", links.join(", ")));
+            }
+            if !note.tags.is_empty() {
+                let tags = note
+                    .tags
+                    .iter()
+                    .map(|t| format!("#{}", t.split_whitespace().collect::<Vec<_>>().join("_")))
+                    .collect::<Vec<_>>();
+                section.push_str(&format!("\nTags {}\n", tags.join(" ")));
+            }
+            sections.push(section);
+        }
+        format!("# {}\n\n{}", title(self.root), sections.join("\n"))
+    }
+
+    /// A file name for the bundle, after the title of the exported note
+    pub fn file_name(&self, extension: &str) -> String {
+        let title = self
+            .notes
+            .first()
+            .map(|n| n.get_title())
+            .unwrap_or_default();
+        let stem: String = title
+            .trim()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        let stem = if stem.is_empty() { "note" } else { &stem };
+        format!("{stem}.{extension}")
+    }
+}
@@ -0,0 +1,294 @@
+use std::ops::Range;
+
+/// Markdown the editor toolbar puts around or in front of the selected text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Bold,
+    Italic,
+    Heading,
+    BulletList,
+    Checkbox,
+    Code,
+    Link,
+}
+
+impl Format {
+    pub const ALL: [Format; 7] = [
+        Format::Bold,
+        Format::Italic,
+        Format::Heading,
+        Format::BulletList,
+        Format::Checkbox,
+        Format::Code,
+        Format::Link,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Bold => "Bold",
+            Format::Italic => "Italic",
+            Format::Heading => "Heading",
+            Format::BulletList => "Bullet list",
+            Format::Checkbox => "Checklist",
+            Format::Code => "Code",
+            Format::Link => "Link",
+        }
+    }
+}
+
+/// Apply `format` to the characters in `selection`, or take it off if they have it already.
+/// Returns the selection afterwards. Positions count characters, like the cursors of egui.
+pub fn apply_format(text: &mut String, selection: Range<usize>, format: Format) -> Range<usize> {
+    let len = text.chars().count();
+    let selection = selection.start.min(len)..selection.end.min(len);
+    match format {
+        Format::Bold => wrap(text, selection, "**"),
+        Format::Italic => wrap(text, selection, "*"),
+        Format::Code if slice(text, selection.clone()).contains('\n') => fence(text, selection),
+        Format::Code => wrap(text, selection, "`"),
+        Format::Link => link(text, selection),
+        Format::Heading => prefix_lines(text, selection, "# "),
+        Format::BulletList => prefix_lines(text, selection, "- "),
+        Format::Checkbox => prefix_lines(text, selection, "- [ ] "),
+    }
+}
+
+/// After Enter was pressed at `cursor`, start the new line with the list marker of the line
+/// before. Enter on an empty item ends the list instead. Returns where the cursor goes if the
+/// text was changed.
+pub fn continue_list(text: &mut String, cursor: usize) -> Option<usize> {
+    let chars: Vec<char> = text.chars().collect();
+    if cursor == 0 || chars.get(cursor - 1) != Some(&'\n') {
+        return None;
+    }
+    let line_start = chars[..cursor - 1]
+        .iter()
+        .rposition(|c| *c == '\n')
+        .map_or(0, |i| i + 1);
+    let line: String = chars[line_start..cursor - 1].iter().collect();
+    let (indent, marker) = list_marker(&line)?;
+    let item = &line[indent.len() + marker.len()..];
+    let rest_empty = chars[cursor..].iter().take_while(|c| **c != '\n').count() == 0;
+    if item.trim().is_empty() && rest_empty {
+        // the empty item goes, together with the new line
+        splice(text, line_start..cursor, "");
+        return Some(line_start);
+    }
+    let next = format!("{indent}{}", next_marker(marker));
+    splice(text, cursor..cursor, &next);
+    Some(cursor + next.chars().count())
+}
+
+/// The characters in `range` of `text`
+fn slice(text: &str, range: Range<usize>) -> String {
+    text.chars()
+        .skip(range.start)
+        .take(range.end - range.start)
+        .collect()
+}
+
+/// Replace the characters in `range` of `text` by `with`
+fn splice(text: &mut String, range: Range<usize>, with: &str) {
+    let byte = |i: usize| text.char_indices().nth(i).map_or(text.len(), |(b, _)| b);
+    let bytes = byte(range.start)..byte(range.end);
+    text.replace_range(bytes, with);
+}
+
+/// Number of `marker` characters right before `end` and right after `start`
+fn runs(chars: &[char], start: usize, end: usize, marker: char) -> (usize, usize) {
+    let before = chars[..start]
+        .iter()
+        .rev()
+        .take_while(|c| **c == marker)
+        .count();
+    let after = chars[end..].iter().take_while(|c| **c == marker).count();
+    (before, after)
+}
+
+/// Whether runs of a marker character belong to `marker`. A single `*` only counts in odd
+/// runs, which aren't bold alone.
+fn marked(runs: (usize, usize), marker: &str) -> bool {
+    let (before, after) = runs;
+    match marker.len() {
+        1 => before % 2 == 1 && after % 2 == 1,
+        n => before >= n && after >= n,
+    }
+}
+
+fn wrap(text: &mut String, selection: Range<usize>, marker: &str) -> Range<usize> {
+    let chars: Vec<char> = text.chars().collect();
+    let m = marker.chars().count();
+    let c = marker.chars().next().unwrap_or_default();
+    let Range { start, end } = selection;
+
+    // the markers are selected with the text
+    let inner = end - start >= 2 * m && {
+        let inside = &chars[start..end];
+        let leading = inside.iter().take_while(|ch| **ch == c).count();
+        let trailing = inside.iter().rev().take_while(|ch| **ch == c).count();
+        leading < inside.len() && marked((leading, trailing), marker)
+    };
+    if inner {
+        splice(text, end - m..end, "");
+        splice(text, start..start + m, "");
+        return start..end - 2 * m;
+    }
+    if marked(runs(&chars, start, end, c), marker) {
+        splice(text, end..end + m, "");
+        splice(text, start - m..start, "");
+        return start - m..end - m;
+    }
+    splice(text, end..end, marker);
+    splice(text, start..start, marker);
+    start + m..end + m
+}
+
+fn fence(text: &mut String, selection: Range<usize>) -> Range<usize> {
+    let Range { start, end } = selection;
+    let chars: Vec<char> = text.chars().collect();
+    let opening = "```\n";
+    let closing = "\n```";
+    let fenced = start >= 4
+        && chars[start - 4..start].iter().collect::<String>() == opening
+        && slice(text, end..end + 4) == closing;
+    if fenced {
+        splice(text, end..end + 4, "");
+        splice(text, start - 4..start, "");
+        return start - 4..end - 4;
+    }
+    splice(text, end..end, closing);
+    splice(text, start..start, opening);
+    start + 4..end + 4
+}
+
+fn link(text: &mut String, selection: Range<usize>) -> Range<usize> {
+    let Range { start, end } = selection;
+    let selected = slice(text, selection.clone());
+    if selected.starts_with("http://") || selected.starts_with("https://") {
+        // the address is there, the title is still to be written
+        splice(text, selection, &format!("[]({selected})"));
+        return start + 1..start + 1;
+    }
+    splice(text, selection, &format!("[{selected}]()"));
+    if selected.is_empty() {
+        start + 1..start + 1
+    } else {
+        // the title is there, the address is still to be written
+        let address = end + 3;
+        address..address
+    }
+}
+
+/// The indentation and list marker a line starts with, like `- [ ] ` or `2. `
+fn list_marker(line: &str) -> Option<(&str, &str)> {
+    let rest = line.trim_start_matches([' ', '\t']);
+    let indent = &line[..line.len() - rest.len()];
+    for marker in ["- [ ] ", "- [x] ", "- [X] ", "- ", "* ", "+ "] {
+        if rest.starts_with(marker) {
+            return Some((indent, marker));
+        }
+    }
+    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && rest[digits..].starts_with(". ") {
+        return Some((indent, &rest[..digits + 2]));
+    }
+    None
+}
+
+/// The marker of the item after one with `marker`
+fn next_marker(marker: &str) -> String {
+    if marker.starts_with("- [") {
+        return "- [ ] ".to_string();
+    }
+    match marker
+        .strip_suffix(". ")
+        .and_then(|n| n.parse::<usize>().ok())
+    {
+        Some(n) => format!("{}. ", n + 1),
+        None => marker.to_string(),
+    }
+}
+
+/// The heading marker a line starts with, like `## `. A `#tag` is not a heading.
+pub fn heading_marker(line: &str) -> Option<&str> {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    ((1..=6).contains(&hashes) && line[hashes..].starts_with(' ')).then(|| &line[..=hashes])
+}
+
+/// Put `marker` in front of the lines in `selection`, in place of another list marker, or take
+/// it off if they all have it
+fn prefix_lines(text: &mut String, selection: Range<usize>, marker: &str) -> Range<usize> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut lines = vec![];
+    let mut line_start = chars[..selection.start]
+        .iter()
+        .rposition(|c| *c == '\n')
+        .map_or(0, |i| i + 1);
+    loop {
+        let line_end = chars[line_start..]
+            .iter()
+            .position(|c| *c == '\n')
+            .map_or(chars.len(), |i| line_start + i);
+        lines.push(line_start..line_end);
+        // a selection ending at the start of a line leaves that line out
+        if line_end + 1 >= selection.end || line_end == chars.len() {
+            break;
+        }
+        line_start = line_end + 1;
+    }
+    // blank lines in between aren't made into items
+    if lines.len() > 1 {
+        lines.retain(|line| chars[line.clone()].iter().any(|c| !c.is_whitespace()));
+    }
+
+    let heading = marker.starts_with('#');
+    // the indentation to keep and the marker to replace on each line
+    let existing: Vec<(usize, String)> = lines
+        .iter()
+        .map(|line| {
+            let text: String = chars[line.clone()].iter().collect();
+            let found = if heading {
+                heading_marker(&text).map(|m| ("", m))
+            } else {
+                list_marker(&text)
+            };
+            found.map_or((0, String::new()), |(indent, marker)| {
+                (indent.chars().count(), marker.to_string())
+            })
+        })
+        .collect();
+    let same_kind = |found: &str| match marker {
+        _ if heading => !found.is_empty(),
+        "- " => ["- ", "* ", "+ "].contains(&found),
+        _ => found.starts_with("- ["),
+    };
+    let remove = existing.iter().all(|(_, found)| same_kind(found));
+
+    // the changes as the position, the characters taken out and the ones put in
+    let mut edits: Vec<(usize, usize, String)> = lines
+        .iter()
+        .zip(&existing)
+        .map(|(line, (indent, old))| {
+            let new = if remove { "" } else { marker };
+            (line.start + indent, old.chars().count(), new.to_string())
+        })
+        .collect();
+    edits.retain(|(at, old, new)| slice(text, *at..at + old) != *new);
+    for (at, old, new) in edits.iter().rev() {
+        splice(text, *at..at + old, new);
+    }
+    let moved = |pos: usize| {
+        let mut shift = 0isize;
+        for (at, old, new) in &edits {
+            let new = new.chars().count();
+            if pos >= at + old {
+                shift += new as isize - *old as isize;
+            } else if pos >= *at {
+                // inside the marker, or right before it
+                return ((*at as isize + shift) as usize) + new;
+            }
+        }
+        (pos as isize + shift) as usize
+    };
+    moved(selection.start)..moved(selection.end)
+}
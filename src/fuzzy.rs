@@ -0,0 +1,38 @@
+/// Where and how well a query matched a text, see `fuzzy_match`
+#[derive(Debug, PartialEq)]
+pub struct FuzzyMatch {
+    /// Higher is better
+    pub score: i32,
+    /// Char indices of the matched characters in the text
+    pub positions: Vec<usize>,
+}
+
+/// A char to compare ignoring case
+fn folded(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Match `query` against `text` if all characters of `query` appear in `text` in the same
+/// order, ignoring case and spaces in the query. Runs of characters and matches at the start of
+/// words score higher, characters skipped between matches count against the score.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<FuzzyMatch> {
+    let text: Vec<char> = text.chars().collect();
+    let mut score = 0;
+    let mut start = 0;
+    let mut positions = vec![];
+    for c in query.chars().filter(|c| !c.is_whitespace()).map(folded) {
+        let found = start + text[start..].iter().position(|t| folded(*t) == c)?;
+        if found > 0 && found == start {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        if !positions.is_empty() {
+            score -= (found - start) as i32;
+        }
+        positions.push(found);
+        start = found + 1;
+    }
+    Some(FuzzyMatch { score, positions })
+}
@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use egui::{epaint::ahash::HashMap, Pos2};
+use egui_graphs::Graph;
+use petgraph::{
+    stable_graph::{NodeIndex, StableGraph},
+    Directed, Direction,
+};
+use rand::Rng;
+
+use crate::Notes;
+
+/// Side length of the square nodes without a known position are placed in
+const SPAWN_SIZE: f32 = 250.;
+
+/// A graph with the note ids as nodes, as shown by the graph view
+pub type NoteGraph = Graph<u128, (), Directed>;
+
+/// The notes and an edge from each note to every note it depends on. Kept up to date one note
+/// at a time as notes change, instead of building it again for every change.
+pub struct DependencyGraph {
+    graph: NoteGraph,
+    nodes: HashMap<u128, NodeIndex>,
+    /// The dependencies each note was added with, including ones on missing notes
+    depends: HashMap<u128, BTreeSet<u128>>,
+    /// Notes depending on a note missing from the graph, by the id of the missing note
+    waiting: HashMap<u128, BTreeSet<u128>>,
+}
+
+impl Default for DependencyGraph {
+    fn default() -> Self {
+        Self {
+            graph: Graph::new(StableGraph::default()),
+            nodes: Default::default(),
+            depends: Default::default(),
+            waiting: Default::default(),
+        }
+    }
+}
+
+impl DependencyGraph {
+    /// Build the graph of all `notes` from scratch. Nodes are placed at `positions`, or at a
+    /// random spot if they have none.
+    pub fn rebuild(&mut self, notes: &Notes, positions: &BTreeMap<u128, Pos2>) {
+        *self = Self::default();
+        for id in notes.keys() {
+            self.update(*id, notes, positions);
+        }
+    }
+
+    /// Bring note `id` up to date with `notes`: add, relabel or remove its node and add or
+    /// remove the edges to its dependencies.
+    pub fn update(&mut self, id: u128, notes: &Notes, positions: &BTreeMap<u128, Pos2>) {
+        let Some(note) = notes.get(&id) else {
+            self.remove(id);
+            return;
+        };
+        let idx = match self.nodes.get(&id) {
+            Some(idx) => *idx,
+            None => self.add_node(id, notes, positions),
+        };
+        if let Some(node) = self.graph.node_mut(idx) {
+            let title = note.get_title();
+            if node.label() != title {
+                node.set_label(title.to_string());
+            }
+        }
+
+        let before = self.depends.remove(&id).unwrap_or_default();
+        for dependency in before.difference(&note.depends) {
+            match self.nodes.get(dependency) {
+                Some(b) => {
+                    self.graph.remove_edges_between(idx, *b);
+                }
+                None => self.stop_waiting(*dependency, id),
+            }
+        }
+        for dependency in note.depends.difference(&before) {
+            // dependencies are added before the notes depending on them, so building the whole
+            // graph visits every note and dependency once
+            if !self.nodes.contains_key(dependency) && notes.contains_key(dependency) {
+                self.update(*dependency, notes, positions);
+            }
+            match self.nodes.get(dependency) {
+                Some(b) => {
+                    self.graph.add_edge(idx, *b, ());
+                }
+                None => {
+                    self.waiting.entry(*dependency).or_default().insert(id);
+                }
+            }
+        }
+        self.depends.insert(id, note.depends.clone());
+    }
+
+    fn add_node(&mut self, id: u128, notes: &Notes, positions: &BTreeMap<u128, Pos2>) -> NodeIndex {
+        let title = notes
+            .get(&id)
+            .map(|n| n.get_title().to_string())
+            .unwrap_or_default();
+        let location = positions.get(&id).copied().unwrap_or_else(|| {
+            let mut rng = rand::thread_rng();
+            Pos2::new(
+                rng.gen_range(0.0..SPAWN_SIZE),
+                rng.gen_range(0.0..SPAWN_SIZE),
+            )
+        });
+        let idx = self
+            .graph
+            .add_node_with_label_and_location(id, title, location);
+        self.nodes.insert(id, idx);
+        // a note that comes back, like a deletion being undone
+        for dependent in self.waiting.remove(&id).unwrap_or_default() {
+            let still_depends = notes
+                .get(&dependent)
+                .is_some_and(|n| n.depends.contains(&id));
+            if let (Some(a), true) = (self.nodes.get(&dependent), still_depends) {
+                self.graph.add_edge(*a, idx, ());
+            }
+        }
+        idx
+    }
+
+    fn remove(&mut self, id: u128) {
+        let Some(idx) = self.nodes.remove(&id) else {
+            return;
+        };
+        let dependents: Vec<u128> = self
+            .graph
+            .g
+            .neighbors_directed(idx, Direction::Incoming)
+            .filter_map(|a| self.graph.node(a).map(|n| *n.payload()))
+            .collect();
+        if !dependents.is_empty() {
+            self.waiting.entry(id).or_default().extend(dependents);
+        }
+        self.graph.remove_node(idx);
+        for dependency in self.depends.remove(&id).unwrap_or_default() {
+            if !self.nodes.contains_key(&dependency) {
+                self.stop_waiting(dependency, id);
+            }
+        }
+    }
+
+    fn stop_waiting(&mut self, dependency: u128, id: u128) {
+        if let Some(dependents) = self.waiting.get_mut(&dependency) {
+            dependents.remove(&id);
+            if dependents.is_empty() {
+                self.waiting.remove(&dependency);
+            }
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    /// The graph of all notes, to show and move its nodes around
+    pub fn graph_mut(&mut self) -> &mut NoteGraph {
+        &mut self.graph
+    }
+
+    /// A separate graph of only the notes `ids` and the dependencies between them
+    pub fn subgraph(&self, ids: &[u128]) -> NoteGraph {
+        let mut graph = Graph::new(StableGraph::default());
+        let mut nodes = HashMap::default();
+        for id in ids {
+            if let Some(node) = self.nodes.get(id).and_then(|idx| self.graph.node(*idx)) {
+                let idx =
+                    graph.add_node_with_label_and_location(*id, node.label(), node.location());
+                nodes.insert(*id, idx);
+            }
+        }
+        for (id, a) in &nodes {
+            for dependency in self.depends.get(id).into_iter().flatten() {
+                if let Some(b) = nodes.get(dependency) {
+                    graph.add_edge(*a, *b, ());
+                }
+            }
+        }
+        graph
+    }
+}
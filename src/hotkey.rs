@@ -0,0 +1,52 @@
+use std::sync::mpsc::{channel, Receiver};
+
+use anyhow::{anyhow, Result};
+use global_hotkey::{hotkey::HotKey, GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+
+/// The shortcut opening the quick capture window, unless configured otherwise
+pub const DEFAULT_CAPTURE_HOTKEY: &str = "ctrl+shift+space";
+
+/// A system wide hotkey that works while another application has focus.
+/// It stays registered until dropped.
+pub struct GlobalHotkey {
+    manager: GlobalHotKeyManager,
+    hotkey: HotKey,
+    pressed: Receiver<()>,
+}
+
+impl GlobalHotkey {
+    /// Register a shortcut like "ctrl+shift+space". A press wakes up the UI of `ctx`.
+    pub fn register(shortcut: &str, ctx: &egui::Context) -> Result<Self> {
+        let hotkey: HotKey = shortcut
+            .parse()
+            .map_err(|e| anyhow!("Invalid hotkey '{shortcut}': {e}"))?;
+        let manager = GlobalHotKeyManager::new()?;
+        manager.register(hotkey)?;
+
+        let (sender, pressed) = channel();
+        let ctx = ctx.clone();
+        let id = hotkey.id();
+        GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+            if event.id() == id && event.state() == HotKeyState::Pressed {
+                _ = sender.send(());
+                ctx.request_repaint();
+            }
+        }));
+        Ok(Self {
+            manager,
+            hotkey,
+            pressed,
+        })
+    }
+
+    /// Whether the hotkey was pressed since the last call
+    pub fn pressed(&self) -> bool {
+        self.pressed.try_iter().count() > 0
+    }
+}
+
+impl Drop for GlobalHotkey {
+    fn drop(&mut self) {
+        _ = self.manager.unregister(self.hotkey);
+    }
+}
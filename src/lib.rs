@@ -1,8 +1,43 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
-pub use app::MeteoraApp;
+pub use app::{Channels, MeteoraApp, Notes, UserData, Visibility};
+mod diagnostics;
+pub use diagnostics::*;
+mod export;
+pub use export::*;
+mod formatting;
+pub use formatting::*;
+mod fuzzy;
+pub use fuzzy::*;
+mod graph;
+pub use graph::*;
+#[cfg(not(target_arch = "wasm32"))]
+mod hotkey;
+#[cfg(not(target_arch = "wasm32"))]
+pub use hotkey::*;
+mod links;
+pub use links::*;
+mod logbook;
+pub use logbook::*;
+mod migrations;
+pub use migrations::*;
 mod notes;
 pub use notes::*;
+mod palette;
+pub use palette::*;
+mod sample;
+mod settings;
+pub use settings::*;
+#[cfg(feature = "spellcheck")]
+mod spellcheck;
+#[cfg(feature = "spellcheck")]
+pub use spellcheck::*;
 mod sync;
 pub use sync::*;
+mod undo;
+pub use undo::*;
+#[cfg(not(target_arch = "wasm32"))]
+mod watch;
+#[cfg(not(target_arch = "wasm32"))]
+pub use watch::*;
@@ -0,0 +1,63 @@
+const SCHEME_PREFIX: &str = "meteora://note/";
+const HASH_PREFIX: &str = "note=";
+
+/// A link that opens the note with `id`
+pub fn note_link(id: u128) -> String {
+    #[cfg(target_arch = "wasm32")]
+    if let Some(location) = web_sys::window().map(|w| w.location()) {
+        if let (Ok(origin), Ok(path)) = (location.origin(), location.pathname()) {
+            return format!("{origin}{path}#{HASH_PREFIX}{id}");
+        }
+    }
+    format!("{SCHEME_PREFIX}{id}")
+}
+
+/// The note id in a `meteora://note/<id>` link, a `#note=<id>` URL hash or a bare id
+pub fn parse_note_link(link: &str) -> Option<u128> {
+    let link = link.trim();
+    let id = link
+        .strip_prefix(SCHEME_PREFIX)
+        .or_else(|| {
+            link.strip_prefix('#')
+                .unwrap_or(link)
+                .strip_prefix(HASH_PREFIX)
+        })
+        .unwrap_or(link);
+    id.trim_end_matches('/').parse().ok()
+}
+
+/// The note requested at startup, via `--note <id>` or a `meteora://note/<id>` argument
+#[cfg(not(target_arch = "wasm32"))]
+pub fn startup_note_link() -> Option<u128> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--note" {
+            return args.next().and_then(|id| parse_note_link(&id));
+        }
+        if arg.starts_with(SCHEME_PREFIX) {
+            return parse_note_link(&arg);
+        }
+    }
+    None
+}
+
+/// The note requested at startup, via `#note=<id>` in the URL
+#[cfg(target_arch = "wasm32")]
+pub fn startup_note_link() -> Option<u128> {
+    let hash = web_sys::window()?.location().hash().ok()?;
+    parse_note_link(&hash)
+}
+
+/// Show the open note in the URL hash, so reloading or sharing the URL opens it again.
+/// Clears the hash for `None`. Does nothing on native.
+pub fn set_linked_note(id: Option<u128>) {
+    #[cfg(target_arch = "wasm32")]
+    if let Some(location) = web_sys::window().map(|w| w.location()) {
+        let hash = id
+            .map(|id| format!("{HASH_PREFIX}{id}"))
+            .unwrap_or_default();
+        _ = location.set_hash(&hash);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = id;
+}
@@ -0,0 +1,105 @@
+use egui::epaint::ahash::HashMap;
+use std::collections::BTreeMap;
+
+use crate::Logbook;
+
+/// Number of distinct lines the index keeps, the most frequent ones
+pub const MAX_INDEXED_LINES: usize = 1000;
+
+/// Number of completions offered at once
+pub const MAX_COMPLETIONS: usize = 5;
+
+/// A line written in the logbook before, with the tags of the entries it was written in
+#[derive(Debug, PartialEq, Clone)]
+pub struct IndexedLine {
+    pub text: String,
+    /// Number of entries the line was written in
+    pub count: usize,
+    /// Number of those entries each tag was applied to
+    pub tags: BTreeMap<String, usize>,
+}
+
+impl IndexedLine {
+    /// The tags applied at least every other time the line was written
+    pub fn usual_tags(&self) -> Vec<&str> {
+        self.tags
+            .iter()
+            .filter(|(_, n)| **n * 2 >= self.count)
+            .map(|(tag, _)| tag.as_str())
+            .collect()
+    }
+}
+
+/// The lines of the logbook by how often they were written, to complete new entries with
+#[derive(Debug, Default)]
+pub struct LogbookIndex {
+    /// The most frequent first
+    lines: Vec<IndexedLine>,
+}
+
+impl LogbookIndex {
+    pub fn new(logbook: &Logbook) -> Self {
+        let mut lines: HashMap<&str, IndexedLine> = HashMap::default();
+        for item in logbook.values().flatten() {
+            for line in item.text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                let indexed = lines.entry(line).or_insert_with(|| IndexedLine {
+                    text: line.to_string(),
+                    count: 0,
+                    tags: BTreeMap::new(),
+                });
+                indexed.count += 1;
+                for tag in &item.tags {
+                    *indexed.tags.entry(tag.clone()).or_default() += 1;
+                }
+            }
+        }
+        let mut lines: Vec<IndexedLine> = lines.into_values().collect();
+        lines.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.text.cmp(&b.text)));
+        lines.truncate(MAX_INDEXED_LINES);
+        Self { lines }
+    }
+
+    /// The most frequent lines starting with `prefix`, ignoring case. Nothing is offered for an
+    /// empty prefix or for a line that is already complete.
+    pub fn complete(&self, prefix: &str) -> Vec<&IndexedLine> {
+        let prefix = prefix.trim_start().to_lowercase();
+        if prefix.is_empty() {
+            return vec![];
+        }
+        self.lines
+            .iter()
+            .filter(|line| {
+                let text = line.text.to_lowercase();
+                text.starts_with(&prefix) && text != prefix
+            })
+            .take(MAX_COMPLETIONS)
+            .collect()
+    }
+}
+
+/// The line of `text` being typed, up to the cursor at char index `cursor`
+pub fn current_line(text: &str, cursor: usize) -> &str {
+    let end = byte_index(text, cursor);
+    &text[line_start(&text[..end])..end]
+}
+
+/// Replace the line of `text` the cursor at char index `cursor` is on by `line`. Returns the
+/// char index at the end of the new line.
+pub fn complete_line(text: &mut String, cursor: usize, line: &str) -> usize {
+    let at = byte_index(text, cursor);
+    let start = line_start(&text[..at]);
+    let end = text[at..].find('\n').map_or(text.len(), |i| at + i);
+    text.replace_range(start..end, line);
+    text[..start].chars().count() + line.chars().count()
+}
+
+fn line_start(text: &str) -> usize {
+    text.rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// The byte index of the char at `cursor`, the end of `text` if it is beyond that
+fn byte_index(text: &str, cursor: usize) -> usize {
+    text.char_indices()
+        .nth(cursor)
+        .map_or(text.len(), |(i, _)| i)
+}
@@ -4,10 +4,17 @@
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result<()> {
-    use egui::IconData;
-    env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+    // Log to stderr (if you run with `RUST_LOG=debug`) and keep recent messages for diagnostics
+    let logger = env_logger::Builder::from_default_env().build();
+    let filter = logger.filter();
+    meteora::init_logging(Box::new(logger), filter).ok();
 
     let native_options = eframe::NativeOptions {
+        // only used on first start, afterwards the window opens where it was closed
+        viewport: eframe::egui::ViewportBuilder::default()
+            .with_inner_size([1200., 800.])
+            .with_min_inner_size([400., 300.]),
+        persist_window: true,
         // always_on_top: true,
         // icon_data: Some(
         //     IconData::try_from_png_bytes(include_bytes!("../assets/icon-256.png")).unwrap(),
@@ -26,7 +33,11 @@ fn main() -> eframe::Result<()> {
 #[cfg(target_arch = "wasm32")]
 fn main() {
     // Redirect `log` message to `console.log` and friends:
-    eframe::WebLogger::init(log::LevelFilter::Debug).ok();
+    meteora::init_logging(
+        Box::new(eframe::WebLogger::new(log::LevelFilter::Debug)),
+        log::LevelFilter::Debug,
+    )
+    .ok();
 
     let web_options = eframe::WebOptions::default();
 
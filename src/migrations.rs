@@ -0,0 +1,71 @@
+use anyhow::{bail, Context, Result};
+
+use crate::UserData;
+
+/// Layout of the stored notes. Bump it and add a step to `MIGRATIONS` when stored data changes
+/// in a way field defaults can't cover.
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Upgrade steps, the one at index `n` turns version `n` into version `n + 1`
+const MIGRATIONS: [fn(&mut UserData); FORMAT_VERSION as usize] =
+    [scratchpad_items, note_status, attachment_contents];
+
+/// Version 0 is everything stored before the version was recorded. Scratchpad sections used to
+/// be plain strings.
+fn scratchpad_items(userdata: &mut UserData) {
+    userdata.scratchpad.migrate();
+}
+
+/// Notes used to be complete or not, which became a status.
+fn note_status(userdata: &mut UserData) {
+    for note in userdata.notes.values_mut() {
+        note.migrate_complete();
+    }
+}
+
+/// Attachments kept their contents in the note, which moved to `UserData::attachments`.
+fn attachment_contents(userdata: &mut UserData) {
+    let notes = userdata
+        .notes
+        .values_mut()
+        .chain(userdata.logbook.values_mut().flatten());
+    userdata.attachments.adopt_legacy(notes);
+}
+
+/// Only the version, to check it before trying to make sense of the rest
+#[derive(serde::Deserialize)]
+struct Versioned {
+    #[serde(default)]
+    format_version: u32,
+}
+
+/// Refuse data written by a newer version, which this one can't be trusted to read or write back.
+fn ensure_supported(format_version: u32) -> Result<()> {
+    if format_version > FORMAT_VERSION {
+        bail!(
+            "These notes were saved by a newer version of Meteora (data format {format_version}, \
+            this version reads up to {FORMAT_VERSION}). Please update Meteora to open them."
+        )
+    }
+    Ok(())
+}
+
+/// Bring `userdata` from its `format_version` up to `FORMAT_VERSION`.
+pub fn migrate(userdata: &mut UserData) -> Result<()> {
+    ensure_supported(userdata.format_version)?;
+    for step in &MIGRATIONS[userdata.format_version as usize..] {
+        step(userdata);
+    }
+    userdata.format_version = FORMAT_VERSION;
+    Ok(())
+}
+
+/// Read user data stored as json by any version up to this one.
+pub fn userdata_from_json(json: &str) -> Result<UserData> {
+    let Versioned { format_version } =
+        serde_json::from_str(json).context("The notes are not valid json")?;
+    ensure_supported(format_version)?;
+    let mut userdata: UserData = serde_json::from_str(json).context("Could not read the notes")?;
+    migrate(&mut userdata)?;
+    Ok(userdata)
+}
@@ -1,10 +1,23 @@
-use chrono::NaiveDate;
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta,
+    TimeZone, Utc,
+};
 use egui::Color32;
+use log::trace;
 use rand::prelude::*;
 use rand_chacha::ChaCha20Rng;
-use std::collections::BTreeSet;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    hash::BuildHasher,
+    ops::Range,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicU8, Ordering},
+        Arc,
+    },
+};
 
-use crate::app::GAMMA_MULT;
+use crate::app::{Notes, GAMMA_MULT};
 
 #[derive(serde::Deserialize, serde::Serialize, PartialEq, Clone, Default, Debug)]
 pub enum Deadline {
@@ -17,6 +30,69 @@ pub enum Deadline {
     Fixed(chrono::NaiveDate),
 }
 
+/// Where a note stands, apart from its priority
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    Default,
+    Debug,
+)]
+pub enum Status {
+    /// Being worked on or up next
+    #[default]
+    Active,
+    /// Can't go on until someone or something else is done, see `Note::waiting_on`
+    Waiting,
+    /// Not planned yet, kept for later
+    Someday,
+    /// Finished
+    Done,
+}
+
+impl Status {
+    pub const ALL: [Status; 4] = [
+        Status::Active,
+        Status::Waiting,
+        Status::Someday,
+        Status::Done,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Active => "Active",
+            Status::Waiting => "Waiting",
+            Status::Someday => "Someday",
+            Status::Done => "Done",
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Status::Active => egui_phosphor::regular::LIGHTNING,
+            Status::Waiting => egui_phosphor::regular::HOURGLASS_MEDIUM,
+            Status::Someday => egui_phosphor::regular::CLOUD,
+            Status::Done => egui_phosphor::regular::CHECK_CIRCLE,
+        }
+    }
+
+    /// Notes are sorted by rank before priority, so someday and finished notes don't compete
+    /// with the ones being worked on
+    pub fn rank(&self) -> u8 {
+        match self {
+            Status::Active | Status::Waiting => 0,
+            Status::Someday => 1,
+            Status::Done => 2,
+        }
+    }
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Default, PartialEq, Clone)]
 #[serde(default)]
 pub struct Note {
@@ -28,28 +104,356 @@ pub struct Note {
     pub progress: f32,
     pub priority: f32,
     pub deadline: Deadline,
-    pub complete: bool,
+    pub status: Status,
+    /// Whom or what a waiting note waits for
+    pub waiting_on: String,
+    /// Older versions only knew whether a note was complete. Migrated into `status` on load.
+    #[serde(rename = "complete", skip_serializing)]
+    pub legacy_complete: bool,
+    /// The day the note was last marked as complete
+    pub completed_on: Option<NaiveDate>,
     pub created: NaiveDate,
+    /// Pinned notes are shown before all others
+    pub pinned: bool,
+    pub attachments: Vec<Attachment>,
+    /// Files on disk referenced by the note
+    pub file_links: Vec<FileLink>,
+    /// Estimated hours of work to finish the note
+    pub effort_hours: f32,
+    /// The tag the note takes its color from, if it has several
+    pub primary_tag: Option<String>,
+    /// Earlier versions of the text and the day they were replaced, oldest first
+    pub history: Vec<(NaiveDate, String)>,
+    /// Start and end of the sessions tracked working on the note
+    pub work_log: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+    /// The note is hidden until this day
+    pub snoozed_until: Option<NaiveDate>,
+}
+
+/// Number of earlier versions of its text a note keeps
+pub const MAX_HISTORY: usize = 10;
+
+/// Working hours in a day, for efforts given in days
+pub const HOURS_PER_DAY: f32 = 8.;
+
+/// Parse an effort like "90m", "2h", "1.5d" or "1h 30m" into hours. A number without unit is
+/// taken as hours, an empty text as no effort.
+pub fn parse_effort(text: &str) -> anyhow::Result<f32> {
+    let invalid = || anyhow::anyhow!("Use minutes, hours or days, like 90m, 2h or 1.5d");
+    let text = text.trim().to_lowercase();
+    if text
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+        && !text.is_empty()
+    {
+        return text.replace(',', ".").parse().map_err(|_| invalid());
+    }
+    let mut rest = text.as_str();
+    let mut hours = 0.;
+    while !rest.is_empty() {
+        let number_end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+            .unwrap_or(rest.len());
+        let number: f32 = rest[..number_end]
+            .replace(',', ".")
+            .parse()
+            .map_err(|_| invalid())?;
+        rest = rest[number_end..].trim_start();
+        let unit_end = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len());
+        hours += match &rest[..unit_end] {
+            "m" | "min" | "mins" | "minute" | "minutes" => number / 60.,
+            "h" | "hr" | "hrs" | "hour" | "hours" => number,
+            "d" | "day" | "days" => number * HOURS_PER_DAY,
+            _ => return Err(invalid()),
+        };
+        rest = rest[unit_end..].trim_start();
+    }
+    Ok(hours)
+}
+
+/// A short text for an effort in hours, like "45m", "2h" or "1.5d". Empty for no effort.
+pub fn format_effort(hours: f32) -> String {
+    let short = |value: f32| format!("{value:.1}").trim_end_matches(".0").to_string();
+    if hours <= 0. {
+        String::new()
+    } else if hours < 1. {
+        format!("{}m", (hours * 60.).round())
+    } else if hours < HOURS_PER_DAY {
+        format!("{}h", short(hours))
+    } else {
+        format!("{}d", short(hours / HOURS_PER_DAY))
+    }
+}
+
+/// Words read per minute, for the reading time of a note
+const READING_SPEED: usize = 200;
+
+/// Counts describing the text of a note, see `Note::stats`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct NoteStats {
+    /// Words outside of link URLs. Each CJK character counts as a word, as they are written
+    /// without spaces.
+    pub words: usize,
+    /// Characters, without line breaks
+    pub characters: usize,
+    pub lines: usize,
+    pub checked: usize,
+    /// Checklist items, checked or not
+    pub checklist: usize,
+    pub links: usize,
+}
+
+impl NoteStats {
+    /// Minutes it takes to read the note, at least one unless it is empty
+    pub fn reading_minutes(&self) -> usize {
+        self.words.div_ceil(READING_SPEED)
+    }
+}
+
+/// Whether `c` is written without spaces between words: Chinese, Japanese kana
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF // Hiragana, Katakana
+        | 0x3400..=0x4DBF // CJK Extension A
+        | 0x4E00..=0x9FFF // CJK Unified Ideographs
+        | 0xF900..=0xFAFF // CJK Compatibility Ideographs
+        | 0x20000..=0x2FA1F // CJK Extensions B to F, Compatibility Supplement
+    )
+}
+
+/// Effort in hours at which a note gets the full `EFFORT_WEIGHT` added to its priority
+const EFFORT_HORIZON: f32 = 40.;
+
+/// The most priority a note gets for the work left on it, so big tasks don't get starved
+const EFFORT_WEIGHT: f32 = 0.2;
+
+/// The total size of the attachments of a note is limited to this many bytes
+pub const MAX_ATTACHMENT_BYTES: usize = 2 * 1024 * 1024;
+
+/// A file attached to a note. Its contents are kept in `Attachments` under `id`, so copies of
+/// the note don't copy them.
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Clone, Default, Debug)]
+#[serde(default)]
+pub struct Attachment {
+    pub id: u128,
+    pub name: String,
+    pub mime: String,
+    /// Format version 2 kept the contents in the note. Moved to `Attachments` on load.
+    #[serde(rename = "data", with = "base64_bytes", skip_serializing)]
+    pub legacy_data: Vec<u8>,
+}
+
+impl Attachment {
+    /// A new attachment named `name`, with the mime type guessed from its extension
+    pub fn from_file(name: &str) -> Self {
+        let extension = name.rsplit('.').next().unwrap_or_default().to_lowercase();
+        let mime = match extension.as_str() {
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "webp" => "image/webp",
+            "bmp" => "image/bmp",
+            "svg" => "image/svg+xml",
+            "pdf" => "application/pdf",
+            "txt" | "md" => "text/plain",
+            _ => "application/octet-stream",
+        };
+        Self {
+            id: new_id(),
+            name: name.to_string(),
+            mime: mime.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime.starts_with("image/")
+    }
+}
+
+/// The contents of an attachment. Clones share them.
+#[derive(PartialEq, Clone, Debug)]
+pub struct AttachmentData(pub Arc<[u8]>);
+
+impl serde::Serialize for AttachmentData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        base64_bytes::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for AttachmentData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        base64_bytes::deserialize(deserializer).map(|data| Self(data.into()))
+    }
+}
+
+/// The contents of the attachments of all notes by attachment id, stored base64 encoded as part
+/// of the notes, so they stay self-contained when encrypted and synced.
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Clone, Default, Debug)]
+#[serde(transparent)]
+pub struct Attachments(pub BTreeMap<u128, AttachmentData>);
+
+impl Attachments {
+    pub fn get(&self, attachment: &Attachment) -> Option<&AttachmentData> {
+        self.0.get(&attachment.id)
+    }
+
+    /// The size of the attachments of `note` in bytes
+    pub fn size_of(&self, note: &Note) -> usize {
+        note.attachments
+            .iter()
+            .filter_map(|a| self.get(a))
+            .map(|data| data.0.len())
+            .sum()
+    }
+
+    /// Attach `data` to `note` as `name`, unless it would exceed `MAX_ATTACHMENT_BYTES`
+    pub fn attach(&mut self, note: &mut Note, name: &str, data: Vec<u8>) -> anyhow::Result<()> {
+        if self.size_of(note) + data.len() > MAX_ATTACHMENT_BYTES {
+            anyhow::bail!(
+                "Can't attach '{name}': attachments of a note are limited to {} MB.",
+                MAX_ATTACHMENT_BYTES / (1024 * 1024)
+            );
+        }
+        let attachment = Attachment::from_file(name);
+        self.0.insert(attachment.id, AttachmentData(data.into()));
+        note.attachments.push(attachment);
+        Ok(())
+    }
+
+    /// Drop the contents no attachment of `notes` refers to. Returns how many were dropped.
+    pub fn retain_used<'a>(&mut self, notes: impl IntoIterator<Item = &'a Note>) -> usize {
+        let used: HashSet<u128> = notes
+            .into_iter()
+            .flat_map(|n| &n.attachments)
+            .map(|a| a.id)
+            .collect();
+        let before = self.0.len();
+        self.0.retain(|id, _| used.contains(id));
+        before - self.0.len()
+    }
+
+    /// Move contents kept in the attachments of `notes` by older versions into the table,
+    /// under a new id for each attachment.
+    pub fn adopt_legacy<'a>(&mut self, notes: impl IntoIterator<Item = &'a mut Note>) {
+        for attachment in notes.into_iter().flat_map(|n| &mut n.attachments) {
+            attachment.id = new_id();
+            let data = std::mem::take(&mut attachment.legacy_data);
+            self.0.insert(attachment.id, AttachmentData(data.into()));
+        }
+    }
+
+    /// The size of the contents as json, computed without encoding them
+    pub fn json_len(&self) -> usize {
+        self.0
+            .iter()
+            // `"id":"base64",`
+            .map(|(id, data)| id.to_string().len() + 4 * data.0.len().div_ceil(3) + 6)
+            .sum()
+    }
+}
+
+/// A reference to a file on the disk of the device it was added on. Unlike attachments
+/// the file itself is not stored with the note.
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Clone, Debug)]
+pub struct FileLink {
+    pub path: PathBuf,
+    /// Host name of the device the link was added on
+    pub host: String,
+}
+
+impl FileLink {
+    /// A link to `path` on this device
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            host: host_name(),
+        }
+    }
+
+    /// The file name, or the whole path if it has none
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or(self.path.as_os_str())
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Whether the file can be found on this device
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Whether the link was added on another device
+    pub fn is_foreign(&self) -> bool {
+        self.host != host_name()
+    }
+}
+
+/// The name of this device. Empty in the browser, which has no access to it.
+pub fn host_name() -> String {
+    #[cfg(not(target_arch = "wasm32"))]
+    return gethostname::gethostname().to_string_lossy().to_string();
+    #[cfg(target_arch = "wasm32")]
+    return String::new();
+}
+
+/// (De)serialize bytes as a base64 string
+mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
 }
 
 impl Note {
     pub fn new() -> Self {
-        // let mut rng = ChaCha20Rng::from_entropy();
-        let mut n = Self::default();
-        let time = chrono::Utc::now().timestamp_micros();
-        n.id = time as u128;
-        n.text = "".to_string();
-        n.created = chrono::Utc::now().date_naive();
-        // n.color = [
-        //     rng.gen_range(0..255),
-        //     rng.gen_range(0..255),
-        //     rng.gen_range(0..255),
-        // ];
-        n.color = [200, 200, 200];
-        n
+        Self {
+            id: new_id(),
+            text: "".to_string(),
+            created: today(),
+            color: [200, 200, 200],
+            ..Default::default()
+        }
+    }
+
+    /// Whether the text contains `filter`, ignoring case. An empty filter matches.
+    pub fn matches(&self, filter: &str) -> bool {
+        filter.is_empty() || self.text.to_lowercase().contains(&filter.to_lowercase())
+    }
+
+    /// Whether the note has any of `tags`. An empty set matches.
+    pub fn has_any_tag<S: BuildHasher>(&self, tags: &HashSet<String, S>) -> bool {
+        tags.is_empty() || self.tags.iter().any(|t| tags.contains(t))
+    }
+
+    /// The hours of work left, going by the estimated effort and the progress
+    pub fn remaining_effort(&self) -> f32 {
+        if self.is_done() {
+            return 0.;
+        }
+        self.effort_hours.max(0.) * (1. - self.progress.clamp(0., 1.))
     }
 
+    /// The priority used for sorting: the base priority raised by a nearing deadline and the
+    /// remaining effort
     pub fn get_final_prio(&self) -> f32 {
+        let effort = (self.remaining_effort() / EFFORT_HORIZON).min(1.) * EFFORT_WEIGHT;
+        self.deadline_prio() + effort
+    }
+
+    /// The base priority raised by a nearing deadline
+    fn deadline_prio(&self) -> f32 {
         match self.deadline {
             Deadline::Eternal => self.priority,
             Deadline::Periodic { start, days } => {
@@ -66,78 +470,96 @@ impl Note {
                 let panic_range = (24 * 5) as f32;
                 let mut remaining_hours = 0.0;
 
-                for d in start.iter_days().step_by(days.max(1) as usize) {
-                    if d > chrono::Utc::now().date_naive() {
-                        println!("Next is {}", d);
-                        remaining_hours = (d
-                            .signed_duration_since(chrono::Utc::now().date_naive())
-                            .num_hours() as f32)
-                            .min(panic_range);
-                        #[cfg(feature = "demo")]
-                        {
-                            remaining_hours = ((chrono::Utc::now().timestamp_millis() / 100)
-                                % panic_range as i64)
-                                as f32;
-                        }
-                        break;
+                if let Some(d) = next_occurrence(start, days, today()) {
+                    remaining_hours =
+                        (d.signed_duration_since(today()).num_hours() as f32).min(panic_range);
+                    #[cfg(feature = "demo")]
+                    {
+                        remaining_hours = ((chrono::Utc::now().timestamp_millis() / 100)
+                            % panic_range as i64) as f32;
                     }
                 }
 
                 let weight = 1. - (remaining_hours / panic_range);
-
-                println!("weight {weight}, rem {remaining_hours}");
-                println!("now {}", chrono::Utc::now().timestamp() % 80);
+                trace!(
+                    "periodic note {}: weight {weight}, {remaining_hours}h left",
+                    self.id
+                );
                 self.priority + weight
             }
             Deadline::Fixed(date) => {
                 // this is the alerting range - the hours in a work week. Anything later is not affecting prio.
                 // TODO later this should be configurable
                 let panic_range = (24 * 5) as f32;
-                let remaining_hours = date
-                    .signed_duration_since(chrono::Utc::now().date_naive())
-                    .num_hours() as f32;
+                let remaining_hours = date.signed_duration_since(today()).num_hours() as f32;
                 #[cfg(feature = "demo")]
                 let remaining_hours =
                     ((chrono::Utc::now().timestamp_millis() / 100) % panic_range as i64) as f32;
                 let weight = 1. - (remaining_hours / panic_range);
-                println!("weight {weight}, rem {remaining_hours}");
-                println!("now {}", chrono::Utc::now().timestamp() % 80);
-                // 96 / 120
-                // println!("remainung minutes: {:?} {weight}", remaining);
+                trace!(
+                    "due note {}: weight {weight}, {remaining_hours}h left",
+                    self.id
+                );
                 self.priority + weight
             }
         }
     }
 
+    /// The day the note is due on: the fixed date, or the next occurrence of a periodic deadline
+    /// from `today` on
+    pub fn due_date(&self, today: NaiveDate) -> Option<NaiveDate> {
+        match self.deadline {
+            Deadline::Eternal => None,
+            Deadline::Periodic { start, days } => {
+                next_occurrence(start, days, today.pred_opt().unwrap_or(today))
+            }
+            Deadline::Fixed(date) => Some(date),
+        }
+    }
+
     pub fn get_title(&self) -> &str {
         self.text.lines().next().unwrap_or("Default")
     }
 
+    /// Replace the first line of the text, keeping the rest of the body as is
+    pub fn set_title(&mut self, title: &str) {
+        self.text = replace_first_line(&self.text, title);
+    }
+
     pub fn get_body(&self) -> String {
-        self.text.lines().skip(0).collect::<Vec<_>>().join("\n")
+        self.text.lines().collect::<Vec<_>>().join("\n")
     }
 
+    /// The clean text after the title on a single line
     pub fn get_excerpt(&self) -> String {
-        self.get_clean_text()
+        let excerpt = self
+            .get_clean_text()
             .lines()
             .skip(1)
+            .filter(|l| !l.is_empty())
             .collect::<Vec<_>>()
-            .join(" ")
+            .join(" ");
+        if excerpt.is_empty() {
+            "(no additional text)".to_string()
+        } else {
+            excerpt
+        }
     }
 
+    /// The text without links and leading markdown syntax, see `clean_line`
     pub fn get_clean_text(&self) -> String {
         let mut t = self
             .text
-            .split(' ')
-            .filter(|w| !w.contains("http"))
+            .lines()
+            .map(clean_line)
             .collect::<Vec<_>>()
-            .join(" ");
+            .join("\n");
         t.push('\n');
         t
     }
 
-    pub fn get_clean_text_truncated(&self) -> String {
-        let max = 200;
+    /// The clean text, cut off after `max` chars
+    pub fn get_clean_text_truncated(&self, max: usize) -> String {
         if self.get_clean_text().chars().count() > max {
             format!(
                 "{}...",
@@ -148,39 +570,225 @@ impl Note {
         }
     }
 
+    /// The tag the note is colored by: the primary tag if the note has it, else the first tag
+    pub fn primary_tag(&self) -> Option<&str> {
+        self.primary_tag
+            .as_deref()
+            .filter(|tag| self.tags.contains(*tag))
+            .or(self.tags.first().map(String::as_str))
+    }
+
+    /// The color of the primary tag, or the manually picked color of untagged notes
     pub fn get_color(&self) -> Color32 {
-        if self.tags.is_empty() {
-            Color32::from_rgb(self.color[0], self.color[1], self.color[2])
-                .gamma_multiply(GAMMA_MULT)
-        } else {
-            let s = self
-                .tags
-                .clone()
-                .into_iter()
-                .collect::<Vec<String>>()
-                .join("");
-            color_from_tag(&s).gamma_multiply(GAMMA_MULT)
+        match self.primary_tag() {
+            Some(tag) => color_from_tag(tag).gamma_multiply(gamma_mult()),
+            None => Color32::from_rgb(self.color[0], self.color[1], self.color[2])
+                .gamma_multiply(gamma_mult()),
         }
     }
-    pub fn get_links(&self) -> Vec<&str> {
-        self.text
-            .split(&[' ', '\n'])
-            .filter(|t| t.contains("http"))
+    /// The links in the text, in order of appearance. Links to the same URL are only listed once.
+    pub fn get_links(&self) -> Vec<Link<'_>> {
+        let mut links: Vec<Link<'_>> = vec![];
+        for link in find_links(&self.text) {
+            if !links.iter().any(|l| l.url == link.url) {
+                links.push(link);
+            }
+        }
+        links
+    }
+
+    /// The distinct links to images: links ending in an image extension, or marked with a
+    /// leading `!`
+    pub fn get_image_links(&self) -> Vec<&str> {
+        self.get_links()
+            .into_iter()
+            .filter(|link| link.image || is_image_url(link.url))
+            .map(|link| link.url)
             .collect()
     }
 
-    /// Calculate the approximate note height in px based on line height and chars per line
-    pub fn get_approx_height(&self, line_height: f32) -> f32 {
-        let chars_per_row = 15;
-        let newlines = self.get_clean_text_truncated().lines().count();
-        let breaks: usize = self
-            .get_clean_text_truncated()
+    /// The height of the text truncated to `max_chars`, wrapped after `chars_per_row`
+    pub fn get_approx_height(
+        &self,
+        line_height: f32,
+        chars_per_row: usize,
+        max_chars: usize,
+    ) -> f32 {
+        let chars_per_row = chars_per_row.max(1);
+        let text = self.get_clean_text_truncated(max_chars);
+        let newlines = text.lines().count();
+        let breaks: usize = text
             .lines()
             .map(|l| l.chars().count() / chars_per_row)
             .sum();
         (newlines + breaks) as f32 * line_height
     }
 
+    /// All `- [ ]` / `- [x]` lines of the note
+    pub fn checklist(&self) -> Vec<ChecklistItem> {
+        self.text
+            .lines()
+            .enumerate()
+            .filter_map(|(line, l)| {
+                let (checked, text) = parse_checklist_line(l)?;
+                Some(ChecklistItem {
+                    line,
+                    checked,
+                    text: text.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// The number of checked and total checklist items
+    pub fn checklist_progress(&self) -> (usize, usize) {
+        let items = self.checklist();
+        (items.iter().filter(|i| i.checked).count(), items.len())
+    }
+
+    /// Flip the checkbox on line `line` of the text, if that line is a checklist item.
+    pub fn toggle_checklist_item(&mut self, line: usize) {
+        self.text = self
+            .text
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(i, l)| {
+                if i != line {
+                    return l.to_string();
+                }
+                let Some((checked, _)) = parse_checklist_line(l) else {
+                    return l.to_string();
+                };
+                // the box is the character after `- [`, behind any indentation
+                let pos = l.len() - l.trim_start().len() + 3;
+                let mut l = l.to_string();
+                l.replace_range(pos..pos + 1, if checked { " " } else { "x" });
+                l
+            })
+            .collect();
+        self.sync_checklist_progress();
+    }
+
+    /// Set `progress` and `complete` from the checklist, if the note has one.
+    pub fn sync_checklist_progress(&mut self) {
+        let (done, total) = self.checklist_progress();
+        if total > 0 {
+            self.progress = done as f32 / total as f32;
+            self.set_complete(done == total, today());
+        }
+    }
+
+    /// Count the words, characters, lines, checklist items and links of the text. Markup like
+    /// list markers and link URLs doesn't count as words.
+    pub fn stats(&self) -> NoteStats {
+        let mut words = 0;
+        let text = strip_links(&self.text);
+        // the box of a checked item isn't a word either
+        let checked_box = |w: &&str| matches!(*w, "[x]" | "[X]");
+        for word in text.split_whitespace().filter(|w| !checked_box(w)) {
+            let mut in_word = false;
+            for c in word.chars() {
+                if is_cjk(c) {
+                    words += 1;
+                    in_word = false;
+                } else if c.is_alphanumeric() && !in_word {
+                    words += 1;
+                    in_word = true;
+                }
+            }
+        }
+        let (checked, checklist) = self.checklist_progress();
+        NoteStats {
+            words,
+            characters: self
+                .text
+                .chars()
+                .filter(|c| !matches!(c, '\n' | '\r'))
+                .count(),
+            lines: self.text.lines().count(),
+            checked,
+            checklist,
+            links: find_links(&self.text).len(),
+        }
+    }
+
+    /// The time tracked working on the note
+    pub fn tracked_time(&self) -> TimeDelta {
+        self.work_log.iter().map(|(start, end)| *end - *start).sum()
+    }
+
+    /// The time tracked working on the note by day, see `split_by_day`
+    pub fn tracked_by_day(&self) -> BTreeMap<NaiveDate, TimeDelta> {
+        let mut days: BTreeMap<NaiveDate, TimeDelta> = BTreeMap::new();
+        for (start, end) in &self.work_log {
+            for (date, spent) in split_by_day(*start, *end) {
+                *days.entry(date).or_default() += spent;
+            }
+        }
+        days
+    }
+
+    /// Keep `previous` as an earlier version of the text, replaced on `today`. Nothing is kept if
+    /// it is the current text or the last kept version. The oldest versions are dropped to stay
+    /// within `MAX_HISTORY`.
+    pub fn record_revision(&mut self, previous: String, today: NaiveDate) {
+        if previous == self.text || self.history.last().is_some_and(|(_, t)| *t == previous) {
+            return;
+        }
+        self.history.push((today, previous));
+        if self.history.len() > MAX_HISTORY {
+            self.history.drain(..self.history.len() - MAX_HISTORY);
+        }
+    }
+
+    /// Bring back the version at `index` of the history. The current text is kept in the
+    /// history in turn, so restoring can be reverted.
+    pub fn restore_revision(&mut self, index: usize, today: NaiveDate) {
+        let Some((_, text)) = self.history.get(index).cloned() else {
+            return;
+        };
+        let current = std::mem::replace(&mut self.text, text);
+        self.record_revision(current, today);
+        self.sync_checklist_progress();
+    }
+
+    /// Whether the note is still hidden on `today`
+    pub fn is_snoozed(&self, today: NaiveDate) -> bool {
+        self.snoozed_until.is_some_and(|until| until > today)
+    }
+
+    /// Whether the note is finished
+    pub fn is_done(&self) -> bool {
+        self.status == Status::Done
+    }
+
+    /// Change the status, recording `today` as completion date when the note becomes done.
+    pub fn set_status(&mut self, status: Status, today: NaiveDate) {
+        if status == Status::Done && !self.is_done() {
+            self.completed_on = Some(today);
+        } else if status != Status::Done {
+            self.completed_on = None;
+        }
+        self.status = status;
+    }
+
+    /// Mark the note as done, or as active again if it was done. Other states are kept when it
+    /// isn't complete.
+    pub fn set_complete(&mut self, complete: bool, today: NaiveDate) {
+        if complete {
+            self.set_status(Status::Done, today);
+        } else if self.is_done() {
+            self.set_status(Status::Active, today);
+        }
+    }
+
+    /// Turn the completion flag of older versions into a status.
+    pub(crate) fn migrate_complete(&mut self) {
+        if std::mem::take(&mut self.legacy_complete) {
+            self.status = Status::Done;
+        }
+    }
+
     pub fn contains_markdown(&self) -> bool {
         self.text.contains("# ")
             || self.text.contains("* ")
@@ -190,13 +798,509 @@ impl Note {
     }
 }
 
+/// The existing notes that `id` depends on, which are shown nested under it.
+pub fn dependencies_of(id: u128, notes: &Notes) -> Vec<u128> {
+    notes
+        .get(&id)
+        .map(|n| {
+            n.depends
+                .iter()
+                .filter(|d| notes.contains_key(d))
+                .copied()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The titles referenced as `[[Title]]` in `text`, in order of appearance
+pub fn wikilinks(text: &str) -> Vec<&str> {
+    let mut links = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        // the innermost `[[` counts, like in `[[a [[b]]`
+        let title = rest[..end].rsplit("[[").next().unwrap_or_default().trim();
+        if !title.is_empty() && !title.contains('\n') {
+            links.push(title);
+        }
+        rest = &rest[end + 2..];
+    }
+    links
+}
+
+/// The note with the title `title`, ignoring case and surrounding whitespace
+pub fn find_by_title(notes: &Notes, title: &str) -> Option<u128> {
+    let title = title.trim();
+    notes
+        .iter()
+        .find(|(_, n)| n.get_title().trim().eq_ignore_ascii_case(title))
+        .map(|(id, _)| *id)
+}
+
+/// `id` and the notes it transitively depends on, up to `depth` steps away, in breadth first order
+pub fn dependency_neighborhood(id: u128, depth: usize, notes: &Notes) -> Vec<u128> {
+    if !notes.contains_key(&id) {
+        return vec![];
+    }
+    let mut reached = vec![id];
+    let mut frontier = vec![id];
+    for _ in 0..depth {
+        let mut next = vec![];
+        for dependency in frontier.iter().flat_map(|n| dependencies_of(*n, notes)) {
+            if !reached.contains(&dependency) {
+                reached.push(dependency);
+                next.push(dependency);
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+    reached
+}
+
+/// The notes that depend on `id`
+pub fn backlinks(id: u128, notes: &Notes) -> Vec<u128> {
+    notes
+        .iter()
+        .filter(|(_, n)| n.depends.contains(&id))
+        .map(|(other, _)| *other)
+        .collect()
+}
+
+/// The number of existing, incomplete notes that `note` depends on
+pub fn blockers(note: &Note, notes: &Notes) -> usize {
+    note.depends
+        .iter()
+        .filter_map(|d| notes.get(d))
+        .filter(|d| !d.is_done())
+        .count()
+}
+
+/// Whether `note` depends on any incomplete note
+pub fn is_blocked(note: &Note, notes: &Notes) -> bool {
+    blockers(note, notes) > 0
+}
+
+/// The notes tagged `tag`
+pub fn notes_tagged(notes: &Notes, tag: &str) -> Vec<u128> {
+    notes
+        .values()
+        .filter(|n| n.tags.contains(tag))
+        .map(|n| n.id)
+        .collect()
+}
+
+/// Tag every note tagged `tag` with `added` as well. Returns the notes that didn't have it yet.
+pub fn add_tag_where_tagged(notes: &mut Notes, tag: &str, added: &str) -> Vec<u128> {
+    notes
+        .values_mut()
+        .filter(|n| n.tags.contains(tag))
+        .filter_map(|n| n.tags.insert(added.to_string()).then_some(n.id))
+        .collect()
+}
+
+/// Remove `tag` from all notes. Returns the notes that had it.
+pub fn remove_tag_from_all(notes: &mut Notes, tag: &str) -> Vec<u128> {
+    notes
+        .values_mut()
+        .filter_map(|n| n.tags.remove(tag).then_some(n.id))
+        .collect()
+}
+
+/// Whether making `note_id` depend on `dependency` would create a cycle,
+/// which is the case if `note_id` can already be reached from `dependency`.
+pub fn would_create_cycle(notes: &Notes, note_id: u128, dependency: u128) -> bool {
+    let mut visited = BTreeSet::new();
+    let mut stack = vec![dependency];
+    while let Some(id) = stack.pop() {
+        if id == note_id {
+            return true;
+        }
+        if visited.insert(id) {
+            if let Some(n) = notes.get(&id) {
+                stack.extend(n.depends.iter().copied());
+            }
+        }
+    }
+    false
+}
+
+/// Lowercase words of `text`, split at anything that is not alphanumeric
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Tags from `global_tags` that `note` doesn't have yet, but whose name appears as a word
+/// (or sequence of words) in its text.
+pub fn suggest_tags(note: &Note, global_tags: &[String]) -> Vec<String> {
+    let text = words(&note.text);
+    global_tags
+        .iter()
+        .filter(|tag| !note.tags.contains(*tag))
+        .filter(|tag| {
+            let tag = words(tag);
+            !tag.is_empty() && text.windows(tag.len()).any(|w| w == tag.as_slice())
+        })
+        .cloned()
+        .collect()
+}
+
+/// A blueprint for notes that are created over and over
+#[derive(serde::Deserialize, serde::Serialize, Default, PartialEq, Clone)]
+#[serde(default)]
+pub struct NoteTemplate {
+    pub name: String,
+    /// Note text, may contain `{{date}}` and `{{cursor}}` placeholders
+    pub text: String,
+    pub tags: BTreeSet<String>,
+    pub priority: f32,
+    /// Make new notes due this many days after creation
+    pub deadline_days: Option<u16>,
+}
+
+impl NoteTemplate {
+    pub fn from_note(note: &Note) -> Self {
+        let deadline_days = match note.deadline {
+            Deadline::Fixed(date) => {
+                let days = date.signed_duration_since(today()).num_days();
+                Some(days.clamp(0, u16::MAX as i64) as u16)
+            }
+            _ => None,
+        };
+        Self {
+            name: note.get_title().to_string(),
+            text: note.text.clone(),
+            tags: note.tags.clone(),
+            priority: note.priority,
+            deadline_days,
+        }
+    }
+
+    /// Create a new note from this template, returning it with the cursor position (in chars) if the template sets one.
+    pub fn instantiate(&self, today: NaiveDate) -> (Note, Option<usize>) {
+        let mut n = Note::new();
+        let (text, cursor) = expand_placeholders(&self.text, today);
+        n.text = text;
+        n.tags = self.tags.clone();
+        n.priority = self.priority;
+        if let Some(days) = self.deadline_days {
+            n.deadline = Deadline::Fixed(today + chrono::Days::new(days as u64));
+        }
+        n.sync_checklist_progress();
+        (n, cursor)
+    }
+}
+
+/// Replace `{{date}}` with `today` and remove `{{cursor}}` markers.
+/// Returns the text and the char index of the first cursor marker.
+pub fn expand_placeholders(text: &str, today: NaiveDate) -> (String, Option<usize>) {
+    let text = text.replace("{{date}}", &today.to_string());
+    let cursor = text
+        .find("{{cursor}}")
+        .map(|pos| text[..pos].chars().count());
+    (text.replace("{{cursor}}", ""), cursor)
+}
+
+/// A single checkbox line within a note
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChecklistItem {
+    /// Line index within the note text
+    pub line: usize,
+    pub checked: bool,
+    pub text: String,
+}
+
+/// Parse a `- [ ] text` or `- [x] text` line into its state and text.
+pub fn parse_checklist_line(line: &str) -> Option<(bool, &str)> {
+    let rest = line.trim_start().strip_prefix("- [")?;
+    let checked = match rest.chars().next()? {
+        ' ' => false,
+        'x' | 'X' => true,
+        _ => return None,
+    };
+    let text = rest[1..].strip_prefix(']')?;
+    Some((checked, text.trim()))
+}
+
+/// Marks that `today` uses the system time zone
+const LOCAL_TIME: i32 = i32::MIN;
+
+/// UTC offset in seconds used by `today`, or `LOCAL_TIME`
+static TIME_ZONE: AtomicI32 = AtomicI32::new(LOCAL_TIME);
+
+/// Make `today` use a fixed UTC offset in hours, or the system time zone if `None`.
+pub fn set_time_zone(offset_hours: Option<i32>) {
+    let offset = offset_hours.map(|h| h.clamp(-23, 23) * 3600);
+    TIME_ZONE.store(offset.unwrap_or(LOCAL_TIME), Ordering::Relaxed);
+}
+
+/// Whether the high contrast mode is on, see `set_high_contrast`
+static HIGH_CONTRAST: AtomicBool = AtomicBool::new(false);
+
+/// Cards are not faded at all in high contrast mode
+const HIGH_CONTRAST_GAMMA_MULT: f32 = 1.0;
+
+/// Turn the high contrast mode on or off. It outlines cards and stops fading their colors.
+pub fn set_high_contrast(enabled: bool) {
+    HIGH_CONTRAST.store(enabled, Ordering::Relaxed);
+}
+
+pub fn high_contrast() -> bool {
+    HIGH_CONTRAST.load(Ordering::Relaxed)
+}
+
+/// The factor tag and note colors are faded by
+pub fn gamma_mult() -> f32 {
+    if high_contrast() {
+        HIGH_CONTRAST_GAMMA_MULT
+    } else {
+        GAMMA_MULT
+    }
+}
+
+/// The current date in the configured time zone
+pub fn today() -> NaiveDate {
+    local_date(Utc::now())
+}
+
+/// When the next day starts in the time zone used by `today`
+pub fn next_midnight() -> DateTime<Utc> {
+    let today = today();
+    start_of_day(today.succ_opt().unwrap_or(today))
+}
+
+/// The Monday of the week after `date`
+pub fn next_monday(date: NaiveDate) -> NaiveDate {
+    date + Days::new(7 - u64::from(date.weekday().num_days_from_monday()))
+}
+
+/// The day `time` falls on in the time zone used by `today`
+pub fn local_date(time: DateTime<Utc>) -> NaiveDate {
+    local_time(time).date()
+}
+
+/// `time` on the clock of the time zone used by `today`
+pub fn local_time(time: DateTime<Utc>) -> NaiveDateTime {
+    match FixedOffset::east_opt(TIME_ZONE.load(Ordering::Relaxed)) {
+        Some(offset) => time.with_timezone(&offset).naive_local(),
+        None => time.with_timezone(&chrono::Local).naive_local(),
+    }
+}
+
+/// The time `date` starts at in the time zone used by `today`
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    let start = match FixedOffset::east_opt(TIME_ZONE.load(Ordering::Relaxed)) {
+        Some(offset) => offset
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|t| t.to_utc()),
+        None => chrono::Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|t| t.to_utc()),
+    };
+    // there is no local midnight on days a clock change skips it
+    start.unwrap_or(midnight.and_utc())
+}
+
+/// The time from `start` to `end` spent on each day, split at midnight in the time zone used by
+/// `today`
+pub fn split_by_day(start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<(NaiveDate, TimeDelta)> {
+    let mut days = vec![];
+    let mut from = start;
+    while from < end {
+        let date = local_date(from);
+        let next_day = date.succ_opt().map(start_of_day).unwrap_or(end);
+        // a clock change can't hold this up
+        let until = if next_day > from {
+            next_day.min(end)
+        } else {
+            end
+        };
+        days.push((date, until - from));
+        from = until;
+    }
+    days
+}
+
+/// A duration like "45m" or "1h 20m"
+pub fn format_duration(duration: TimeDelta) -> String {
+    let minutes = duration.num_minutes().max(0);
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h {m}m"),
+    }
+}
+
+/// The first day of a periodic deadline after `after`
+fn next_occurrence(start: NaiveDate, days: u16, after: NaiveDate) -> Option<NaiveDate> {
+    start
+        .iter_days()
+        .step_by(days.max(1) as usize)
+        .find(|d| *d > after)
+}
+
+/// How soon a note is due, to group the board by
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum DeadlineBucket {
+    Overdue,
+    Today,
+    ThisWeek,
+    Later,
+    NoDeadline,
+}
+
+impl DeadlineBucket {
+    pub const ALL: [DeadlineBucket; 5] = [
+        DeadlineBucket::Overdue,
+        DeadlineBucket::Today,
+        DeadlineBucket::ThisWeek,
+        DeadlineBucket::Later,
+        DeadlineBucket::NoDeadline,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeadlineBucket::Overdue => "Overdue",
+            DeadlineBucket::Today => "Today",
+            DeadlineBucket::ThisWeek => "This week",
+            DeadlineBucket::Later => "Later",
+            DeadlineBucket::NoDeadline => "No deadline",
+        }
+    }
+
+    /// The bucket of `note` as seen on `today`. "This week" are the next seven days.
+    pub fn of(note: &Note, today: NaiveDate) -> Self {
+        let Some(due) = note.due_date(today) else {
+            return DeadlineBucket::NoDeadline;
+        };
+        match (due - today).num_days() {
+            ..0 => DeadlineBucket::Overdue,
+            0 => DeadlineBucket::Today,
+            1..=7 => DeadlineBucket::ThisWeek,
+            _ => DeadlineBucket::Later,
+        }
+    }
+}
+
+/// The active and waiting notes due on `date` or overdue by then, the most urgent first: the
+/// longest overdue come first, notes due on the same day are sorted by `Note::get_final_prio`.
+/// Snoozed notes are left out.
+pub fn agenda_for(date: NaiveDate, notes: &Notes) -> Vec<u128> {
+    let mut due: Vec<(NaiveDate, f32, u128)> = notes
+        .values()
+        .filter(|note| note.status.rank() == 0 && !note.is_snoozed(date))
+        .filter_map(|note| {
+            let due = note.due_date(date).filter(|due| *due <= date)?;
+            Some((due, note.get_final_prio(), note.id))
+        })
+        .collect();
+    due.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)).then(a.2.cmp(&b.2)));
+    due.into_iter().map(|(_, _, id)| id).collect()
+}
+
+/// The date format used if none or an invalid one is configured
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// `fmt` if it is a valid strftime pattern for dates, `DEFAULT_DATE_FORMAT` otherwise
+pub fn valid_date_format(fmt: &str) -> &str {
+    use std::fmt::Write;
+    let parses = !fmt.is_empty()
+        && !chrono::format::StrftimeItems::new(fmt).any(|i| i == chrono::format::Item::Error);
+    // formatting still fails for specifiers that need a time or time zone
+    if parses && write!(String::new(), "{}", NaiveDate::MIN.format(fmt)).is_ok() {
+        fmt
+    } else {
+        DEFAULT_DATE_FORMAT
+    }
+}
+
+/// Format a date with the strftime pattern `fmt`, falling back to ISO if the pattern is invalid.
+pub fn format_date(d: NaiveDate, fmt: &str) -> String {
+    d.format(valid_date_format(fmt)).to_string()
+}
+
+/// A new note id. The high bits are the creation time, so ids still sort chronologically,
+/// the low bits are random so notes created in the same microsecond don't collide.
+pub fn new_id() -> u128 {
+    let time = chrono::Utc::now().timestamp_micros() as u128;
+    (time << 64) | thread_rng().gen::<u64>() as u128
+}
+
+/// The colors tags are picked from
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum TagPalette {
+    #[default]
+    Default,
+    /// Blues, oranges and yellows that stay apart without green perception
+    Deuteranopia,
+    /// Blues and yellows of different brightness that stay apart without red perception
+    Protanopia,
+    /// Shades of gray. Tags on cards are told apart by shape as well.
+    Monochrome,
+}
+
+impl TagPalette {
+    pub const ALL: [TagPalette; 4] = [
+        TagPalette::Default,
+        TagPalette::Deuteranopia,
+        TagPalette::Protanopia,
+        TagPalette::Monochrome,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TagPalette::Default => "Default",
+            TagPalette::Deuteranopia => "Deuteranopia-safe",
+            TagPalette::Protanopia => "Protanopia-safe",
+            TagPalette::Monochrome => "Monochrome",
+        }
+    }
+
+    /// The colors of the gradient tag colors are picked from
+    fn colors(&self) -> &'static [&'static str] {
+        match self {
+            TagPalette::Default => &["gold", "hotpink", "darkturquoise", "seagreen"],
+            TagPalette::Deuteranopia => &["#0072b2", "#56b4e9", "#e69f00", "#f0e442"],
+            TagPalette::Protanopia => &["#332288", "#88ccee", "#ddcc77", "#ffffb3"],
+            TagPalette::Monochrome => &["#383838", "#e8e8e8"],
+        }
+    }
+}
+
+/// The palette used by `color_from_tag`, as index into `TagPalette::ALL`
+static TAG_PALETTE: AtomicU8 = AtomicU8::new(0);
+
+/// Pick tag colors from `palette`
+pub fn set_tag_palette(palette: TagPalette) {
+    let index = TagPalette::ALL.iter().position(|p| *p == palette);
+    TAG_PALETTE.store(index.unwrap_or_default() as u8, Ordering::Relaxed);
+}
+
+pub fn tag_palette() -> TagPalette {
+    TagPalette::ALL
+        .get(TAG_PALETTE.load(Ordering::Relaxed) as usize)
+        .copied()
+        .unwrap_or_default()
+}
+
 pub fn color_from_tag(tag: &str) -> Color32 {
     let x: i32 = tag.as_bytes().iter().map(|x| *x as i32).sum();
     let mut rng = ChaCha20Rng::seed_from_u64(x as u64);
     // let g = colorgrad::rainbow();
 
     let g = colorgrad::CustomGradient::new()
-        .html_colors(&["gold", "hotpink", "darkturquoise", "seagreen"])
+        .html_colors(tag_palette().colors())
         .build()
         .unwrap();
 
@@ -205,23 +1309,226 @@ pub fn color_from_tag(tag: &str) -> Color32 {
     Color32::from_rgb((c.r * 255.) as u8, (c.g * 255.) as u8, (c.b * 255.) as u8)
 }
 
-pub fn link_text(raw_link: &str) -> &str {
-    raw_link
-        .split("//")
-        .nth(1)
-        .unwrap_or_default()
-        .split('/')
-        .next()
-        .unwrap_or_default()
+/// Links are shortened to this many chars for display
+const MAX_LINK_TEXT: usize = 30;
+
+/// A short form of `url` for display: without the scheme, `www.` and a trailing slash,
+/// truncated to `MAX_LINK_TEXT` chars
+pub fn link_text(url: &str) -> String {
+    let short = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let short = short
+        .strip_prefix("www.")
+        .unwrap_or(short)
+        .trim_end_matches('/');
+    if short.chars().count() > MAX_LINK_TEXT {
+        format!(
+            "{}…",
+            short.chars().take(MAX_LINK_TEXT - 1).collect::<String>()
+        )
+    } else {
+        short.to_string()
+    }
+}
+
+/// Relative luminance of a color as defined by WCAG 2.x
+fn relative_luminance(color: Color32) -> f32 {
+    let channel = |c: u8| {
+        let c = c as f32 / 255.;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(color.r()) + 0.7152 * channel(color.g()) + 0.0722 * channel(color.b())
 }
 
-// determine the color's brigheness level and then invert it
+/// The WCAG contrast ratio between two colors, ranging from 1 (none) to 21 (black on white)
+pub fn contrast_ratio(a: Color32, b: Color32) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// The contrast ratio WCAG AA requires for normal text
+pub const MIN_CONTRAST: f32 = 4.5;
+
+/// Whichever of a dark or light text color contrasts more with the background. If that is still
+/// below `MIN_CONTRAST`, black or white is used instead.
 pub fn readable_text(color: &Color32) -> Color32 {
-    let brightness = color.r() as f32 * 0.299 + color.g() as f32 * 0.587 + color.b() as f32 * 0.114;
-    if brightness > 120.0 {
-        Color32::from_rgb(40, 40, 40)
+    let more_contrast = |dark: Color32, light: Color32| {
+        if contrast_ratio(*color, dark) >= contrast_ratio(*color, light) {
+            dark
+        } else {
+            light
+        }
+    };
+    let text = more_contrast(
+        Color32::from_rgb(40, 40, 40),
+        Color32::from_rgb(230, 230, 230),
+    );
+    if contrast_ratio(*color, text) >= MIN_CONTRAST {
+        text
     } else {
-        Color32::from_rgb(230, 230, 230)
+        more_contrast(Color32::BLACK, Color32::WHITE)
+    }
+}
+
+/// Replace the first line of `text` with `line`. The line break after it (`\n` or `\r\n`) and
+/// everything following is kept. Line breaks in `line` are replaced by spaces.
+pub fn replace_first_line(text: &str, line: &str) -> String {
+    let line = line.replace(['\r', '\n'], " ");
+    let rest = match text.find('\n') {
+        Some(i) if text[..i].ends_with('\r') => &text[i - 1..],
+        Some(i) => &text[i..],
+        None => "",
+    };
+    format!("{line}{rest}")
+}
+
+/// A line without leading markdown tokens (headings, quotes, list bullets, checkboxes) and links,
+/// with whitespace collapsed. Markdown links are replaced by their label.
+fn clean_line(line: &str) -> String {
+    let line = strip_links(line);
+    let mut words = line.split_whitespace().peekable();
+    while let Some(word) = words.peek() {
+        let is_token = word.chars().all(|c| c == '#' || c == '>')
+            || ["-", "*", "+", "[", "]", "[]", "[x]", "[X]"].contains(word)
+            || word
+                .strip_suffix(['.', ')'])
+                .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+        if !is_token {
+            break;
+        }
+        words.next();
+    }
+    words.collect::<Vec<_>>().join(" ")
+}
+
+/// A http(s) link in a text
+#[derive(PartialEq, Clone, Debug)]
+pub struct Link<'a> {
+    pub url: &'a str,
+    /// The label of a markdown link `[label](url)`
+    pub label: Option<&'a str>,
+    /// Marked as an image by a leading `!`, as in `!https://…` or `![label](https://…)`
+    pub image: bool,
+    /// Where the link is in the text, including any markdown syntax around it
+    pub span: Range<usize>,
+}
+
+impl Link<'_> {
+    /// The label of a markdown link, or else the shortened URL
+    pub fn text(&self) -> String {
+        self.label
+            .map(str::to_string)
+            .unwrap_or_else(|| link_text(self.url))
     }
-    // Color32::from_rgb(255-color.r(), 255-color.g(), 255-color.b())
+}
+
+/// The http(s) links in `text`, bare or in markdown syntax. A link has to start with the scheme
+/// at the beginning of a word, so bare domains are not links. Trailing punctuation and unbalanced
+/// closing brackets are not part of a bare link.
+pub fn find_links(text: &str) -> Vec<Link<'_>> {
+    let mut links = vec![];
+    let mut from = 0;
+    while let Some(offset) = text[from..].find("http") {
+        let start = from + offset;
+        from = start + "http".len();
+        let rest = &text[start..];
+        let Some(scheme) = ["http://", "https://"]
+            .into_iter()
+            .find(|scheme| rest.starts_with(scheme))
+        else {
+            continue;
+        };
+        if text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric())
+        {
+            continue;
+        }
+        let len = url_len(rest);
+        if len <= scheme.len() {
+            continue;
+        }
+        let url = &rest[..len];
+        let end = start + len;
+
+        let label_start = text[..start]
+            .strip_suffix("](")
+            .filter(|_| text[end..].starts_with(')'))
+            .and_then(|before| before.rfind('[').map(|i| (before, i)))
+            .filter(|(before, i)| !before[i + 1..].contains([']', '\n']));
+        let link = match label_start {
+            Some((before, i)) => {
+                let image = text[..i].ends_with('!');
+                Link {
+                    url,
+                    label: Some(before[i + 1..].trim()).filter(|l| !l.is_empty()),
+                    image,
+                    span: if image { i - 1 } else { i }..end + 1,
+                }
+            }
+            None => {
+                let image = text[..start].ends_with('!');
+                Link {
+                    url,
+                    label: None,
+                    image,
+                    span: if image { start - 1 } else { start }..end,
+                }
+            }
+        };
+        from = link.span.end;
+        links.push(link);
+    }
+    links
+}
+
+/// The length of the URL at the start of `text`
+fn url_len(text: &str) -> usize {
+    let mut len = text
+        .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`'))
+        .unwrap_or(text.len());
+    // trailing punctuation belongs to the sentence, closing brackets only if they are unbalanced
+    while let Some(last) = text[..len].chars().next_back() {
+        let url = &text[..len];
+        let trim = match last {
+            '.' | ',' | ';' | ':' | '!' | '?' | '\'' | '*' => true,
+            ')' => url.matches('(').count() < url.matches(')').count(),
+            ']' => url.matches('[').count() < url.matches(']').count(),
+            _ => false,
+        };
+        if !trim {
+            break;
+        }
+        len -= last.len_utf8();
+    }
+    len
+}
+
+/// `text` with bare links removed and markdown links replaced by their label
+pub fn strip_links(text: &str) -> String {
+    let mut stripped = String::new();
+    let mut last = 0;
+    for link in find_links(text) {
+        stripped.push_str(&text[last..link.span.start]);
+        stripped.push_str(link.label.unwrap_or_default());
+        last = link.span.end;
+    }
+    stripped.push_str(&text[last..]);
+    stripped
+}
+
+/// Whether `url` points to an image, judging by its extension
+pub fn is_image_url(url: &str) -> bool {
+    let path = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    [".png", ".jpg", ".jpeg", ".webp", ".gif"]
+        .iter()
+        .any(|extension| path.ends_with(extension))
 }
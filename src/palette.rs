@@ -0,0 +1,63 @@
+use crate::ViewMode;
+
+/// Something the command palette can do
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    NewNote,
+    View(ViewMode),
+    ToggleSettings,
+    ToggleScratchpad,
+    ToggleLogbook,
+    ToggleTags,
+    Save,
+    /// Load the notes from storage again
+    Restore,
+    /// Open a note in the editor
+    JumpTo(u128),
+    /// Set the status of the selected notes to done
+    CompleteSelected,
+    ClearSelection,
+}
+
+impl Command {
+    /// All commands except jumping to a note, which the palette offers per note
+    pub const ALL: [Command; 13] = [
+        Command::NewNote,
+        Command::View(ViewMode::Board),
+        Command::View(ViewMode::List),
+        Command::View(ViewMode::Graph),
+        Command::View(ViewMode::Agenda),
+        Command::ToggleSettings,
+        Command::ToggleScratchpad,
+        Command::ToggleLogbook,
+        Command::ToggleTags,
+        Command::Save,
+        Command::Restore,
+        Command::CompleteSelected,
+        Command::ClearSelection,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::NewNote => "New note",
+            Command::View(ViewMode::Board) => "Show board",
+            Command::View(ViewMode::List) => "Show list",
+            Command::View(ViewMode::Graph) => "Show graph",
+            Command::View(ViewMode::Agenda) => "Show today's agenda",
+            Command::ToggleSettings => "Toggle settings",
+            Command::ToggleScratchpad => "Toggle scratchpad",
+            Command::ToggleLogbook => "Toggle logbook",
+            Command::ToggleTags => "Toggle tags",
+            Command::Save => "Save notes",
+            Command::Restore => "Restore notes from storage",
+            Command::JumpTo(_) => "Jump to note",
+            Command::CompleteSelected => "Mark selected notes done",
+            Command::ClearSelection => "Clear the selection",
+        }
+    }
+
+    /// Whether the command acts on the selected notes, so it is only offered if there are any
+    pub fn needs_selection(&self) -> bool {
+        matches!(self, Command::CompleteSelected | Command::ClearSelection)
+    }
+}
@@ -0,0 +1,166 @@
+use chrono::Days;
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{today, Deadline, Note, UserData};
+
+/// Number of notes in the sample data
+const SAMPLE_NOTES: usize = 40;
+
+const TAGS: [&str; 7] = [
+    "work", "home", "errands", "reading", "ideas", "health", "finance",
+];
+
+const TITLES: [&str; 24] = [
+    "Renew passport",
+    "Quarterly report",
+    "Call the plumber",
+    "Plan team offsite",
+    "Read \"The Pragmatic Programmer\"",
+    "Fix the bike brakes",
+    "Dentist appointment",
+    "Review pull requests",
+    "Groceries",
+    "Book flights",
+    "Update CV",
+    "Tax return",
+    "Prepare talk slides",
+    "Birthday present for Sam",
+    "Clean up the garage",
+    "Migrate the build server",
+    "Learn some Rust macros",
+    "Cancel old subscriptions",
+    "Write blog post",
+    "Order new glasses",
+    "Backup photos",
+    "Onboarding checklist",
+    "Water the plants",
+    "Compare electricity plans",
+];
+
+const BODY_LINES: [&str; 16] = [
+    "Needs to happen before the end of the month.",
+    "Ask about the **budget** first.",
+    "- [ ] Draft",
+    "- [ ] Review with someone",
+    "- [x] Collect the documents",
+    "- [x] Check the opening hours",
+    "See https://example.com/docs/getting-started",
+    "[Meeting notes](https://example.org/notes/42)",
+    "*Keep it short*, nobody reads long ones anyway.",
+    "## Open questions\n- Who pays?\n- Is there a deadline?",
+    "Last time this took about two hours.",
+    "`cargo build --release` takes a while, start early.",
+    "Prices: https://example.net/compare?sort=price",
+    "> Done is better than perfect.",
+    "Depends on the weather.",
+    "Call between 9 and 12.",
+];
+
+const LOG_ENTRIES: [&str; 10] = [
+    "Went through the inbox",
+    "Pairing session on the parser",
+    "Long walk, good for thinking",
+    "Sorted out the insurance paperwork",
+    "Read two chapters",
+    "Meeting about the roadmap",
+    "Fixed the flaky test",
+    "Cooked for the week",
+    "Cleared the desk",
+    "Planned the next sprint",
+];
+
+impl UserData {
+    /// Realistic looking notes, logbook and scratchpad for screenshots, demos and testing views.
+    /// The same `seed` gives the same data, with dates relative to today.
+    pub fn sample(seed: u64) -> Self {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let today = today();
+        let days_from_today = |days: i64| {
+            if days < 0 {
+                today.checked_sub_days(Days::new(days.unsigned_abs()))
+            } else {
+                today.checked_add_days(Days::new(days as u64))
+            }
+            .unwrap_or(today)
+        };
+
+        let mut userdata = UserData {
+            tags: TAGS.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        };
+
+        let mut ids = vec![];
+        for i in 0..SAMPLE_NOTES {
+            let mut lines = vec![TITLES[i % TITLES.len()].to_string()];
+            if i >= TITLES.len() {
+                lines[0].push_str(" (again)");
+            }
+            for _ in 0..rng.gen_range(0..=4) {
+                lines.push(BODY_LINES.choose(&mut rng).unwrap_or(&"").to_string());
+            }
+            let mut note = Note {
+                // ids are made up of a creation time and a random part, see `new_id`
+                id: (i as u128) << 64 | rng.gen::<u64>() as u128,
+                text: lines.join("\n"),
+                created: days_from_today(-rng.gen_range(0..60)),
+                priority: rng.gen_range(0.0..1.0),
+                ..Note::new()
+            };
+            let tag_count = rng.gen_range(0..=2);
+            for tag in TAGS.choose_multiple(&mut rng, tag_count) {
+                note.tags.insert(tag.to_string());
+            }
+            note.deadline = match rng.gen_range(0..10) {
+                0..=4 => Deadline::Fixed(days_from_today(rng.gen_range(-10..30))),
+                5 => Deadline::Periodic {
+                    start: days_from_today(-rng.gen_range(0..14)),
+                    days: *[1, 7, 14].choose(&mut rng).unwrap_or(&7),
+                },
+                _ => Deadline::Eternal,
+            };
+            if rng.gen_bool(0.3) {
+                note.effort_hours = rng.gen_range(1..=16) as f32;
+            }
+            note.sync_checklist_progress();
+            if rng.gen_bool(0.15) {
+                note.progress = 1.;
+                note.set_complete(true, days_from_today(-rng.gen_range(0..14)));
+            }
+            // only depending on earlier notes keeps the dependencies free of cycles
+            if !ids.is_empty() && rng.gen_bool(0.2) {
+                if let Some(dependency) = ids.choose(&mut rng) {
+                    note.depends.insert(*dependency);
+                }
+            }
+            ids.push(userdata.add_note(note));
+        }
+
+        for days_ago in 0..7 {
+            let items = (0..rng.gen_range(1..=4))
+                .map(|_| {
+                    let mut item = Note {
+                        id: rng.gen(),
+                        text: LOG_ENTRIES.choose(&mut rng).unwrap_or(&"").to_string(),
+                        ..Note::new()
+                    };
+                    if rng.gen_bool(0.5) {
+                        if let Some(tag) = TAGS.choose(&mut rng) {
+                            item.tags.insert(tag.to_string());
+                        }
+                    }
+                    item
+                })
+                .collect();
+            userdata.logbook.insert(days_from_today(-days_ago), items);
+        }
+
+        userdata
+            .scratchpad
+            .add_section("Phone numbers\nPlumber: 555-0134\nDentist: 555-0178");
+        userdata.scratchpad.add_section(
+            "Gift ideas\n- a good notebook\n- concert tickets\n- something from the market",
+        );
+        userdata
+    }
+}
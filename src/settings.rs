@@ -0,0 +1,374 @@
+use std::{collections::BTreeSet, path::PathBuf};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::DEFAULT_CAPTURE_HOTKEY;
+use crate::{Status, StorageMode, TagPalette, DEFAULT_DATE_FORMAT};
+
+/// Layout of the stored settings. Older state is migrated when it is loaded.
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// The range the UI scale can be set to
+pub const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.75..=2.0;
+
+/// The range the note text size can be set to
+pub const NOTE_TEXT_SIZE_RANGE: std::ops::RangeInclusive<f32> = 10.0..=30.0;
+
+/// The range the card width can be set to
+pub const CARD_WIDTH_RANGE: std::ops::RangeInclusive<f32> = 100.0..=400.0;
+
+#[derive(serde::Deserialize, serde::Serialize, Default, Debug, PartialEq, Eq, Clone)]
+pub enum ViewMode {
+    #[default]
+    Board,
+    List,
+    Graph,
+    /// The notes due today or overdue
+    Agenda,
+}
+
+/// How much space board cards take up
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum Density {
+    Compact,
+    #[default]
+    Comfortable,
+}
+
+impl Density {
+    /// Space between the edge of a card and its text
+    pub fn padding(&self) -> f32 {
+        match self {
+            Density::Compact => 5.,
+            Density::Comfortable => 10.,
+        }
+    }
+
+    /// Number of chars shown on a card before the text is cut off
+    pub fn text_limit(&self) -> usize {
+        match self {
+            Density::Compact => 120,
+            Density::Comfortable => 200,
+        }
+    }
+
+    pub fn min_card_height(&self) -> f32 {
+        match self {
+            Density::Compact => 80.,
+            Density::Comfortable => 150.,
+        }
+    }
+}
+
+/// How the board is split into columns
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum BoardGrouping {
+    /// All notes in one flow
+    #[default]
+    None,
+    /// A column per `DeadlineBucket`
+    Deadline,
+    /// A column per primary tag
+    Tag,
+}
+
+impl BoardGrouping {
+    pub const ALL: [BoardGrouping; 3] = [
+        BoardGrouping::None,
+        BoardGrouping::Deadline,
+        BoardGrouping::Tag,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BoardGrouping::None => "None",
+            BoardGrouping::Deadline => "Deadline",
+            BoardGrouping::Tag => "Tag",
+        }
+    }
+}
+
+/// Everything the user configures, grouped like the tabs of the settings panel
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct Settings {
+    /// `SETTINGS_VERSION` at the time the settings were stored
+    pub version: u32,
+    pub appearance: Appearance,
+    pub behavior: Behavior,
+    pub sync: SyncSettings,
+    pub shortcuts: Shortcuts,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            appearance: Default::default(),
+            behavior: Default::default(),
+            sync: Default::default(),
+            shortcuts: Default::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Stand-in for settings missing from the stored state, which is the case for state written
+    /// before the settings were grouped. See `LegacySettings`.
+    pub fn unmigrated() -> Self {
+        Self {
+            version: 0,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct Appearance {
+    /// How notes are displayed
+    pub viewmode: ViewMode,
+    /// Zoom factor of the whole UI
+    pub ui_scale: f32,
+    /// A font file to use instead of the bundled one
+    pub custom_font: Option<PathBuf>,
+    /// Outline cards and don't fade their colors
+    pub high_contrast: bool,
+    pub tag_palette: TagPalette,
+    /// strftime pattern for displayed dates
+    pub date_format: String,
+    /// UTC offset in hours that decides what "today" is, the system time zone if `None`
+    pub utc_offset: Option<i32>,
+    /// Font size of the text on board cards
+    pub note_text_size: f32,
+    /// Width of a board card
+    pub card_width: f32,
+    pub density: Density,
+    /// Lay the board out in this many columns instead of wrapping cards
+    pub board_columns: Option<usize>,
+    pub board_grouping: BoardGrouping,
+    /// Show the number of words of each note in the list view
+    pub list_word_count: bool,
+    /// Group the list view by the status of the notes
+    pub list_by_status: bool,
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Self {
+            viewmode: ViewMode::Board,
+            ui_scale: 1.0,
+            custom_font: None,
+            high_contrast: false,
+            tag_palette: TagPalette::Default,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            utc_offset: None,
+            note_text_size: 15.,
+            card_width: 150.,
+            density: Density::Comfortable,
+            board_columns: None,
+            board_grouping: BoardGrouping::None,
+            list_word_count: false,
+            list_by_status: false,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct Behavior {
+    /// Show notes nested under the notes that depend on them on the board
+    pub stack_dependencies: bool,
+    /// Hide notes from the board until their dependencies are complete
+    pub hide_blocked: bool,
+    /// Show finished notes in a "Done" column on the board and a group in the list
+    pub show_done: bool,
+    /// The statuses of the unfinished notes on the board
+    pub board_statuses: BTreeSet<Status>,
+    /// Show the lowest priority first
+    pub sort_ascending: bool,
+    /// Fetch images linked in notes to show previews of them
+    pub load_remote_images: bool,
+    /// Keep the window above other windows
+    pub always_on_top: bool,
+    /// Show quick capture in a window of its own, above other windows
+    pub capture_on_top: bool,
+    /// Hours of work per week the effort of notes due this week is compared to
+    pub weekly_capacity: f32,
+    /// Turn off animations and highlights that fade out
+    pub reduce_motion: bool,
+    /// Only repaint on input while the window is in the background
+    pub save_power: bool,
+    /// Repaint every frame even if nothing happens, for demos
+    pub continuous_repaint: bool,
+    /// Underline misspelled words in the editor, if built with spell checking
+    pub spellcheck: bool,
+    /// The language of the dictionary, like `en_US`
+    pub spell_language: String,
+    /// A folder with Hunspell dictionaries, looked in before the ones of the system
+    pub dictionary_dir: Option<PathBuf>,
+    /// Words added to the dictionary
+    pub spell_words: BTreeSet<String>,
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Self {
+            stack_dependencies: false,
+            hide_blocked: false,
+            show_done: false,
+            board_statuses: BTreeSet::from([Status::Active]),
+            sort_ascending: false,
+            load_remote_images: false,
+            always_on_top: false,
+            capture_on_top: false,
+            weekly_capacity: 40.,
+            reduce_motion: false,
+            save_power: true,
+            continuous_repaint: false,
+            spellcheck: true,
+            spell_language: "en_US".to_string(),
+            dictionary_dir: None,
+            spell_words: BTreeSet::new(),
+        }
+    }
+}
+
+/// Where notes are stored. The login stays with the app, as the secret is never persisted.
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct SyncSettings {
+    /// How data is stored
+    pub storage_mode: StorageMode,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct Shortcuts {
+    /// System wide shortcut opening the quick capture window
+    #[cfg(not(target_arch = "wasm32"))]
+    pub capture_hotkey: String,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            capture_hotkey: DEFAULT_CAPTURE_HOTKEY.to_string(),
+        }
+    }
+}
+
+/// The settings as older versions stored them, spread over the app state and its `ui_state`.
+/// Read from the stored app state once to migrate it into `Settings`.
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+pub struct LegacySettings {
+    viewmode: ViewMode,
+    always_on_top: bool,
+    storage_mode: StorageMode,
+    ui_state: LegacyUiState,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default)]
+struct LegacyUiState {
+    ui_scale: f32,
+    custom_font: Option<PathBuf>,
+    high_contrast: bool,
+    tag_palette: TagPalette,
+    date_format: String,
+    utc_offset: Option<i32>,
+    note_text_size: f32,
+    card_width: f32,
+    density: Density,
+    board_columns: Option<usize>,
+    stack_dependencies: bool,
+    hide_blocked: bool,
+    show_done: bool,
+    sort_ascending: bool,
+    load_remote_images: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    capture_hotkey: String,
+}
+
+impl Default for LegacyUiState {
+    fn default() -> Self {
+        let Settings {
+            appearance,
+            behavior,
+            shortcuts,
+            ..
+        } = Settings::default();
+        #[cfg(target_arch = "wasm32")]
+        let _ = shortcuts;
+        Self {
+            ui_scale: appearance.ui_scale,
+            custom_font: appearance.custom_font,
+            high_contrast: appearance.high_contrast,
+            tag_palette: appearance.tag_palette,
+            date_format: appearance.date_format,
+            utc_offset: appearance.utc_offset,
+            note_text_size: appearance.note_text_size,
+            card_width: appearance.card_width,
+            density: appearance.density,
+            board_columns: appearance.board_columns,
+            stack_dependencies: behavior.stack_dependencies,
+            hide_blocked: behavior.hide_blocked,
+            show_done: behavior.show_done,
+            sort_ascending: behavior.sort_ascending,
+            load_remote_images: behavior.load_remote_images,
+            #[cfg(not(target_arch = "wasm32"))]
+            capture_hotkey: shortcuts.capture_hotkey,
+        }
+    }
+}
+
+impl From<LegacySettings> for Settings {
+    fn from(legacy: LegacySettings) -> Self {
+        let ui = legacy.ui_state;
+        Self {
+            version: SETTINGS_VERSION,
+            appearance: Appearance {
+                viewmode: legacy.viewmode,
+                ui_scale: ui.ui_scale,
+                custom_font: ui.custom_font,
+                high_contrast: ui.high_contrast,
+                tag_palette: ui.tag_palette,
+                date_format: ui.date_format,
+                utc_offset: ui.utc_offset,
+                note_text_size: ui.note_text_size,
+                card_width: ui.card_width,
+                density: ui.density,
+                board_columns: ui.board_columns,
+                board_grouping: BoardGrouping::None,
+                list_word_count: false,
+                list_by_status: false,
+            },
+            behavior: Behavior {
+                stack_dependencies: ui.stack_dependencies,
+                hide_blocked: ui.hide_blocked,
+                show_done: ui.show_done,
+                board_statuses: Behavior::default().board_statuses,
+                sort_ascending: ui.sort_ascending,
+                load_remote_images: ui.load_remote_images,
+                always_on_top: legacy.always_on_top,
+                capture_on_top: false,
+                weekly_capacity: Behavior::default().weekly_capacity,
+                reduce_motion: false,
+                save_power: true,
+                continuous_repaint: false,
+                spellcheck: true,
+                spell_language: Behavior::default().spell_language,
+                dictionary_dir: None,
+                spell_words: BTreeSet::new(),
+            },
+            sync: SyncSettings {
+                storage_mode: legacy.storage_mode,
+            },
+            shortcuts: Shortcuts {
+                #[cfg(not(target_arch = "wasm32"))]
+                capture_hotkey: ui.capture_hotkey,
+            },
+        }
+    }
+}
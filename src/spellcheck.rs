@@ -0,0 +1,603 @@
+use anyhow::{Context, Result};
+use egui::epaint::ahash::{HashMap, HashSet};
+use std::{
+    collections::BTreeSet,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+};
+
+use crate::{find_links, wikilinks};
+
+/// Seconds the text has to stay unchanged before it is checked again
+pub const SPELL_CHECK_DELAY: f64 = 0.5;
+
+/// Number of suggestions offered for a misspelled word
+pub const MAX_SUGGESTIONS: usize = 6;
+
+/// Where dictionaries are looked for, after `custom`
+pub fn dictionary_dirs(custom: Option<&Path>) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = custom.map(Path::to_path_buf).into_iter().collect();
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(PathBuf::from(&home).join("Library/Spelling"));
+        dirs.push(PathBuf::from(home).join(".local/share/hunspell"));
+    }
+    for dir in [
+        "/usr/share/hunspell",
+        "/usr/share/myspell",
+        "/usr/share/myspell/dicts",
+        "/usr/local/share/hunspell",
+        "/Library/Spelling",
+    ] {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs
+}
+
+/// How the flags of words and affixes are written in a Hunspell dictionary
+#[derive(Clone, Copy)]
+enum FlagFormat {
+    Char,
+    /// Two characters each
+    Long,
+    /// Numbers separated by commas
+    Num,
+}
+
+impl FlagFormat {
+    fn parse(self, flags: &str) -> Vec<String> {
+        match self {
+            FlagFormat::Char => flags.chars().map(String::from).collect(),
+            FlagFormat::Long => {
+                let chars: Vec<char> = flags.chars().collect();
+                chars.chunks(2).map(|c| c.iter().collect()).collect()
+            }
+            FlagFormat::Num => flags.split(',').map(|f| f.trim().to_string()).collect(),
+        }
+    }
+}
+
+/// A part of an affix condition, matching one character
+#[derive(Debug)]
+enum CharClass {
+    Any,
+    Is(char),
+    OneOf(Vec<char>),
+    NoneOf(Vec<char>),
+}
+
+impl CharClass {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CharClass::Any => true,
+            CharClass::Is(x) => *x == c,
+            CharClass::OneOf(set) => set.contains(&c),
+            CharClass::NoneOf(set) => !set.contains(&c),
+        }
+    }
+}
+
+fn parse_condition(condition: &str) -> Vec<CharClass> {
+    let mut classes = vec![];
+    let mut chars = condition.chars();
+    while let Some(c) = chars.next() {
+        classes.push(match c {
+            '.' => CharClass::Any,
+            '[' => {
+                let set: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                match set.strip_prefix('^') {
+                    Some(set) => CharClass::NoneOf(set.chars().collect()),
+                    None => CharClass::OneOf(set.chars().collect()),
+                }
+            }
+            c => CharClass::Is(c),
+        });
+    }
+    classes
+}
+
+/// A prefix or suffix rule of a Hunspell affix file
+#[derive(Debug)]
+struct Affix {
+    /// Removed from the word before `add` is added
+    strip: String,
+    add: String,
+    /// What the word has to start with for a prefix, or end with for a suffix
+    condition: Vec<CharClass>,
+    /// Can be combined with an affix of the other kind
+    cross: bool,
+}
+
+impl Affix {
+    fn apply_suffix(&self, word: &str) -> Option<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let n = self.condition.len();
+        if n > chars.len()
+            || !self
+                .condition
+                .iter()
+                .zip(&chars[chars.len() - n..])
+                .all(|(class, c)| class.matches(*c))
+        {
+            return None;
+        }
+        let stem = word.strip_suffix(self.strip.as_str())?;
+        Some(format!("{stem}{}", self.add))
+    }
+
+    fn apply_prefix(&self, word: &str) -> Option<String> {
+        let chars: Vec<char> = word.chars().collect();
+        if self.condition.len() > chars.len()
+            || !self
+                .condition
+                .iter()
+                .zip(&chars)
+                .all(|(class, c)| class.matches(*c))
+        {
+            return None;
+        }
+        let stem = word.strip_prefix(self.strip.as_str())?;
+        Some(format!("{}{stem}", self.add))
+    }
+}
+
+/// The encoding named by the `SET` line of an affix file, UTF-8 if there is none
+fn encoding(aff: &[u8]) -> String {
+    aff.split(|b| *b == b'\n')
+        .find_map(|line| {
+            let line = String::from_utf8_lossy(line);
+            match line
+                .trim_start_matches('\u{feff}')
+                .split_whitespace()
+                .collect::<Vec<_>>()[..]
+            {
+                ["SET", name, ..] => Some(name.to_uppercase()),
+                _ => None,
+            }
+        })
+        .unwrap_or_else(|| "UTF-8".to_string())
+}
+
+/// The text of a dictionary file written in `encoding`
+fn decode(bytes: &[u8], encoding: &str) -> Result<String> {
+    match encoding {
+        "UTF-8" => String::from_utf8(bytes.to_vec()).context("The dictionary is not valid UTF-8"),
+        // the first 256 characters of Unicode are the ones of ISO 8859-1
+        "ISO8859-1" => Ok(bytes.iter().copied().map(char::from).collect()),
+        "ISO8859-15" => Ok(bytes.iter().copied().map(latin9).collect()),
+        _ => anyhow::bail!("Dictionaries in {encoding} are not supported"),
+    }
+}
+
+/// The character of `byte` in ISO 8859-15, which replaces a few of ISO 8859-1
+fn latin9(byte: u8) -> char {
+    match byte {
+        0xA4 => '€',
+        0xA6 => 'Š',
+        0xA8 => 'š',
+        0xB4 => 'Ž',
+        0xB8 => 'ž',
+        0xBC => 'Œ',
+        0xBD => 'œ',
+        0xBE => 'Ÿ',
+        byte => char::from(byte),
+    }
+}
+
+/// The words that may be joined into compound words, by where they may go in one
+#[derive(Default)]
+struct Compounds {
+    begin: HashSet<String>,
+    middle: HashSet<String>,
+    end: HashSet<String>,
+    /// Characters each part has at least
+    min: usize,
+}
+
+impl Compounds {
+    /// Whether `word` is made of two or more parts
+    fn contains(&self, word: &str) -> bool {
+        !self.begin.is_empty() && self.joined(word, &self.begin)
+    }
+
+    /// Whether `word` starts with one of `first`, followed by middle parts and an end part
+    fn joined(&self, word: &str, first: &HashSet<String>) -> bool {
+        word.char_indices().skip(self.min.max(1)).any(|(i, _)| {
+            let (part, rest) = word.split_at(i);
+            first.contains(part)
+                && (rest.chars().count() >= self.min && self.end.contains(rest)
+                    || self.joined(rest, &self.middle))
+        })
+    }
+}
+
+/// The words of a language, expanded from a Hunspell dictionary and its affix rules
+#[derive(Default)]
+pub struct Dictionary {
+    words: HashSet<String>,
+    compounds: Compounds,
+    /// Characters tried when looking for suggestions, the most common first
+    alphabet: Vec<char>,
+}
+
+impl Dictionary {
+    /// Load the dictionary of `language`, like `en_US`, from the first of `dirs` that has it
+    pub fn load(language: &str, dirs: &[PathBuf]) -> Result<Self> {
+        let dir = dirs
+            .iter()
+            .find(|dir| dir.join(format!("{language}.dic")).is_file())
+            .with_context(|| format!("No dictionary for {language} found"))?;
+        let read = |extension: &str| {
+            let path = dir.join(format!("{language}.{extension}"));
+            std::fs::read(&path).with_context(|| format!("Could not read {}", path.display()))
+        };
+        let aff = read("aff").unwrap_or_default();
+        // both files are written in the encoding the affix file names
+        let encoding = encoding(&aff);
+        Ok(Self::parse(
+            &decode(&aff, &encoding)?,
+            &decode(&read("dic")?, &encoding)?,
+        ))
+    }
+
+    /// A dictionary from the contents of a Hunspell `.aff` and `.dic` file
+    pub fn parse(aff: &str, dic: &str) -> Self {
+        let mut format = FlagFormat::Char;
+        let mut alphabet: Vec<char> = vec![];
+        let mut prefixes: HashMap<String, Vec<Affix>> = HashMap::default();
+        let mut suffixes: HashMap<String, Vec<Affix>> = HashMap::default();
+        let mut cross: HashMap<String, bool> = HashMap::default();
+        let mut compounds = Compounds {
+            min: 3,
+            ..Default::default()
+        };
+        // the flags of words that may go anywhere in a compound, at its start, in its middle
+        // or at its end, and of words that may only be part of one
+        let mut compound_flags: [Option<String>; 5] = Default::default();
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["FLAG", "long", ..] => format = FlagFormat::Long,
+                ["FLAG", "num", ..] => format = FlagFormat::Num,
+                ["TRY", letters, ..] => alphabet = letters.chars().collect(),
+                ["COMPOUNDFLAG", flag, ..] => compound_flags[0] = Some(flag.to_string()),
+                ["COMPOUNDBEGIN", flag, ..] => compound_flags[1] = Some(flag.to_string()),
+                ["COMPOUNDMIDDLE", flag, ..] => compound_flags[2] = Some(flag.to_string()),
+                ["COMPOUNDEND", flag, ..] => compound_flags[3] = Some(flag.to_string()),
+                ["ONLYINCOMPOUND", flag, ..] => compound_flags[4] = Some(flag.to_string()),
+                ["COMPOUNDMIN", min, ..] => {
+                    compounds.min = min.parse().unwrap_or(compounds.min);
+                }
+                // the header of a rule group, `SFX A Y 3`
+                [kind @ ("PFX" | "SFX"), flag, allowed, count]
+                    if count.parse::<usize>().is_ok() && matches!(*allowed, "Y" | "N") =>
+                {
+                    let key = format!("{kind}{flag}");
+                    cross.insert(key, *allowed == "Y");
+                }
+                [kind @ ("PFX" | "SFX"), flag, strip, add, rest @ ..] => {
+                    let key = format!("{kind}{flag}");
+                    let affix = Affix {
+                        strip: if *strip == "0" { "" } else { strip }.to_string(),
+                        // flags of affixes that may follow this one are not supported
+                        add: match add.split('/').next().unwrap_or_default() {
+                            "0" => "",
+                            add => add,
+                        }
+                        .to_string(),
+                        condition: parse_condition(rest.first().copied().unwrap_or(".")),
+                        cross: cross.get(&key).copied().unwrap_or_default(),
+                    };
+                    let rules = if *kind == "PFX" {
+                        &mut prefixes
+                    } else {
+                        &mut suffixes
+                    };
+                    rules.entry(flag.to_string()).or_default().push(affix);
+                }
+                _ => {}
+            }
+        }
+        if alphabet.is_empty() {
+            alphabet = ('a'..='z').collect();
+        }
+
+        let mut words = HashSet::default();
+        // the first line is the number of words
+        for line in dic.lines().skip(1) {
+            let entry = line.split_whitespace().next().unwrap_or_default();
+            let (word, flags) = match entry.split_once('/') {
+                Some((word, flags)) => (word, format.parse(flags)),
+                None => (entry, vec![]),
+            };
+            if word.is_empty() {
+                continue;
+            }
+            let word_prefixes: Vec<&Affix> = flags
+                .iter()
+                .filter_map(|f| prefixes.get(f))
+                .flatten()
+                .collect();
+            let mut suffixed = vec![];
+            for suffix in flags.iter().filter_map(|f| suffixes.get(f)).flatten() {
+                let Some(form) = suffix.apply_suffix(word) else {
+                    continue;
+                };
+                for prefix in word_prefixes.iter().filter(|p| p.cross && suffix.cross) {
+                    words.extend(prefix.apply_prefix(&form));
+                }
+                suffixed.push(form);
+            }
+            let prefixed: Vec<String> = word_prefixes
+                .iter()
+                .filter_map(|p| p.apply_prefix(word))
+                .collect();
+
+            let [anywhere, begin, middle, end, only] = compound_flags
+                .each_ref()
+                .map(|flag| flag.as_ref().is_some_and(|f| flags.contains(f)));
+            // a compound takes prefixes at its start and suffixes at its end
+            if anywhere || begin {
+                compounds.begin.insert(word.to_string());
+                compounds.begin.extend(prefixed.iter().cloned());
+            }
+            if anywhere || middle {
+                compounds.middle.insert(word.to_string());
+            }
+            if anywhere || end {
+                compounds.end.insert(word.to_string());
+                compounds.end.extend(suffixed.iter().cloned());
+            }
+            if only {
+                continue;
+            }
+            words.extend(suffixed);
+            words.extend(prefixed);
+            words.insert(word.to_string());
+        }
+        Self {
+            words,
+            compounds,
+            alphabet,
+        }
+    }
+
+    /// Whether `word` is in the dictionary as it is written
+    fn contains(&self, word: &str) -> bool {
+        self.words.contains(word) || self.compounds.contains(word)
+    }
+
+    /// Whether `word` is spelled right. Words may start with a capital letter or be all
+    /// capitals, but names can't be written in lower case.
+    pub fn check(&self, word: &str) -> bool {
+        if self.contains(word) {
+            return true;
+        }
+        let mut chars = word.chars();
+        let first_upper = chars.next().is_some_and(char::is_uppercase);
+        let rest_lower = chars.clone().all(|c| !c.is_uppercase());
+        let all_upper = word.chars().all(|c| !c.is_lowercase());
+        if !(first_upper && rest_lower || all_upper) {
+            return false;
+        }
+        let lower = word.to_lowercase();
+        self.contains(&lower) || self.contains(&capitalized(&lower))
+    }
+
+    /// Known words one change away from `word`, or two for short words if there are none,
+    /// written in the same case as `word`
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let lower = word.to_lowercase();
+        let mut found: BTreeSet<String> = edits(&lower, &self.alphabet)
+            .into_iter()
+            .filter(|w| self.check(w))
+            .collect();
+        if found.is_empty() && lower.chars().count() <= 10 {
+            found = edits(&lower, &self.alphabet)
+                .iter()
+                .flat_map(|w| edits(w, &self.alphabet))
+                .filter(|w| self.check(w))
+                .collect();
+        }
+        let mut found: Vec<String> = found.into_iter().collect();
+        // the first letter is rarely the one that is wrong
+        let first = lower.chars().next();
+        found.sort_by_key(|w| w.chars().next() != first);
+        found.truncate(MAX_SUGGESTIONS);
+        let all_upper = word.chars().count() > 1 && word.chars().all(|c| !c.is_lowercase());
+        if all_upper {
+            found.iter_mut().for_each(|w| *w = w.to_uppercase());
+        } else if word.chars().next().is_some_and(char::is_uppercase) {
+            found.iter_mut().for_each(|w| *w = capitalized(w));
+        }
+        found
+    }
+}
+
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// All words one deletion, swap, replacement or insertion away from `word`
+fn edits(word: &str, alphabet: &[char]) -> Vec<String> {
+    let chars: Vec<char> = word.chars().collect();
+    let joined = |left: &[char], middle: &[char], right: &[char]| -> String {
+        left.iter().chain(middle).chain(right).collect()
+    };
+    let mut edits = vec![];
+    for i in 0..=chars.len() {
+        let (left, right) = chars.split_at(i);
+        if let Some((_, rest)) = right.split_first() {
+            edits.push(joined(left, &[], rest));
+        }
+        if right.len() > 1 {
+            edits.push(joined(left, &[right[1], right[0]], &right[2..]));
+        }
+        for c in alphabet {
+            if let Some((_, rest)) = right.split_first() {
+                edits.push(joined(left, &[*c], rest));
+            }
+            edits.push(joined(left, &[*c], right));
+        }
+    }
+    edits
+}
+
+/// The parts of `text` that are not prose: code, links and wikilinks
+fn skipped_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans: Vec<Range<usize>> = find_links(text).into_iter().map(|l| l.span).collect();
+    for title in wikilinks(text) {
+        let start = title.as_ptr() as usize - text.as_ptr() as usize;
+        spans.push(start..start + title.len());
+    }
+    let mut in_fence = false;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let fence = line.trim_start().starts_with("```");
+        if in_fence || fence {
+            spans.push(offset..offset + line.len());
+        } else {
+            // inline code between backticks
+            let ticks: Vec<usize> = line.match_indices('`').map(|(i, _)| i).collect();
+            for (open, close) in ticks.iter().step_by(2).zip(ticks.iter().skip(1).step_by(2)) {
+                spans.push(offset + open..offset + close + 1);
+            }
+        }
+        if fence {
+            in_fence = !in_fence;
+        }
+        offset += line.len();
+    }
+    spans
+}
+
+/// Where the misspelled words of `text` are. Code, links, #tags and words with digits are not
+/// checked, nor are the `known` words a user added.
+pub fn misspelled(
+    text: &str,
+    dictionary: &Dictionary,
+    known: &BTreeSet<String>,
+) -> Vec<Range<usize>> {
+    let skipped = skipped_spans(text);
+    let is_word_char = |c: char| c.is_alphanumeric() || matches!(c, '\'' | '’' | '_');
+    let mut found = vec![];
+    let mut rest = text.char_indices().peekable();
+    while let Some((start, c)) = rest.next() {
+        if !is_word_char(c) {
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        while let Some((i, c)) = rest.next_if(|(_, c)| is_word_char(*c)) {
+            end = i + c.len_utf8();
+        }
+        // apostrophes around a word are quotes
+        let word = text[start..end].trim_matches(['\'', '’']);
+        let start = start + text[start..end].find(word).unwrap_or_default();
+        let range = start..start + word.len();
+        let before = text[..start].chars().next_back();
+        let after = text[range.end..].chars().next();
+        let skip = word.chars().count() < 2
+            || word.contains(|c: char| c.is_numeric() || c == '_')
+            || matches!(before, Some('#' | '@' | '/' | '\\' | '.'))
+            || matches!(after, Some('@' | '/' | '\\'))
+            || skipped
+                .iter()
+                .any(|s| s.start <= range.start && range.end <= s.end)
+            || known.contains(word)
+            || known.contains(&word.to_lowercase());
+        if skip || dictionary.check(word) {
+            continue;
+        }
+        // possessives of known words
+        if let Some(stem) = word.strip_suffix("'s").or(word.strip_suffix("’s")) {
+            if dictionary.check(stem) {
+                continue;
+            }
+        }
+        found.push(range);
+    }
+    found
+}
+
+/// Load the dictionary of `language` from `dirs` on a thread of its own, as expanding the
+/// affixes of a large one takes a while. It arrives in the returned channel, and `ctx` repaints.
+pub fn load_dictionary(
+    ctx: &egui::Context,
+    language: String,
+    dirs: Vec<PathBuf>,
+) -> Receiver<Result<Dictionary>> {
+    let (sender, receiver) = channel();
+    let ctx = ctx.clone();
+    let load = move || {
+        _ = sender.send(Dictionary::load(&language, &dirs));
+        ctx.request_repaint();
+    };
+    // the browser has no threads, and no dictionary files to read either
+    #[cfg(target_arch = "wasm32")]
+    load();
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::spawn(load);
+    receiver
+}
+
+/// Spell checks the text of the note being edited once it has not changed for a moment
+pub struct SpellChecker {
+    pub dictionary: Dictionary,
+    /// The misspelled words of the text last checked, with where they were
+    misspelled: Vec<(Range<usize>, String)>,
+    /// Hash of the text and known words last checked
+    checked: u64,
+    /// Hash of the text waiting to be checked, and the time it was first seen
+    pending: Option<(u64, f64)>,
+}
+
+impl SpellChecker {
+    pub fn new(dictionary: Dictionary) -> Self {
+        Self {
+            dictionary,
+            misspelled: vec![],
+            checked: 0,
+            pending: None,
+        }
+    }
+
+    /// Check `text` if it stayed the same for `SPELL_CHECK_DELAY`. Returns how long to wait
+    /// before calling again if a check is due.
+    pub fn update(&mut self, text: &str, known: &BTreeSet<String>, now: f64) -> Option<f64> {
+        let hash = egui::util::hash((text, known));
+        if hash == self.checked {
+            return None;
+        }
+        match self.pending {
+            Some((pending, since)) if pending == hash => {
+                let waited = now - since;
+                if waited < SPELL_CHECK_DELAY {
+                    return Some(SPELL_CHECK_DELAY - waited);
+                }
+            }
+            _ => {
+                self.pending = Some((hash, now));
+                return Some(SPELL_CHECK_DELAY);
+            }
+        }
+        self.misspelled = misspelled(text, &self.dictionary, known)
+            .into_iter()
+            .map(|range| (range.clone(), text[range].to_string()))
+            .collect();
+        self.checked = hash;
+        self.pending = None;
+        None
+    }
+
+    /// The misspelled words found in the last check that are still where they were in `text`
+    pub fn misspelled_in<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.misspelled
+            .iter()
+            .filter(|(range, word)| text.get(range.clone()) == Some(word.as_str()))
+            .map(|(range, _)| range.clone())
+    }
+}
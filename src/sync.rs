@@ -1,213 +1,557 @@
 use anyhow::{anyhow, Context, Result};
 
 use ehttp::headers;
-use log::info;
+use log::{debug, info};
 use magic_crypt::{new_magic_crypt, MagicCryptTrait};
 use serde_json::json;
-use std::{fs::write, path::PathBuf};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
 
 use crate::app::{Channels, Message, UserData};
+use crate::userdata_from_json;
 
+/// Username and encryption secret. The secret is never persisted and is wiped from memory on drop.
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone)]
+#[serde(default)]
+pub struct Credentials {
+    pub username: String,
+    #[serde(skip)]
+    pub secret: Zeroizing<String>,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Credentials {}", self.username)
+    }
+}
+
+/// A place the encrypted notes are kept. Encrypting and decrypting them is left to the caller.
+pub trait StorageBackend {
+    /// Store the encrypted notes, telling `channels` once they are saved.
+    fn save(&mut self, blob: &str, channels: &Channels, manual_save: bool) -> Result<()>;
+    /// Fetch the encrypted notes and send them to `channels`.
+    fn load(&self, channels: &Channels) -> Result<()>;
+    /// The encrypted notes as saved the time before the last, if the storage keeps them
+    fn read_backup(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+    /// Store the encrypted notes in place of ones that could not be loaded. The backup of those
+    /// is left as it is, as it may be the last readable version.
+    fn replace(&mut self, blob: &str, channels: &Channels, manual_save: bool) -> Result<()> {
+        self.save(blob, channels, manual_save)
+    }
+}
+
+/// Whether the notes shown may be written to the storage, and why not
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Lock {
+    /// The notes of the storage were loaded, or the ones shown were chosen over them
+    #[default]
+    Unlocked,
+    /// The secret was not entered since the start. The notes shown are the ones of the last
+    /// session, which may be kept instead.
+    Startup,
+    /// The notes of the profile switched to are not loaded yet. Any notes shown are not its
+    /// own, so they must never replace the ones in its storage.
+    ProfileSwitch,
+}
+
+impl Lock {
+    pub fn is_locked(self) -> bool {
+        self != Lock::Unlocked
+    }
+
+    /// Whether the notes shown may be kept, to overwrite the ones in the storage
+    pub fn may_keep_notes(self) -> bool {
+        self != Lock::ProfileSwitch
+    }
+
+    /// Unlock without loading, so the notes shown overwrite the ones in the storage
+    pub fn keep_notes(&mut self) -> Result<()> {
+        if !self.may_keep_notes() {
+            anyhow::bail!("These notes are not of this profile. Enter its secret to load its own.");
+        }
+        *self = Lock::Unlocked;
+        Ok(())
+    }
+
+    /// Fails until the notes of the storage are loaded or the ones shown are kept
+    pub fn ensure_unlocked(self) -> Result<()> {
+        if self.is_locked() {
+            anyhow::bail!("Enter your secret first");
+        }
+        Ok(())
+    }
+}
+
+/// What loading from a storage found
+#[derive(Debug, PartialEq)]
+pub enum Loaded {
+    /// The encrypted notes
+    Notes(String),
+    /// No notes were saved there yet
+    Nothing,
+}
+
+/// The storage the notes are kept in, with its settings
 #[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Ord, PartialOrd, Clone)]
+#[serde(from = "StoredMode", into = "StoredMode")]
 pub enum StorageMode {
+    Local(LocalStorage),
+    JsonBin(JsonBinStorage),
+}
+
+/// How the storage settings are persisted, kept from before each storage had a type of its own
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename = "StorageMode")]
+enum StoredMode {
     Local {
         path: PathBuf,
     },
     JsonBin {
+        /// Older versions stored the key in plain text. It is still read, but no longer written.
+        #[serde(default, skip_serializing)]
         masterkey: String,
         bin_id: Option<String>,
+        #[serde(default)]
+        backup_path: Option<PathBuf>,
     },
 }
 
+impl From<StoredMode> for StorageMode {
+    fn from(stored: StoredMode) -> Self {
+        match stored {
+            StoredMode::Local { path } => StorageMode::Local(LocalStorage { path }),
+            StoredMode::JsonBin {
+                masterkey,
+                bin_id,
+                backup_path,
+            } => StorageMode::JsonBin(JsonBinStorage {
+                masterkey,
+                bin_id,
+                backup_path,
+            }),
+        }
+    }
+}
+
+impl From<StorageMode> for StoredMode {
+    fn from(mode: StorageMode) -> Self {
+        match mode {
+            StorageMode::Local(LocalStorage { path }) => StoredMode::Local { path },
+            StorageMode::JsonBin(JsonBinStorage {
+                masterkey,
+                bin_id,
+                backup_path,
+            }) => StoredMode::JsonBin {
+                masterkey,
+                bin_id,
+                backup_path,
+            },
+        }
+    }
+}
+
+/// A file on disk, or in the browser an entry of its local storage named after the path
+#[derive(PartialEq, Eq, Ord, PartialOrd, Clone)]
+pub struct LocalStorage {
+    pub path: PathBuf,
+}
+
+/// A bin on jsonbin.io
+#[derive(PartialEq, Eq, Ord, PartialOrd, Clone)]
+pub struct JsonBinStorage {
+    /// The JsonBin API key, entered in the settings or when unlocking. Only kept in memory.
+    pub masterkey: String,
+    pub bin_id: Option<String>,
+    /// A local file the notes are written to as well on every save, in case the upload fails
+    pub backup_path: Option<PathBuf>,
+}
+
 impl std::fmt::Debug for StorageMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            StorageMode::Local { path } => {
-                write!(f, "Local {}", path.to_string_lossy().to_string())
+            StorageMode::Local(LocalStorage { path }) if cfg!(target_arch = "wasm32") => {
+                write!(f, "Browser storage {}", path.to_string_lossy())
             }
-            StorageMode::JsonBin { .. } => write!(f, "JsonBin"),
+            StorageMode::Local(LocalStorage { path }) => {
+                write!(f, "Local {}", path.to_string_lossy())
+            }
+            StorageMode::JsonBin(_) => write!(f, "JsonBin"),
         }
     }
 }
 
 impl StorageMode {
+    pub fn backend(&self) -> &dyn StorageBackend {
+        match self {
+            StorageMode::Local(local) => local,
+            StorageMode::JsonBin(jsonbin) => jsonbin,
+        }
+    }
+
+    pub fn backend_mut(&mut self) -> &mut dyn StorageBackend {
+        match self {
+            StorageMode::Local(local) => local,
+            StorageMode::JsonBin(jsonbin) => jsonbin,
+        }
+    }
+
+    /// Encrypt and save the notes. Returns the encrypted notes as saved.
     pub fn save_userdata(
         &mut self,
         userdata: &UserData,
-        credentials: &(String, String),
+        credentials: &Credentials,
         channels: &Channels,
         manual_save: bool,
+    ) -> Result<String> {
+        debug!("Saving {} notes to {self:?}", userdata.notes.len());
+        let blob = encrypt_userdata(userdata, credentials)?;
+        self.backend_mut().save(&blob, channels, manual_save)?;
+        Ok(blob)
+    }
+
+    /// Like `save_userdata`, but for replacing notes that could not be loaded, so their backup
+    /// is kept.
+    pub fn replace_userdata(
+        &mut self,
+        userdata: &UserData,
+        credentials: &Credentials,
+        channels: &Channels,
+        manual_save: bool,
+    ) -> Result<String> {
+        debug!("Replacing the notes in {self:?}");
+        let blob = encrypt_userdata(userdata, credentials)?;
+        self.backend_mut().replace(&blob, channels, manual_save)?;
+        Ok(blob)
+    }
+
+    /// Start loading the encrypted notes, which arrive in `channels.loaded_channel`. A storage
+    /// nothing was saved to yet is not an error, it reports `Loaded::Nothing`.
+    pub fn load_userdata(&self, channels: &Channels) -> Result<()> {
+        debug!("Loading notes from {self:?}");
+        self.backend().load(channels)
+    }
+}
+
+impl Default for StorageMode {
+    fn default() -> Self {
+        StorageMode::Local(LocalStorage {
+            path: PathBuf::from("meteora.json"),
+        })
+    }
+}
+
+impl LocalStorage {
+    fn write(
+        &self,
+        blob: &str,
+        channels: &Channels,
+        manual_save: bool,
+        keep_backup: bool,
     ) -> Result<()> {
+        write_local(&self.path, blob, keep_backup)?;
+        _ = channels.saved_channel.0.send(());
+        if manual_save {
+            _ = channels
+                .msg_channel
+                .0
+                .send(Message::Info("Saved notes!".into()));
+        }
+        Ok(())
+    }
+}
+
+impl StorageBackend for LocalStorage {
+    fn save(&mut self, blob: &str, channels: &Channels, manual_save: bool) -> Result<()> {
+        self.write(blob, channels, manual_save, true)
+    }
+
+    fn replace(&mut self, blob: &str, channels: &Channels, manual_save: bool) -> Result<()> {
+        self.write(blob, channels, manual_save, false)
+    }
+
+    fn load(&self, channels: &Channels) -> Result<()> {
+        let loaded = match read_local(&self.path)? {
+            Some(blob) => Loaded::Notes(blob),
+            None => Loaded::Nothing,
+        };
+        _ = channels.loaded_channel.0.send(loaded);
+        Ok(())
+    }
+
+    // the browser storage replaces an entry at once, it needs no backup
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_backup(&self) -> Result<Option<String>> {
+        read_local(&backup_path(&self.path))
+    }
+}
+
+const JSONBIN_URL: &str = "https://api.jsonbin.io/v3/b";
+/// Lists the bins outside of collections, which needs a valid master key but changes nothing
+const JSONBIN_CHECK_URL: &str = "https://api.jsonbin.io/v3/c/uncategorized/bins";
+
+impl JsonBinStorage {
+    /// Check that JsonBin accepts the master key, without creating a bin. The outcome is sent to
+    /// `channels` as a message.
+    pub fn test_connection(&self, channels: &Channels) -> Result<()> {
+        ensure_masterkey(&self.masterkey)?;
+        let msg_sender = channels.msg_channel.0.clone();
+        let request = ehttp::Request {
+            method: "GET".into(),
+            url: JSONBIN_CHECK_URL.into(),
+            body: vec![],
+            headers: headers(&[("Accept", "*/*"), ("X-Master-Key", &self.masterkey)]),
+        };
+        ehttp::fetch(request, move |result: ehttp::Result<ehttp::Response>| {
+            let message = match connection_from_response(result) {
+                Ok(()) => Message::Info("Connected to JsonBin, the master key works.".into()),
+                Err(e) => Message::err(&format!("Could not connect to JsonBin: {e}")),
+            };
+            _ = msg_sender.send(message);
+        });
+        Ok(())
+    }
+}
+
+impl StorageBackend for JsonBinStorage {
+    fn save(&mut self, blob: &str, channels: &Channels, manual_save: bool) -> Result<()> {
+        ensure_masterkey(&self.masterkey)?;
         let id_sender = channels.id_channel.0.clone();
         let msg_sender = channels.msg_channel.0.clone();
-        match self {
-            StorageMode::Local { path } => {
-                #[cfg(not(target_arch = "wasm32"))]
-                if let Ok(enc) = encrypt_userdata(&userdata, credentials) {
-                    _ = write(path, enc);
+        let saved_sender = channels.saved_channel.0.clone();
+        // the backup is written even if the upload fails, so don't give up on errors. It is
+        // written like a local storage, so a failed write leaves the previous one intact.
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = &self.backup_path {
+            if let Err(e) = write_local(path, blob, true) {
+                _ = msg_sender.send(Message::err(&format!(
+                    "Could not write the local backup: {e}"
+                )));
+            }
+        }
+        // rewrite notes so we can encrypt them
+        let notes = json!({ "encrypted": blob });
+
+        // no bin configured, we need to ask for one
+        let Some(bin_id) = &self.bin_id else {
+            let request = ehttp::Request {
+                method: "POST".into(),
+                url: JSONBIN_URL.into(),
+                body: notes.to_string().into_bytes(),
+                headers: headers(&[
+                    ("Accept", "*/*"),
+                    ("Content-Type", "application/json; charset=utf-8"),
+                    ("X-Master-Key", &self.masterkey),
+                ]),
+            };
+            ehttp::fetch(request, move |result: ehttp::Result<ehttp::Response>| {
+                match id_from_response(result) {
+                    Ok(id) => {
+                        _ = id_sender.send(id);
+                        _ = saved_sender.send(());
+                        info!("Saved");
+                        if manual_save {
+                            _ = msg_sender.send(Message::Info("Saved notes!".into()));
+                        }
+                    }
+                    Err(e) => {
+                        _ = msg_sender.send(Message::err(&format!("Could not save notes: {e}")));
+                    }
+                }
+            });
+            return Ok(());
+        };
+
+        let request = ehttp::Request {
+            method: "PUT".into(),
+            url: format!("{JSONBIN_URL}/{bin_id}"),
+            body: notes.to_string().into_bytes(),
+            headers: headers(&[
+                ("Accept", "*/*"),
+                ("Content-Type", "application/json; charset=utf-8"),
+                ("X-Master-Key", &self.masterkey),
+            ]),
+        };
+        ehttp::fetch(
+            request,
+            move |result: ehttp::Result<ehttp::Response>| match check_response(result) {
+                Ok(_) => {
+                    _ = saved_sender.send(());
                     if manual_save {
                         _ = msg_sender.send(Message::Info("Saved notes!".into()));
                     }
                 }
-            }
-            StorageMode::JsonBin { masterkey, bin_id } => {
-                // rewrite notes so we can encrypt them
-                let notes = json!({
-                    "encrypted": encrypt_userdata(&userdata, credentials)?
-                });
-
-                let url = "https://api.jsonbin.io/v3/b";
-
-                // no bin configured, we need to ask for one
-                if bin_id.is_none() {
-                    let request = ehttp::Request {
-                        method: "POST".into(),
-                        url: url.into(),
-                        body: notes.to_string().into_bytes(),
-                        headers: headers(&[
-                            ("Accept", "*/*"),
-                            ("Content-Type", "application/json; charset=utf-8"),
-                            ("X-Master-Key", masterkey),
-                        ]),
-                    };
-                    ehttp::fetch(request, move |result: ehttp::Result<ehttp::Response>| {
-                        match id_from_response(result) {
-                            Ok(id) => {
-                                _ = id_sender.send(id);
-                                info!("Saved");
-                                if manual_save {
-                                    _ = msg_sender.send(Message::Info("Saved notes!".into()));
-                                }
-                            }
-                            Err(e) => {
-                                _ = msg_sender.send(Message::err(&e.to_string()));
-                            }
-                        }
-                    });
-                } else {
-                    // safe, since we checked if the Option is Some
-                    let bin_id = bin_id.clone().unwrap_or_default();
-                    // rewrite bin url with bin id
-                    let bin_url = format!("{url}/{bin_id}");
-
-                    let request = ehttp::Request {
-                        method: "PUT".into(),
-                        url: bin_url,
-                        body: notes.to_string().into_bytes(),
-                        headers: headers(&[
-                            ("Accept", "*/*"),
-                            ("Content-Type", "application/json; charset=utf-8"),
-                            ("X-Master-Key", masterkey),
-                        ]),
-                    };
-                    ehttp::fetch(
-                        request,
-                        move |result: ehttp::Result<ehttp::Response>| match result {
-                            Ok(_id) => {
-                                if manual_save {
-                                    _ = msg_sender.send(Message::Info("Saved notes!".into()));
-                                }
-                            }
-                            Err(e) => {
-                                _ = msg_sender.send(Message::err(&e.to_string()));
-                            }
-                        },
-                    );
+                Err(e) => {
+                    _ = msg_sender.send(Message::err(&format!("Could not save notes: {e}")));
                 }
-            }
-        }
+            },
+        );
         Ok(())
     }
 
-    pub fn load_userdata(&self, credentials: &(String, String), channels: &Channels) -> Result<()> {
-        let userdata_sender = channels.userdata_channel.0.clone();
+    fn load(&self, channels: &Channels) -> Result<()> {
+        ensure_masterkey(&self.masterkey)?;
+        let bin_id = self
+            .bin_id
+            .as_ref()
+            .context("Bin ID is needed for loading!")?;
+        let loaded_sender = channels.loaded_channel.0.clone();
         let msg_sender = channels.msg_channel.0.clone();
-        match self {
-            // Disk mode
-            StorageMode::Local { path } => {
-                #[cfg(not(target_arch = "wasm32"))]
-                {
-                    let decrypted_userdata = std::fs::read_to_string(path)?;
-                    let userdata = decrypt_notes(&decrypted_userdata, credentials)?;
-                    _ = msg_sender.send(Message::Info(format!(
-                        "Loaded {} notes",
-                        userdata.notes.len()
-                    )));
-                    _ = userdata_sender.send(userdata);
-                    Ok(())
+
+        let request = ehttp::Request {
+            method: "GET".into(),
+            url: format!("{JSONBIN_URL}/{bin_id}?meta=false"),
+            body: vec![],
+            headers: headers(&[
+                ("Accept", "*/*"),
+                ("Content-Type", "application/json; charset=utf-8"),
+                ("X-Master-Key", &self.masterkey),
+            ]),
+        };
+        ehttp::fetch(request, move |result: ehttp::Result<ehttp::Response>| {
+            match encrypted_from_response(result) {
+                Ok(blob) => {
+                    _ = loaded_sender.send(Loaded::Notes(blob));
                 }
-                #[cfg(target_arch = "wasm32")]
-                {
-                    // wasm should err here
-                    anyhow::bail!("Could not load notes")
+                Err(e) => {
+                    _ = msg_sender.send(Message::err(&format!("Could not load notes: {e}")));
                 }
             }
-            // JsonBin
-            StorageMode::JsonBin { masterkey, bin_id } => {
-                let url = "https://api.jsonbin.io/v3/b";
-                let bin_id = bin_id.clone().context("Bin ID is needed for loading!")?;
-                // rewrite bin url with bin id
-                let bin_url = format!("{url}/{bin_id}?meta=false");
-
-                let request = ehttp::Request {
-                    method: "GET".into(),
-                    url: bin_url,
-                    body: vec![],
-                    headers: headers(&[
-                        ("Accept", "*/*"),
-                        ("Content-Type", "application/json; charset=utf-8"),
-                        ("X-Master-Key", masterkey),
-                    ]),
-                };
-                // closure takes ownership, clone to move
-                let credentials = credentials.clone();
-                ehttp::fetch(request, move |result: ehttp::Result<ehttp::Response>| {
-                    match notes_from_response(result, &credentials) {
-                        Ok(userdata) => {
-                            // let n = decrypt_notes(&String::from_utf8_lossy(&resp.bytes), &credentials).unwrap();
-                            _ = msg_sender.send(Message::Info(format!(
-                                "Loaded {} notes",
-                                userdata.notes.len()
-                            )));
-                            _ = userdata_sender.send(userdata);
-                        }
-                        Err(e) => {
-                            _ = msg_sender.send(Message::err(&e.to_string()));
-                        }
-                    }
-                });
-                Ok(())
-            }
-        }
+        });
+        Ok(())
     }
 }
 
-impl Default for StorageMode {
-    fn default() -> Self {
-        StorageMode::Local {
-            path: PathBuf::from("meteora.json"),
-        }
+/// Where the previous version of the notes file at `path` is kept
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+/// `path` with `suffix` appended to the file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Write the notes to a file next to `path` first and then move it over `path`, so a crash
+/// while writing leaves the previous notes intact. With `keep_backup` they are kept as backup
+/// as well, otherwise the backup stays as it was.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_local(path: &Path, encrypted: &str, keep_backup: bool) -> Result<()> {
+    use std::io::Write;
+
+    let temp_path = with_suffix(path, ".tmp");
+    let mut temp = std::fs::File::create(&temp_path)
+        .with_context(|| format!("Could not create {}", temp_path.display()))?;
+    temp.write_all(encrypted.as_bytes())
+        .and_then(|_| temp.sync_all())
+        .context("Could not write notes to disk")?;
+    drop(temp);
+    if keep_backup && path.exists() {
+        std::fs::copy(path, backup_path(path)).context("Could not keep a backup of the notes")?;
     }
+    std::fs::rename(&temp_path, path).context("Could not write notes to disk")
+}
+
+/// The notes kept in the file at `path`, or `None` if there is no such file yet
+#[cfg(not(target_arch = "wasm32"))]
+fn read_local(path: &Path) -> Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(blob) => Ok(Some(blob)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Could not read notes from {}", path.display())),
+    }
+}
+
+/// The local storage of the browser, which keeps the notes for `LocalStorage`
+#[cfg(target_arch = "wasm32")]
+fn browser_storage() -> Result<web_sys::Storage> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .context("This browser does not let the page store data. Use JsonBin instead.")
+}
+
+/// The key the notes for `path` are kept under in the browser storage
+#[cfg(target_arch = "wasm32")]
+fn browser_storage_key(path: &Path) -> String {
+    format!("meteora/{}", path.to_string_lossy())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_local(path: &Path, encrypted: &str, _keep_backup: bool) -> Result<()> {
+    browser_storage()?
+        .set_item(&browser_storage_key(path), encrypted)
+        // the storage is full or disabled
+        .map_err(|e| anyhow!("The browser refused to store the notes: {e:?}"))
+}
+
+/// The notes kept in the browser under `path`, or `None` if none were saved there yet
+#[cfg(target_arch = "wasm32")]
+fn read_local(path: &Path) -> Result<Option<String>> {
+    browser_storage()?
+        .get_item(&browser_storage_key(path))
+        .map_err(|e| anyhow!("Could not read the browser storage: {e:?}"))
 }
 
-pub fn decrypt_notes(raw_notes: &str, credentials: &(String, String)) -> Result<UserData> {
+fn ensure_masterkey(masterkey: &str) -> Result<()> {
+    if masterkey.trim().is_empty() {
+        anyhow::bail!("No JsonBin master key configured. Enter it in the settings.")
+    }
+    Ok(())
+}
+
+pub fn decrypt_notes(raw_notes: &str, credentials: &Credentials) -> Result<UserData> {
     // encrypt using key
-    let mc = new_magic_crypt!(&credentials.1, 256);
-    let d = mc.decrypt_base64_to_string(raw_notes)?;
-    #[cfg(debug_assertions)]
-    dbg!("decrypted with ", credentials);
-    Ok(serde_json::from_str(&d)?)
+    let mc = new_magic_crypt!(credentials.secret.as_str(), 256);
+    let d = mc
+        .decrypt_base64_to_string(raw_notes)
+        .map_err(|_| anyhow!("Could not decrypt the notes. Is the secret correct?"))?;
+    userdata_from_json(&d)
 }
 
-pub fn encrypt_userdata(userdata: &UserData, credentials: &(String, String)) -> Result<String> {
+pub fn encrypt_userdata(userdata: &UserData, credentials: &Credentials) -> Result<String> {
     // encrypt using key
-    let mc = new_magic_crypt!(&credentials.1, 256);
+    let mc = new_magic_crypt!(credentials.secret.as_str(), 256);
     Ok(mc.encrypt_str_to_base64(serde_json::to_string(userdata)?))
 }
 
-fn id_from_response(result: ehttp::Result<ehttp::Response>) -> Result<String> {
-    let res = result.unwrap();
-    println!("res {}", res.status_text);
+/// The response of a JsonBin request if it succeeded, otherwise an error explaining what went
+/// wrong, with the reason JsonBin gives if there is one.
+fn check_response(result: ehttp::Result<ehttp::Response>) -> Result<ehttp::Response> {
+    let res = result.map_err(|e| anyhow!("Could not reach JsonBin: {e}"))?;
+    debug!("JsonBin answered {} {}", res.status, res.status_text);
+    if res.ok {
+        return Ok(res);
+    }
+    let problem = match res.status {
+        401 => "JsonBin did not accept the master key. Check it in the settings.".to_string(),
+        404 => {
+            "JsonBin has no such bin. It may have been deleted, or the bin id is wrong.".to_string()
+        }
+        413 => "The notes are too large for JsonBin. Try removing attachments.".to_string(),
+        429 => "JsonBin received too many requests. Try again later.".to_string(),
+        _ => format!("JsonBin answered {} {}", res.status, res.status_text),
+    };
+    // JsonBin errors look like {"message": "..."}
+    let reason = serde_json::from_slice::<serde_json::Value>(&res.bytes)
+        .ok()
+        .and_then(|body| body.get("message")?.as_str().map(str::to_string));
+    match reason {
+        Some(reason) => anyhow::bail!("{problem} ({reason})"),
+        None => anyhow::bail!("{problem}"),
+    }
+}
+
+/// Whether the response to testing the connection shows that the master key is accepted
+pub fn connection_from_response(result: ehttp::Result<ehttp::Response>) -> Result<()> {
+    check_response(result).map(|_| ())
+}
+
+/// The id of the bin JsonBin created, from the response to creating it
+pub fn id_from_response(result: ehttp::Result<ehttp::Response>) -> Result<String> {
+    let res = check_response(result)?;
 
     let val: serde_json::Value = serde_json::from_slice(res.bytes.as_slice())?;
 
@@ -227,23 +571,25 @@ fn id_from_response(result: ehttp::Result<ehttp::Response>) -> Result<String> {
     Ok(id.to_string())
 }
 
-fn notes_from_response(
-    result: ehttp::Result<ehttp::Response>,
-    credentials: &(String, String),
-) -> Result<UserData> {
-    let resp = result.map_err(|e| anyhow!(e))?;
-    // println!("res {}", res.status_text);
+/// The encrypted notes in the response to loading a bin
+pub fn encrypted_from_response(result: ehttp::Result<ehttp::Response>) -> Result<String> {
+    let resp = check_response(result)?;
 
     let n: serde_json::Value = serde_json::from_slice(&resp.bytes)?;
-    let decrypted_notes = decrypt_notes(
-        n.as_object()
-            .context("notes must be obj")?
-            .get("encrypted")
-            .context("There must be an 'encrypted' key")?
-            .as_str()
-            .context("The value must be string")?,
-        credentials,
-    )?;
-
-    Ok(decrypted_notes)
+    let encrypted = n
+        .as_object()
+        .context("notes must be obj")?
+        .get("encrypted")
+        .context("There must be an 'encrypted' key")?
+        .as_str()
+        .context("The value must be string")?;
+    Ok(encrypted.to_string())
+}
+
+/// The notes in the response to loading a bin, decrypted with `credentials`
+pub fn notes_from_response(
+    result: ehttp::Result<ehttp::Response>,
+    credentials: &Credentials,
+) -> Result<UserData> {
+    decrypt_notes(&encrypted_from_response(result)?, credentials)
 }
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::{app::Notes, backlinks, Note};
+
+/// Maximum number of changes kept for undo
+const MAX_UNDO: usize = 200;
+
+/// Seconds without further edits after which a text change is committed
+const TEXT_DEBOUNCE: f64 = 1.0;
+
+/// A single change to a note. `None` means the note did not exist (creation / deletion).
+#[derive(Clone, Default)]
+pub struct NoteChange {
+    pub id: u128,
+    pub before: Option<Note>,
+    pub after: Option<Note>,
+    /// For a deletion, the notes that depended on the deleted note
+    pub dependents: Vec<u128>,
+}
+
+/// The note currently being observed for changes
+struct WatchedNote {
+    /// State at the last commit
+    baseline: Note,
+    /// State in the last frame
+    last_seen: Note,
+    /// Time of the last observed change
+    changed_at: f64,
+}
+
+/// Application-level undo/redo of note mutations
+#[derive(Default)]
+pub struct UndoStack {
+    undo: VecDeque<NoteChange>,
+    redo: Vec<NoteChange>,
+    watched: Option<WatchedNote>,
+    /// Notes changed since the last call to `take_touched`
+    touched: Vec<u128>,
+}
+
+impl UndoStack {
+    /// Record a change. Any redo history is discarded.
+    pub fn push(&mut self, change: NoteChange) {
+        if change.before == change.after {
+            return;
+        }
+        self.redo.clear();
+        self.touched.push(change.id);
+        self.undo.push_back(change);
+        if self.undo.len() > MAX_UNDO {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Revert the last change. Returns the id of the affected note.
+    pub fn undo(&mut self, notes: &mut Notes) -> Option<u128> {
+        self.watched = None;
+        let change = self.undo.pop_back()?;
+        self.touched.push(change.id);
+        apply(notes, change.id, change.before.clone());
+        if change.after.is_none() {
+            for dependent in &change.dependents {
+                if let Some(note) = notes.get_mut(dependent) {
+                    note.depends.insert(change.id);
+                }
+            }
+        }
+        let id = change.id;
+        self.redo.push(change);
+        Some(id)
+    }
+
+    /// Re-apply the last undone change. Returns the id of the affected note.
+    pub fn redo(&mut self, notes: &mut Notes) -> Option<u128> {
+        self.watched = None;
+        let mut change = self.redo.pop()?;
+        self.touched.push(change.id);
+        if change.after.is_none() {
+            change.dependents = backlinks(change.id, notes);
+        }
+        apply(notes, change.id, change.after.clone());
+        let id = change.id;
+        self.undo.push_back(change);
+        Some(id)
+    }
+
+    /// Ids of the notes changed through the history since the last call, by recording, undoing
+    /// or redoing a change
+    pub fn take_touched(&mut self) -> Vec<u128> {
+        std::mem::take(&mut self.touched)
+    }
+
+    /// All versions of notes kept in the history, to be restored by undo or redo
+    pub fn notes(&self) -> impl Iterator<Item = &Note> {
+        self.undo
+            .iter()
+            .chain(&self.redo)
+            .flat_map(|c| c.before.iter().chain(&c.after))
+            .chain(self.watched.iter().map(|w| &w.baseline))
+    }
+
+    /// Observe the note being edited, called once per frame.
+    /// Text edits are committed once they settle, other changes right away.
+    /// Returns true while a text change is waiting to be committed.
+    pub fn watch(&mut self, note: Option<&Note>, now: f64) -> bool {
+        let same_note =
+            matches!((&self.watched, note), (Some(w), Some(n)) if w.baseline.id == n.id);
+        if !same_note {
+            self.flush();
+            self.watched = note.map(|n| WatchedNote {
+                baseline: n.clone(),
+                last_seen: n.clone(),
+                changed_at: now,
+            });
+            return false;
+        }
+
+        let (Some(watched), Some(note)) = (&mut self.watched, note) else {
+            return false;
+        };
+        if &watched.last_seen != note {
+            watched.last_seen = note.clone();
+            watched.changed_at = now;
+            let mut text_only = watched.baseline.clone();
+            text_only.text = note.text.clone();
+            if &text_only != note {
+                self.flush();
+            }
+        } else if watched.baseline != watched.last_seen && now - watched.changed_at > TEXT_DEBOUNCE
+        {
+            self.flush();
+        }
+        self.watched
+            .as_ref()
+            .is_some_and(|w| w.baseline != w.last_seen)
+    }
+
+    /// Commit any pending change of the watched note.
+    pub fn flush(&mut self) {
+        let Some(watched) = &mut self.watched else {
+            return;
+        };
+        if watched.baseline == watched.last_seen {
+            return;
+        }
+        let change = NoteChange {
+            id: watched.baseline.id,
+            before: Some(watched.baseline.clone()),
+            after: Some(watched.last_seen.clone()),
+            ..Default::default()
+        };
+        watched.baseline = watched.last_seen.clone();
+        self.push(change);
+    }
+}
+
+fn apply(notes: &mut Notes, id: u128, state: Option<Note>) {
+    match state {
+        Some(note) => {
+            notes.insert(id, note);
+        }
+        None => {
+            notes.remove(&id);
+            // like `UserData::delete_note`, don't leave dependencies on the removed note behind
+            for other in notes.values_mut() {
+                other.depends.remove(&id);
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use notify_debouncer_mini::{
+    new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+    DebounceEventResult, Debouncer,
+};
+
+/// Changes are reported once the file has been left alone for this long
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watches a file for changes by other programs, like an editor or a sync client.
+/// Watching stops when dropped.
+pub struct FileWatcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+    path: PathBuf,
+    changed: Receiver<()>,
+    /// Modification time of the file as last written or read by us
+    known_modified: Option<SystemTime>,
+}
+
+impl FileWatcher {
+    /// Watch the file at `path`. A change wakes up the UI of `ctx`.
+    pub fn watch(path: &Path, ctx: &egui::Context) -> Result<Self> {
+        let file_name = path
+            .file_name()
+            .with_context(|| format!("{} is not a file", path.display()))?
+            .to_owned();
+        // watch the directory, as files are often replaced instead of written to
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let (sender, changed) = channel();
+        let ctx = ctx.clone();
+        let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| {
+            let Ok(events) = result else {
+                return;
+            };
+            if events
+                .iter()
+                .any(|e| e.path.file_name() == Some(file_name.as_os_str()))
+            {
+                _ = sender.send(());
+                ctx.request_repaint();
+            }
+        })?;
+        debouncer
+            .watcher()
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Could not watch {}", dir.display()))?;
+
+        Ok(Self {
+            _debouncer: debouncer,
+            path: path.to_path_buf(),
+            changed,
+            known_modified: modified(path),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Take the file as it is now as known, after writing or reading it
+    pub fn mark_known(&mut self) {
+        self.known_modified = modified(&self.path);
+    }
+
+    /// Whether another program changed the file since the last call
+    pub fn changed(&mut self) -> bool {
+        if self.changed.try_iter().count() == 0 {
+            return false;
+        }
+        let current = modified(&self.path);
+        if current.is_none() || current == self.known_modified {
+            return false;
+        }
+        self.known_modified = current;
+        true
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
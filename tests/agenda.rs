@@ -0,0 +1,68 @@
+//! The agenda lists what is due on a day or overdue by then, the most urgent first.
+
+mod common;
+
+use common::{add, day};
+use meteora::*;
+
+fn due(deadline: Deadline, priority: f32) -> Note {
+    Note {
+        deadline,
+        priority,
+        ..Note::new()
+    }
+}
+
+#[test]
+fn due_and_overdue_notes() {
+    let mut notes = Notes::new();
+    let today = add(&mut notes, due(Deadline::Fixed(day(10)), 0.5));
+    let overdue = add(&mut notes, due(Deadline::Fixed(day(8)), 0.1));
+    let tomorrow = add(&mut notes, due(Deadline::Fixed(day(11)), 1.0));
+    let eternal = add(&mut notes, due(Deadline::Eternal, 1.0));
+    let agenda = agenda_for(day(10), &notes);
+    assert_eq!(agenda, vec![overdue, today]);
+    assert!(!agenda.contains(&tomorrow));
+    assert!(!agenda.contains(&eternal));
+
+    // the next day the agenda has rolled over
+    assert_eq!(agenda_for(day(11), &notes), vec![overdue, today, tomorrow]);
+}
+
+#[test]
+fn repeating_deadlines() {
+    let mut notes = Notes::new();
+    let every_third = add(
+        &mut notes,
+        due(
+            Deadline::Periodic {
+                start: day(1),
+                days: 3,
+            },
+            0.5,
+        ),
+    );
+    assert_eq!(agenda_for(day(7), &notes), vec![every_third]);
+    assert!(agenda_for(day(8), &notes).is_empty());
+}
+
+#[test]
+fn same_day_by_priority() {
+    let mut notes = Notes::new();
+    let low = add(&mut notes, due(Deadline::Fixed(day(10)), 0.1));
+    let high = add(&mut notes, due(Deadline::Fixed(day(10)), 0.9));
+    assert_eq!(agenda_for(day(10), &notes), vec![high, low]);
+}
+
+#[test]
+fn finished_and_snoozed_notes_are_left_out() {
+    let mut notes = Notes::new();
+    let mut finished = due(Deadline::Fixed(day(9)), 0.5);
+    finished.status = Status::Done;
+    add(&mut notes, finished);
+    let mut snoozed = due(Deadline::Fixed(day(9)), 0.5);
+    snoozed.snoozed_until = Some(day(12));
+    let snoozed = add(&mut notes, snoozed);
+    assert!(agenda_for(day(10), &notes).is_empty());
+    assert_eq!(agenda_for(day(12), &notes), vec![snoozed]);
+}
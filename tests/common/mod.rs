@@ -0,0 +1,53 @@
+//! Notes and dates the tests are built from. Each test uses only some of them.
+#![allow(dead_code)]
+
+use chrono::NaiveDate;
+use meteora::*;
+
+/// A day of March 2024
+pub fn day(d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 3, d).unwrap()
+}
+
+pub fn note(text: &str) -> Note {
+    Note {
+        text: text.to_string(),
+        ..Note::new()
+    }
+}
+
+pub fn tagged(tags: &[&str]) -> Note {
+    Note {
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        ..Note::new()
+    }
+}
+
+/// Insert `note` and return its id
+pub fn add(notes: &mut Notes, note: Note) -> u128 {
+    let id = note.id;
+    notes.insert(id, note);
+    id
+}
+
+/// Notes with ids `1..=count`, each depending on the ids given for it
+pub fn notes(count: u128, depends: &[(u128, &[u128])]) -> Notes {
+    let mut notes: Notes = (1..=count)
+        .map(|id| {
+            let note = Note {
+                id,
+                text: format!("Note {id}\nDetails of {id}"),
+                ..Note::new()
+            };
+            (id, note)
+        })
+        .collect();
+    for (id, dependencies) in depends {
+        notes
+            .get_mut(id)
+            .unwrap()
+            .depends
+            .extend(dependencies.iter().copied());
+    }
+    notes
+}
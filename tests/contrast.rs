@@ -0,0 +1,35 @@
+//! Card text is black-ish or white-ish, whichever contrasts more with the card, as measured by
+//! the WCAG contrast ratio.
+
+use egui::Color32;
+use meteora::*;
+
+#[test]
+fn ratio_ranges_from_1_to_21() {
+    assert!((contrast_ratio(Color32::BLACK, Color32::WHITE) - 21.).abs() < 0.01);
+    // the order of the colors doesn't matter
+    assert_eq!(
+        contrast_ratio(Color32::WHITE, Color32::BLACK),
+        contrast_ratio(Color32::BLACK, Color32::WHITE)
+    );
+    for color in [
+        Color32::BLACK,
+        Color32::WHITE,
+        Color32::from_rgb(200, 30, 90),
+    ] {
+        assert!((contrast_ratio(color, color) - 1.).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn tag_colors_of_all_palettes_get_readable_text() {
+    for palette in TagPalette::ALL {
+        set_tag_palette(palette);
+        for i in 0..500 {
+            let color = color_from_tag(&format!("tag {i}"));
+            let ratio = contrast_ratio(color, readable_text(&color));
+            assert!(ratio >= MIN_CONTRAST, "{palette:?}: {color:?} has {ratio}");
+        }
+    }
+    set_tag_palette(TagPalette::Default);
+}
@@ -0,0 +1,71 @@
+//! A note is blocked while it depends on incomplete notes. Dependencies must not form a cycle.
+
+mod common;
+
+use common::{day, notes};
+use meteora::*;
+
+#[test]
+fn blocked_by_incomplete_dependencies_only() {
+    let mut notes = notes(3, &[(3, &[1, 2])]);
+    assert!(is_blocked(&notes[&3], &notes));
+    assert!(!is_blocked(&notes[&1], &notes));
+
+    notes.get_mut(&1).unwrap().set_status(Status::Done, day(1));
+    assert!(is_blocked(&notes[&3], &notes));
+    notes.get_mut(&2).unwrap().set_status(Status::Done, day(1));
+    assert!(!is_blocked(&notes[&3], &notes));
+}
+
+#[test]
+fn missing_dependencies_do_not_block() {
+    let notes = notes(1, &[(1, &[7])]);
+    assert!(!is_blocked(&notes[&1], &notes));
+}
+
+#[test]
+fn direct_cycle() {
+    let notes = notes(2, &[(2, &[1])]);
+    assert!(would_create_cycle(&notes, 1, 2));
+    assert!(!would_create_cycle(&notes, 2, 1));
+}
+
+#[test]
+fn transitive_cycle() {
+    let notes = notes(4, &[(2, &[1]), (3, &[2])]);
+    assert!(would_create_cycle(&notes, 1, 3));
+    // 4 is not part of the chain
+    assert!(!would_create_cycle(&notes, 1, 4));
+    assert!(!would_create_cycle(&notes, 4, 3));
+}
+
+#[test]
+fn self_dependency_is_a_cycle() {
+    let notes = notes(1, &[]);
+    assert!(would_create_cycle(&notes, 1, 1));
+}
+
+#[test]
+fn completed_blockers_still_count_for_cycles() {
+    let mut notes = notes(2, &[(2, &[1])]);
+    notes.get_mut(&1).unwrap().set_status(Status::Done, day(1));
+    assert!(!is_blocked(&notes[&2], &notes));
+    assert!(would_create_cycle(&notes, 1, 2));
+}
+
+#[test]
+fn adding_a_dependency_explains_refusals() {
+    let mut userdata = UserData {
+        notes: notes(2, &[(2, &[1])]),
+        ..Default::default()
+    };
+    let itself = userdata.add_dependency(1, 1).unwrap_err();
+    assert_eq!(itself.to_string(), "A note can't depend on itself");
+    let cycle = userdata.add_dependency(1, 2).unwrap_err();
+    assert!(
+        cycle.to_string().contains("already depends on this note"),
+        "{cycle}"
+    );
+    assert!(userdata.add_dependency(1, 7).is_err());
+    assert!(userdata.notes[&1].depends.is_empty());
+}
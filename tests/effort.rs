@@ -0,0 +1,71 @@
+//! Efforts are entered as short texts like "2h" and summed up against the weekly capacity.
+
+use chrono::{Days, NaiveDate};
+use meteora::*;
+
+#[test]
+fn parse_units() {
+    assert_eq!(parse_effort("90m").unwrap(), 1.5);
+    assert_eq!(parse_effort("2h").unwrap(), 2.);
+    assert_eq!(parse_effort("1.5d").unwrap(), 12.);
+    assert_eq!(parse_effort(" 1h 30m ").unwrap(), 1.5);
+    assert_eq!(parse_effort("2 Hours").unwrap(), 2.);
+    assert_eq!(parse_effort("0,5d").unwrap(), 4.);
+    // no unit means hours
+    assert_eq!(parse_effort("3").unwrap(), 3.);
+    assert_eq!(parse_effort("").unwrap(), 0.);
+}
+
+#[test]
+fn parse_errors() {
+    for text in ["2x", "h", "1.2.3h", "-1h", "2h 30", "soon", "1e3", "inf"] {
+        assert!(parse_effort(text).is_err(), "{text}");
+    }
+}
+
+#[test]
+fn format_round_trip() {
+    assert_eq!(format_effort(0.), "");
+    assert_eq!(format_effort(0.75), "45m");
+    assert_eq!(format_effort(2.), "2h");
+    assert_eq!(format_effort(1.5), "1.5h");
+    assert_eq!(format_effort(12.), "1.5d");
+    for hours in [0.25, 1., 2.5, 8., 20.] {
+        assert_eq!(parse_effort(&format_effort(hours)).unwrap(), hours);
+    }
+}
+
+#[test]
+fn due_this_week() {
+    let today = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+    let mut userdata = UserData::default();
+    let mut add = |deadline: Deadline, effort_hours: f32, progress: f32, complete: bool| {
+        userdata.add_note(Note {
+            deadline,
+            effort_hours,
+            progress,
+            status: if complete {
+                Status::Done
+            } else {
+                Status::Active
+            },
+            ..Note::new()
+        });
+    };
+    let in_days = |days| Deadline::Fixed(today.checked_add_days(Days::new(days)).unwrap());
+    // overdue
+    add(
+        Deadline::Fixed(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()),
+        2.,
+        0.,
+        false,
+    );
+    // half done
+    add(in_days(0), 4., 0.5, false);
+    add(in_days(7), 3., 0., false);
+    // not counted: later, without deadline, finished
+    add(in_days(8), 5., 0., false);
+    add(Deadline::Eternal, 5., 0., false);
+    add(in_days(1), 5., 0., true);
+    assert_eq!(userdata.effort_due_this_week(today), 7.);
+}
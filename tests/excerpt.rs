@@ -0,0 +1,52 @@
+//! Cards show the note text without markdown syntax, cut off after a number of chars.
+
+mod common;
+
+use common::note;
+
+#[test]
+fn single_line_note_has_no_excerpt() {
+    assert_eq!(note("Just a title").get_excerpt(), "(no additional text)");
+    assert_eq!(note("Title\n\n  \n").get_excerpt(), "(no additional text)");
+}
+
+#[test]
+fn markdown_is_stripped() {
+    let note = note("Title\n## Heading\n> a   quote\n1. first step\n* [a link](https://x.com)");
+    assert_eq!(note.get_excerpt(), "Heading a quote first step a link");
+}
+
+#[test]
+fn checklist_is_stripped() {
+    let note = note("Shopping\n- [ ] milk\n- [x] eggs\n- [X] bread");
+    assert_eq!(note.get_excerpt(), "milk eggs bread");
+}
+
+#[test]
+fn truncated_at_chars() {
+    let greeting = note("# Grüße aus Köln");
+    assert_eq!(greeting.get_clean_text_truncated(4), "Grüß...");
+    assert_eq!(greeting.get_clean_text_truncated(16), "Grüße aus Köln\n");
+
+    let party = note("🎉🎉🎉 party");
+    assert_eq!(party.get_clean_text_truncated(2), "🎉🎉...");
+}
+
+#[test]
+fn only_urls_are_removed() {
+    let proxy = note("Proxy\nthe http protocol, https-everywhere and https://x.com");
+    assert_eq!(
+        proxy.get_clean_text(),
+        "Proxy\nthe http protocol, https-everywhere and\n"
+    );
+    let links: Vec<&str> = proxy.get_links().iter().map(|l| l.url).collect();
+    assert_eq!(links, ["https://x.com"]);
+
+    // no scheme, no link
+    let docs = note("Docs\nsee http:/x.com and www.x.com");
+    assert_eq!(
+        docs.get_clean_text(),
+        "Docs\nsee http:/x.com and www.x.com\n"
+    );
+    assert!(docs.get_links().is_empty());
+}
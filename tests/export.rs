@@ -0,0 +1,140 @@
+//! An exported subtree must contain the note, everything it depends on and the edges between
+//! them, and nothing else.
+
+mod common;
+
+use common::notes;
+use meteora::*;
+
+fn ids(bundle: &NoteBundle) -> Vec<u128> {
+    bundle.notes.iter().map(|n| n.id).collect()
+}
+
+#[test]
+fn subtree_only() {
+    // 5 depends on 1, but 1 doesn't depend on 5
+    let notes = notes(5, &[(1, &[2, 3]), (3, &[4]), (5, &[1])]);
+    let bundle = NoteBundle::new(1, &notes).unwrap();
+    assert_eq!(bundle.root, 1);
+    assert_eq!(ids(&bundle), vec![1, 2, 3, 4]);
+    assert_eq!(bundle.dependencies, vec![(1, 2), (1, 3), (3, 4)]);
+
+    assert!(NoteBundle::new(6, &notes).is_none());
+}
+
+#[test]
+fn cycles_and_shared_dependencies() {
+    let notes = notes(4, &[(1, &[2, 3]), (2, &[4]), (3, &[4]), (4, &[1])]);
+    let bundle = NoteBundle::new(1, &notes).unwrap();
+    assert_eq!(ids(&bundle), vec![1, 2, 3, 4]);
+    assert_eq!(
+        bundle.dependencies,
+        vec![(1, 2), (1, 3), (2, 4), (3, 4), (4, 1)]
+    );
+}
+
+#[test]
+fn missing_dependencies_are_left_out() {
+    let notes = notes(2, &[(1, &[2, 99])]);
+    let bundle = NoteBundle::new(1, &notes).unwrap();
+    assert_eq!(ids(&bundle), vec![1, 2]);
+    assert_eq!(bundle.dependencies, vec![(1, 2)]);
+}
+
+#[test]
+fn json_round_trip() {
+    let notes = notes(3, &[(1, &[2]), (2, &[3])]);
+    let bundle = NoteBundle::new(1, &notes).unwrap();
+    let json = bundle.to_json().unwrap();
+    let imported: NoteBundle = serde_json::from_str(&json).unwrap();
+    assert!(imported == bundle);
+}
+
+#[test]
+fn markdown_links_dependencies() {
+    let notes = notes(3, &[(1, &[2, 3])]);
+    let markdown = NoteBundle::new(1, &notes).unwrap().to_markdown();
+    assert!(markdown.starts_with("# Note 1\n"), "{markdown}");
+    assert!(
+        markdown.contains("## Note 2\n\nDetails of 2\n"),
+        "{markdown}"
+    );
+    assert!(
+        markdown.contains("Depends on [[Note 2]], [[Note 3]]"),
+        "{markdown}"
+    );
+}
+
+#[test]
+fn file_name_from_title() {
+    let mut notes = notes(1, &[]);
+    notes.get_mut(&1).unwrap().text = "Launch: v2/beta\nbody".to_string();
+    let bundle = NoteBundle::new(1, &notes).unwrap();
+    assert_eq!(bundle.file_name("json"), "Launch__v2_beta.json");
+}
+
+#[test]
+fn import_remaps_ids() {
+    let shared = notes(3, &[(1, &[2, 3]), (2, &[3])]);
+    let bundle = NoteBundle::new(1, &shared).unwrap();
+
+    // the receiver already has notes with the same ids, even the same notes
+    let mut userdata = UserData {
+        notes: shared.clone(),
+        ..Default::default()
+    };
+    let import = userdata.import_bundle(&bundle);
+    assert_eq!(import.notes.len(), 3);
+    assert_eq!(import.dependencies, 3);
+    assert_eq!(userdata.notes.len(), 6);
+    assert!(userdata.notes.iter().all(|(id, n)| *id == n.id));
+    assert!(shared.iter().all(|(id, n)| userdata.notes[id] == *n));
+    assert!(import.notes.iter().all(|id| !shared.contains_key(id)));
+
+    let [root, two, three] = import.notes[..] else {
+        panic!("expected 3 notes");
+    };
+    assert_eq!(import.root, Some(root));
+    assert_eq!(userdata.notes[&root].get_title(), "Note 1");
+    assert_eq!(
+        userdata.notes[&root].depends,
+        [two, three].into_iter().collect()
+    );
+    assert_eq!(userdata.notes[&two].depends, [three].into_iter().collect());
+    assert!(userdata.notes[&three].depends.is_empty());
+
+    // importing again adds another copy
+    let again = userdata.import_bundle(&bundle);
+    assert!(again.notes.iter().all(|id| !import.notes.contains(id)));
+    assert_eq!(userdata.notes.len(), 9);
+}
+
+#[test]
+fn attachments_travel_with_the_bundle() {
+    let mut userdata = UserData {
+        notes: notes(3, &[(1, &[2])]),
+        ..Default::default()
+    };
+    for id in [2, 3] {
+        let note = userdata.notes.get_mut(&id).unwrap();
+        let data = format!("contents of {id}").into_bytes();
+        userdata.attachments.attach(note, "file.txt", data).unwrap();
+    }
+    let bundle = NoteBundle::new(1, &userdata.notes)
+        .unwrap()
+        .with_attachments(&userdata.attachments);
+    assert_eq!(bundle.attachments.0.len(), 1);
+
+    let mut receiver = UserData::default();
+    let bundle = NoteBundle::from_json(&bundle.to_json().unwrap()).unwrap();
+    let import = receiver.import_bundle(&bundle);
+    let two = &receiver.notes[&import.notes[1]];
+    let data = receiver.attachments.get(&two.attachments[0]).unwrap();
+    assert_eq!(&*data.0, b"contents of 2");
+}
+
+#[test]
+fn import_rejects_other_json() {
+    assert!(NoteBundle::from_json(r#"{"notes": "none"}"#).is_err());
+    assert!(NoteBundle::from_json("not json").is_err());
+}
@@ -0,0 +1,234 @@
+//! The stored data format: notes must survive a round trip through storage, and data saved by
+//! earlier releases must stay readable.
+
+use chrono::NaiveDate;
+use meteora::*;
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+
+/// Notes saved by the release before `format_version` was recorded, see `fixture_credentials`
+const FIXTURE_V0: &str = include_str!("fixtures/notes-v0.json");
+
+/// Number of random cases per generated test
+const CASES: u64 = 200;
+
+fn fixture_credentials() -> Credentials {
+    let mut credentials = Credentials::default();
+    *credentials.secret = "fixture secret".to_string();
+    credentials
+}
+
+/// Text mixing markdown, escapes, non-ascii and control characters
+fn random_text(rng: &mut impl Rng) -> String {
+    const PIECES: [&str; 12] = [
+        "plain",
+        " ",
+        "\n",
+        "- [ ] todo",
+        "- [x] done",
+        "\"quoted\"",
+        "\\",
+        "\t",
+        "ünïcödé",
+        "🌠",
+        "\u{0}",
+        "https://example.com/?a=1&b=2",
+    ];
+    (0..rng.gen_range(0..20))
+        .map(|_| *PIECES.choose(rng).unwrap())
+        .collect()
+}
+
+fn random_date(rng: &mut impl Rng) -> NaiveDate {
+    NaiveDate::from_num_days_from_ce_opt(rng.gen_range(700_000..800_000)).unwrap()
+}
+
+/// Floats serde_json can represent, which rules out NaN and infinities
+fn random_f32(rng: &mut impl Rng) -> f32 {
+    match rng.gen_range(0..4) {
+        0 => 0.,
+        1 => f32::MIN_POSITIVE,
+        _ => rng.gen_range(-1e6..1e6),
+    }
+}
+
+/// A note with random contents, its attachments stored in `attachments`
+fn random_note(rng: &mut impl Rng, attachments: &mut Attachments) -> Note {
+    let mut note = Note {
+        text: random_text(rng),
+        tags: (0..rng.gen_range(0..4)).map(|_| random_text(rng)).collect(),
+        id: rng.gen(),
+        depends: (0..rng.gen_range(0..4)).map(|_| rng.gen()).collect(),
+        color: rng.gen(),
+        progress: random_f32(rng),
+        priority: random_f32(rng),
+        deadline: match rng.gen_range(0..3) {
+            0 => Deadline::Eternal,
+            1 => Deadline::Periodic {
+                start: random_date(rng),
+                days: rng.gen(),
+            },
+            _ => Deadline::Fixed(random_date(rng)),
+        },
+        status: Status::ALL[rng.gen_range(0..Status::ALL.len())],
+        waiting_on: random_text(rng),
+        legacy_complete: false,
+        completed_on: rng.gen_bool(0.5).then(|| random_date(rng)),
+        created: random_date(rng),
+        pinned: rng.gen(),
+        attachments: vec![],
+        file_links: (0..rng.gen_range(0..3))
+            .map(|_| FileLink {
+                path: random_text(rng).into(),
+                host: random_text(rng),
+            })
+            .collect(),
+        effort_hours: random_f32(rng),
+        primary_tag: rng.gen_bool(0.5).then(|| random_text(rng)),
+        history: (0..rng.gen_range(0..3))
+            .map(|_| (random_date(rng), random_text(rng)))
+            .collect(),
+        work_log: (0..rng.gen_range(0..3))
+            .map(|_| {
+                let start = random_date(rng)
+                    .and_hms_opt(rng.gen_range(0..24), 0, 0)
+                    .unwrap();
+                let end = start + chrono::TimeDelta::minutes(rng.gen_range(0..2000));
+                (start.and_utc(), end.and_utc())
+            })
+            .collect(),
+        snoozed_until: rng.gen_bool(0.5).then(|| random_date(rng)),
+    };
+    for _ in 0..rng.gen_range(0..3) {
+        let data = (0..rng.gen_range(0..64)).map(|_| rng.gen()).collect();
+        let name = format!("{}.png", random_text(rng));
+        attachments.attach(&mut note, &name, data).unwrap();
+    }
+    note
+}
+
+#[test]
+fn userdata_json_round_trip() {
+    for seed in 0..10 {
+        let userdata = UserData::sample(seed);
+        let json = serde_json::to_string(&userdata).unwrap();
+        assert!(
+            userdata_from_json(&json).unwrap() == userdata,
+            "sample {seed} changed in the round trip"
+        );
+    }
+}
+
+#[test]
+fn random_notes_survive_storage() {
+    let credentials = fixture_credentials();
+    for seed in 0..CASES {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let mut userdata = UserData::default();
+        for _ in 0..rng.gen_range(0..5) {
+            let note = random_note(&mut rng, &mut userdata.attachments);
+            userdata.notes.insert(note.id, note);
+        }
+        let encrypted = encrypt_userdata(&userdata, &credentials).unwrap();
+        let decrypted = decrypt_notes(&encrypted, &credentials).unwrap();
+        assert!(
+            decrypted == userdata,
+            "case {seed} changed in storage: {}",
+            serde_json::to_string(&userdata).unwrap()
+        );
+    }
+}
+
+#[test]
+fn fixture_from_v0_still_loads() {
+    let userdata = decrypt_notes(FIXTURE_V0, &fixture_credentials()).unwrap();
+    assert_eq!(userdata.format_version, FORMAT_VERSION);
+    assert_eq!(userdata.notes.len(), 10);
+    assert_eq!(userdata.tags.len(), 4);
+    assert_eq!(userdata.logbook.len(), 3);
+    // completion became a status
+    assert_eq!(
+        userdata
+            .notes
+            .values()
+            .filter(|n| n.status == Status::Done)
+            .count(),
+        3
+    );
+    assert!(userdata.notes.values().all(|n| n.history.is_empty()));
+    assert!(userdata.notes[&1700000000000001]
+        .text
+        .starts_with("Renew passport"));
+    let scratchpad = serde_json::to_value(&userdata.scratchpad).unwrap();
+    assert_eq!(scratchpad["items"].as_array().unwrap().len(), 2);
+    // v0 kept the scratchpad as plain strings
+    assert_eq!(scratchpad["items"][0]["title"], "Ideas");
+    assert_eq!(scratchpad["items"][0]["text"], "- a garden bench");
+    assert_eq!(scratchpad["items"][1]["title"], "Phone numbers");
+}
+
+#[test]
+fn fixture_needs_the_secret() {
+    let mut credentials = fixture_credentials();
+    *credentials.secret = "wrong".to_string();
+    assert!(decrypt_notes(FIXTURE_V0, &credentials).is_err());
+}
+
+#[test]
+fn legacy_scratchpad_is_migrated() {
+    let userdata = userdata_from_json(r#"{"scratchpad": {"sections": ["Title\nbody"]}}"#).unwrap();
+    assert_eq!(userdata.format_version, FORMAT_VERSION);
+    let scratchpad = serde_json::to_value(&userdata.scratchpad).unwrap();
+    assert_eq!(scratchpad["items"][0]["title"], "Title");
+    assert_eq!(scratchpad["items"][0]["text"], "body");
+}
+
+#[test]
+fn attachments_move_out_of_the_notes() {
+    // "aGVsbG8=" is "hello"
+    let json = r#"{
+        "format_version": 2,
+        "notes": {"1": {"id": 1, "text": "Trip", "attachments": [
+            {"name": "ticket.png", "mime": "image/png", "data": "aGVsbG8="},
+            {"name": "empty.txt", "mime": "text/plain", "data": ""}
+        ]}}
+    }"#;
+    let userdata = userdata_from_json(json).unwrap();
+    assert_eq!(userdata.format_version, FORMAT_VERSION);
+    let note = &userdata.notes[&1];
+    assert_eq!(note.attachments.len(), 2);
+    assert_ne!(note.attachments[0].id, note.attachments[1].id);
+    assert_eq!(
+        &*userdata.attachments.get(&note.attachments[0]).unwrap().0,
+        b"hello"
+    );
+    assert_eq!(userdata.attachments.size_of(note), 5);
+
+    // the contents are only written to the table
+    let saved = serde_json::to_string(&userdata).unwrap();
+    assert_eq!(saved.matches("aGVsbG8=").count(), 1, "{saved}");
+    assert!(!saved.contains("\"data\""), "{saved}");
+}
+
+#[test]
+fn newer_format_is_refused() {
+    let newer = FORMAT_VERSION + 1;
+    // a future version may change anything, the version must be checked before parsing the rest
+    let json = format!(r#"{{"format_version": {newer}, "notes": "changed beyond recognition"}}"#);
+    let message = userdata_from_json(&json).err().unwrap().to_string();
+    assert!(message.contains("newer version"), "{message}");
+
+    let encrypted = encrypt_userdata(
+        &UserData {
+            format_version: newer,
+            ..Default::default()
+        },
+        &fixture_credentials(),
+    )
+    .unwrap();
+    let message = decrypt_notes(&encrypted, &fixture_credentials())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(message.contains("newer version"), "{message}");
+}
@@ -0,0 +1,160 @@
+//! The editor toolbar wraps or prefixes the selection with markdown, and takes it off again.
+//! Positions count characters and the selection follows the text it was on.
+
+use meteora::*;
+use std::ops::Range;
+
+/// Apply `format` to a text with the selection written as `[` and `]`, or `|` for a cursor,
+/// and write the result the same way
+fn formatted(marked: &str, format: Format) -> String {
+    let (mut text, selection) = unmark(marked);
+    let selection = apply_format(&mut text, selection, format);
+    mark(&text, selection)
+}
+
+fn unmark(marked: &str) -> (String, Range<usize>) {
+    let chars: Vec<char> = marked.chars().collect();
+    if let Some(cursor) = chars.iter().position(|c| *c == '|') {
+        return (marked.replacen('|', "", 1), cursor..cursor);
+    }
+    let start = chars.iter().position(|c| *c == '[').unwrap();
+    let end = chars.iter().position(|c| *c == ']').unwrap() - 1;
+    (marked.replacen('[', "", 1).replacen(']', "", 1), start..end)
+}
+
+fn mark(text: &str, selection: Range<usize>) -> String {
+    let mut chars: Vec<char> = text.chars().collect();
+    if selection.is_empty() {
+        chars.insert(selection.start, '|');
+    } else {
+        chars.insert(selection.end, ']');
+        chars.insert(selection.start, '[');
+    }
+    chars.into_iter().collect()
+}
+
+#[test]
+fn wrapping_the_selection() {
+    assert_eq!(formatted("a [word] b", Format::Bold), "a **[word]** b");
+    assert_eq!(formatted("a [word] b", Format::Italic), "a *[word]* b");
+    assert_eq!(formatted("a [word] b", Format::Code), "a `[word]` b");
+    assert_eq!(formatted("a | b", Format::Bold), "a **|** b");
+    // characters outside of ASCII count once
+    assert_eq!(formatted("ünï [cödé]", Format::Bold), "ünï **[cödé]**");
+}
+
+#[test]
+fn wrapping_again_takes_it_off() {
+    assert_eq!(formatted("a **[word]** b", Format::Bold), "a [word] b");
+    assert_eq!(formatted("a [**word**] b", Format::Bold), "a [word] b");
+    assert_eq!(formatted("a *[word]* b", Format::Italic), "a [word] b");
+    assert_eq!(formatted("a **|** b", Format::Bold), "a | b");
+    // bold is not italic
+    assert_eq!(
+        formatted("a **[word]** b", Format::Italic),
+        "a ***[word]*** b"
+    );
+    assert_eq!(
+        formatted("a ***[word]*** b", Format::Italic),
+        "a **[word]** b"
+    );
+    assert_eq!(formatted("a *[word]* b", Format::Bold), "a ***[word]*** b");
+}
+
+#[test]
+fn code_over_lines_is_fenced() {
+    assert_eq!(
+        formatted("[let a;\nlet b;]", Format::Code),
+        "```\n[let a;\nlet b;]\n```"
+    );
+    assert_eq!(
+        formatted("```\n[let a;\nlet b;]\n```", Format::Code),
+        "[let a;\nlet b;]"
+    );
+}
+
+#[test]
+fn links() {
+    assert_eq!(formatted("see [docs]", Format::Link), "see [docs](|)");
+    assert_eq!(formatted("see |", Format::Link), "see [|]()");
+    assert_eq!(
+        formatted("see [https://example.com]", Format::Link),
+        "see [|](https://example.com)"
+    );
+}
+
+#[test]
+fn line_prefixes() {
+    assert_eq!(formatted("Title|", Format::Heading), "# Title|");
+    assert_eq!(formatted("# Ti|tle", Format::Heading), "Ti|tle");
+    assert_eq!(formatted("## Title|", Format::Heading), "Title|");
+    assert_eq!(formatted("|", Format::BulletList), "- |");
+    assert_eq!(
+        formatted("intro\n|milk", Format::Checkbox),
+        "intro\n- [ ] |milk"
+    );
+    assert_eq!(
+        formatted("[milk\n\neggs]\nbread", Format::BulletList),
+        "- [milk\n\n- eggs]\nbread"
+    );
+    // a selection ending at the start of a line leaves it out
+    assert_eq!(
+        formatted("[milk\n]eggs", Format::BulletList),
+        "- [milk\n]eggs"
+    );
+}
+
+#[test]
+fn list_markers_are_replaced_and_taken_off() {
+    assert_eq!(formatted("- milk|", Format::Checkbox), "- [ ] milk|");
+    assert_eq!(formatted("  - [x] milk|", Format::BulletList), "  - milk|");
+    assert_eq!(formatted("1. milk|", Format::BulletList), "- milk|");
+    assert_eq!(
+        formatted("[- milk\n* eggs]", Format::BulletList),
+        "[milk\neggs]"
+    );
+    assert_eq!(formatted("- [x] mi|lk", Format::Checkbox), "mi|lk");
+    // the cursor inside a marker that is taken off goes to the start of the line
+    assert_eq!(formatted("-| milk", Format::BulletList), "|milk");
+    // some lines still lack it
+    assert_eq!(
+        formatted("[- milk\neggs]", Format::BulletList),
+        "[- milk\n- eggs]"
+    );
+}
+
+fn entered(marked: &str) -> Option<String> {
+    let (mut text, cursor) = unmark(marked);
+    let cursor = continue_list(&mut text, cursor.start)?;
+    Some(mark(&text, cursor..cursor))
+}
+
+#[test]
+fn enter_continues_lists() {
+    assert_eq!(entered("- milk\n|").as_deref(), Some("- milk\n- |"));
+    assert_eq!(
+        entered("  - [x] milk\n|").as_deref(),
+        Some("  - [x] milk\n  - [ ] |")
+    );
+    assert_eq!(entered("9. milk\n|").as_deref(), Some("9. milk\n10. |"));
+    // the rest of the line moves into the new item
+    assert_eq!(entered("- mi\n|lk").as_deref(), Some("- mi\n- |lk"));
+    assert_eq!(entered("milk\n|"), None);
+    assert_eq!(entered("- milk|"), None);
+}
+
+#[test]
+fn enter_on_an_empty_item_ends_the_list() {
+    assert_eq!(entered("- milk\n- \n|").as_deref(), Some("- milk\n|"));
+    assert_eq!(entered("- [ ] \n|\nrest").as_deref(), Some("|\nrest"));
+}
+
+#[test]
+fn headings_need_a_space() {
+    assert_eq!(heading_marker("# Title"), Some("# "));
+    assert_eq!(heading_marker("### Agenda"), Some("### "));
+    // tags and too many hashes are no headings
+    assert_eq!(heading_marker("#work"), None);
+    assert_eq!(heading_marker("####### Seven"), None);
+    assert_eq!(heading_marker("#"), None);
+}
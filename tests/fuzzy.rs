@@ -0,0 +1,33 @@
+//! The quick switcher and the command palette rank titles with `fuzzy_match`.
+
+use meteora::*;
+
+#[test]
+fn subsequence_positions() {
+    let matched = fuzzy_match("gcr", "Grocery list").unwrap();
+    assert_eq!(matched.positions, vec![0, 3, 5]);
+}
+
+#[test]
+fn ignores_case_and_spaces() {
+    let matched = fuzzy_match("SHOP list", "shopping list").unwrap();
+    assert_eq!(matched.positions, vec![0, 1, 2, 3, 9, 10, 11, 12]);
+    assert!(fuzzy_match("", "anything").is_some());
+}
+
+#[test]
+fn order_matters() {
+    assert!(fuzzy_match("tac", "cat").is_none());
+    assert!(fuzzy_match("cats", "cat").is_none());
+}
+
+#[test]
+fn ranking() {
+    let score = |query, text| fuzzy_match(query, text).unwrap().score;
+    // a run of characters beats scattered ones
+    assert!(score("tax", "Taxes 2024") > score("tax", "To ask Xavier"));
+    // the start of a word beats the middle of one
+    assert!(score("plan", "Weekly plan") > score("plan", "Explanation"));
+    // fewer characters skipped between matches are better
+    assert!(score("rd", "Read") > score("rd", "Reorganized"));
+}
@@ -0,0 +1,102 @@
+//! The dependency graph must match the notes, whether it was built at once or kept up to date
+//! note by note.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::notes;
+use meteora::*;
+
+fn built(notes: &Notes) -> DependencyGraph {
+    let mut graph = DependencyGraph::default();
+    graph.rebuild(notes, &BTreeMap::new());
+    graph
+}
+
+fn counts(graph: &DependencyGraph) -> (usize, usize) {
+    (graph.node_count(), graph.edge_count())
+}
+
+#[test]
+fn build_counts_every_note_and_dependency_once() {
+    // 9 is missing, depending on it adds no edge
+    let notes = notes(
+        5,
+        &[(2, &[1]), (3, &[1, 2]), (4, &[3, 9]), (5, &[1, 2, 3, 4])],
+    );
+    assert_eq!(counts(&built(&notes)), (5, 8));
+}
+
+#[test]
+fn dense_build() {
+    let depends: Vec<(u128, Vec<u128>)> = (1..=2000)
+        .map(|id: u128| (id, (id.saturating_sub(10).max(1)..id).collect()))
+        .collect();
+    let depends: Vec<(u128, &[u128])> = depends.iter().map(|(id, d)| (*id, &d[..])).collect();
+    let notes = notes(2000, &depends);
+    let edges = (1..=2000).map(|id: usize| (id - 1).min(10)).sum();
+    assert_eq!(counts(&built(&notes)), (2000, edges));
+}
+
+#[test]
+fn updates_match_a_fresh_build() {
+    let mut notes = notes(4, &[(2, &[1]), (3, &[1, 2])]);
+    let positions = BTreeMap::new();
+    let mut graph = built(&notes);
+    assert_eq!(counts(&graph), (4, 3));
+
+    notes.get_mut(&4).unwrap().depends.insert(3);
+    graph.update(4, &notes, &positions);
+    assert_eq!(counts(&graph), (4, 4));
+
+    notes.get_mut(&3).unwrap().depends.remove(&1);
+    graph.update(3, &notes, &positions);
+    assert_eq!(counts(&graph), (4, 3));
+
+    // a dependency on a note that doesn't exist yet shows up once the note does
+    notes.get_mut(&1).unwrap().depends.insert(5);
+    graph.update(1, &notes, &positions);
+    assert_eq!(counts(&graph), (4, 3));
+    notes.insert(
+        5,
+        Note {
+            id: 5,
+            ..Note::new()
+        },
+    );
+    graph.update(5, &notes, &positions);
+    assert_eq!(counts(&graph), (5, 4));
+    assert_eq!(counts(&graph), counts(&built(&notes)));
+
+    // deleting a note takes its edges along, undoing the deletion brings them back
+    let removed = notes.remove(&1).unwrap();
+    graph.update(1, &notes, &positions);
+    assert_eq!(counts(&graph), (4, 2));
+    notes.insert(1, removed);
+    graph.update(1, &notes, &positions);
+    assert_eq!(counts(&graph), (5, 4));
+
+    // updating an unchanged note changes nothing
+    graph.update(2, &notes, &positions);
+    assert_eq!(counts(&graph), counts(&built(&notes)));
+}
+
+#[test]
+fn deleted_dependencies_stay_gone() {
+    let mut userdata = UserData::default();
+    for (id, depends) in notes(3, &[(2, &[1]), (3, &[1])]) {
+        userdata.notes.insert(id, depends);
+    }
+    let positions = BTreeMap::new();
+    let mut graph = built(&userdata.notes);
+
+    // deleting removes the dependencies on the note, so restoring only the note adds no edges
+    let removed = userdata.delete_note(1).unwrap();
+    graph.update(1, &userdata.notes, &positions);
+    assert_eq!(counts(&graph), (2, 0));
+    userdata.add_note(removed);
+    graph.update(1, &userdata.notes, &positions);
+    assert_eq!(counts(&graph), (3, 0));
+    assert_eq!(counts(&graph), counts(&built(&userdata.notes)));
+}
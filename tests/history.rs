@@ -0,0 +1,58 @@
+//! Each note keeps a few earlier versions of its text, to look at and bring back.
+
+mod common;
+
+use common::{day, note};
+use meteora::*;
+
+#[test]
+fn unchanged_text_is_not_kept() {
+    let mut note = note("Same");
+    note.record_revision("Same".to_string(), day(1));
+    assert!(note.history.is_empty());
+
+    note.text = "Changed".to_string();
+    note.record_revision("Same".to_string(), day(1));
+    note.record_revision("Same".to_string(), day(2));
+    assert_eq!(note.history, vec![(day(1), "Same".to_string())]);
+}
+
+#[test]
+fn bounded() {
+    let mut note = note("current");
+    for i in 0..MAX_HISTORY + 5 {
+        note.record_revision(format!("version {i}"), day(1));
+    }
+    assert_eq!(note.history.len(), MAX_HISTORY);
+    assert_eq!(note.history[0].1, "version 5");
+    assert_eq!(
+        note.history.last().unwrap().1,
+        format!("version {}", MAX_HISTORY + 4)
+    );
+}
+
+#[test]
+fn restore_keeps_the_current_text() {
+    let mut note = note("Third\n- [x] done");
+    note.record_revision("First\n- [ ] todo".to_string(), day(1));
+    note.record_revision("Second".to_string(), day(2));
+
+    note.restore_revision(0, day(3));
+    assert_eq!(note.text, "First\n- [ ] todo");
+    assert_eq!(note.progress, 0.);
+    assert_eq!(
+        note.history.last().unwrap(),
+        &(day(3), "Third\n- [x] done".to_string())
+    );
+
+    // out of range does nothing
+    let before = note.clone();
+    note.restore_revision(99, day(4));
+    assert!(note == before);
+}
+
+#[test]
+fn old_notes_have_no_history() {
+    let note: Note = serde_json::from_str(r#"{"text": "Stored before history"}"#).unwrap();
+    assert!(note.history.is_empty());
+}
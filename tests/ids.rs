@@ -0,0 +1,19 @@
+//! Note ids start with the creation time and end with random bits.
+
+use std::collections::HashSet;
+
+use meteora::*;
+
+#[test]
+fn ids_are_unique() {
+    // many of these are created within the same microsecond
+    let ids: HashSet<u128> = (0..10_000).map(|_| new_id()).collect();
+    assert_eq!(ids.len(), 10_000);
+}
+
+#[test]
+fn ids_sort_by_creation_time() {
+    let first = new_id();
+    std::thread::sleep(std::time::Duration::from_millis(1));
+    assert!(new_id() > first);
+}
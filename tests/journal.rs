@@ -0,0 +1,51 @@
+//! The logbook is exported as a Markdown journal with a section per day.
+
+mod common;
+
+use common::{day, note, tagged};
+use meteora::*;
+
+fn logbook() -> Logbook {
+    let mut call = note("Called the bank\nabout the loan");
+    call.tags = ["money".to_string(), "long tag".to_string()].into();
+    let mut garden = tagged(&["home"]);
+    garden.text = "Mowed the lawn".to_string();
+    [
+        (day(1), vec![call, note("  ")]),
+        (day(2), vec![note("")]),
+        (day(3), vec![garden]),
+    ]
+    .into()
+}
+
+#[test]
+fn newest_first() {
+    assert_eq!(
+        logbook_to_markdown(&logbook(), JournalOrder::NewestFirst),
+        "## 2024-03-03\n\n\
+         - Mowed the lawn #home\n\
+         \n\
+         ## 2024-03-01\n\n\
+         - Called the bank\n  about the loan #long_tag #money\n"
+    );
+}
+
+#[test]
+fn oldest_first() {
+    assert_eq!(
+        logbook_to_markdown(&logbook(), JournalOrder::OldestFirst),
+        "## 2024-03-01\n\n\
+         - Called the bank\n  about the loan #long_tag #money\n\
+         \n\
+         ## 2024-03-03\n\n\
+         - Mowed the lawn #home\n"
+    );
+}
+
+#[test]
+fn empty_logbook_is_an_empty_journal() {
+    assert_eq!(
+        logbook_to_markdown(&Logbook::new(), JournalOrder::NewestFirst),
+        ""
+    );
+}
@@ -0,0 +1,109 @@
+//! Answers from JsonBin, successful or not, must turn into notes, a bin id or an error a user
+//! can act on.
+
+use meteora::*;
+
+fn response(status: u16, status_text: &str, body: &str) -> ehttp::Result<ehttp::Response> {
+    Ok(ehttp::Response {
+        url: "https://api.jsonbin.io/v3/b".to_string(),
+        ok: (200..300).contains(&status),
+        status,
+        status_text: status_text.to_string(),
+        headers: Default::default(),
+        bytes: body.as_bytes().to_vec(),
+    })
+}
+
+fn credentials() -> Credentials {
+    let mut credentials = Credentials::default();
+    *credentials.secret = "jsonbin secret".to_string();
+    credentials
+}
+
+fn id_error(result: ehttp::Result<ehttp::Response>) -> String {
+    id_from_response(result).err().unwrap().to_string()
+}
+
+#[test]
+fn created_bin_id() {
+    let created = response(
+        200,
+        "OK",
+        r#"{"record": {"encrypted": "..."}, "metadata": {"id": "65a1b2c3", "private": true}}"#,
+    );
+    assert_eq!(id_from_response(created).unwrap(), "65a1b2c3");
+}
+
+#[test]
+fn loaded_notes() {
+    let mut userdata = UserData::default();
+    userdata.add_note(Note {
+        text: "From the bin".to_string(),
+        ..Note::new()
+    });
+    let encrypted = encrypt_userdata(&userdata, &credentials()).unwrap();
+    let body = serde_json::json!({ "encrypted": encrypted }).to_string();
+    let loaded = notes_from_response(response(200, "OK", &body), &credentials()).unwrap();
+    assert!(loaded == userdata);
+}
+
+#[test]
+fn network_error() {
+    let message = id_error(Err("connection refused".to_string()));
+    assert!(message.contains("Could not reach JsonBin"), "{message}");
+    assert!(message.contains("connection refused"), "{message}");
+}
+
+#[test]
+fn client_errors_are_explained() {
+    for (status, expected) in [
+        (401, "master key"),
+        (404, "no such bin"),
+        (413, "too large"),
+        (429, "too many requests"),
+    ] {
+        let body = r#"{"message": "reason given by JsonBin"}"#;
+        let message = id_error(response(status, "Error", body));
+        assert!(message.contains(expected), "{status}: {message}");
+        assert!(
+            message.contains("reason given by JsonBin"),
+            "{status}: {message}"
+        );
+
+        let message = notes_from_response(response(status, "Error", body), &credentials())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(message.contains(expected), "{status}: {message}");
+    }
+}
+
+#[test]
+fn other_errors_show_the_status() {
+    let message = id_error(response(400, "Bad Request", ""));
+    assert_eq!(message, "JsonBin answered 400 Bad Request");
+
+    let message = id_error(response(503, "Service Unavailable", "<html>down</html>"));
+    assert_eq!(message, "JsonBin answered 503 Service Unavailable");
+}
+
+#[test]
+fn testing_the_connection() {
+    let listed = response(200, "OK", r#"[{"record": "65a1b2c3", "private": true}]"#);
+    assert!(connection_from_response(listed).is_ok());
+
+    let body = r#"{"message": "Invalid X-Master-Key provided"}"#;
+    let message = connection_from_response(response(401, "Unauthorized", body))
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(message.contains("master key"), "{message}");
+
+    // nothing is sent without a master key
+    let storage = JsonBinStorage {
+        masterkey: String::new(),
+        bin_id: None,
+        backup_path: None,
+    };
+    assert!(storage.test_connection(&Channels::default()).is_err());
+}
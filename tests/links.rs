@@ -0,0 +1,75 @@
+//! Links are found in the note text, bare or in markdown syntax, and shortened for display.
+
+use meteora::*;
+
+fn urls(text: &str) -> Vec<&str> {
+    find_links(text).iter().map(|l| l.url).collect()
+}
+
+#[test]
+fn bare_links() {
+    let text = "see https://x.com/a?b=1 and http://y.org";
+    let links = find_links(text);
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0].url, "https://x.com/a?b=1");
+    assert_eq!(links[0].label, None);
+    assert!(!links[0].image);
+    assert_eq!(&text[links[1].span.clone()], "http://y.org");
+}
+
+#[test]
+fn trailing_punctuation_is_not_part_of_the_link() {
+    assert_eq!(
+        urls("Go to https://x.com/a, https://x.com/b. Or https://x.com/c!"),
+        ["https://x.com/a", "https://x.com/b", "https://x.com/c"]
+    );
+    assert_eq!(urls("(see https://x.com)"), ["https://x.com"]);
+    // balanced brackets are part of it
+    assert_eq!(
+        urls("(see https://en.wikipedia.org/wiki/Rust_(language))"),
+        ["https://en.wikipedia.org/wiki/Rust_(language)"]
+    );
+}
+
+#[test]
+fn markdown_links() {
+    let text = "read [the docs](https://docs.rs/egui) now";
+    let links = find_links(text);
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].url, "https://docs.rs/egui");
+    assert_eq!(links[0].label, Some("the docs"));
+    assert_eq!(
+        &text[links[0].span.clone()],
+        "[the docs](https://docs.rs/egui)"
+    );
+    assert_eq!(links[0].text(), "the docs");
+    assert_eq!(strip_links(text), "read the docs now");
+
+    let image = find_links("![a cat](https://x.com/cat.png)");
+    assert!(image[0].image);
+    assert_eq!(image[0].span, 0..31);
+}
+
+#[test]
+fn only_links_with_a_scheme_at_the_start_of_a_word() {
+    assert!(urls("http:/x.com www.x.com ftp://x.com https://").is_empty());
+    assert!(urls("größerhttps://x.com").is_empty());
+    assert_eq!(urls("Grüße:https://x.com"), ["https://x.com"]);
+}
+
+#[test]
+fn non_ascii_links() {
+    assert_eq!(
+        urls("Ünïcode https://例え.jp/パス, 🎉"),
+        ["https://例え.jp/パス"]
+    );
+    assert_eq!(strip_links("🎉 https://例え.jp/パス 🎉"), "🎉  🎉");
+}
+
+#[test]
+fn shortened_for_display() {
+    assert_eq!(link_text("https://www.example.com/"), "example.com");
+    assert_eq!(link_text("http://x.com/a/b"), "x.com/a/b");
+    let long = format!("https://例え.jp/{}", "パ".repeat(40));
+    assert_eq!(link_text(&long), format!("例え.jp/{}…", "パ".repeat(23)));
+}
@@ -0,0 +1,104 @@
+//! Logbook entries are completed from the lines written most often before, along with the tags
+//! they usually came with.
+
+use chrono::NaiveDate;
+use meteora::*;
+
+fn entry(text: &str, tags: &[&str]) -> Note {
+    Note {
+        text: text.to_string(),
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        ..Note::new()
+    }
+}
+
+fn logbook(days: Vec<Vec<Note>>) -> Logbook {
+    days.into_iter()
+        .enumerate()
+        .map(|(i, items)| {
+            let date = NaiveDate::from_ymd_opt(2024, 3, 1 + i as u32).unwrap();
+            (date, items)
+        })
+        .collect()
+}
+
+fn completions(index: &LogbookIndex, prefix: &str) -> Vec<String> {
+    index
+        .complete(prefix)
+        .into_iter()
+        .map(|line| line.text.clone())
+        .collect()
+}
+
+#[test]
+fn most_frequent_lines_first() {
+    let index = LogbookIndex::new(&logbook(vec![
+        vec![entry("Standup", &[]), entry("Reviewed PRs for sync", &[])],
+        vec![entry("Standup\nReviewed PRs for the editor", &[])],
+        vec![
+            entry("standup notes", &[]),
+            entry("Reviewed PRs for sync", &[]),
+        ],
+    ]));
+    assert_eq!(
+        completions(&index, "rev"),
+        vec!["Reviewed PRs for sync", "Reviewed PRs for the editor"]
+    );
+    assert_eq!(
+        completions(&index, "  STAND"),
+        vec!["Standup", "standup notes"]
+    );
+    // a finished line is not offered again, but longer ones are
+    assert_eq!(completions(&index, "standup"), vec!["standup notes"]);
+    assert!(completions(&index, "").is_empty());
+    assert!(completions(&index, "lunch").is_empty());
+}
+
+#[test]
+fn usual_tags_come_along() {
+    let index = LogbookIndex::new(&logbook(vec![
+        vec![entry("Standup", &["team", "daily"])],
+        vec![entry("Standup", &["team"])],
+        vec![entry("Standup", &["team", "remote"])],
+    ]));
+    let line = index.complete("stand")[0];
+    assert_eq!(line.count, 3);
+    // tags applied only once in three times are left out
+    assert_eq!(line.usual_tags(), vec!["team"]);
+}
+
+#[test]
+fn index_is_capped() {
+    let items = (0..MAX_INDEXED_LINES + 10)
+        .map(|i| entry(&format!("Line {i}"), &[]))
+        .collect();
+    let index = LogbookIndex::new(&logbook(vec![items]));
+    assert_eq!(index.complete("line").len(), MAX_COMPLETIONS);
+    // the lines sorted last were dropped
+    assert!(index.complete("line 999").is_empty());
+}
+
+#[test]
+fn completing_the_last_line() {
+    let mut text = "Morning\nrev".to_string();
+    assert_eq!(current_line(&text, 11), "rev");
+    assert_eq!(complete_line(&mut text, 11, "Reviewed PRs"), 20);
+    assert_eq!(text, "Morning\nReviewed PRs");
+}
+
+#[test]
+fn completing_the_line_at_the_cursor() {
+    let mut text = "Morning\nrev\nLunch".to_string();
+    assert_eq!(current_line(&text, 11), "rev");
+    assert_eq!(complete_line(&mut text, 11, "Reviewed PRs"), 20);
+    assert_eq!(text, "Morning\nReviewed PRs\nLunch");
+
+    // only the text up to the cursor is completed, but the whole line is replaced
+    let mut text = "Grüße\nLunch at noon".to_string();
+    assert_eq!(current_line(&text, 8), "Lu");
+    assert_eq!(complete_line(&mut text, 8, "Lunch with the team"), 25);
+    assert_eq!(text, "Grüße\nLunch with the team");
+
+    assert_eq!(current_line("Grüße", 4), "Grüß");
+    assert_eq!(current_line("Morning\n", 8), "");
+}
@@ -0,0 +1,76 @@
+//! Notes repaired on load: `UserData::sanitize` fixes corrupted data and reports what it changed.
+
+mod common;
+
+use common::{day, note, notes, tagged};
+use meteora::*;
+
+fn tags(tags: &[&str]) -> Vec<String> {
+    tags.iter().map(|t| t.to_string()).collect()
+}
+
+#[test]
+fn consistent_data_is_left_alone() {
+    let mut userdata = UserData {
+        notes: notes(2, &[(2, &[1])]),
+        tags: tags(&["home"]),
+        ..Default::default()
+    };
+    userdata.notes.get_mut(&1).unwrap().tags = ["home".to_string()].into();
+    assert!(userdata.sanitize().is_empty());
+    assert_eq!(userdata.notes[&2].depends, [1].into());
+    assert_eq!(userdata.tags, tags(&["home"]));
+}
+
+#[test]
+fn dependencies_on_missing_notes_are_removed() {
+    let mut userdata = UserData {
+        notes: notes(3, &[(2, &[1, 8]), (3, &[8, 9])]),
+        ..Default::default()
+    };
+    assert_eq!(
+        userdata.sanitize(),
+        ["removed 3 dependencies on missing notes"]
+    );
+    assert_eq!(userdata.notes[&2].depends, [1].into());
+    assert!(userdata.notes[&3].depends.is_empty());
+}
+
+#[test]
+fn empty_and_duplicate_tags_are_removed() {
+    let mut userdata = UserData {
+        tags: tags(&["work", "", "home", "work"]),
+        ..Default::default()
+    };
+    let id = userdata.add_note(tagged(&["", "work"]));
+    assert_eq!(
+        userdata.sanitize(),
+        ["removed 2 empty tags", "removed 1 duplicate tags"]
+    );
+    assert_eq!(userdata.tags, tags(&["work", "home"]));
+    assert_eq!(userdata.notes[&id].tags, ["work".to_string()].into());
+}
+
+#[test]
+fn unknown_tags_are_added() {
+    let mut userdata = UserData {
+        tags: tags(&["work"]),
+        ..Default::default()
+    };
+    userdata.add_note(tagged(&["work", "garden"]));
+    userdata.add_note(tagged(&["garden", "bills"]));
+    assert_eq!(userdata.sanitize(), ["added 2 unknown tags"]);
+    assert_eq!(userdata.tags, tags(&["work", "bills", "garden"]));
+}
+
+#[test]
+fn empty_logbook_days_are_removed() {
+    let mut userdata = UserData::default();
+    userdata
+        .logbook
+        .insert(day(1), vec![note("Called the bank")]);
+    userdata.logbook.insert(day(2), vec![note(" "), note("")]);
+    userdata.logbook.insert(day(3), vec![]);
+    assert_eq!(userdata.sanitize(), ["removed 2 empty logbook days"]);
+    assert_eq!(userdata.logbook.keys().collect::<Vec<_>>(), [&day(1)]);
+}
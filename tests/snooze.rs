@@ -0,0 +1,71 @@
+//! Snoozed notes stay hidden until their day comes and then show up again.
+
+mod common;
+
+use chrono::{Days, NaiveDate};
+use common::day;
+use egui::epaint::ahash::HashSet;
+use meteora::*;
+
+fn snoozed(text: &str, until: Option<NaiveDate>) -> Note {
+    Note {
+        text: text.to_string(),
+        snoozed_until: until,
+        ..Note::new()
+    }
+}
+
+#[test]
+fn hidden_until_the_day() {
+    let note = snoozed("Later", Some(day(5)));
+    assert!(note.is_snoozed(day(4)));
+    assert!(!note.is_snoozed(day(5)));
+    assert!(!note.is_snoozed(day(6)));
+    assert!(!snoozed("Now", None).is_snoozed(day(4)));
+}
+
+#[test]
+fn snoozed_notes_are_not_visible() {
+    let today = today();
+    let mut userdata = UserData::default();
+    let hidden = userdata.add_note(snoozed("Hidden", today.checked_add_days(Days::new(2))));
+    let back = userdata.add_note(snoozed("Back", Some(today)));
+    let shown = userdata.add_note(snoozed("Shown", None));
+
+    let visible = userdata.visible_notes("", &HashSet::default(), Visibility::All, false);
+    assert!(!visible.contains(&hidden));
+    assert!(visible.contains(&back));
+    assert!(visible.contains(&shown));
+    assert_eq!(userdata.snoozed_notes(today), vec![hidden]);
+}
+
+#[test]
+fn waking_up_clears_the_snooze() {
+    let mut userdata = UserData::default();
+    let first = userdata.add_note(snoozed("First", Some(day(4))));
+    let second = userdata.add_note(snoozed("Second", Some(day(5))));
+    let later = userdata.add_note(snoozed("Later", Some(day(9))));
+    userdata.add_note(snoozed("Awake", None));
+
+    assert!(userdata.wake_snoozed(day(3)).is_empty());
+    assert_eq!(userdata.snoozed_notes(day(3)), vec![first, second, later]);
+
+    let mut woken = userdata.wake_snoozed(day(5));
+    woken.sort();
+    let mut expected = vec![first, second];
+    expected.sort();
+    assert_eq!(woken, expected);
+    assert_eq!(userdata.notes[&first].snoozed_until, None);
+    assert_eq!(userdata.notes[&later].snoozed_until, Some(day(9)));
+
+    // each note wakes up once
+    assert!(userdata.wake_snoozed(day(5)).is_empty());
+}
+
+#[test]
+fn next_week_starts_on_monday() {
+    // 2024-03-04 is a Monday
+    assert_eq!(next_monday(day(4)), day(11));
+    assert_eq!(next_monday(day(6)), day(11));
+    assert_eq!(next_monday(day(10)), day(11));
+}
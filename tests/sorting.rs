@@ -0,0 +1,62 @@
+//! Notes are shown pinned first, then by priority. Notes of equal priority keep their order.
+
+mod common;
+
+use std::collections::HashSet;
+
+use common::{day, notes};
+use meteora::*;
+
+fn sorted(userdata: &UserData, ascending: bool) -> Vec<u128> {
+    userdata.visible_notes("", &HashSet::new(), Visibility::All, ascending)
+}
+
+/// Notes 1 to 5, all of the same priority and created on the same day
+fn userdata() -> UserData {
+    let mut notes = notes(5, &[]);
+    for note in notes.values_mut() {
+        note.priority = 0.5;
+        note.created = day(1);
+    }
+    UserData {
+        notes,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn equal_priority_is_sorted_by_id() {
+    let userdata = userdata();
+    assert_eq!(sorted(&userdata, false), [1, 2, 3, 4, 5]);
+    // the order of equal notes does not flip with the sort direction
+    assert_eq!(sorted(&userdata, true), [1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn equal_priority_is_sorted_by_creation() {
+    let mut userdata = userdata();
+    userdata.notes.get_mut(&2).unwrap().created = day(3);
+    userdata.notes.get_mut(&4).unwrap().created = day(2);
+    assert_eq!(sorted(&userdata, false), [1, 3, 5, 4, 2]);
+    assert_eq!(sorted(&userdata, true), [2, 4, 1, 3, 5]);
+}
+
+#[test]
+fn order_is_stable() {
+    let mut userdata = userdata();
+    userdata.notes.get_mut(&3).unwrap().priority = 0.9;
+    let first = sorted(&userdata, false);
+    assert_eq!(first, [3, 1, 2, 4, 5]);
+    for _ in 0..10 {
+        assert_eq!(sorted(&userdata, false), first);
+    }
+}
+
+#[test]
+fn pinned_notes_come_first() {
+    let mut userdata = userdata();
+    userdata.notes.get_mut(&3).unwrap().priority = 0.9;
+    userdata.notes.get_mut(&5).unwrap().pinned = true;
+    assert_eq!(sorted(&userdata, false), [5, 3, 1, 2, 4]);
+    assert_eq!(sorted(&userdata, true), [5, 1, 2, 4, 3]);
+}
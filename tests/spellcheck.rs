@@ -0,0 +1,162 @@
+//! Misspelled words are found with a Hunspell dictionary. Code, links and #tags are left alone,
+//! and the check waits until the text stays the same for a moment.
+#![cfg(feature = "spellcheck")]
+
+use meteora::*;
+use std::collections::BTreeSet;
+
+const AFF: &str = "SET UTF-8
+TRY esianrtolcdugmphbyfvkwz
+
+PFX U Y 1
+PFX U 0 un .
+
+SFX S Y 2
+SFX S 0 s [^sy]
+SFX S y ies [^aeiou]y
+
+SFX D Y 1
+SFX D 0 ed [^e]
+";
+
+const DIC: &str = "6
+berry/S
+check/DSU
+note/S
+the
+write
+Paris
+";
+
+fn dictionary() -> Dictionary {
+    Dictionary::parse(AFF, DIC)
+}
+
+fn words<'a>(text: &'a str, known: &BTreeSet<String>) -> Vec<&'a str> {
+    misspelled(text, &dictionary(), known)
+        .into_iter()
+        .map(|range| &text[range])
+        .collect()
+}
+
+#[test]
+fn affixes_expand_the_words() {
+    let dictionary = dictionary();
+    for word in ["berries", "notes", "checked", "unchecked", "unchecks"] {
+        assert!(dictionary.check(word), "{word}");
+    }
+    // the condition of the rule doesn't match
+    assert!(!dictionary.check("berrys"));
+    assert!(!dictionary.check("notess"));
+    assert!(!dictionary.check("unnote"));
+}
+
+#[test]
+fn capitals() {
+    let dictionary = dictionary();
+    assert!(dictionary.check("Note"));
+    assert!(dictionary.check("NOTES"));
+    assert!(dictionary.check("Paris"));
+    assert!(!dictionary.check("paris"));
+    assert!(!dictionary.check("nOte"));
+}
+
+#[test]
+fn code_links_and_tags_are_skipped() {
+    let known = BTreeSet::new();
+    let text = "the nte #wrte `chekc` [[Berys]] https://exmple.com/pth\n```\nwrte\n```\nberyy";
+    assert_eq!(words(text, &known), vec!["nte", "beryy"]);
+}
+
+#[test]
+fn added_words_are_known() {
+    let known = BTreeSet::from(["meteora".to_string()]);
+    assert_eq!(words("Meteora notes", &known), Vec::<&str>::new());
+    assert_eq!(words("the note's 'chekc'", &known), vec!["chekc"]);
+}
+
+#[test]
+fn suggestions_keep_the_case() {
+    let dictionary = dictionary();
+    assert!(dictionary.suggest("nte").contains(&"note".to_string()));
+    assert!(dictionary.suggest("Chekc").contains(&"Check".to_string()));
+    assert!(dictionary.suggest("BERYS").contains(&"BERRY".to_string()));
+}
+
+#[test]
+fn checks_once_the_text_settles() {
+    let known = BTreeSet::new();
+    let mut checker = SpellChecker::new(dictionary());
+    assert_eq!(
+        checker.update("the nte", &known, 0.),
+        Some(SPELL_CHECK_DELAY)
+    );
+    assert_eq!(checker.misspelled_in("the nte").count(), 0);
+    // typing starts the wait over
+    assert_eq!(
+        checker.update("the nte x", &known, 0.3),
+        Some(SPELL_CHECK_DELAY)
+    );
+    assert!(checker
+        .update("the nte x", &known, 0.3 + SPELL_CHECK_DELAY)
+        .is_none());
+    assert_eq!(
+        checker.misspelled_in("the nte x").collect::<Vec<_>>(),
+        vec![4..7]
+    );
+    // the word moved, so the old underline is dropped until the next check
+    assert_eq!(checker.misspelled_in("a the nte x").count(), 0);
+}
+
+#[test]
+fn compounds_are_made_of_flagged_words() {
+    let aff = "COMPOUNDFLAG X\nCOMPOUNDMIN 2\nONLYINCOMPOUND O\n\nSFX S Y 1\nSFX S 0 s .\n";
+    let dictionary = Dictionary::parse(aff, "4\nfoot/XS\nball/XS\nbook/S\nto/XO\n");
+    for word in [
+        "football",
+        "footballs",
+        "ballfootball",
+        "footto",
+        "Football",
+    ] {
+        assert!(dictionary.check(word), "{word}");
+    }
+    // only the end of a compound takes a suffix, and only flagged words join
+    assert!(!dictionary.check("footsball"));
+    assert!(!dictionary.check("footbook"));
+    // a part that only goes into compounds is no word of its own
+    assert!(!dictionary.check("to"));
+}
+
+fn dictionary_dir(name: &str, aff: &[u8], dic: &[u8]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("meteora-spelling-{name}-{}", new_id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("xx_XX.aff"), aff).unwrap();
+    std::fs::write(dir.join("xx_XX.dic"), dic).unwrap();
+    dir
+}
+
+#[test]
+fn dictionaries_are_read_in_their_encoding() {
+    // "Grüße" in ISO 8859-1
+    let dir = dictionary_dir("latin1", b"SET ISO8859-1\n", b"1\nGr\xfc\xdfe\n");
+    let dictionary = Dictionary::load("xx_XX", std::slice::from_ref(&dir)).unwrap();
+    assert!(dictionary.check("Grüße"));
+    _ = std::fs::remove_dir_all(dir);
+
+    let dir = dictionary_dir("koi8", b"SET KOI8-R\n", b"1\n\xd3\xcc\xcf\xd7\xcf\n");
+    let error = Dictionary::load("xx_XX", std::slice::from_ref(&dir))
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("KOI8-R"), "{error}");
+    _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn dictionaries_load_in_the_background() {
+    let dir = dictionary_dir("background", b"SET UTF-8\n", "1\nGrüße\n".as_bytes());
+    let loading = load_dictionary(&egui::Context::default(), "xx_XX".into(), vec![dir.clone()]);
+    let dictionary = loading.recv().unwrap().unwrap();
+    assert!(dictionary.check("Grüße"));
+    _ = std::fs::remove_dir_all(dir);
+}
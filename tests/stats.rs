@@ -0,0 +1,65 @@
+//! The counts shown under the editor and on list rows.
+
+use meteora::*;
+
+fn stats(text: &str) -> NoteStats {
+    Note {
+        text: text.to_string(),
+        ..Note::new()
+    }
+    .stats()
+}
+
+#[test]
+fn plain_text() {
+    let stats = stats("Groceries\nbuy milk, eggs and well-known bread\n");
+    assert_eq!(stats.words, 7);
+    assert_eq!(stats.lines, 2);
+    assert_eq!(stats.characters, 44);
+    assert_eq!(stats.links, 0);
+    assert_eq!(stats.reading_minutes(), 1);
+}
+
+#[test]
+fn empty() {
+    assert_eq!(stats(""), NoteStats::default());
+    assert_eq!(stats("").reading_minutes(), 0);
+}
+
+#[test]
+fn markup_is_not_counted() {
+    let stats = stats("# Trip\n- [x] book hotel\n- [ ] pack\n* 2 bags");
+    assert_eq!(stats.words, 6);
+    assert_eq!((stats.checked, stats.checklist), (1, 2));
+}
+
+#[test]
+fn cjk_counts_characters() {
+    // "I like to read books" in Chinese, and a Japanese sentence with kana
+    assert_eq!(stats("我喜欢看书").words, 5);
+    assert_eq!(stats("本を読む。").words, 4);
+    // mixed with latin words and counted by character, not bytes
+    let mixed = stats("Rust 很好 ok");
+    assert_eq!(mixed.words, 4);
+    assert_eq!(mixed.characters, 10);
+}
+
+#[test]
+fn only_links() {
+    let stats = stats("https://example.com/a https://example.com/b\nhttp://example.org");
+    assert_eq!(stats.links, 3);
+    assert_eq!(stats.words, 0);
+    assert_eq!(stats.lines, 2);
+
+    // the label of a markdown link is read, the URL isn't
+    let labelled = self::stats("[the docs](https://docs.rs)");
+    assert_eq!(labelled.links, 1);
+    assert_eq!(labelled.words, 2);
+}
+
+#[test]
+fn reading_time() {
+    let words = vec!["word"; 412].join(" ");
+    assert_eq!(stats(&words).words, 412);
+    assert_eq!(stats(&words).reading_minutes(), 3);
+}
@@ -0,0 +1,74 @@
+//! Notes are active, waiting, kept for someday or done. Older data only knew whether a note was
+//! complete.
+
+mod common;
+
+use common::day;
+use meteora::*;
+
+#[test]
+fn complete_notes_become_done() {
+    let json = r#"{
+        "format_version": 1,
+        "notes": {
+            "1": {"id": 1, "text": "Finished", "complete": true},
+            "2": {"id": 2, "text": "Open", "complete": false}
+        }
+    }"#;
+    let userdata = userdata_from_json(json).unwrap();
+    assert_eq!(userdata.format_version, FORMAT_VERSION);
+    assert_eq!(userdata.notes[&1].status, Status::Done);
+    assert_eq!(userdata.notes[&2].status, Status::Active);
+
+    // the old flag isn't written back
+    let saved = serde_json::to_string(&userdata).unwrap();
+    assert!(!saved.contains("\"complete\""), "{saved}");
+}
+
+#[test]
+fn bundles_from_before_the_status() {
+    let json = r#"{
+        "root": 1,
+        "notes": [{"id": 1, "text": "Finished", "complete": true}],
+        "dependencies": []
+    }"#;
+    let bundle = NoteBundle::from_json(json).unwrap();
+    assert_eq!(bundle.notes[0].status, Status::Done);
+}
+
+#[test]
+fn completion_date_follows_the_status() {
+    let mut note = Note::new();
+    note.set_status(Status::Done, day(4));
+    assert_eq!(note.completed_on, Some(day(4)));
+    // staying done keeps the day it was finished
+    note.set_complete(true, day(5));
+    assert_eq!(note.completed_on, Some(day(4)));
+    note.set_status(Status::Someday, day(6));
+    assert_eq!(note.completed_on, None);
+}
+
+#[test]
+fn unfinished_checklist_keeps_the_status() {
+    let mut note = Note {
+        text: "Waiting\n- [ ] reply".to_string(),
+        status: Status::Waiting,
+        waiting_on: "Alex".to_string(),
+        ..Note::new()
+    };
+    note.sync_checklist_progress();
+    assert_eq!(note.status, Status::Waiting);
+
+    note.text = "Waiting\n- [x] reply".to_string();
+    note.sync_checklist_progress();
+    assert!(note.is_done());
+    note.set_complete(false, day(4));
+    assert_eq!(note.status, Status::Active);
+}
+
+#[test]
+fn someday_and_done_rank_after_active_work() {
+    assert_eq!(Status::Active.rank(), Status::Waiting.rank());
+    assert!(Status::Active.rank() < Status::Someday.rank());
+    assert!(Status::Someday.rank() < Status::Done.rank());
+}
@@ -0,0 +1,139 @@
+//! Storage backends keep the encrypted notes as they are given. Their settings are persisted in
+//! the same shape as before each backend had a type of its own.
+
+use meteora::*;
+use std::path::PathBuf;
+
+#[test]
+fn local_file_round_trip() {
+    let path = std::env::temp_dir().join(format!("meteora-storage-{}.json", new_id()));
+    let mut storage = LocalStorage { path: path.clone() };
+    let channels = Channels::default();
+
+    storage.save("encrypted notes", &channels, false).unwrap();
+    assert!(channels.saved_channel.1.try_recv().is_ok());
+    // only manual saves are announced
+    assert!(channels.msg_channel.1.try_recv().is_err());
+
+    storage.load(&channels).unwrap();
+    assert_eq!(
+        channels.loaded_channel.1.try_recv().unwrap(),
+        Loaded::Notes("encrypted notes".to_string())
+    );
+    _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn previous_save_is_kept_as_backup() {
+    let path = std::env::temp_dir().join(format!("meteora-backup-{}.json", new_id()));
+    let mut storage = LocalStorage { path: path.clone() };
+    let channels = Channels::default();
+
+    storage.save("first", &channels, false).unwrap();
+    assert_eq!(storage.read_backup().unwrap(), None);
+    storage.save("second", &channels, false).unwrap();
+    assert_eq!(storage.read_backup().unwrap().as_deref(), Some("first"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+
+    // nothing is left over from writing
+    assert!(!PathBuf::from(format!("{}.tmp", path.display())).exists());
+
+    _ = std::fs::remove_file(backup_path(&path));
+    _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn replacing_unreadable_notes_keeps_the_backup() {
+    let path = std::env::temp_dir().join(format!("meteora-replace-{}.json", new_id()));
+    let mut storage = LocalStorage { path: path.clone() };
+    let channels = Channels::default();
+
+    storage.save("good", &channels, false).unwrap();
+    storage.save("corrupt", &channels, false).unwrap();
+    storage.replace("restored", &channels, false).unwrap();
+    assert_eq!(storage.read_backup().unwrap().as_deref(), Some("good"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "restored");
+
+    _ = std::fs::remove_file(backup_path(&path));
+    _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn missing_file_has_nothing_to_load() {
+    let storage = LocalStorage {
+        path: std::env::temp_dir().join(format!("meteora-missing-{}.json", new_id())),
+    };
+    let channels = Channels::default();
+    storage.load(&channels).unwrap();
+    assert_eq!(
+        channels.loaded_channel.1.try_recv().unwrap(),
+        Loaded::Nothing
+    );
+}
+
+#[test]
+fn unreadable_file_is_an_error() {
+    // a directory can't be read as notes
+    let storage = LocalStorage {
+        path: std::env::temp_dir(),
+    };
+    let channels = Channels::default();
+    assert!(storage.load(&channels).is_err());
+    assert!(channels.loaded_channel.1.try_recv().is_err());
+}
+
+#[test]
+fn jsonbin_needs_a_master_key() {
+    let mut storage = StorageMode::JsonBin(JsonBinStorage {
+        masterkey: " ".to_string(),
+        bin_id: Some("65a1b2c3".to_string()),
+        backup_path: None,
+    });
+    let channels = Channels::default();
+    assert!(storage.backend_mut().save("blob", &channels, true).is_err());
+    assert!(storage.load_userdata(&channels).is_err());
+}
+
+#[test]
+fn settings_keep_their_shape() {
+    let local = r#"{"Local":{"path":"notes.json"}}"#;
+    let mode: StorageMode = serde_json::from_str(local).unwrap();
+    assert!(
+        mode == StorageMode::Local(LocalStorage {
+            path: PathBuf::from("notes.json")
+        })
+    );
+    assert_eq!(serde_json::to_string(&mode).unwrap(), local);
+
+    // written before the local backup existed
+    let jsonbin = r#"{"JsonBin":{"masterkey":"key","bin_id":null}}"#;
+    let mode: StorageMode = serde_json::from_str(jsonbin).unwrap();
+    assert!(
+        mode == StorageMode::JsonBin(JsonBinStorage {
+            masterkey: "key".to_string(),
+            bin_id: None,
+            backup_path: None,
+        })
+    );
+    // the master key is no longer written
+    assert_eq!(
+        serde_json::to_string(&mode).unwrap(),
+        r#"{"JsonBin":{"bin_id":null,"backup_path":null}}"#
+    );
+}
+
+#[test]
+fn notes_of_another_profile_are_never_kept() {
+    // switching profiles locks until the notes of the new one are loaded
+    let mut lock = Lock::ProfileSwitch;
+    assert!(!lock.may_keep_notes());
+    assert!(lock.keep_notes().is_err());
+    // so saving is refused
+    assert!(lock.ensure_unlocked().is_err());
+
+    // the notes of the last session may replace the ones in the storage
+    let mut lock = Lock::Startup;
+    assert!(lock.ensure_unlocked().is_err());
+    lock.keep_notes().unwrap();
+    assert!(lock.ensure_unlocked().is_ok());
+}
@@ -0,0 +1,81 @@
+//! The weekly summary lists the logbook items and the notes completed between two days.
+
+mod common;
+
+use common::{add, day, note, tagged};
+use meteora::*;
+
+fn item(text: &str, tags: &[&str]) -> Note {
+    Note {
+        text: text.to_string(),
+        ..tagged(tags)
+    }
+}
+
+fn completed(userdata: &mut UserData, title: &str, on: Option<u32>) {
+    let mut note = note(title);
+    if let Some(d) = on {
+        note.set_status(Status::Done, day(d));
+    }
+    add(&mut userdata.notes, note);
+}
+
+#[test]
+fn range_is_inclusive() {
+    let mut userdata = UserData::default();
+    for d in 3..=11 {
+        userdata
+            .logbook
+            .insert(day(d), vec![item(&format!("Day {d}"), &[])]);
+    }
+    let summary = weekly_summary(&userdata, day(4), day(10));
+    assert!(!summary.contains("Day 3 "), "{summary}");
+    assert!(summary.contains("- Day 4 (2024-03-04)"), "{summary}");
+    assert!(summary.contains("- Day 10 (2024-03-10)"), "{summary}");
+    assert!(!summary.contains("Day 11 "), "{summary}");
+}
+
+#[test]
+fn items_are_grouped_by_tag() {
+    let mut userdata = UserData::default();
+    userdata.logbook.insert(
+        day(4),
+        vec![
+            item("Paid rent", &[]),
+            item("Planned the release", &["work", "meetings"]),
+        ],
+    );
+    assert_eq!(
+        weekly_summary(&userdata, day(4), day(10)),
+        "# Summary 2024-03-04 to 2024-03-10\n\
+         \n## Logbook\n\
+         \n### meetings\n\n- Planned the release (2024-03-04)\n\
+         \n### work\n\n- Planned the release (2024-03-04)\n\
+         \n### Untagged\n\n- Paid rent (2024-03-04)\n"
+    );
+}
+
+#[test]
+fn only_notes_completed_in_range() {
+    let mut userdata = UserData::default();
+    completed(&mut userdata, "Before", Some(3));
+    completed(&mut userdata, "First day", Some(4));
+    completed(&mut userdata, "Last day", Some(10));
+    completed(&mut userdata, "After", Some(11));
+    completed(&mut userdata, "Still open", None);
+    let summary = weekly_summary(&userdata, day(4), day(10));
+    let (_, done) = summary.split_once("## Completed\n").unwrap();
+    let mut done: Vec<&str> = done.lines().filter(|l| !l.is_empty()).collect();
+    done.sort();
+    assert_eq!(done, ["- First day", "- Last day"]);
+}
+
+#[test]
+fn no_activity() {
+    let mut userdata = UserData::default();
+    completed(&mut userdata, "Still open", None);
+    assert_eq!(
+        weekly_summary(&userdata, day(4), day(10)),
+        "# Summary 2024-03-04 to 2024-03-10\n\nNo activity.\n"
+    );
+}
@@ -0,0 +1,66 @@
+//! Tags can be added to or removed from all notes carrying a tag at once.
+
+mod common;
+
+use common::{add, tagged};
+use meteora::*;
+
+fn sorted(mut ids: Vec<u128>) -> Vec<u128> {
+    ids.sort();
+    ids
+}
+
+#[test]
+fn notes_with_a_tag() {
+    let mut notes = Notes::new();
+    let a = add(&mut notes, tagged(&["project"]));
+    let b = add(&mut notes, tagged(&["project", "urgent"]));
+    add(&mut notes, tagged(&["urgent"]));
+    add(&mut notes, tagged(&[]));
+    assert_eq!(sorted(notes_tagged(&notes, "project")), sorted(vec![a, b]));
+    assert!(notes_tagged(&notes, "missing").is_empty());
+}
+
+#[test]
+fn tag_added_where_tagged() {
+    let mut notes = Notes::new();
+    let plain = add(&mut notes, tagged(&["project"]));
+    let already = add(&mut notes, tagged(&["project", "archive"]));
+    let other = add(&mut notes, tagged(&["urgent"]));
+
+    let changed = add_tag_where_tagged(&mut notes, "project", "archive");
+    assert_eq!(changed, vec![plain]);
+    assert!(notes[&plain].tags.contains("archive"));
+    assert_eq!(notes[&already].tags.len(), 2);
+    assert!(!notes[&other].tags.contains("archive"));
+
+    // nothing left to do the second time
+    assert!(add_tag_where_tagged(&mut notes, "project", "archive").is_empty());
+}
+
+#[test]
+fn tag_removed_from_all() {
+    let mut notes = Notes::new();
+    let a = add(&mut notes, tagged(&["project", "urgent"]));
+    let b = add(&mut notes, tagged(&["project"]));
+    let other = add(&mut notes, tagged(&["urgent"]));
+    notes.get_mut(&a).unwrap().primary_tag = Some("project".to_string());
+
+    let changed = remove_tag_from_all(&mut notes, "project");
+    assert_eq!(sorted(changed), sorted(vec![a, b]));
+    assert!(notes_tagged(&notes, "project").is_empty());
+    assert!(notes[&b].tags.is_empty());
+    assert!(notes[&other].tags.contains("urgent"));
+    // the color now comes from the remaining tag
+    assert_eq!(notes[&a].primary_tag(), Some("urgent"));
+}
+
+#[test]
+fn removed_tag_stays_in_the_list() {
+    let mut userdata = UserData::default();
+    let id = userdata.add_note(Note::new());
+    userdata.tag_note(id, "project");
+    remove_tag_from_all(&mut userdata.notes, "project");
+    assert!(userdata.notes[&id].tags.is_empty());
+    assert_eq!(userdata.tags, vec!["project".to_string()]);
+}
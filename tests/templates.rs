@@ -0,0 +1,49 @@
+//! Templates may contain `{{date}}`, replaced by the current date, and `{{cursor}}`, where editing
+//! starts.
+
+mod common;
+
+use common::day;
+use meteora::*;
+
+#[test]
+fn date_is_replaced_everywhere() {
+    let (text, cursor) = expand_placeholders("Standup {{date}}\nsince {{date}}:", day(5));
+    assert_eq!(text, "Standup 2024-03-05\nsince 2024-03-05:");
+    assert_eq!(cursor, None);
+}
+
+#[test]
+fn cursor_is_removed_and_counted_in_chars() {
+    let (text, cursor) = expand_placeholders("Grüße\n- {{cursor}}", day(5));
+    assert_eq!(text, "Grüße\n- ");
+    assert_eq!(cursor, Some(8));
+}
+
+#[test]
+fn cursor_is_counted_after_expanding_the_date() {
+    let (text, cursor) = expand_placeholders("{{date}} {{cursor}}", day(5));
+    assert_eq!(text, "2024-03-05 ");
+    assert_eq!(cursor, Some(11));
+}
+
+#[test]
+fn only_the_first_cursor_counts() {
+    let (text, cursor) = expand_placeholders("a{{cursor}}b{{cursor}}", day(5));
+    assert_eq!(text, "ab");
+    assert_eq!(cursor, Some(1));
+}
+
+#[test]
+fn template_deadline_is_relative_to_today() {
+    let template = NoteTemplate {
+        name: "Report".into(),
+        text: "Report {{date}}\n{{cursor}}".into(),
+        deadline_days: Some(3),
+        ..Default::default()
+    };
+    let (note, cursor) = template.instantiate(day(5));
+    assert_eq!(note.text, "Report 2024-03-05\n");
+    assert_eq!(cursor, Some(18));
+    assert_eq!(note.deadline, Deadline::Fixed(day(8)));
+}
@@ -0,0 +1,96 @@
+//! Time tracked on a note is kept as sessions and summed up per day in the logbook.
+
+mod common;
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use common::day;
+use meteora::*;
+
+fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, day, hour, minute, 0).unwrap()
+}
+
+fn logbook_lines(userdata: &UserData, d: u32) -> Vec<String> {
+    userdata
+        .logbook
+        .get(&day(d))
+        .map(|items| items.iter().map(|item| item.text.clone()).collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn sessions_are_split_at_midnight() {
+    set_time_zone(Some(0));
+    assert_eq!(
+        split_by_day(at(1, 22, 30), at(2, 1, 15)),
+        vec![
+            (day(1), TimeDelta::minutes(90)),
+            (day(2), TimeDelta::minutes(75))
+        ]
+    );
+    assert_eq!(
+        split_by_day(at(1, 9, 0), at(1, 10, 0)),
+        vec![(day(1), TimeDelta::hours(1))]
+    );
+    assert!(split_by_day(at(1, 10, 0), at(1, 9, 0)).is_empty());
+}
+
+#[test]
+fn durations() {
+    assert_eq!(format_duration(TimeDelta::seconds(30)), "0m");
+    assert_eq!(format_duration(TimeDelta::minutes(45)), "45m");
+    assert_eq!(format_duration(TimeDelta::hours(2)), "2h");
+    assert_eq!(format_duration(TimeDelta::minutes(80)), "1h 20m");
+}
+
+#[test]
+fn stopped_session_is_logged_per_day() {
+    set_time_zone(Some(0));
+    let mut userdata = UserData::default();
+    let id = userdata.add_note(Note {
+        text: "Report".to_string(),
+        ..Note::new()
+    });
+    assert!(userdata.log_work(id, at(1, 23, 0), at(2, 0, 30)));
+    assert_eq!(logbook_lines(&userdata, 1), vec!["Worked 1h on Report"]);
+    assert_eq!(logbook_lines(&userdata, 2), vec!["Worked 30m on Report"]);
+
+    // the seconds before midnight don't get a line of their own
+    assert!(userdata.log_work(id, at(2, 23, 59) + TimeDelta::seconds(30), at(3, 0, 20)));
+    assert_eq!(logbook_lines(&userdata, 2).len(), 1);
+    assert_eq!(logbook_lines(&userdata, 3), vec!["Worked 20m on Report"]);
+
+    let note = &userdata.notes[&id];
+    assert_eq!(note.work_log.len(), 2);
+    assert_eq!(
+        note.tracked_time(),
+        TimeDelta::minutes(110) + TimeDelta::seconds(30)
+    );
+    assert_eq!(note.tracked_by_day()[&day(1)], TimeDelta::hours(1));
+}
+
+#[test]
+fn work_starting_a_day_follows_the_template() {
+    set_time_zone(Some(0));
+    let mut userdata = UserData {
+        logbook_template: "# {{date}}\n".to_string(),
+        ..Default::default()
+    };
+    let id = userdata.add_note(Note {
+        text: "Report".to_string(),
+        ..Note::new()
+    });
+    assert!(userdata.log_work(id, at(1, 9, 0), at(1, 10, 0)));
+    assert!(userdata.log_work(id, at(1, 11, 0), at(1, 11, 30)));
+    assert_eq!(
+        logbook_lines(&userdata, 1),
+        vec!["# 2024-03-01\nWorked 1h on Report", "Worked 30m on Report"]
+    );
+}
+
+#[test]
+fn missing_note_is_not_logged() {
+    let mut userdata = UserData::default();
+    assert!(!userdata.log_work(42, at(1, 9, 0), at(1, 10, 0)));
+    assert!(userdata.logbook.is_empty());
+}
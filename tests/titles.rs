@@ -0,0 +1,43 @@
+//! Editing the title of a note in place replaces its first line and keeps the body as it is.
+
+use meteora::*;
+
+#[test]
+fn empty_text() {
+    assert_eq!(replace_first_line("", "Title"), "Title");
+    assert_eq!(replace_first_line("", ""), "");
+}
+
+#[test]
+fn single_line() {
+    assert_eq!(replace_first_line("Titel", "Title"), "Title");
+    assert_eq!(replace_first_line("Title", ""), "");
+}
+
+#[test]
+fn body_is_kept() {
+    assert_eq!(
+        replace_first_line("Titel\nbody\n\nmore", "Title"),
+        "Title\nbody\n\nmore"
+    );
+    // an empty first line is replaced, not the first line with text
+    assert_eq!(replace_first_line("\nbody", "Title"), "Title\nbody");
+    assert_eq!(replace_first_line("Title\nbody", ""), "\nbody");
+}
+
+#[test]
+fn crlf_is_kept() {
+    assert_eq!(
+        replace_first_line("Titel\r\nbody\r\n", "Title"),
+        "Title\r\nbody\r\n"
+    );
+    assert_eq!(replace_first_line("\r\nbody", "Title"), "Title\r\nbody");
+}
+
+#[test]
+fn line_breaks_in_the_title_become_spaces() {
+    assert_eq!(
+        replace_first_line("Titel\nbody", "Two\nlines\r\n"),
+        "Two lines  \nbody"
+    );
+}
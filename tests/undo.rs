@@ -0,0 +1,67 @@
+//! Deleting a note takes it out of every other note's dependencies, undoing the deletion puts it
+//! back.
+
+mod common;
+
+use common::notes;
+use meteora::*;
+
+fn delete(userdata: &mut UserData, history: &mut UndoStack, id: u128) {
+    let dependents = backlinks(id, &userdata.notes);
+    let note = userdata.delete_note(id);
+    history.push(NoteChange {
+        id,
+        before: note,
+        after: None,
+        dependents,
+    });
+}
+
+#[test]
+fn deleting_a_note_removes_it_from_dependencies() {
+    let mut userdata = UserData {
+        notes: notes(3, &[(2, &[1]), (3, &[1, 2])]),
+        ..Default::default()
+    };
+    userdata.delete_note(1);
+    assert!(!userdata.notes.contains_key(&1));
+    assert!(userdata.notes[&2].depends.is_empty());
+    assert_eq!(userdata.notes[&3].depends, [2].into());
+}
+
+#[test]
+fn undoing_a_deletion_restores_dependencies() {
+    let mut userdata = UserData {
+        notes: notes(3, &[(2, &[1]), (3, &[1, 2])]),
+        ..Default::default()
+    };
+    let before = userdata.notes.clone();
+    let mut history = UndoStack::default();
+
+    delete(&mut userdata, &mut history, 1);
+    assert_eq!(history.undo(&mut userdata.notes), Some(1));
+    assert!(userdata.notes == before);
+
+    // redoing the deletion strips the dependencies again, undoing restores them again
+    assert_eq!(history.redo(&mut userdata.notes), Some(1));
+    assert!(userdata.notes[&2].depends.is_empty());
+    assert_eq!(userdata.notes[&3].depends, [2].into());
+    history.undo(&mut userdata.notes);
+    assert!(userdata.notes == before);
+}
+
+#[test]
+fn dependents_deleted_in_the_meantime_are_skipped() {
+    let mut userdata = UserData {
+        notes: notes(3, &[(2, &[1]), (3, &[1])]),
+        ..Default::default()
+    };
+    let mut history = UndoStack::default();
+    delete(&mut userdata, &mut history, 1);
+    // removed without going through the history, e.g. by a sync
+    userdata.notes.remove(&3);
+
+    history.undo(&mut userdata.notes);
+    assert_eq!(userdata.notes.len(), 2);
+    assert_eq!(userdata.notes[&2].depends, [1].into());
+}